@@ -0,0 +1,39 @@
+use crate::runner::shell_split;
+
+/// Expand `@path` arguments into the contents of the file at `path`, so long
+/// comparison sets can be kept in a file instead of fought through the shell.
+///
+/// Each non-empty line that doesn't start with `#` is one command, except
+/// lines starting with `-`, which are options split like a shell would
+/// (`--runs 10`, `--theme "my theme.toml"`).
+pub fn expand(args: impl IntoIterator<Item = String>) -> Result<Vec<String>, String> {
+    let mut expanded = Vec::new();
+    for arg in args {
+        match arg.strip_prefix('@') {
+            Some(path) if !path.is_empty() => expanded.extend(read(path)?),
+            _ => expanded.push(arg),
+        }
+    }
+    Ok(expanded)
+}
+
+fn read(path: &str) -> Result<Vec<String>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read argument file {}: {}", path, e))?;
+
+    let mut args = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('-') {
+            let options =
+                shell_split(line).map_err(|e| format!("{} at {}:{}", e, path, n + 1))?;
+            args.extend(options);
+        } else {
+            args.push(line.to_string());
+        }
+    }
+    Ok(args)
+}
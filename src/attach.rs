@@ -0,0 +1,85 @@
+use crate::progress;
+use crate::record::RecordedCommand;
+use crate::system;
+use crate::types::RunResult;
+use chrono::Utc;
+use std::time::{Duration, Instant};
+
+/// One running process being watched by `clash attach`.
+pub struct Attached {
+    pub pid: u32,
+    /// Process name, for labels
+    pub name: String,
+    /// One result per sampling interval
+    pub samples: RecordedCommand,
+}
+
+/// Watch `pids` side by side for `duration`. Every `interval` becomes one
+/// result per process: the CPU time it used during the interval as the
+/// duration, and its RSS at the end of the interval as the memory.
+///
+/// A process that exits early keeps the samples taken until then.
+pub fn sample(pids: &[u32], duration: Duration, interval: Duration) -> Result<Vec<Attached>, String> {
+    if interval.is_zero() || interval > duration {
+        return Err("--interval must be greater than 0 and at most --duration".to_string());
+    }
+    if !system::SAMPLES_PROCESSES {
+        return Err("clash attach needs clash built with the \"memory\" feature".to_string());
+    }
+    let mut table = system::process_table();
+
+    let mut attached = Vec::new();
+    let mut cpu_times = Vec::new();
+    for &pid in pids {
+        let process = table
+            .sample(pid)
+            .ok_or_else(|| format!("No running process with PID {}", pid))?;
+        let name = process.name;
+        cpu_times.push(Some(process.cpu_time));
+        attached.push(Attached {
+            pid,
+            samples: RecordedCommand {
+                command: format!("pid {} ({})", pid, name),
+                label: None,
+                location: None,
+                provenance: None,
+                results: Vec::new(),
+                memory_unrecorded: false,
+            },
+            name,
+        });
+    }
+
+    let intervals = (duration.as_secs_f64() / interval.as_secs_f64()).floor() as u64;
+    let pb = progress::bar(intervals, "    Sampling {bar:20.cyan/dim} {pos}/{len} intervals");
+    let start = Instant::now();
+    for i in 1..=intervals {
+        let started_at = Utc::now();
+        std::thread::sleep((start + interval * i as u32).saturating_duration_since(Instant::now()));
+        for (a, cpu_time) in attached.iter_mut().zip(&mut cpu_times) {
+            let (Some(process), Some(before)) = (table.sample(a.pid), *cpu_time) else {
+                *cpu_time = None;
+                continue;
+            };
+            let now = process.cpu_time;
+            a.samples.results.push(RunResult {
+                duration: now.saturating_sub(before),
+                peak_memory_bytes: Some(process.memory_bytes),
+                exit_code: Some(0),
+                started_at: Some(started_at),
+                ..RunResult::default()
+            });
+            *cpu_time = Some(now);
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    if let Some(gone) = attached.iter().find(|a| a.samples.results.is_empty()) {
+        return Err(format!(
+            "PID {} ({}) exited before the first interval ended",
+            gone.pid, gone.name
+        ));
+    }
+    Ok(attached)
+}
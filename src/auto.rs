@@ -0,0 +1,189 @@
+use crate::remote::shell_quote;
+use crate::runner::shell_split;
+use owo_colors::OwoColorize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How `--auto` runs a source file of one language.
+struct Language {
+    extensions: &'static [&'static str],
+    /// Interpreter or compiler, the first one found on PATH
+    programs: &'static [&'static str],
+    /// Arguments before the source file
+    args: &'static [&'static str],
+    /// Compile to a native binary first, then benchmark the binary
+    compiled: bool,
+}
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        extensions: &["py"],
+        programs: &["python3", "python"],
+        args: &[],
+        compiled: false,
+    },
+    Language {
+        extensions: &["js", "mjs", "cjs"],
+        programs: &["node"],
+        args: &[],
+        compiled: false,
+    },
+    Language {
+        extensions: &["rb"],
+        programs: &["ruby"],
+        args: &[],
+        compiled: false,
+    },
+    Language {
+        extensions: &["pl"],
+        programs: &["perl"],
+        args: &[],
+        compiled: false,
+    },
+    Language {
+        extensions: &["php"],
+        programs: &["php"],
+        args: &[],
+        compiled: false,
+    },
+    Language {
+        extensions: &["lua"],
+        programs: &["lua"],
+        args: &[],
+        compiled: false,
+    },
+    Language {
+        extensions: &["sh"],
+        programs: &["bash", "sh"],
+        args: &[],
+        compiled: false,
+    },
+    Language {
+        extensions: &["ps1"],
+        programs: &["pwsh", "powershell"],
+        args: &["-File"],
+        compiled: false,
+    },
+    Language {
+        extensions: &["rs"],
+        programs: &["rustc"],
+        args: &["-O"],
+        compiled: true,
+    },
+    Language {
+        extensions: &["c"],
+        programs: &["cc", "gcc", "clang"],
+        args: &["-O2"],
+        compiled: true,
+    },
+    Language {
+        extensions: &["cpp", "cc", "cxx"],
+        programs: &["c++", "g++", "clang++"],
+        args: &["-O2"],
+        compiled: true,
+    },
+    Language {
+        extensions: &["go"],
+        programs: &["go"],
+        args: &["build"],
+        compiled: true,
+    },
+];
+
+/// Turn a command starting with a source file into one that runs it:
+/// `a.py 100` becomes `python3 a.py 100`, and `a.rs` is compiled with
+/// `rustc -O` and replaced by the binary. Commands that don't start with a
+/// known source file are returned unchanged.
+pub fn expand(cmd: &str, quiet: bool) -> Result<String, String> {
+    let words = shell_split(cmd)?;
+    let source = Path::new(&words[0]);
+    let Some(language) = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(|e| LANGUAGES.iter().find(|l| l.extensions.contains(&e)))
+    else {
+        return Ok(cmd.to_string());
+    };
+    if !source.is_file() {
+        return Err(format!("--auto: no such file '{}'", source.display()));
+    }
+    let Some(program) = language.programs.iter().find(|p| on_path(p)) else {
+        return Err(format!(
+            "--auto: running '{}' needs {} on PATH",
+            source.display(),
+            language.programs.join(" or ")
+        ));
+    };
+
+    let mut command: Vec<String> = if language.compiled {
+        vec![compile(source, program, language.args, quiet)?.to_string_lossy().into_owned()]
+    } else {
+        let mut command = vec![program.to_string()];
+        command.extend(language.args.iter().map(|a| a.to_string()));
+        command.push(words[0].clone());
+        command
+    };
+    command.extend(words[1..].iter().cloned());
+    Ok(command.iter().map(|w| quote(w)).collect::<Vec<_>>().join(" "))
+}
+
+/// Quote `word` only if splitting the command again would break it up.
+fn quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && !word.contains(|c: char| c.is_whitespace() || matches!(c, '\'' | '"' | '\\'));
+    if plain { word.to_string() } else { shell_quote(word) }
+}
+
+/// Compile `source` into clash's temporary directory, returning the binary.
+fn compile(source: &Path, compiler: &str, args: &[&str], quiet: bool) -> Result<PathBuf, String> {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    // Numbered, so sources sharing a name in different directories don't clash
+    static COMPILED: AtomicU32 = AtomicU32::new(0);
+
+    let dir = std::env::temp_dir().join("clash-auto");
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create '{}': {}", dir.display(), e))?;
+    let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+    let binary = dir.join(format!(
+        "{}-{}{}",
+        stem,
+        COMPILED.fetch_add(1, Ordering::Relaxed),
+        std::env::consts::EXE_SUFFIX
+    ));
+
+    if !quiet {
+        println!(
+            "  {} Compiling {} with {} {}",
+            "[~]".cyan(),
+            source.display(),
+            compiler,
+            args.join(" ")
+        );
+    }
+    let output = Command::new(compiler)
+        .args(args)
+        .arg("-o")
+        .arg(&binary)
+        .arg(source)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", compiler, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Compiling '{}' failed:\n{}",
+            source.display(),
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        ));
+    }
+    Ok(binary)
+}
+
+/// Whether `program` is an executable in a PATH directory.
+fn on_path(program: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&path).any(|dir| {
+        dir.join(program).is_file()
+            || dir.join(format!("{}{}", program, std::env::consts::EXE_SUFFIX)).is_file()
+    })
+}
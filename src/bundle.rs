@@ -0,0 +1,214 @@
+use crate::system;
+use crate::types::Export;
+use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePublicKey};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Bundle layout version, so later layouts can still be told apart.
+const FORMAT: &str = "clash-bundle/2";
+
+/// The machine and build that produced a bundle's results.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Environment {
+    pub clash_version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kernel: Option<String>,
+    pub arch: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<String>,
+    pub cpus: usize,
+    pub memory_bytes: u64,
+}
+
+impl Environment {
+    /// Describe the machine clash is running on.
+    pub fn current() -> Self {
+        Self {
+            clash_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: system::os_version(),
+            kernel: system::kernel_version(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpu: system::cpu_brand(),
+            cpus: std::thread::available_parallelism().map_or(0, |n| n.get()),
+            memory_bytes: system::total_memory(),
+        }
+    }
+}
+
+/// A session's export and environment, signed with an Ed25519 key so
+/// anyone holding the public key can check they weren't altered.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Bundle {
+    pub format: String,
+    /// What the signature covers, `environment` and `export`, as the exact
+    /// JSON text that was signed: parsing and writing it again could round
+    /// its floats differently
+    pub signed: String,
+    /// The signer's public key, PEM-encoded
+    pub public_key: String,
+    /// Hex-encoded signature of the bytes of `signed`
+    pub signature: String,
+}
+
+impl Bundle {
+    /// Sign `export` with the PKCS#8 PEM private key at `key_path`, as
+    /// written by `openssl genpkey -algorithm ed25519`.
+    pub fn sign(export: &Export, key_path: &str) -> Result<Self, String> {
+        let pem = std::fs::read_to_string(key_path)
+            .map_err(|e| format!("Failed to read key '{}': {}", key_path, e))?;
+        let key = SigningKey::from_pkcs8_pem(&pem)
+            .map_err(|e| format!("'{}' is not an Ed25519 private key: {}", key_path, e))?;
+        let signed = serde_json::to_string(&serde_json::json!({
+            "environment": Environment::current(),
+            "export": export,
+        }))
+        .map_err(|e| e.to_string())?;
+        let signature = key.sign(signed.as_bytes());
+        Ok(Self {
+            format: FORMAT.to_string(),
+            signed,
+            public_key: key
+                .verifying_key()
+                .to_public_key_pem(LineEnding::LF)
+                .map_err(|e| e.to_string())?,
+            signature: hex(&signature.to_bytes()),
+        })
+    }
+
+    /// Read a bundle written by `--sign`.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+        let bundle: Self = serde_json::from_str(&text)
+            .map_err(|e| format!("'{}' is not a clash bundle: {}", path, e))?;
+        if bundle.format != FORMAT {
+            return Err(format!("'{}' has unknown bundle format '{}'", path, bundle.format));
+        }
+        Ok(bundle)
+    }
+
+    /// Check the signature against the embedded public key, or against
+    /// `trusted` (a PEM public key) when given, returning the signer's key.
+    pub fn verify(&self, trusted: Option<&str>) -> Result<VerifyingKey, String> {
+        let embedded = VerifyingKey::from_public_key_pem(&self.public_key)
+            .map_err(|e| format!("Bundle has an unreadable public key: {}", e))?;
+        if let Some(trusted) = trusted {
+            let trusted = VerifyingKey::from_public_key_pem(trusted)
+                .map_err(|e| format!("--key is not an Ed25519 public key: {}", e))?;
+            if trusted != embedded {
+                return Err("Bundle was signed by a different key than --key".to_string());
+            }
+        }
+        let bytes: [u8; 64] = unhex(&self.signature)
+            .and_then(|b| b.try_into().ok())
+            .ok_or("Bundle has a malformed signature")?;
+        embedded
+            .verify(self.signed.as_bytes(), &Signature::from_bytes(&bytes))
+            .map_err(|_| "Signature does not match: the bundle was altered".to_string())?;
+        Ok(embedded)
+    }
+
+    /// The signed `environment` and `export`, to show once verified.
+    pub fn payload(&self) -> Result<Value, String> {
+        serde_json::from_str(&self.signed)
+            .map_err(|e| format!("Bundle's signed payload is not JSON: {}", e))
+    }
+}
+
+/// Lowercase hex digits of `bytes`.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats;
+    use crate::testutil::run_result;
+    use crate::types::{Estimator, RunSettings, SessionInfo};
+    use ed25519_dalek::pkcs8::EncodePrivateKey;
+    use std::time::Duration;
+
+    /// A session whose statistics are floats that serde_json doesn't parse
+    /// back exactly, such as a mean of 0.010391335500000001s.
+    fn export() -> Export {
+        let stats: Vec<_> = [[10_391_335, 10_391_336, 10_391_335], [20_783_671, 7, 3]]
+            .iter()
+            .enumerate()
+            .map(|(i, nanos)| {
+                let runs: Vec<_> = nanos
+                    .iter()
+                    .map(|&n| {
+                        let mut run = run_result(0, 1 << 20);
+                        run.duration = Duration::from_nanos(n);
+                        run
+                    })
+                    .collect();
+                stats::compute_stats(&format!("cmd{}", i), &runs, &RunSettings::default())
+            })
+            .collect();
+        let now = chrono::Utc::now();
+        let session = SessionInfo {
+            started_at: now,
+            finished_at: now,
+            wall_clock: Duration::from_nanos(31_175_006),
+            setup: Duration::ZERO,
+            host: None,
+            settings: RunSettings::default(),
+            estimator: Estimator::default(),
+            numa_topology: Vec::new(),
+            monitor_overhead: None,
+        };
+        Export::new(session, &stats)
+    }
+
+    /// A fresh key's PEM file, named for the test using it.
+    fn key_file(name: &str) -> String {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let pem = key.to_pkcs8_pem(LineEnding::LF).unwrap();
+        let path = std::env::temp_dir().join(format!("clash-{}-{}.pem", name, std::process::id()));
+        std::fs::write(&path, pem.as_bytes()).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    /// A bundle as `clash verify` reads it back from disk.
+    fn written(bundle: &Bundle) -> Bundle {
+        serde_json::from_str(&serde_json::to_string_pretty(bundle).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn fresh_bundles_verify() {
+        let key = key_file("fresh");
+        let bundle = written(&Bundle::sign(&export(), &key).unwrap());
+        std::fs::remove_file(&key).ok();
+
+        assert!(bundle.verify(None).is_ok());
+        let results = &bundle.payload().unwrap()["export"]["results"];
+        assert_eq!(results.as_array().map(Vec::len), Some(2));
+    }
+
+    #[test]
+    fn altered_bundles_are_rejected() {
+        let key = key_file("altered");
+        let mut bundle = written(&Bundle::sign(&export(), &key).unwrap());
+        std::fs::remove_file(&key).ok();
+
+        bundle.signed = bundle.signed.replacen("cmd0", "cmd9", 1);
+        let err = bundle.verify(None).unwrap_err();
+        assert!(err.contains("altered"), "{}", err);
+    }
+}
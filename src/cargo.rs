@@ -0,0 +1,164 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// A binary to benchmark, built by `clash cargo`.
+pub struct Build {
+    /// Label for the report, e.g. "mytool (main)"
+    pub label: String,
+    pub path: PathBuf,
+}
+
+/// What `clash cargo` builds in each tree.
+pub struct Target<'a> {
+    pub name: &'a str,
+    /// An example rather than a binary
+    pub example: bool,
+}
+
+/// Build `target` in the current workspace and in `against`, which is a
+/// git ref, another crate directory, or an already-built executable (say,
+/// an installed release).
+pub fn build_pair(target: &Target, against: &str) -> Result<(Build, Build), String> {
+    let workspace = workspace()?;
+    let current = Build {
+        label: format!("{} (working tree)", target.name),
+        path: build(&workspace.root, None, target)?,
+    };
+
+    let path = Path::new(against);
+    let reference = if path.is_file() {
+        path.canonicalize()
+            .map_err(|e| format!("Failed to resolve '{}': {}", against, e))?
+    } else if path.is_dir() {
+        build(path, None, target)?
+    } else {
+        // Its own target directory keeps the two builds apart while still
+        // reusing compiled dependencies on the next run
+        let worktree = Worktree::add(&workspace.root, against)?;
+        let target_dir = workspace.target.join("clash-against");
+        build(&worktree.workspace, Some(&target_dir), target)?
+    };
+    Ok((
+        current,
+        Build {
+            label: format!("{} ({})", target.name, against),
+            path: reference,
+        },
+    ))
+}
+
+/// The current workspace's root and target directory.
+struct Workspace {
+    root: PathBuf,
+    target: PathBuf,
+}
+
+fn workspace() -> Result<Workspace, String> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("Failed to run cargo: {}", e))?;
+    if !output.status.success() {
+        return Err("cargo metadata failed; run clash cargo inside a Cargo workspace".to_string());
+    }
+    let metadata: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Unreadable cargo metadata: {}", e))?;
+    let dir = |field: &str| metadata.get(field).and_then(Value::as_str).map(PathBuf::from);
+    match (dir("workspace_root"), dir("target_directory")) {
+        (Some(root), Some(target)) => Ok(Workspace { root, target }),
+        _ => Err("cargo metadata has no workspace root".to_string()),
+    }
+}
+
+/// `cargo build --release` for `target` in `dir`, returning the path of the
+/// executable cargo reports. Build progress goes to the terminal.
+fn build(dir: &Path, target_dir: Option<&Path>, target: &Target) -> Result<PathBuf, String> {
+    let kind = if target.example { "--example" } else { "--bin" };
+    let mut command = Command::new("cargo");
+    command
+        .args(["build", "--release", "--message-format=json-render-diagnostics", kind])
+        .arg(target.name)
+        .current_dir(dir)
+        .stderr(Stdio::inherit());
+    if let Some(target_dir) = target_dir {
+        command.env("CARGO_TARGET_DIR", target_dir);
+    }
+    let output = command.output().map_err(|e| format!("Failed to run cargo: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Building {} in {} failed", target.name, dir.display()));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|message| {
+            message.get("reason").and_then(Value::as_str) == Some("compiler-artifact")
+                && message.pointer("/target/name").and_then(Value::as_str) == Some(target.name)
+        })
+        .find_map(|message| message.get("executable")?.as_str().map(PathBuf::from))
+        .ok_or_else(|| {
+            format!("cargo built no executable named {} in {}", target.name, dir.display())
+        })
+}
+
+/// A temporary git worktree checked out at some ref, removed on drop.
+struct Worktree {
+    repo: PathBuf,
+    dir: PathBuf,
+    /// The workspace's counterpart inside the worktree
+    workspace: PathBuf,
+}
+
+impl Worktree {
+    /// Check out `reference` of the repository holding `workspace`.
+    fn add(workspace: &Path, reference: &str) -> Result<Self, String> {
+        let prefix = Command::new("git")
+            .arg("-C")
+            .arg(workspace)
+            .args(["rev-parse", "--show-prefix"])
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .ok_or_else(|| {
+                format!(
+                    "--against '{}' is not a file or directory, and this isn't a git repository",
+                    reference
+                )
+            })?;
+        let prefix = String::from_utf8_lossy(&prefix.stdout).trim().to_string();
+        let dir = std::env::temp_dir().join(format!("clash-cargo-{}", std::process::id()));
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(workspace)
+            .args(["worktree", "add", "--quiet", "--detach"])
+            .arg(&dir)
+            .arg(reference)
+            .status()
+            .map_err(|e| format!("Failed to run git: {}", e))?;
+        if !status.success() {
+            return Err(format!(
+                "--against '{}' is neither a file, a directory nor a git ref",
+                reference
+            ));
+        }
+        Ok(Self {
+            repo: workspace.to_path_buf(),
+            workspace: dir.join(prefix),
+            dir,
+        })
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        Command::new("git")
+            .arg("-C")
+            .arg(&self.repo)
+            .args(["worktree", "remove", "--force"])
+            .arg(&self.dir)
+            .status()
+            .ok();
+    }
+}
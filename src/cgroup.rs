@@ -0,0 +1,179 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// A memory cgroup holding one run's processes, so their memory is read
+/// from the kernel's own accounting: children and threads included, and
+/// without scanning the process table on every sample.
+pub struct MemoryCgroup {
+    dir: PathBuf,
+    v1: bool,
+    /// Memory charged right now
+    current: PathBuf,
+    /// High-water mark of `current`, where the kernel keeps one
+    peak: Option<PathBuf>,
+}
+
+impl MemoryCgroup {
+    /// A new cgroup inside clash's own, or `None` where memory cgroups
+    /// aren't available or clash may not create them.
+    #[cfg(target_os = "linux")]
+    pub fn create() -> Option<Self> {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        static CREATED: AtomicU32 = AtomicU32::new(0);
+
+        let (home, v1) = own_memory_cgroup()?;
+        let name = format!(
+            "clash-{}-{}",
+            std::process::id(),
+            CREATED.fetch_add(1, Ordering::Relaxed)
+        );
+        let dir = home.join(name);
+        std::fs::create_dir(&dir).ok()?;
+        let (current, peak) = if v1 {
+            ("memory.usage_in_bytes", "memory.max_usage_in_bytes")
+        } else {
+            ("memory.current", "memory.peak")
+        };
+        let cgroup = Self {
+            current: dir.join(current),
+            peak: Some(dir.join(peak)).filter(|p| p.exists()),
+            dir,
+            v1,
+        };
+        // Without the memory controller delegated, there's nothing to read
+        cgroup.current.exists().then_some(cgroup)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn create() -> Option<Self> {
+        None
+    }
+
+    /// Have the process `command` starts move itself into this cgroup
+    /// between fork and exec, so it's accounted here from its first
+    /// instruction while clash and its other threads stay where they are.
+    /// `contains` tells whether the move worked.
+    #[cfg(target_os = "linux")]
+    pub fn enter_on_spawn(&self, command: &mut Command) {
+        use std::os::fd::AsRawFd;
+        use std::os::unix::process::CommandExt;
+        let procs = std::fs::OpenOptions::new().write(true).open(self.dir.join("cgroup.procs"));
+        let Ok(procs) = procs else {
+            return;
+        };
+        let enter = move || {
+            // "0" names the writer, the forked child; a failed move shows
+            // in `contains`, so the run goes ahead regardless
+            // SAFETY: writes one byte from a static buffer to an open file
+            unsafe { libc::write(procs.as_raw_fd(), b"0".as_ptr().cast(), 1) };
+            Ok(())
+        };
+        // SAFETY: the hook only makes an async-signal-safe system call on a
+        // file opened before the fork
+        unsafe {
+            command.pre_exec(enter);
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn enter_on_spawn(&self, _: &mut Command) {}
+
+    /// Whether process `pid` made it into the cgroup.
+    pub fn contains(&self, pid: u32) -> bool {
+        std::fs::read_to_string(self.dir.join("cgroup.procs"))
+            .is_ok_and(|procs| procs.lines().any(|line| line.trim() == pid.to_string()))
+    }
+
+    /// Kill every process in the cgroup, such as the workers a stopped run
+    /// left behind.
+    #[cfg(target_os = "linux")]
+    pub fn kill_all(&self) {
+        let Ok(procs) = std::fs::read_to_string(self.dir.join("cgroup.procs")) else {
+            return;
+        };
+        for pid in procs.lines().filter_map(|line| line.trim().parse().ok()) {
+            // SAFETY: kill takes no pointers
+            unsafe { libc::kill(pid, libc::SIGKILL) };
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn kill_all(&self) {}
+
+    /// Whether no process is left in the cgroup.
+    pub fn is_empty(&self) -> bool {
+        std::fs::read_to_string(self.dir.join("cgroup.procs"))
+            .is_ok_and(|procs| procs.trim().is_empty())
+    }
+
+    /// Memory currently charged to the cgroup, in bytes.
+    pub fn current(&self) -> Option<u64> {
+        read_bytes(&self.current)
+    }
+
+    /// Most memory ever charged to the cgroup, in bytes, where the kernel
+    /// tracks it.
+    pub fn peak(&self) -> Option<u64> {
+        read_bytes(self.peak.as_ref()?)
+    }
+
+    /// CPU time the cgroup's processes have used, where the kernel reports
+    /// it alongside memory (cgroup v2's `cpu.stat`).
+    pub fn cpu_time(&self) -> Option<Duration> {
+        if self.v1 {
+            return None;
+        }
+        let stat = std::fs::read_to_string(self.dir.join("cpu.stat")).ok()?;
+        let usec = stat.lines().find_map(|line| line.strip_prefix("usage_usec "))?;
+        Some(Duration::from_micros(usec.trim().parse().ok()?))
+    }
+
+    /// Cap the cgroup's memory at `bytes`, with no swap to spill into, so a
+    /// process needing more is killed. Whether the kernel took the cap.
+    pub fn limit(&self, bytes: u64) -> bool {
+        let (limit, swap) = if self.v1 {
+            // v1 caps memory plus swap as a whole, no lower than memory alone
+            ("memory.limit_in_bytes", "memory.memsw.limit_in_bytes")
+        } else {
+            ("memory.max", "memory.swap.max")
+        };
+        let set = std::fs::write(self.dir.join(limit), bytes.to_string()).is_ok();
+        if set {
+            let swap_cap = if self.v1 { bytes.to_string() } else { "0".to_string() };
+            std::fs::write(self.dir.join(swap), swap_cap).ok();
+        }
+        set
+    }
+}
+
+impl Drop for MemoryCgroup {
+    fn drop(&mut self) {
+        // Fails while a process that outlived the run is still inside
+        std::fs::remove_dir(&self.dir).ok();
+    }
+}
+
+fn read_bytes(path: &Path) -> Option<u64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Directory of the memory cgroup clash runs in, and whether it is a
+/// cgroup v1 hierarchy.
+#[cfg(target_os = "linux")]
+fn own_memory_cgroup() -> Option<(PathBuf, bool)> {
+    let cgroups = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+    let mut unified = None;
+    for line in cgroups.lines() {
+        let mut fields = line.splitn(3, ':');
+        let (id, controllers, path) = (fields.next()?, fields.next()?, fields.next()?);
+        let path = path.trim_start_matches('/');
+        if controllers.split(',').any(|c| c == "memory") {
+            return Some((PathBuf::from("/sys/fs/cgroup/memory").join(path), true));
+        }
+        if id == "0" && controllers.is_empty() {
+            unified = Some(PathBuf::from("/sys/fs/cgroup").join(path));
+        }
+    }
+    Some((unified?, false))
+}
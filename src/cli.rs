@@ -0,0 +1,882 @@
+use crate::cores::CoreType;
+use crate::events::EventFormat;
+use crate::exec::Follow;
+use crate::export::Target;
+use crate::notify::Notify;
+use crate::output::{Layout, Style};
+use crate::progress::ProgressFormat;
+use crate::runner::{LocalOptions, Preset, RunPlan};
+use crate::stats;
+use crate::timecmd::{Backend, Flavor};
+use crate::types::{
+    Delay, Estimator, Limits, Matrix, RunSettings, StatusPolicy, Statuses, Weights,
+};
+use crate::units::{ByteUnits, Locale};
+use clap::{ArgMatches, Args, Parser, Subcommand};
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "clash",
+    version,
+    about = "⚔️  clash — benchmark comparator\n\nRun commands head-to-head and compare their performance.\nMeasures execution time AND peak memory usage.",
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub subcommand: Option<Subcommands>,
+
+    /// Commands to benchmark (at least 2)
+    #[arg(required_unless_present_any = ["aa", "stdin", "each"], num_args = 1..)]
+    pub commands: Vec<String>,
+
+    /// Report the commands that follow as a section of their own, with its own tables and
+    /// winner (repeatable, e.g. --group Parsers a b --group Serializers c d)
+    #[arg(long, value_name = "NAME", conflicts_with = "aa")]
+    pub group: Vec<String>,
+
+    /// Read additional commands from stdin, one per line
+    #[arg(long, conflicts_with = "aa")]
+    pub stdin: bool,
+
+    /// Benchmark every file matching this glob as its own command
+    #[arg(long, value_name = "GLOB", conflicts_with = "aa")]
+    pub each: Vec<String>,
+
+    /// Arguments appended to every command expanded from --each
+    #[arg(long, value_name = "ARGS", requires = "each", allow_hyphen_values = true)]
+    pub each_args: Option<String>,
+
+    /// Benchmark every command once per value, replacing {VAR} in it, and show a heatmap,
+    /// e.g. "threads=1,2,4,8"
+    #[arg(long, value_name = "VAR=VALUES")]
+    pub matrix: Option<Matrix>,
+
+    /// Like --matrix over thread counts, adding each command's speedup and parallel
+    /// efficiency at every count, e.g. "threads=1,2,4,8"
+    #[arg(long, value_name = "VAR=COUNTS", conflicts_with = "matrix")]
+    pub scaling: Option<Matrix>,
+
+    /// hyperfine's spelling of --matrix: -L threads 1,2,4,8
+    #[arg(
+        short = 'L',
+        long,
+        num_args = 2,
+        value_names = ["VAR", "VALUES"],
+        conflicts_with_all = ["matrix", "scaling"]
+    )]
+    pub parameter_list: Vec<String>,
+
+    /// Run source files by extension: a.py with python, b.js with node, c.rs compiled with rustc
+    #[arg(long)]
+    pub auto: bool,
+
+    /// A/A test: benchmark CMD twice as if it were two commands and check
+    /// that no significant difference is found, to gauge environment noise
+    #[arg(long, value_name = "CMD", conflicts_with = "commands")]
+    pub aa: Option<String>,
+
+    /// Untimed command to run before every run (once for all, or once per command)
+    #[arg(long, value_name = "CMD")]
+    pub prepare: Vec<String>,
+
+    /// Run each --prepare command once before benchmarking instead of before every run, and
+    /// skip it in later sessions while it and its --prepare-input files are unchanged
+    #[arg(long, requires = "prepare")]
+    pub prepare_once: bool,
+
+    /// File whose contents --prepare-once checks for changes, e.g. the generator script or
+    /// the file the prepare command creates (repeatable)
+    #[arg(long, value_name = "PATH", requires = "prepare_once")]
+    pub prepare_input: Vec<String>,
+
+    /// Run every command as a pipeline of stages, one per --stage, each appended to the
+    /// command as its arguments, and time the stages separately (repeatable, in order)
+    #[arg(long, value_name = "ARGS", conflicts_with = "phases", allow_hyphen_values = true)]
+    pub stage: Vec<String>,
+
+    /// Command run once before benchmarking, timed separately in a Build table (once for
+    /// all, or once per command), e.g. a compile step
+    #[arg(long, value_name = "CMD")]
+    pub build: Vec<String>,
+
+    /// Also run each command once, untimed, under strace -c and compare how many system calls
+    /// it makes and which (Linux)
+    #[arg(long)]
+    pub syscalls: bool,
+
+    /// Measure this no-op baseline (e.g. "python -c pass") and subtract its mean from every
+    /// command that starts with the same program, to exclude interpreter startup (repeatable)
+    #[arg(long, value_name = "CMD")]
+    pub subtract: Vec<String>,
+
+    /// Run commands on a remote host over SSH (once for all, or once per command)
+    #[arg(long, value_name = "USER@HOST")]
+    pub remote: Vec<String>,
+
+    /// Run commands inside a container image via docker/podman (once for all, or once per command)
+    #[arg(long, value_name = "IMAGE")]
+    pub container: Vec<String>,
+
+    /// Nonzero exit codes that count as success, comma-separated (once for all, or once per command; "" for none)
+    #[arg(long, value_name = "CODES")]
+    pub expect_exit: Vec<String>,
+
+    /// Record every run's raw measurements to a session file for `clash replay`
+    #[arg(long, value_name = "PATH")]
+    pub record: Option<String>,
+
+    /// Exit with the time verdict: 0 for a winner beyond noise, 2 for a tie, 3 when inconclusive
+    #[arg(long)]
+    pub verdict_exit: bool,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+/// Options controlling how each command is run, shared by every mode that
+/// runs commands.
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    /// Number of benchmark runs per command [default: 5]
+    #[arg(short, long)]
+    pub runs: Option<usize>,
+
+    /// Number of warmup runs before benchmarking [default: 0]
+    #[arg(short, long)]
+    pub warmup: Option<usize>,
+
+    /// Start from a canned run count and rigor; --runs, --warmup and --delay still override it
+    #[arg(long, value_enum)]
+    pub preset: Option<Preset>,
+
+    /// Run adaptively, until the mean is within 5% (or the preset's target), but at least this many times [default: 5]
+    #[arg(long, value_name = "N", conflicts_with = "runs")]
+    pub min_runs: Option<usize>,
+
+    /// Stop adaptive runs after this many even if the target wasn't reached [default: 500]
+    #[arg(long, value_name = "N", conflicts_with = "runs")]
+    pub max_runs: Option<usize>,
+
+    /// Run local commands through this shell (e.g. "sh", "bash") instead of directly
+    #[arg(short = 'S', long)]
+    pub shell: Option<String>,
+
+    /// Run commands directly, without a shell: the default, accepted as in hyperfine (as is
+    /// --shell none)
+    #[arg(short = 'N', conflicts_with = "shell")]
+    pub no_shell: bool,
+
+    /// Pause this many milliseconds before each run so the system can settle [default: 0]
+    #[arg(long, value_name = "MS")]
+    pub delay: Option<u64>,
+
+    /// Add a random extra pause of up to this many milliseconds to --delay
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    pub delay_jitter: u64,
+
+    /// Finish within this time (e.g. "10m"), trimming run counts as needed; unused time carries over to later commands
+    #[arg(long, value_name = "DURATION", value_parser = crate::units::parse_duration)]
+    pub session_budget: Option<Duration>,
+
+    /// Run each command over and over for this long (e.g. "30m") and report whether its time or
+    /// memory drifts over the session
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = crate::units::parse_duration,
+        conflicts_with_all = ["runs", "min_runs", "max_runs", "session_budget", "rounds"]
+    )]
+    pub soak: Option<Duration>,
+
+    /// Read these files into the page cache before each command's runs, so every command starts
+    /// with the same data sets warm
+    #[arg(long, value_name = "FILE", num_args = 1..)]
+    pub prefetch: Vec<String>,
+
+    /// Repeat the whole comparison this many times and report whether the winner held up
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub rounds: usize,
+
+    /// Wait this long between --rounds (e.g. "2h"), to catch the machine in different states
+    #[arg(long, value_name = "DURATION", value_parser = crate::units::parse_duration)]
+    pub round_interval: Option<Duration>,
+
+    /// Run the commands in a random order, drawn afresh for each round
+    #[arg(long)]
+    pub shuffle: bool,
+
+    /// Seed for --shuffle, to repeat an earlier session's order (recorded in the export)
+    #[arg(long, value_name = "N", requires = "shuffle")]
+    pub seed: Option<u64>,
+
+    /// Count each run's stdout/stderr bytes and lines (pipes output instead of discarding it)
+    #[arg(long)]
+    pub count_output: bool,
+
+    /// Time the phases a command ends by printing "CLASH_MARK:<name>" lines to stderr, and compare them per phase
+    #[arg(long)]
+    pub phases: bool,
+
+    /// Bind local commands' CPUs and memory to this NUMA node (needs numactl)
+    #[arg(long, value_name = "N")]
+    pub numa_node: Option<u32>,
+
+    /// On hybrid CPUs, run local commands only on performance (p) or efficiency (e) cores
+    #[arg(long, value_enum, value_name = "TYPE")]
+    pub core_type: Option<CoreType>,
+
+    /// Time each run until every process it starts has exited, for commands that daemonize (Linux)
+    #[arg(long, conflicts_with = "pidfile")]
+    pub follow_forks: bool,
+
+    /// Kill a run that takes longer than this (e.g. "30s"); it counts as failed and is left out of the stats
+    #[arg(long, value_name = "DURATION", value_parser = crate::units::parse_duration)]
+    pub timeout: Option<Duration>,
+
+    /// Also read each run's peak RSS from the kernel when it exits, catching spikes the 30ms
+    /// sampler misses (Linux and macOS; elsewhere memory is only sampled)
+    #[arg(long)]
+    pub precise_memory: bool,
+
+    /// How local runs are measured: sample memory while they run, or launch them through
+    /// /usr/bin/time (GNU or BSD) and read max RSS, page faults and context switches from its
+    /// report
+    #[arg(long, value_enum, default_value = "sample", conflicts_with = "precise_memory")]
+    pub backend: Backend,
+
+    /// Launch this many copies of each command at once per run, timing them until the last exits
+    /// and adding up their memory, to compare behavior under contention
+    #[arg(long, value_name = "K", default_value_t = 1)]
+    pub instances: usize,
+
+    /// Time each run until the process whose PID the command writes to this file exits (given once, or once per command; "" for commands that don't daemonize)
+    #[arg(long, value_name = "PATH")]
+    pub pidfile: Vec<String>,
+}
+
+impl RunArgs {
+    /// How many times each command runs: the preset's plan (or 5 runs
+    /// without warmup), with explicit --runs and --warmup taking precedence.
+    /// --min-runs/--max-runs make the plan adaptive if it wasn't already.
+    pub fn plan(&self) -> Result<RunPlan, String> {
+        let mut plan = self.preset.map_or(RunPlan::fixed(5, 0), Preset::plan);
+        if self.min_runs.is_some() || self.max_runs.is_some() {
+            if plan.target_rse.is_none() {
+                plan.target_rse = Some(stats::MAX_RELATIVE_STD_ERROR);
+                plan.min_runs = 5;
+                plan.max_runs = 500;
+            }
+            plan.min_runs = self.min_runs.unwrap_or(plan.min_runs);
+            plan.max_runs = self.max_runs.unwrap_or(plan.max_runs);
+            // A mean's error can't be judged from fewer than two runs
+            if plan.min_runs < 2 {
+                return Err("--min-runs must be at least 2".to_string());
+            }
+            if plan.max_runs < plan.min_runs {
+                return Err(format!(
+                    "--max-runs ({}) must be at least --min-runs ({})",
+                    plan.max_runs, plan.min_runs
+                ));
+            }
+        }
+        if let Some(runs) = self.runs {
+            if runs == 0 {
+                return Err("--runs must be at least 1".to_string());
+            }
+            plan.min_runs = runs;
+            plan.max_runs = match plan.target_rse {
+                Some(_) => plan.max_runs.max(runs),
+                None => runs,
+            };
+        }
+        if let Some(warmup) = self.warmup {
+            plan.warmup = warmup;
+        }
+        if self.rounds == 0 {
+            return Err("--rounds must be at least 1".to_string());
+        }
+        if let Some(soak) = self.soak {
+            if soak.is_zero() {
+                return Err("--soak must be longer than zero".to_string());
+            }
+            plan.soak = Some(soak);
+            plan.target_rse = None;
+            plan.min_runs = 1;
+            plan.max_runs = 1;
+        }
+        Ok(plan)
+    }
+
+    /// Session-wide settings recorded alongside the results.
+    pub fn settings(&self) -> RunSettings {
+        let delay = self
+            .delay
+            .unwrap_or_else(|| self.preset.map_or(0, Preset::delay_ms));
+        RunSettings {
+            delay: Delay {
+                base: Duration::from_millis(delay),
+                jitter: Duration::from_millis(self.delay_jitter),
+            },
+            numa_node: self.numa_node,
+            core_type: self.core_type,
+            session_budget: self.session_budget,
+            preset: self.preset,
+            reject_outliers: self.preset.is_some_and(Preset::rejects_outliers),
+            significance: self.preset == Some(Preset::Paranoid),
+            prefetch: self.prefetch.clone(),
+            precise_memory: self.precise_memory && crate::exec::PRECISE_MEMORY,
+            rounds: self.rounds,
+            round_interval: self.round_interval,
+            seed: self.shuffle.then(|| self.seed.unwrap_or_else(rand::random)),
+            soak: self.soak,
+            // How runs are analyzed comes with the report options
+            ..RunSettings::default()
+        }
+    }
+
+    /// How local commands are launched.
+    pub fn local_options(&self) -> Result<LocalOptions, String> {
+        if self.instances == 0 {
+            return Err("--instances must be at least 1".to_string());
+        }
+        let mut prefix = match self.numa_node {
+            Some(node) => crate::numa::bind_prefix(node)?,
+            None => Vec::new(),
+        };
+        if let Some(core_type) = self.core_type {
+            prefix.extend(crate::cores::bind_prefix(core_type)?);
+        }
+        Ok(LocalOptions {
+            shell: self.shell.clone().filter(|shell| shell != "none"),
+            env: Vec::new(),
+            prefix,
+            count_output: self.count_output,
+            follow: if self.follow_forks {
+                Follow::Forks
+            } else {
+                Follow::Launcher
+            },
+            phases: self.phases,
+            timeout: self.timeout,
+            precise_memory: self.precise_memory,
+            instances: self.instances,
+            time: match self.backend {
+                Backend::Sample => None,
+                Backend::Time => Some(Flavor::detect()?),
+            },
+            redirect: None,
+        })
+    }
+}
+
+/// Options controlling how results are reported, shared by every mode that
+/// produces a report.
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    /// Export results as FORMAT:PATH (json, csv, markdown, html or junit), or to a PATH whose
+    /// extension picks the format, JSON by default (repeatable)
+    #[arg(short, long, value_name = "[FORMAT:]PATH")]
+    pub export: Vec<Target>,
+
+    /// Sign the results and environment with this Ed25519 private key (PKCS#8 PEM), writing a
+    /// bundle that clash verify can check
+    #[arg(long, value_name = "KEYFILE")]
+    pub sign: Option<String>,
+
+    /// Where --sign writes the signed bundle
+    #[arg(long, value_name = "PATH", requires = "sign", default_value = "clash-bundle.json")]
+    pub bundle: String,
+
+    /// Also store the session, its commands and every run in this SQLite database
+    #[arg(long, value_name = "PATH")]
+    pub db: Option<String>,
+
+    /// Also write the rendered report to this text file, colors included
+    #[arg(long, value_name = "FILE")]
+    pub export_txt: Option<String>,
+
+    /// Leave colors and other terminal escapes out of --export-txt
+    #[arg(long, requires = "export_txt")]
+    pub strip_ansi: bool,
+
+    /// Stream machine-readable progress events (to stdout unless --events-output is set)
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub events: Option<EventFormat>,
+
+    /// How to show progress: bars, or json for one JSON line per run on stderr (command, run, eta)
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "bars")]
+    pub progress: ProgressFormat,
+
+    /// hyperfine-style output: full, basic (no colors or progress bars), nocolor, color (no
+    /// progress bars), or none (no output)
+    #[arg(long, value_enum)]
+    pub style: Option<Style>,
+
+    /// Write --events to this file instead of stdout
+    #[arg(long, value_name = "PATH", requires = "events")]
+    pub events_output: Option<String>,
+
+    /// Serve live progress and the final results over HTTP on this port, with a small dashboard
+    #[arg(long, value_name = "PORT")]
+    pub serve: Option<u16>,
+
+    /// Address --serve listens on; 0.0.0.0 serves every interface
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1", requires = "serve")]
+    pub serve_address: std::net::IpAddr,
+
+    /// Print GitHub Actions annotations and append a report to $GITHUB_STEP_SUMMARY
+    #[arg(long)]
+    pub github_annotations: bool,
+
+    /// When the session ends, send a desktop notification or POST the report to a webhook URL
+    #[arg(long, value_name = "desktop|URL")]
+    pub notify: Option<Notify>,
+
+    /// Report colors: default, colorblind, monochrome, or a path to a TOML palette
+    #[arg(long, value_name = "THEME", default_value = "default")]
+    pub theme: String,
+
+    /// What run times are compared on: mean, median, trimmed(N%) (mean without the N%
+    /// slowest and fastest runs), a percentile such as p95, min or max. --rank-by is another name
+    /// for this option, so give one or the other
+    #[arg(long, visible_alias = "rank-by", value_name = "ESTIMATOR", default_value = "mean")]
+    pub estimator: Estimator,
+
+    /// Run statuses counted as failed: nonzero, signaled and timeout, comma-separated, or none
+    #[arg(long, value_name = "STATUSES", default_value = "nonzero,timeout")]
+    pub fail_on: Statuses,
+
+    /// Count runs killed by a signal as failed (same as adding "signaled" to --fail-on)
+    #[arg(long)]
+    pub treat_signals_as_failures: bool,
+
+    /// Leave runs that ended in these statuses out of the stats, as with --fail-on
+    #[arg(long, value_name = "STATUSES", default_value = "timeout")]
+    pub discard_runs: Statuses,
+
+    /// Warn about a possible leak when RSS grows faster than this many KB per minute over runs
+    /// spanning 10s or more, as in --soak and clash attach
+    #[arg(long, value_name = "KB_PER_MIN", default_value_t = stats::DEFAULT_LEAK_THRESHOLD)]
+    pub leak_threshold: f64,
+
+    /// Metric weights for an overall winner, e.g. "time=0.7,memory=0.3"
+    #[arg(long, value_name = "WEIGHTS")]
+    pub weight: Option<Weights>,
+
+    /// Color values over these budgets red in the tables and flag them in the summary, e.g.
+    /// "time=1s,memory=500MB"
+    #[arg(long, value_name = "LIMITS")]
+    pub highlight_over: Option<Limits>,
+
+    /// Table orientation: one column per command, or one row per command
+    #[arg(long, value_enum, default_value_t = Layout::Columns)]
+    pub layout: Layout,
+
+    /// Maximum width of command labels in tables and charts, in columns (5 to 1000)
+    #[arg(
+        long,
+        value_name = "COLUMNS",
+        default_value_t = stats::LABEL_WIDTH,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new()
+            .range(stats::MIN_LABEL_WIDTH as u64..=stats::MAX_LABEL_WIDTH as u64)
+    )]
+    pub label_width: usize,
+
+    /// Number separators: plain (1234.5), en (1,234.5), de (1.234,5) or fr (1 234,5)
+    #[arg(long, value_enum, default_value_t = Locale::Plain)]
+    pub locale: Locale,
+
+    /// Decimal places for every displayed time and memory value
+    #[arg(long, value_name = "DIGITS")]
+    pub precision: Option<usize>,
+
+    /// Memory units: iec (KiB, MiB; powers of 1024) or si (KB, MB; powers of 1000)
+    #[arg(long, value_enum, default_value_t = ByteUnits::Iec)]
+    pub byte_units: ByteUnits,
+
+    /// Also report clash's own measurement overhead
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Plot each command's time against its peak memory and name the ones nothing beats on both
+    #[arg(long)]
+    pub tradeoff: bool,
+
+    /// Say whether each command appears CPU-, I/O- or memory-bound, and which profiler to try next
+    #[arg(long)]
+    pub characterize: bool,
+
+    /// After the report, prompt for commands to list runs, inspect the slowest and fastest, re-run one command or export some of them
+    #[arg(long)]
+    pub explore: bool,
+}
+
+/// The `--group` each positional command was listed under, in order: the
+/// closest marker before it on the command line.
+pub fn command_groups(matches: &ArgMatches) -> Vec<Option<String>> {
+    let Some(commands) = matches.indices_of("commands") else {
+        return Vec::new();
+    };
+    let markers: Vec<(usize, &String)> = match (
+        matches.indices_of("group"),
+        matches.get_many::<String>("group"),
+    ) {
+        (Some(indices), Some(names)) => indices.zip(names).collect(),
+        _ => return Vec::new(),
+    };
+    commands
+        .map(|at| {
+            markers
+                .iter()
+                .take_while(|(marker, _)| *marker < at)
+                .last()
+                .map(|(_, name)| name.to_string())
+        })
+        .collect()
+}
+
+impl ReportArgs {
+    /// Whether events streamed to stdout replace the human-readable report.
+    pub fn quiet(&self) -> bool {
+        self.events.is_some() && self.events_output.is_none() || self.style == Some(Style::None)
+    }
+
+    /// How to show progress, once --style has had its say.
+    pub fn progress_format(&self) -> ProgressFormat {
+        match self.style {
+            Some(style) if !style.interactive() => ProgressFormat::None,
+            _ => self.progress,
+        }
+    }
+
+    /// Which run statuses count as failed and which are discarded.
+    pub fn status_policy(&self) -> StatusPolicy {
+        let mut failed = self.fail_on;
+        failed.signaled |= self.treat_signals_as_failures;
+        StatusPolicy {
+            failed,
+            discarded: self.discard_runs,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Subcommands {
+    /// Replay a recorded session's report without running any commands
+    Replay {
+        /// Session file written by --record
+        path: String,
+
+        /// Playback speed multiplier for the simulated progress
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+
+        #[command(flatten)]
+        report: ReportArgs,
+    },
+
+    /// Show the full report for results files: clash exports or hyperfine JSON
+    Report {
+        /// Files written by --export or hyperfine --export-json; their commands are compared together
+        #[arg(required = true, num_args = 1..)]
+        paths: Vec<String>,
+
+        #[command(flatten)]
+        report: ReportArgs,
+    },
+
+    /// Compare the same commands across two results files (clash exports or hyperfine JSON)
+    Diff {
+        /// Earlier results
+        before: String,
+
+        /// Later results
+        after: String,
+
+        /// Report colors: default, colorblind, monochrome, or a path to a TOML palette
+        #[arg(long, value_name = "THEME", default_value = "default")]
+        theme: String,
+    },
+
+    /// Plot one command's run-time distribution from two results files against each other
+    CompareRuns {
+        /// Earlier results
+        before: String,
+
+        /// Later results
+        after: String,
+
+        /// Command to compare, by label or command line; optional when each file has one command
+        #[arg(long, value_name = "LABEL")]
+        command: Option<String>,
+
+        /// Report colors: default, colorblind, monochrome, or a path to a TOML palette
+        #[arg(long, value_name = "THEME", default_value = "default")]
+        theme: String,
+    },
+
+    /// Benchmark a results file's commands again, or only those slower than in a baseline, and
+    /// write the file back out with the fresh results in their place
+    Rerun {
+        /// Results written by --export (or hyperfine --export-json)
+        path: String,
+
+        /// Earlier results to judge regressions against
+        #[arg(long, value_name = "PATH")]
+        baseline: Option<String>,
+
+        /// Re-run only commands that regressed against --baseline
+        #[arg(long, requires = "baseline")]
+        only_regressed: bool,
+
+        /// A regression is a mean more than this percentage slower that a rank-sum test can tell
+        /// from noise
+        #[arg(long, value_name = "PERCENT", default_value_t = 5.0)]
+        threshold: f64,
+
+        /// Write the merged results here, as --export would
+        #[arg(short, long, value_name = "[FORMAT:]PATH")]
+        output: String,
+
+        #[command(flatten)]
+        run: RunArgs,
+
+        #[command(flatten)]
+        report: ReportArgs,
+    },
+
+    /// Combine exports from several machines or sessions and compare hosts side by side
+    Merge {
+        /// Files written by --export (or earlier merges)
+        #[arg(required = true, num_args = 1..)]
+        paths: Vec<String>,
+
+        /// Write the combined results to this JSON file
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<String>,
+
+        /// Report colors: default, colorblind, monochrome, or a path to a TOML palette
+        #[arg(long, value_name = "THEME", default_value = "default")]
+        theme: String,
+
+        /// Table orientation
+        #[arg(long, value_enum, default_value_t = Layout::Columns)]
+        layout: Layout,
+    },
+
+    /// Sample the CPU time and memory of already-running processes over the same window
+    Attach {
+        /// Processes to watch side by side
+        #[arg(value_name = "PID", required = true, num_args = 1..)]
+        pids: Vec<u32>,
+
+        /// How long to watch them
+        #[arg(long, value_name = "DURATION", default_value = "10s",
+              value_parser = crate::units::parse_duration)]
+        duration: Duration,
+
+        /// Length of each sample; every interval counts as one run
+        #[arg(long, value_name = "DURATION", default_value = "1s",
+              value_parser = crate::units::parse_duration)]
+        interval: Duration,
+
+        #[command(flatten)]
+        report: ReportArgs,
+    },
+
+    /// Build a Cargo binary here and at another git ref (or path), then benchmark the two
+    Cargo {
+        /// Binary (or, with --example, example) to build
+        target: String,
+
+        /// Build an example rather than a binary
+        #[arg(long)]
+        example: bool,
+
+        /// What to compare against: a git ref, a crate directory, or an already-built executable
+        #[arg(long, value_name = "REF|PATH")]
+        against: String,
+
+        /// Arguments passed to both builds, after --
+        #[arg(last = true)]
+        args: Vec<String>,
+
+        #[command(flatten)]
+        run: RunArgs,
+
+        #[command(flatten)]
+        report: ReportArgs,
+    },
+
+    /// Benchmark the commands listed in a clash.toml suite, or the tagged subset --filter picks
+    Suite {
+        /// Suite file, with one [[bench]] table per command
+        ///
+        /// Keys of a [[bench]] table, all optional but `command`:
+        ///   command      the command to benchmark
+        ///   name         label in the report (default: the command)
+        ///   tags         groups --filter and --skip match (default: none)
+        ///   prepare      untimed command run before each run (default: none)
+        ///   stdin        file fed to every run (default: clash's own stdin)
+        ///   stdout       file each run's output replaces (default: discarded)
+        ///   expect_exit  nonzero exit codes that count as success, e.g. [1]
+        ///                (default: none, so only 0 succeeds)
+        /// Paths are relative to the directory clash runs in.
+        #[arg(default_value = "clash.toml", verbatim_doc_comment)]
+        path: String,
+
+        /// Run only benchmarks with this tag, or whose name or command contains it (repeatable)
+        #[arg(long, value_name = "TAG")]
+        filter: Vec<String>,
+
+        /// Leave out benchmarks with this tag, or whose name or command contains it (repeatable)
+        #[arg(long, value_name = "TAG")]
+        skip: Vec<String>,
+
+        #[command(flatten)]
+        run: RunArgs,
+
+        #[command(flatten)]
+        report: ReportArgs,
+    },
+
+    /// Read sessions stored with --db back: slowest commands, a label's trend, recent sessions
+    Query {
+        /// Database written by --db
+        #[arg(long, value_name = "PATH")]
+        db: String,
+
+        #[command(subcommand)]
+        query: QueryKind,
+    },
+
+    /// Check that a bundle written by --sign is intact, and show who signed what
+    Verify {
+        /// Bundle written by --sign
+        bundle: String,
+
+        /// Also require the bundle to be signed with this public key (PEM)
+        #[arg(long, value_name = "PUBFILE")]
+        key: Option<String>,
+    },
+
+    /// Chart one label's time and memory across sessions stored with --db, marking regressions
+    Trend {
+        /// Command label (or the command itself, for unlabeled commands)
+        label: String,
+
+        /// Database written by --db
+        #[arg(long, value_name = "PATH")]
+        db: String,
+
+        /// Chart at most this many of the label's most recent sessions
+        #[arg(long, value_name = "N", default_value_t = 30)]
+        last: usize,
+
+        /// Mark a session whose time or memory grew by more than this percentage over the one
+        /// before (time changes must also pass a rank-sum test)
+        #[arg(long, value_name = "PERCENT", default_value_t = 5.0)]
+        threshold: f64,
+
+        /// Report colors: default, colorblind, monochrome, or a path to a TOML palette
+        #[arg(long, value_name = "THEME", default_value = "default")]
+        theme: String,
+    },
+
+    /// Find the smallest memory cap each command still succeeds under, by bisecting, and
+    /// compare them
+    Memlimit {
+        /// Commands to test
+        #[arg(required = true)]
+        commands: Vec<String>,
+
+        /// Run commands through this shell (e.g. "sh", "bash") instead of directly
+        #[arg(short = 'S', long)]
+        shell: Option<String>,
+
+        /// Stop bisecting once the cap is known to within this percentage
+        #[arg(long, value_name = "PERCENT", default_value_t = 1.0)]
+        tolerance: f64,
+
+        /// Report colors: default, colorblind, monochrome, or a path to a TOML palette
+        #[arg(long, value_name = "THEME", default_value = "default")]
+        theme: String,
+    },
+
+    /// Benchmark one command under several sets of environment variables
+    EnvCompare {
+        /// Command to benchmark
+        command: String,
+
+        /// Environment for one contender, e.g. "RAYON_NUM_THREADS=8 RUST_LOG=off" (at least 2)
+        #[arg(long = "set", value_name = "VARS", required = true, num_args = 1)]
+        sets: Vec<String>,
+
+        #[command(flatten)]
+        run: RunArgs,
+
+        #[command(flatten)]
+        report: ReportArgs,
+    },
+}
+
+/// What `clash query` reads from the database.
+#[derive(Subcommand, Debug)]
+pub enum QueryKind {
+    /// Every label's latest results, slowest first
+    Slowest {
+        /// Show at most this many commands
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// One label's results across its most recent sessions, oldest first
+    Trend {
+        /// Command label (or the command itself, for unlabeled commands)
+        label: String,
+
+        /// Show at most this many sessions
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Recent sessions run on this machine
+    Sessions {
+        /// Show sessions run on this host instead
+        #[arg(long, value_name = "NAME", conflicts_with = "all_hosts")]
+        host: Option<String>,
+
+        /// Show sessions from every host
+        #[arg(long)]
+        all_hosts: bool,
+
+        /// Show at most this many sessions
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label_width(width: &str) -> Result<usize, clap::Error> {
+        Cli::try_parse_from(["clash", "--label-width", width, "a", "b"])
+            .map(|cli| cli.report.label_width)
+    }
+
+    #[test]
+    fn label_width_leaves_room_for_the_ellipsis_and_a_wide_grapheme() {
+        assert_eq!(label_width("5").unwrap(), stats::MIN_LABEL_WIDTH);
+        let err = label_width("4").unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ValueValidation);
+        assert!(label_width("1000").is_ok());
+        assert!(label_width("1001").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::runner::{RunIndex, Runner};
+use crate::types::RunResult;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Wrapper executed inside the container. It runs the benchmarked command
+/// through the image's `sh`, then reports its own timing and the container
+/// cgroup's peak memory (v2 `memory.peak`, falling back to v1) on fd 3.
+/// Timestamps are nanoseconds from `date +%s%N`, or from `/proc/uptime`
+/// (10ms resolution) where `date` lacks `%N`, and what taking one costs is
+/// measured first and reported too.
+const WRAPPER: &str = r#"exec 3>&2 >/dev/null 2>&1
+now() {
+  t=$(date +%s%N)
+  case $t in
+    ''|*[!0-9]*) t=$(cut -d' ' -f1 /proc/uptime | tr -d .)0000000 ;;
+  esac
+  echo "$t"
+}
+c0=$(now); c1=$(now)
+s=$(now)
+sh -c "$1"
+rc=$?
+e=$(now)
+p=$(cat /sys/fs/cgroup/memory.peak || cat /sys/fs/cgroup/memory/memory.max_usage_in_bytes || echo 0)
+echo "CLASH_STATS $s $e $rc $p $((c1 - c0))" >&3
+exit $rc"#;
+
+/// Runs each iteration inside a fresh container of `image`.
+///
+/// Peak memory is read from the container's cgroup, so it covers every
+/// process the command starts, not just the top-level one.
+pub struct ContainerRunner {
+    pub image: String,
+}
+
+impl Runner for ContainerRunner {
+    fn run_once(&self, cmd: &str, index: &RunIndex) -> Result<RunResult, String> {
+        run_once(cmd, &self.image, index)
+    }
+
+    fn location(&self) -> Option<String> {
+        Some(format!("in {}", self.image))
+    }
+
+    fn host_files(&self) -> bool {
+        false
+    }
+}
+
+fn run_once(cmd: &str, image: &str, index: &RunIndex) -> Result<RunResult, String> {
+    let engine = engine()?;
+
+    let mut command = Command::new(engine);
+    command.args(["run", "--rm"]);
+    for (name, value) in index.vars() {
+        command.arg("-e").arg(format!("{}={}", name, value));
+    }
+    let start = Instant::now();
+    let output = command
+        .args([image, "sh", "-c", WRAPPER, "clash", cmd])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| format!("Failed to start {} for '{}': {}", engine, cmd, e))?;
+    let local_duration = start.elapsed();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let Some(stats) = stderr.lines().find_map(|l| l.strip_prefix("CLASH_STATS ")) else {
+        let reason = stderr.lines().last().unwrap_or("no output").trim();
+        return Err(format!(
+            "{} run of '{}' in '{}' failed: {}",
+            engine, cmd, image, reason
+        ));
+    };
+
+    let fields: Vec<&str> = stats.split_whitespace().collect();
+    let nanos = |i: usize| fields.get(i).and_then(|v| v.parse::<u64>().ok());
+    let duration = match (nanos(0), nanos(1)) {
+        // Less one timestamp's cost, without going below what's left
+        (Some(s), Some(e)) if e >= s => {
+            Duration::from_nanos((e - s).saturating_sub(nanos(4).unwrap_or(0)))
+        }
+        _ => local_duration,
+    };
+
+    Ok(RunResult {
+        duration,
+        peak_memory_bytes: fields.get(3).and_then(|v| v.parse().ok()).filter(|&b: &u64| b > 0),
+        exit_code: fields.get(2).and_then(|v| v.parse().ok()),
+        ..RunResult::default()
+    })
+}
+
+/// Pick the container engine: docker if available, otherwise podman.
+fn engine() -> Result<&'static str, String> {
+    static ENGINE: OnceLock<Option<&'static str>> = OnceLock::new();
+    ENGINE
+        .get_or_init(|| {
+            ["docker", "podman"].into_iter().find(|bin| {
+                Command::new(bin)
+                    .arg("--version")
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status()
+                    .is_ok_and(|s| s.success())
+            })
+        })
+        .ok_or_else(|| "--container requires docker or podman on PATH".to_string())
+}
@@ -0,0 +1,99 @@
+use serde::Serialize;
+use std::process::{Command, Stdio};
+
+/// Intel hybrid CPUs list their performance and efficiency cores here.
+const INTEL_P_CORES: &str = "/sys/devices/cpu_core/cpus";
+const INTEL_E_CORES: &str = "/sys/devices/cpu_atom/cpus";
+
+/// Per-CPU directories, holding `cpu_capacity` on ARM big.LITTLE systems.
+const CPUS: &str = "/sys/devices/system/cpu";
+
+/// Which cores of a hybrid CPU commands run on, for `--core-type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CoreType {
+    /// Performance cores
+    P,
+    /// Efficiency cores
+    E,
+}
+
+/// The prefix that keeps a command on cores of type `core_type`: `taskset`
+/// on Linux, `taskpolicy` on macOS (which can only confine to E-cores).
+pub fn bind_prefix(core_type: CoreType) -> Result<Vec<String>, String> {
+    if cfg!(target_os = "macos") {
+        return macos_prefix(core_type);
+    }
+    let cpus = cpu_list(core_type)?;
+    let taskset = Command::new("taskset")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    if taskset.is_err() {
+        return Err("--core-type needs taskset (util-linux) to be installed".to_string());
+    }
+    Ok(vec!["taskset".to_string(), "-c".to_string(), cpus])
+}
+
+/// CPUs of type `core_type` in kernel list notation, e.g. "0-7".
+fn cpu_list(core_type: CoreType) -> Result<String, String> {
+    let intel = match core_type {
+        CoreType::P => INTEL_P_CORES,
+        CoreType::E => INTEL_E_CORES,
+    };
+    if let Ok(cpus) = std::fs::read_to_string(intel) {
+        return Ok(cpus.trim().to_string());
+    }
+
+    // ARM big.LITTLE: the biggest cores report the highest capacity
+    let mut capacities: Vec<(u32, u64)> = std::fs::read_dir(CPUS)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let cpu = entry.file_name().to_str()?.strip_prefix("cpu")?.parse().ok()?;
+            let capacity = std::fs::read_to_string(entry.path().join("cpu_capacity")).ok()?;
+            Some((cpu, capacity.trim().parse().ok()?))
+        })
+        .collect();
+    capacities.sort();
+    let Some(highest) = capacities.iter().map(|&(_, c)| c).max() else {
+        return Err(no_hybrid_topology());
+    };
+    let cpus: Vec<String> = capacities
+        .iter()
+        .filter(|&&(_, c)| (c == highest) == (core_type == CoreType::P))
+        .map(|(cpu, _)| cpu.to_string())
+        .collect();
+    if cpus.is_empty() || cpus.len() == capacities.len() {
+        return Err(no_hybrid_topology());
+    }
+    Ok(cpus.join(","))
+}
+
+fn no_hybrid_topology() -> String {
+    "--core-type needs a CPU with both performance and efficiency cores".to_string()
+}
+
+/// macOS has no CPU affinity, but background QoS keeps a process on the
+/// efficiency cores of Apple Silicon.
+fn macos_prefix(core_type: CoreType) -> Result<Vec<String>, String> {
+    let levels = Command::new("sysctl")
+        .args(["-n", "hw.nperflevels"])
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .and_then(|s| s.trim().parse::<u32>().ok())
+        .unwrap_or(1);
+    if levels < 2 {
+        return Err(no_hybrid_topology());
+    }
+    match core_type {
+        CoreType::E => Ok(vec!["taskpolicy".to_string(), "-b".to_string()]),
+        CoreType::P => Err(
+            "--core-type p isn't supported on macOS, which can't pin processes to P-cores"
+                .to_string(),
+        ),
+    }
+}
@@ -0,0 +1,223 @@
+use crate::types::{CommandStats, SessionInfo};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, params};
+use std::time::Duration;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY,
+    started_at TEXT NOT NULL,
+    finished_at TEXT NOT NULL,
+    host TEXT,
+    estimator TEXT NOT NULL,
+    clash_version TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS commands (
+    id INTEGER PRIMARY KEY,
+    session_id INTEGER NOT NULL REFERENCES sessions(id),
+    position INTEGER NOT NULL,
+    label TEXT NOT NULL,
+    command TEXT NOT NULL,
+    runs INTEGER NOT NULL,
+    failed_runs INTEGER NOT NULL,
+    time_mean REAL NOT NULL,
+    time_center REAL NOT NULL,
+    time_std_dev REAL NOT NULL,
+    peak_memory_bytes INTEGER
+);
+CREATE TABLE IF NOT EXISTS runs (
+    command_id INTEGER NOT NULL REFERENCES commands(id),
+    run INTEGER NOT NULL,
+    duration REAL NOT NULL,
+    peak_memory_bytes INTEGER,
+    status TEXT NOT NULL,
+    started_at TEXT
+);
+CREATE INDEX IF NOT EXISTS commands_by_label ON commands(label);
+";
+
+/// A SQLite database of every session stored with `--db`: one row per
+/// session, per command and per run, with times in seconds.
+pub struct Database {
+    conn: Connection,
+}
+
+/// A command's results from one stored session.
+#[derive(Debug)]
+pub struct CommandRow {
+    pub id: i64,
+    pub label: String,
+    pub started_at: DateTime<Utc>,
+    pub host: Option<String>,
+    pub runs: usize,
+    pub time_mean: Duration,
+    pub time_std_dev: Duration,
+    pub peak_memory_bytes: Option<u64>,
+}
+
+/// A stored session and the commands it compared.
+#[derive(Debug)]
+pub struct SessionRow {
+    pub id: i64,
+    pub started_at: DateTime<Utc>,
+    pub host: Option<String>,
+    pub labels: Vec<String>,
+}
+
+impl Database {
+    /// Open the database at `path`, creating it and its tables if needed.
+    pub fn open(path: &str) -> Result<Self, String> {
+        let conn =
+            Connection::open(path).map_err(|e| format!("Failed to open '{}': {}", path, e))?;
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| format!("'{}' is not a clash database: {}", path, e))?;
+        Ok(Self { conn })
+    }
+
+    /// Store a finished session with every command and run.
+    pub fn record(&mut self, session: &SessionInfo, stats: &[CommandStats]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT INTO sessions (started_at, finished_at, host, estimator, clash_version)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                session.started_at,
+                session.finished_at,
+                session.host,
+                session.estimator.to_string(),
+                env!("CARGO_PKG_VERSION"),
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        let session_id = tx.last_insert_rowid();
+        for (position, s) in stats.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO commands (session_id, position, label, command, runs, failed_runs,
+                     time_mean, time_center, time_std_dev, peak_memory_bytes)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    session_id,
+                    position as i64,
+                    s.label,
+                    s.command,
+                    s.runs as i64,
+                    s.failed_runs as i64,
+                    s.time_mean.as_secs_f64(),
+                    s.time_center.as_secs_f64(),
+                    s.time_std_dev.as_secs_f64(),
+                    s.peak_memory_bytes.map(|b| b as i64),
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+            let command_id = tx.last_insert_rowid();
+            for (run, r) in s.all_runs.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO runs (command_id, run, duration, peak_memory_bytes, status,
+                         started_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        command_id,
+                        run as i64,
+                        r.duration.as_secs_f64(),
+                        r.peak_memory_bytes.map(|b| b as i64),
+                        r.status().to_string(),
+                        r.started_at,
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Every label's latest results, slowest first.
+    pub fn slowest(&self, limit: usize) -> Result<Vec<CommandRow>, String> {
+        self.commands(
+            "WHERE c.id IN (SELECT MAX(id) FROM commands GROUP BY label)
+             ORDER BY c.time_mean DESC LIMIT ?1",
+            params![limit as i64],
+        )
+    }
+
+    /// A label's results in its last `limit` sessions, oldest first.
+    pub fn trend(&self, label: &str, limit: usize) -> Result<Vec<CommandRow>, String> {
+        let mut rows = self.commands(
+            "WHERE c.label = ?1 ORDER BY s.started_at DESC, c.id DESC LIMIT ?2",
+            params![label, limit as i64],
+        )?;
+        rows.reverse();
+        Ok(rows)
+    }
+
+    /// The last `limit` sessions, only those run on `host` if given,
+    /// newest first.
+    pub fn sessions(&self, host: Option<&str>, limit: usize) -> Result<Vec<SessionRow>, String> {
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT s.id, s.started_at, s.host, GROUP_CONCAT(c.label, char(31))
+                 FROM sessions s LEFT JOIN commands c ON c.session_id = s.id
+                 WHERE ?1 IS NULL OR s.host = ?1
+                 GROUP BY s.id ORDER BY s.started_at DESC LIMIT ?2",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map(params![host, limit as i64], |row| {
+                let labels: Option<String> = row.get(3)?;
+                Ok(SessionRow {
+                    id: row.get(0)?,
+                    started_at: row.get(1)?,
+                    host: row.get(2)?,
+                    labels: labels.map_or(Vec::new(), |l| {
+                        l.split('\u{1f}').map(str::to_string).collect()
+                    }),
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Durations in seconds of the runs stored for command row `id`, in run
+    /// order.
+    pub fn durations(&self, id: i64) -> Result<Vec<f64>, String> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT duration FROM runs WHERE command_id = ?1 ORDER BY run")
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map(params![id], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    /// Command rows joined with their session, narrowed and ordered by
+    /// `clause`.
+    fn commands(
+        &self,
+        clause: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<CommandRow>, String> {
+        let sql = format!(
+            "SELECT c.id, c.label, s.started_at, s.host, c.runs, c.time_mean, c.time_std_dev,
+                 c.peak_memory_bytes
+             FROM commands c JOIN sessions s ON s.id = c.session_id {}",
+            clause
+        );
+        let mut statement = self.conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map(params, |row| {
+                Ok(CommandRow {
+                    id: row.get(0)?,
+                    label: row.get(1)?,
+                    started_at: row.get(2)?,
+                    host: row.get(3)?,
+                    runs: row.get::<_, i64>(4)? as usize,
+                    time_mean: Duration::from_secs_f64(row.get(5)?),
+                    time_std_dev: Duration::from_secs_f64(row.get(6)?),
+                    peak_memory_bytes: row.get::<_, Option<i64>>(7)?.map(|b| b as u64),
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+}
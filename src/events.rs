@@ -0,0 +1,64 @@
+use crate::types::{CommandStats, RunResult};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Machine-readable event formats for `--events`.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum EventFormat {
+    /// One JSON object per line
+    Jsonl,
+}
+
+/// A progress event emitted while a session runs.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    BenchmarkStart {
+        index: usize,
+        command: &'a str,
+        runs: usize,
+        warmup: usize,
+    },
+    RunComplete {
+        index: usize,
+        run: usize,
+        #[serde(flatten)]
+        result: &'a RunResult,
+    },
+    CommandDone {
+        index: usize,
+        #[serde(flatten)]
+        stats: &'a CommandStats,
+    },
+    Report {
+        results: &'a [CommandStats],
+    },
+}
+
+/// Writes events as they happen to stdout or a file.
+pub struct EventSink {
+    out: Box<dyn Write>,
+}
+
+impl EventSink {
+    /// Open a sink writing to `path`, or to stdout when no path is given.
+    pub fn open(path: Option<&str>) -> io::Result<Self> {
+        let out: Box<dyn Write> = match path {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(io::stdout()),
+        };
+        Ok(Self { out })
+    }
+
+    /// Emit one event, flushing immediately so consumers see it live.
+    ///
+    /// Write errors are ignored: a consumer closing the stream early should
+    /// not abort the benchmark.
+    pub fn emit(&mut self, event: &Event) {
+        if let Ok(line) = serde_json::to_string(event) {
+            let _ = writeln!(self.out, "{}", line);
+            let _ = self.out.flush();
+        }
+    }
+}
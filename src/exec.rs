@@ -0,0 +1,983 @@
+use crate::cgroup::MemoryCgroup;
+use crate::reactor::{self, InFlight};
+use crate::system::{self, ProcessTable};
+use crate::types::{CpuUsage, MonitorOverhead, OutputCounts, Phase, RunResult, RunStatus};
+use std::fs::File;
+use std::io;
+#[cfg(not(unix))]
+use std::io::Read;
+#[cfg(unix)]
+use std::os::fd::OwnedFd;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+/// How often a run's memory is sampled.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(30);
+
+/// How often to check on the launched process besides when SIGCHLD says
+/// it may have exited, which is the only check where there's no SIGCHLD.
+const EXIT_POLL: Duration = if cfg!(unix) {
+    Duration::from_millis(100)
+} else {
+    Duration::from_millis(1)
+};
+
+/// How often to look for a `--pidfile`, and then for its worker's exit.
+const FOLLOW_POLL: Duration = Duration::from_millis(10);
+
+/// How long to wait for a `--pidfile` to name the worker process.
+const PIDFILE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Start of a stderr line with which a command ends a named phase of its
+/// run, e.g. `CLASH_MARK:load`.
+pub const MARK_PREFIX: &str = "CLASH_MARK:";
+
+/// Whether `--precise-memory` can read the kernel's peak RSS here.
+pub const PRECISE_MEMORY: bool = cfg!(unix);
+
+/// Source of monotonic time for measurements.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The real monotonic clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Starts child processes for measurement.
+pub trait ProcessSpawner {
+    /// Start `command`, beginning memory sampling immediately.
+    fn spawn(&self, command: &mut Command) -> io::Result<Box<dyn RunningProcess>>;
+}
+
+/// A child process started by a `ProcessSpawner`.
+pub trait RunningProcess {
+    /// Block until the process exits, returning how it ended.
+    fn wait(&mut self) -> io::Result<RunStatus>;
+
+    /// Stop sampling and report what was observed.
+    fn finish(self: Box<Self>) -> Observed;
+}
+
+/// What a process monitor saw, and what watching cost.
+pub struct Observed {
+    /// `None` if the process ended before the monitor sampled it
+    pub peak_memory_bytes: Option<u64>,
+    pub overhead: Option<MonitorOverhead>,
+    /// Output counts, when stdout and stderr were piped
+    pub output: Option<OutputCounts>,
+    /// Phases the command marked on a piped stderr, in order
+    pub phases: Vec<Phase>,
+    /// CPU the run used, when it could be measured
+    pub cpu: Option<CpuUsage>,
+}
+
+/// Which processes a run lasts for, for commands that start a worker and
+/// exit, as daemonizing tools do.
+#[derive(Debug, Clone, Default)]
+pub enum Follow {
+    /// Just the launched process
+    #[default]
+    Launcher,
+    /// The launched process and everything it starts, until the last one
+    /// exits; memory is their combined RSS (Linux only)
+    Forks,
+    /// The process whose PID the command writes to this file
+    Pidfile(PathBuf),
+}
+
+/// Spawns real processes and samples their memory every 30ms: from a memory
+/// cgroup of their own where one can be created, else their RSS from the
+/// process table.
+///
+/// Every run is watched from the shared event loop in `reactor`: the wait
+/// for the process, its time limit, sampling and the draining of its pipes
+/// are driven by the loop's timers and signals rather than threads per run.
+#[derive(Default)]
+pub struct SystemSpawner {
+    pub follow: Follow,
+    /// Kill the launched process once it has run this long
+    pub timeout: Option<Duration>,
+    /// Also take the kernel's own peak RSS of the launched process as it
+    /// exits, which catches spikes between samples
+    pub precise_memory: bool,
+}
+
+impl ProcessSpawner for SystemSpawner {
+    fn spawn(&self, command: &mut Command) -> io::Result<Box<dyn RunningProcess>> {
+        let in_flight = InFlight::start();
+        match &self.follow {
+            Follow::Launcher => {}
+            Follow::Forks => become_subreaper()?,
+            Follow::Pidfile(path) => {
+                // A stale file from an earlier run would name a dead worker
+                if path.exists() {
+                    std::fs::remove_file(path)?;
+                }
+            }
+        }
+        if self.precise_memory {
+            trace_exit(command);
+        }
+        let cgroup = MemoryCgroup::create();
+        if let Some(cgroup) = &cgroup {
+            cgroup.enter_on_spawn(command);
+        }
+        // Listening before the child exists, so its exit can't be missed
+        let exits = ChildExits::new()?;
+        // Made ready beforehand, so the run's clock starts soon after spawning
+        let table = system::process_table();
+        let (worker, worker_seen) = watch::channel(None);
+        let (stop_sampling, stop) = oneshot::channel();
+        let cpu_before = children_cpu_time();
+        let mut child = command.spawn()?;
+        let spawned = Instant::now();
+        let pid = child.id();
+        let stdout = child.stdout.take().map(|pipe| drain(pipe, None));
+        let stderr = child.stderr.take().map(|pipe| drain(pipe, Some(spawned)));
+        let cgroup = cgroup.filter(|c| c.contains(pid)).map(Arc::new);
+
+        // Sampling runs alongside the wait, so it never delays noticing
+        // the exit
+        let sampling = Sampler {
+            table,
+            pid,
+            worker: worker_seen,
+            forks: matches!(self.follow, Follow::Forks),
+            cgroup: cgroup.clone(),
+            peak: 0,
+            overhead: MonitorOverhead {
+                samples: 0,
+                busy: Duration::ZERO,
+            },
+            cpu: Vec::new(),
+        }
+        .start(stop);
+
+        Ok(Box::new(MonitoredChild {
+            child,
+            follow: self.follow.clone(),
+            deadline: self.timeout.map(|limit| spawned + limit),
+            timed_out: false,
+            trace: self.precise_memory.then(Trace::default),
+            reaped: false,
+            exits,
+            interrupt: reactor::interrupt(),
+            interrupted: false,
+            worker,
+            stop_sampling: Some(stop_sampling),
+            sampling,
+            cgroup,
+            cpu_before,
+            stdout,
+            stderr,
+            in_flight: Some(in_flight),
+        }))
+    }
+}
+
+/// What was read from one of a child's pipes.
+#[derive(Default)]
+struct Drained {
+    bytes: u64,
+    lines: u64,
+    phases: Vec<Phase>,
+    /// End of the previous phase, when marks are being read
+    last_mark: Option<Instant>,
+    /// The line read so far, while marks are being read
+    line: Vec<u8>,
+}
+
+impl Drained {
+    /// Count a chunk of the pipe, ending a phase at each mark line in it.
+    fn feed(&mut self, chunk: &[u8]) {
+        self.bytes += chunk.len() as u64;
+        self.lines += chunk.iter().filter(|&&b| b == b'\n').count() as u64;
+        if self.last_mark.is_none() {
+            return;
+        }
+        for piece in chunk.split_inclusive(|&b| b == b'\n') {
+            self.line.extend_from_slice(piece);
+            if piece.ends_with(b"\n") {
+                self.end_line();
+            }
+        }
+    }
+
+    fn end_line(&mut self) {
+        if let Some(name) = self.line.strip_prefix(MARK_PREFIX.as_bytes())
+            && let Some(last_mark) = self.last_mark
+        {
+            let now = Instant::now();
+            self.phases.push(Phase {
+                name: String::from_utf8_lossy(name).trim().to_string(),
+                duration: now - last_mark,
+            });
+            self.last_mark = Some(now);
+        }
+        self.line.clear();
+    }
+
+    /// The counts once the pipe is closed, a last unfinished line included.
+    fn done(mut self) -> Self {
+        if !self.line.is_empty() {
+            self.end_line();
+        }
+        self
+    }
+}
+
+/// Drain a pipe on the event loop, counting bytes and newlines. With
+/// `marks_from`, lines starting with `MARK_PREFIX` also end a phase, timed
+/// from the previous mark or else from `marks_from`.
+#[cfg(unix)]
+fn drain(pipe: impl Into<OwnedFd>, marks_from: Option<Instant>) -> JoinHandle<Drained> {
+    let pipe: OwnedFd = pipe.into();
+    reactor::runtime().spawn(async move {
+        let mut drained = Drained {
+            last_mark: marks_from,
+            ..Drained::default()
+        };
+        let Ok(pipe) = tokio::net::unix::pipe::Receiver::from_owned_fd(pipe) else {
+            return drained;
+        };
+        let mut buf = [0u8; 8192];
+        while pipe.readable().await.is_ok() {
+            match pipe.try_read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => drained.feed(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => break,
+            }
+        }
+        drained.done()
+    })
+}
+
+/// Pipes can't be polled here, so each is read on the loop's blocking pool.
+#[cfg(not(unix))]
+fn drain(mut pipe: impl Read + Send + 'static, marks_from: Option<Instant>) -> JoinHandle<Drained> {
+    reactor::runtime().spawn_blocking(move || {
+        let mut drained = Drained {
+            last_mark: marks_from,
+            ..Drained::default()
+        };
+        let mut buf = [0u8; 8192];
+        while let Ok(n) = pipe.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            drained.feed(&buf[..n]);
+        }
+        drained.done()
+    })
+}
+
+/// Wakes whenever a child of clash may have changed state: on SIGCHLD where
+/// there is one, else never, leaving it to `EXIT_POLL`.
+struct ChildExits {
+    #[cfg(unix)]
+    sigchld: tokio::signal::unix::Signal,
+}
+
+impl ChildExits {
+    #[cfg(unix)]
+    fn new() -> io::Result<Self> {
+        use tokio::signal::unix::{SignalKind, signal};
+        let _context = reactor::runtime().enter();
+        Ok(Self {
+            sigchld: signal(SignalKind::child())?,
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn new() -> io::Result<Self> {
+        Ok(Self {})
+    }
+
+    async fn next(&mut self) {
+        #[cfg(unix)]
+        if self.sigchld.recv().await.is_some() {
+            return;
+        }
+        std::future::pending().await
+    }
+}
+
+/// Samples one run's memory and CPU time, timing its own work so the
+/// observer effect can be reported.
+struct Sampler {
+    table: Box<dyn ProcessTable>,
+    pid: u32,
+    /// PID read from the `--pidfile`, once known
+    worker: watch::Receiver<Option<u32>>,
+    forks: bool,
+    cgroup: Option<Arc<MemoryCgroup>>,
+    peak: u64,
+    overhead: MonitorOverhead,
+    /// CPU time used so far, when each sample was taken
+    cpu: Vec<(Instant, Duration)>,
+}
+
+impl Sampler {
+    /// Sample every `SAMPLE_INTERVAL` on the event loop until `stop` is
+    /// sent or dropped, then once more.
+    fn start(mut self, mut stop: oneshot::Receiver<()>) -> JoinHandle<Sampler> {
+        reactor::runtime().spawn(async move {
+            let mut samples = tokio::time::interval(SAMPLE_INTERVAL);
+            samples.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    // Reading the process table blocks, so the loop's other
+                    // work, such as noticing exits, moves to another thread
+                    _ = samples.tick() => tokio::task::block_in_place(|| self.sample()),
+                    _ = &mut stop => break,
+                }
+            }
+            // One final check
+            tokio::task::block_in_place(|| self.sample());
+            self
+        })
+    }
+
+    fn sample(&mut self) {
+        let started = Instant::now();
+        let worker = *self.worker.borrow();
+        let pids: Vec<u32> = [Some(self.pid), worker].into_iter().flatten().collect();
+        let memory = if let Some(cgroup) = &self.cgroup {
+            cgroup.current().unwrap_or(0)
+        } else if self.forks {
+            self.table.tree_memory(std::process::id())
+        } else {
+            self.table.memory(&pids)
+        };
+        self.peak = self.peak.max(memory);
+
+        // CPU covers the whole process tree, which takes a scan of the
+        // process table, so it's sampled less often
+        if self.cpu.last().is_none_or(|&(at, _)| started - at >= CPU_WINDOW) {
+            let time = match self.cgroup.as_ref().and_then(|c| c.cpu_time()) {
+                Some(time) => Some(time),
+                None => self.table.tree_cpu_time(&pids),
+            };
+            if let Some(time) = time {
+                self.cpu.push((started, time));
+            }
+        }
+        self.overhead.samples += 1;
+        self.overhead.busy += started.elapsed();
+    }
+}
+
+struct MonitoredChild {
+    child: Child,
+    follow: Follow,
+    /// When the launched process gets killed, until it has exited
+    deadline: Option<Instant>,
+    timed_out: bool,
+    /// Where `--precise-memory` is up to with the launched process
+    trace: Option<Trace>,
+    /// Whether the launched process has been waited for
+    reaped: bool,
+    exits: ChildExits,
+    /// Changes once Ctrl-C has been pressed
+    interrupt: watch::Receiver<bool>,
+    interrupted: bool,
+    /// PID read from the `--pidfile`, shared with the sampler
+    worker: watch::Sender<Option<u32>>,
+    /// Sampling stops when this is sent or dropped
+    stop_sampling: Option<oneshot::Sender<()>>,
+    sampling: JoinHandle<Sampler>,
+    /// Cgroup the run's processes are accounted in, if one could be used
+    cgroup: Option<Arc<MemoryCgroup>>,
+    /// CPU time of clash's reaped children before the run started
+    cpu_before: Option<Duration>,
+    /// Readers of whichever of stdout and stderr were piped
+    stdout: Option<JoinHandle<Drained>>,
+    stderr: Option<JoinHandle<Drained>>,
+    in_flight: Option<InFlight>,
+}
+
+impl RunningProcess for MonitoredChild {
+    fn wait(&mut self) -> io::Result<RunStatus> {
+        match reactor::runtime().block_on(self.watch())? {
+            Some(status) => Ok(status),
+            None => {
+                // Stopped by Ctrl-C: remove the cgroup, whose last other
+                // holder is the sampler, then let the event loop exit
+                self.stop_sampling = None;
+                let _ = reactor::runtime().block_on(&mut self.sampling);
+                self.cgroup = None;
+                self.in_flight = None;
+                reactor::halt()
+            }
+        }
+    }
+
+    fn finish(mut self: Box<Self>) -> Observed {
+        self.stop_sampling = None;
+        let sampler = reactor::runtime().block_on(&mut self.sampling).ok();
+        let collect = |pipe: Option<JoinHandle<Drained>>| {
+            pipe.map(|p| reactor::runtime().block_on(p).unwrap_or_default())
+        };
+        let (stdout, stderr) = (collect(self.stdout.take()), collect(self.stderr.take()));
+        // Output is only counted when both streams were piped
+        let output = stdout.zip(stderr.as_ref()).map(|(stdout, stderr)| OutputCounts {
+            stdout_bytes: stdout.bytes,
+            stdout_lines: stdout.lines,
+            stderr_bytes: stderr.bytes,
+            stderr_lines: stderr.lines,
+        });
+        let mut peak = sampler.as_ref().map_or(0, |s| s.peak);
+        if let Some(trace_peak) = self.trace.as_ref().and_then(|t| t.peak) {
+            peak = peak.max(trace_peak);
+        }
+        // The kernel's high-water mark also catches peaks between samples
+        if let Some(cgroup_peak) = self.cgroup.as_ref().and_then(|c| c.peak()) {
+            peak = peak.max(cgroup_peak);
+        }
+        let (overhead, cpu) = match sampler {
+            Some(sampler) => (Some(sampler.overhead), sampler.cpu),
+            None => (None, Vec::new()),
+        };
+        Observed {
+            peak_memory_bytes: Some(peak).filter(|&bytes| bytes > 0),
+            overhead,
+            output,
+            phases: stderr.map(|s| s.phases).unwrap_or_default(),
+            cpu: cpu_usage(&cpu, self.cpu_before),
+        }
+    }
+}
+
+impl MonitoredChild {
+    /// Watch the run to its end: the launched process, then whatever else
+    /// `follow` says the run lasts for. `None` if Ctrl-C stopped it.
+    async fn watch(&mut self) -> io::Result<Option<RunStatus>> {
+        // Killed on Ctrl-C too, but always waited for
+        let Some(status) = self.until(EXIT_POLL, Self::reap).await? else {
+            return Ok(None);
+        };
+        let followed = match self.follow.clone() {
+            Follow::Launcher => Some(()),
+            Follow::Forks => self.until(EXIT_POLL, |_| reap_adopted()).await?,
+            Follow::Pidfile(path) => {
+                let give_up = Instant::now() + PIDFILE_TIMEOUT;
+                match self.until(FOLLOW_POLL, |_| read_pid(&path, give_up)).await? {
+                    Some(pid) => {
+                        self.worker.send_replace(Some(pid));
+                        let mut table = system::process_table();
+                        let exited = |_: &mut Self| Ok((!table.running(pid)).then_some(()));
+                        self.until(FOLLOW_POLL, exited).await?
+                    }
+                    None => None,
+                }
+            }
+        };
+        if followed.is_none() || self.interrupted {
+            return Ok(None);
+        }
+        Ok(Some(if self.timed_out { RunStatus::Timeout } else { classify(status) }))
+    }
+
+    /// Wait until `done` has an answer, asking it whenever a child may have
+    /// changed state and every `poll` besides. Meanwhile the launched
+    /// process is killed at its deadline, and on Ctrl-C with the rest of the
+    /// run, after which this gives up with `None` once that process has been
+    /// reaped and the run's cgroup is empty.
+    async fn until<T>(
+        &mut self,
+        poll: Duration,
+        mut done: impl FnMut(&mut Self) -> io::Result<Option<T>>,
+    ) -> io::Result<Option<T>> {
+        let mut recheck = tokio::time::interval(poll);
+        loop {
+            if let Some(answer) = done(self)? {
+                return Ok(Some(answer));
+            }
+            // Once nothing is left, so the cgroup can be removed
+            let emptied = self.cgroup.as_ref().is_none_or(|c| c.is_empty());
+            if self.interrupted && self.reaped && emptied {
+                return Ok(None);
+            }
+            let deadline = self.deadline.filter(|_| !self.reaped);
+            let due = tokio::time::Instant::from_std(deadline.unwrap_or_else(Instant::now));
+            tokio::select! {
+                _ = self.exits.next() => {}
+                _ = recheck.tick() => {}
+                _ = tokio::time::sleep_until(due), if deadline.is_some() => {
+                    self.deadline = None;
+                    // Already gone, so it finished in time after all
+                    self.timed_out = self.child.kill().is_ok();
+                }
+                _ = self.interrupt.changed(), if !self.interrupted => self.stop(),
+            }
+        }
+    }
+
+    /// Kill everything the run started, for Ctrl-C.
+    fn stop(&mut self) {
+        self.interrupted = true;
+        if !self.reaped {
+            self.child.kill().ok();
+        }
+        if let Some(worker) = *self.worker.borrow() {
+            system::process_table().kill(worker);
+        }
+        if let Some(cgroup) = &self.cgroup {
+            cgroup.kill_all();
+        }
+    }
+
+    /// Reap the launched process if it has exited, without blocking.
+    fn reap(&mut self) -> io::Result<Option<ExitStatus>> {
+        let status = match &mut self.trace {
+            Some(trace) => reap_traced(&mut self.child, trace)?,
+            None => self.child.try_wait()?,
+        };
+        self.reaped = status.is_some();
+        Ok(status)
+    }
+}
+
+/// How a process ended, from its exit status.
+fn classify(status: ExitStatus) -> RunStatus {
+    match status.code() {
+        Some(0) => RunStatus::Success,
+        Some(code) => RunStatus::Nonzero(code),
+        None => RunStatus::Signaled(signal(status)),
+    }
+}
+
+#[cfg(unix)]
+fn signal(status: ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn signal(_: ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Have the process `command` starts stop for clash right before it exits,
+/// while its memory can still be read: traced with ptrace on Linux.
+#[cfg(target_os = "linux")]
+fn trace_exit(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    let trace_me = || {
+        // SAFETY: PTRACE_TRACEME takes no pointers and only marks this
+        // (forked, not yet exec'd) process as traced by its parent
+        let traced = unsafe {
+            libc::ptrace(
+                libc::PTRACE_TRACEME,
+                0,
+                std::ptr::null_mut::<libc::c_void>(),
+                std::ptr::null_mut::<libc::c_void>(),
+            )
+        };
+        if traced != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    };
+    // SAFETY: the hook only makes an async-signal-safe system call
+    unsafe {
+        command.pre_exec(trace_me);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn trace_exit(_: &mut Command) {}
+
+/// Where a `--precise-memory` run is up to with its launched process.
+#[derive(Default)]
+struct Trace {
+    /// Past the first exec, with exit stops set up
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    started: bool,
+    /// The kernel's peak RSS for the process, once it's known
+    peak: Option<u64>,
+}
+
+/// Reap a child started after `trace_exit` if it has exited, without
+/// blocking, taking its peak RSS from `/proc` at the exit stop. Other stops
+/// are let go on the way.
+#[cfg(target_os = "linux")]
+fn reap_traced(child: &mut Child, trace: &mut Trace) -> io::Result<Option<ExitStatus>> {
+    use std::os::unix::process::ExitStatusExt;
+    let pid = child.id() as libc::pid_t;
+    let request = |request, data: usize| {
+        // SAFETY: the requests used here take no pointers; `data` carries
+        // options or a signal number
+        let (addr, data) = (std::ptr::null_mut::<libc::c_void>(), data as *mut libc::c_void);
+        unsafe { libc::ptrace(request, pid, addr, data) }
+    };
+    loop {
+        let mut status = 0;
+        // SAFETY: waitpid only writes the status of our own child
+        match unsafe { libc::waitpid(pid, &mut status, libc::WNOHANG) } {
+            0 => return Ok(None),
+            found if found < 0 => {
+                let err = io::Error::last_os_error();
+                if err.raw_os_error() == Some(libc::EINTR) {
+                    continue;
+                }
+                return Err(err);
+            }
+            _ => {}
+        }
+        if !libc::WIFSTOPPED(status) {
+            return Ok(Some(ExitStatus::from_raw(status)));
+        }
+        let signal = libc::WSTOPSIG(status);
+        let deliver = match status >> 16 {
+            libc::PTRACE_EVENT_EXIT => {
+                trace.peak = high_water_mark(pid as u32);
+                0
+            }
+            // Scripts exec their interpreter in turn
+            libc::PTRACE_EVENT_EXEC => 0,
+            _ if signal == libc::SIGTRAP && !trace.started => {
+                // Stopped at the first exec: from now on, stop right before
+                // exit, and report later execs as events, not signals
+                let options =
+                    libc::PTRACE_O_TRACEEXIT | libc::PTRACE_O_TRACEEXEC | libc::PTRACE_O_EXITKILL;
+                request(libc::PTRACE_SETOPTIONS, options as usize);
+                trace.started = true;
+                0
+            }
+            _ => signal,
+        };
+        request(libc::PTRACE_CONT, deliver as usize);
+    }
+}
+
+/// Peak RSS of a live process, as the kernel tracks it (VmHWM).
+#[cfg(target_os = "linux")]
+fn high_water_mark(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let line = status.lines().find_map(|l| l.strip_prefix("VmHWM:"))?;
+    let kilobytes: u64 = line.trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Reap `child` if it has exited, without blocking, taking the peak RSS
+/// the kernel reports for it in its resource usage.
+#[cfg(all(unix, not(target_os = "linux")))]
+fn reap_traced(child: &mut Child, trace: &mut Trace) -> io::Result<Option<ExitStatus>> {
+    use std::os::unix::process::ExitStatusExt;
+    let pid = child.id() as libc::pid_t;
+    let mut status = 0;
+    // SAFETY: an all-zero rusage is a valid value
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        // SAFETY: wait4 only writes the exit status and resource usage of
+        // our own child into them
+        match unsafe { libc::wait4(pid, &mut status, libc::WNOHANG, &mut usage) } {
+            0 => return Ok(None),
+            found if found < 0 => {
+                let err = io::Error::last_os_error();
+                if err.raw_os_error() != Some(libc::EINTR) {
+                    return Err(err);
+                }
+            }
+            _ => break,
+        }
+    }
+    // macOS reports bytes, the BSDs kilobytes
+    let unit = if cfg!(target_os = "macos") { 1 } else { 1024 };
+    trace.peak = Some(usage.ru_maxrss as u64 * unit).filter(|&bytes| bytes > 0);
+    Ok(Some(ExitStatus::from_raw(status)))
+}
+
+#[cfg(not(unix))]
+fn reap_traced(child: &mut Child, _: &mut Trace) -> io::Result<Option<ExitStatus>> {
+    child.try_wait()
+}
+
+/// Shortest stretch of a run over which busy cores are measured, so one
+/// sample landing on a scheduler tick doesn't read as a burst.
+const CPU_WINDOW: Duration = Duration::from_millis(100);
+
+/// A run's CPU time and busiest stretch, from the monitor's samples of
+/// CPU time used so far. The total comes from the kernel's accounting of
+/// reaped children where available, since samples stop short of the exit
+/// and can miss short-lived children.
+fn cpu_usage(samples: &[(Instant, Duration)], before: Option<Duration>) -> Option<CpuUsage> {
+    let sampled = samples.iter().map(|(_, time)| *time).max();
+    let reaped = children_cpu_time().zip(before).map(|(after, before)| after - before);
+    let time = sampled.max(reaped)?;
+    let peak_cores = samples
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &(start, used))| {
+            let &(end, later) = samples[i..].iter().find(|(t, _)| *t - start >= CPU_WINDOW)?;
+            Some(later.saturating_sub(used).as_secs_f64() / (end - start).as_secs_f64())
+        })
+        .reduce(f64::max)
+        // Clock-tick rounding can nudge a window past what the machine has
+        .map(machine_cores_at_most);
+    Some(CpuUsage { time, peak_cores })
+}
+
+/// `cores`, capped at the cores this machine has.
+pub fn machine_cores_at_most(cores: f64) -> f64 {
+    match std::thread::available_parallelism() {
+        Ok(n) => cores.min(n.get() as f64),
+        Err(_) => cores,
+    }
+}
+
+/// User and system time of every child clash has reaped so far.
+#[cfg(target_os = "linux")]
+pub fn children_cpu_time() -> Option<Duration> {
+    // SAFETY: getrusage only writes the struct it's given
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, &mut usage) } != 0 {
+        return None;
+    }
+    let time = |t: libc::timeval| {
+        Duration::from_secs(t.tv_sec as u64) + Duration::from_micros(t.tv_usec as u64)
+    };
+    Some(time(usage.ru_utime) + time(usage.ru_stime))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn children_cpu_time() -> Option<Duration> {
+    None
+}
+
+/// Adopt orphaned descendants, so a daemon's worker stays our child (and
+/// waitable) after the process that started it exits.
+#[cfg(target_os = "linux")]
+fn become_subreaper() -> io::Result<()> {
+    // SAFETY: PR_SET_CHILD_SUBREAPER only changes this process's attributes
+    if unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn become_subreaper() -> io::Result<()> {
+    Err(io::Error::other("--follow-forks is only supported on Linux"))
+}
+
+/// Reap whichever children, adopted ones included, have exited, without
+/// blocking. `Some` once none are left.
+#[cfg(target_os = "linux")]
+fn reap_adopted() -> io::Result<Option<()>> {
+    loop {
+        let mut status = 0;
+        // SAFETY: waitpid only writes the exit status into `status`
+        match unsafe { libc::waitpid(-1, &mut status, libc::WNOHANG) } {
+            0 => return Ok(None),
+            found if found < 0 => {
+                let err = io::Error::last_os_error();
+                match err.raw_os_error() {
+                    Some(libc::EINTR) => {}
+                    Some(libc::ECHILD) => return Ok(Some(())),
+                    _ => return Err(err),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reap_adopted() -> io::Result<Option<()>> {
+    Ok(Some(()))
+}
+
+/// The PID in `path`, once the command or its worker has written it there.
+fn read_pid(path: &Path, give_up: Instant) -> io::Result<Option<u32>> {
+    if let Ok(text) = std::fs::read_to_string(path)
+        && let Ok(pid) = text.trim().parse()
+    {
+        return Ok(Some(pid));
+    }
+    if Instant::now() >= give_up {
+        return Err(io::Error::other(format!(
+            "no PID written to '{}' within {}s",
+            path.display(),
+            PIDFILE_TIMEOUT.as_secs()
+        )));
+    }
+    Ok(None)
+}
+
+/// Files a command's runs read stdin from and write stdout to, in place of
+/// clash's own stdin and discarding the output. Other commands run by the
+/// same executor, such as a `--prepare`, aren't redirected.
+#[derive(Debug, Clone)]
+pub struct Redirect {
+    pub command: String,
+    pub stdin: Option<PathBuf>,
+    /// Replaced by each run, so it ends up holding the last run's output
+    pub stdout: Option<PathBuf>,
+}
+
+/// Measures local processes using an injectable clock and spawner, so tests
+/// can simulate durations, failures and memory values.
+pub struct Executor {
+    pub clock: Box<dyn Clock>,
+    pub spawner: Box<dyn ProcessSpawner>,
+    /// Pipe stdout/stderr so the spawner can count them
+    pub count_output: bool,
+    /// Pipe stderr so the spawner can read phase marks from it
+    pub phases: bool,
+    /// Where one command's runs read stdin from and write stdout to
+    pub redirect: Option<Redirect>,
+}
+
+impl Default for Executor {
+    fn default() -> Self {
+        Self {
+            clock: Box::new(SystemClock),
+            spawner: Box::<SystemSpawner>::default(),
+            count_output: false,
+            phases: false,
+            redirect: None,
+        }
+    }
+}
+
+impl Executor {
+    /// Spawn a prepared command, measuring time and peak memory.
+    pub fn measure(&self, mut command: Command, cmd: &str) -> Result<RunResult, String> {
+        if self.count_output {
+            command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        } else if self.phases {
+            command.stdout(Stdio::null()).stderr(Stdio::piped());
+        } else {
+            command.stdout(Stdio::null()).stderr(Stdio::null());
+        }
+        let redirect = self.redirect.as_ref().filter(|r| r.command == cmd);
+        if let Some(path) = redirect.and_then(|r| r.stdin.as_ref()) {
+            let file = File::open(path)
+                .map_err(|e| format!("Failed to open stdin file '{}': {}", path.display(), e))?;
+            command.stdin(file);
+        }
+        if let Some(path) = redirect.and_then(|r| r.stdout.as_ref()) {
+            let file = File::create(path)
+                .map_err(|e| format!("Failed to create stdout file '{}': {}", path.display(), e))?;
+            command.stdout(file);
+        }
+        let mut process = self
+            .spawner
+            .spawn(&mut command)
+            .map_err(|e| format!("Failed to start '{}': {}", cmd, e))?;
+
+        let start = self.clock.now();
+        let status = process
+            .wait()
+            .map_err(|e| format!("Failed to wait for '{}': {}", cmd, e))?;
+        let duration = self.clock.now().duration_since(start);
+
+        let observed = process.finish();
+        Ok(RunResult {
+            duration,
+            peak_memory_bytes: observed.peak_memory_bytes,
+            exit_code: match status {
+                RunStatus::Success => Some(0),
+                RunStatus::Nonzero(code) => Some(code),
+                RunStatus::Signaled(_) | RunStatus::Timeout => None,
+            },
+            signal: match status {
+                RunStatus::Signaled(signal) => signal,
+                _ => None,
+            },
+            timed_out: status == RunStatus::Timeout,
+            overhead: observed.overhead,
+            output: observed.output,
+            phases: observed.phases,
+            cpu: observed.cpu,
+            ..RunResult::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{FakeRun, fake_executor};
+
+    #[test]
+    fn measure_times_the_wait_on_the_injected_clock() {
+        let (exec, spawned) = fake_executor([FakeRun::ok(120, 4096)]);
+        let result = exec.measure(Command::new("app"), "app").unwrap();
+        assert_eq!(result.duration, Duration::from_millis(120));
+        assert_eq!(result.peak_memory_bytes, Some(4096));
+        assert_eq!(result.status(), RunStatus::Success);
+        assert_eq!(*spawned.borrow(), [["app"]]);
+    }
+
+    #[test]
+    fn measure_reports_how_the_process_ended() {
+        let killed = FakeRun::Exit {
+            duration: Duration::from_millis(7),
+            memory: 1024,
+            exit_code: None,
+        };
+        let (exec, _) = fake_executor([FakeRun::failed(5, 0, 3), killed]);
+
+        let failed = exec.measure(Command::new("app"), "app").unwrap();
+        assert_eq!(failed.exit_code, Some(3));
+        assert_eq!(failed.status(), RunStatus::Nonzero(3));
+        // Never sampled, rather than zero bytes
+        assert_eq!(failed.peak_memory_bytes, None);
+
+        let killed = exec.measure(Command::new("app"), "app").unwrap();
+        assert_eq!(killed.exit_code, None);
+        assert_eq!(killed.status(), RunStatus::Signaled(None));
+        assert_eq!(killed.duration, Duration::from_millis(7));
+    }
+
+    #[test]
+    fn measure_names_a_command_that_fails_to_start() {
+        let (exec, _) = fake_executor([FakeRun::SpawnError(io::ErrorKind::NotFound)]);
+        let err = exec.measure(Command::new("missing"), "missing --flag").unwrap_err();
+        assert!(err.starts_with("Failed to start 'missing --flag'"), "{}", err);
+    }
+
+    #[test]
+    fn marks_split_across_reads_still_end_phases() {
+        let mut drained = Drained {
+            last_mark: Some(Instant::now()),
+            ..Drained::default()
+        };
+        for chunk in ["log\nCLASH_MA", "RK:load\nCLASH_MARK:", "run"] {
+            drained.feed(chunk.as_bytes());
+        }
+        let drained = drained.done();
+        let names: Vec<_> = drained.phases.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["load", "run"]);
+        assert_eq!((drained.bytes, drained.lines), (34, 2));
+    }
+
+    #[test]
+    fn redirect_applies_only_to_its_command() {
+        let (mut exec, _) = fake_executor([FakeRun::ok(1, 0), FakeRun::ok(1, 0)]);
+        exec.redirect = Some(Redirect {
+            command: "app".to_string(),
+            stdin: Some(PathBuf::from("/nonexistent/clash-stdin")),
+            stdout: None,
+        });
+        let err = exec.measure(Command::new("app"), "app").unwrap_err();
+        assert!(err.contains("/nonexistent/clash-stdin"), "{}", err);
+        // Other commands, such as a --prepare, run as usual
+        assert!(exec.measure(Command::new("prep"), "prep").is_ok());
+    }
+}
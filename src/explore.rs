@@ -0,0 +1,233 @@
+use crate::export::Target;
+use crate::output::{self, ReportOptions};
+use crate::stats;
+use crate::types::{CommandStats, Export, RunResult, RunSettings, SessionInfo};
+use crate::units::{ByteUnit, TimeUnit};
+use chrono::Local;
+use owo_colors::OwoColorize;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+  list                   Commands with their run counts and times
+  runs N                 Every measured run of command N
+  slowest N [K]          Details of command N's K slowest runs (3 by default)
+  fastest N [K]          Details of command N's K fastest runs
+  rerun N [RUNS]         Measure command N again with RUNS runs (twice as many by default)
+  report                 Print the full report again, with any re-runs
+  export PATH [N...]     Export commands N... (every command by default) to PATH
+  quit                   Leave the explorer";
+
+/// Prompt for commands that drill into a finished session, for
+/// `--explore`, until the user quits or input ends. Commands are numbered
+/// from 1 as they were benchmarked. `rerun` measures command `i` again with
+/// the given number of runs, and its results replace the old ones.
+pub fn explore(
+    all_stats: &mut [CommandStats],
+    session: &SessionInfo,
+    opts: &ReportOptions,
+    rerun: &mut dyn FnMut(usize, usize) -> Result<CommandStats, String>,
+) {
+    println!("  {} Exploring the results; type \"help\" for commands", "[~]".cyan());
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        print!("  {} ", "explore>".bold());
+        io::stdout().flush().ok();
+        let Some(Ok(line)) = lines.next() else {
+            println!();
+            return;
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words[..] {
+            [] => Ok(()),
+            ["quit" | "q" | "exit"] => return,
+            ["help" | "?"] => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            ["list"] => {
+                print_list(all_stats);
+                Ok(())
+            }
+            ["runs", n] => command(all_stats, n).map(print_runs),
+            ["slowest" | "fastest", n, ref rest @ ..] if rest.len() <= 1 => {
+                let count = match rest {
+                    [k] => k.parse().map_err(|_| format!("'{}' is not a number of runs", k)),
+                    _ => Ok(3),
+                };
+                count.and_then(|count| {
+                    let s = command(all_stats, n)?;
+                    print_extremes(s, count, words[0] == "slowest");
+                    Ok(())
+                })
+            }
+            ["rerun", n, ref rest @ ..] if rest.len() <= 1 => {
+                rerun_command(all_stats, &session.settings, n, rest.first().copied(), rerun)
+            }
+            ["report"] => {
+                output::print_report(all_stats, opts);
+                Ok(())
+            }
+            ["export", path, ref picked @ ..] => export(all_stats, session, path, picked),
+            _ => Err(format!("Unknown command '{}'; type \"help\" for commands", line.trim())),
+        };
+        if let Err(e) = result {
+            println!("  {} {}", "Error:".red().bold(), e);
+        }
+    }
+}
+
+/// Position of command `n`, numbered from 1.
+fn index(all_stats: &[CommandStats], n: &str) -> Result<usize, String> {
+    match n.parse::<usize>() {
+        Ok(n) if (1..=all_stats.len()).contains(&n) => Ok(n - 1),
+        _ => Err(format!("No command {}; they're numbered 1 to {}", n, all_stats.len())),
+    }
+}
+
+fn command<'a>(all_stats: &'a [CommandStats], n: &str) -> Result<&'a CommandStats, String> {
+    Ok(&all_stats[index(all_stats, n)?])
+}
+
+fn print_list(all_stats: &[CommandStats]) {
+    let unit = TimeUnit::common(all_stats.iter().map(|s| s.time_mean));
+    for (i, s) in all_stats.iter().enumerate() {
+        println!(
+            "  [{}] {}  {} runs, mean {} ± {}",
+            (i + 1).to_string().cyan(),
+            s.label.bold(),
+            s.runs,
+            unit.format(s.time_mean),
+            unit.format(s.time_std_dev)
+        );
+    }
+}
+
+fn print_runs(s: &CommandStats) {
+    let units = Units::new(&s.all_runs);
+    for (k, r) in s.all_runs.iter().enumerate() {
+        println!(
+            "  #{:<4} {:>10} {:>10}  {}",
+            s.run_numbers.get(k).map_or(k, |&n| n) + 1,
+            units.time.format(r.duration),
+            r.peak_memory_bytes.map_or("—".to_string(), |b| units.bytes.format(b)),
+            r.status()
+        );
+    }
+}
+
+/// The `count` slowest or fastest of `s`'s runs, with everything recorded
+/// about each.
+fn print_extremes(s: &CommandStats, count: usize, slowest: bool) {
+    let mut order: Vec<usize> = (0..s.all_runs.len()).collect();
+    order.sort_by_key(|&k| s.all_runs[k].duration);
+    if slowest {
+        order.reverse();
+    }
+    let units = Units::new(&s.all_runs);
+    for &k in order.iter().take(count) {
+        let r = &s.all_runs[k];
+        let mut details = vec![r.status().to_string()];
+        if let Some(b) = r.peak_memory_bytes {
+            details.push(format!("peak {}", units.bytes.format(b)));
+        }
+        if let Some(cpu) = r.cpu {
+            details.push(format!("CPU {}", units.time.format(cpu.time)));
+        }
+        if let Some(prepare) = r.prepare {
+            details.push(format!("prepare {}", units.time.format(prepare)));
+        }
+        if let Some(mhz) = r.cpu_mhz {
+            details.push(format!("{} MHz", mhz));
+        }
+        if let Some(at) = r.started_at {
+            details.push(format!("started {}", at.with_timezone(&Local).format("%H:%M:%S%.3f")));
+        }
+        println!(
+            "  #{:<4} {:>10}  {}",
+            s.run_numbers.get(k).map_or(k, |&n| n) + 1,
+            units.time.format(r.duration),
+            details.join(", ").dimmed()
+        );
+    }
+}
+
+fn rerun_command(
+    all_stats: &mut [CommandStats],
+    settings: &RunSettings,
+    n: &str,
+    runs: Option<&str>,
+    rerun: &mut dyn FnMut(usize, usize) -> Result<CommandStats, String>,
+) -> Result<(), String> {
+    let i = index(all_stats, n)?;
+    let runs = match runs {
+        Some(runs) => match runs.parse() {
+            Ok(runs) if runs > 0 => runs,
+            _ => return Err(format!("'{}' is not a number of runs", runs)),
+        },
+        None => all_stats[i].runs.max(1) * 2,
+    };
+    let mut fresh = rerun(i, runs)?;
+    let old = &all_stats[i];
+    // What was measured once for the session, or names the command, stays
+    fresh.label = old.label.clone();
+    fresh.baseline = old.baseline.clone();
+    fresh.build = old.build.clone();
+    fresh.syscalls = old.syscalls.clone();
+    fresh.provenance = old.provenance.clone();
+    fresh.matrix = old.matrix.clone();
+    fresh.group = old.group.clone();
+    let unit = TimeUnit::common([old.time_mean, fresh.time_mean]);
+    println!(
+        "  {} {}: mean {} ± {} over {} runs, was {} ± {} over {}",
+        "→".cyan(),
+        fresh.label.bold(),
+        unit.format(fresh.time_mean),
+        unit.format(fresh.time_std_dev),
+        fresh.runs,
+        unit.format(old.time_mean),
+        unit.format(old.time_std_dev),
+        old.runs
+    );
+    all_stats[i] = fresh;
+    stats::collect_warnings(all_stats, settings);
+    Ok(())
+}
+
+fn export(
+    all_stats: &[CommandStats],
+    session: &SessionInfo,
+    path: &str,
+    picked: &[&str],
+) -> Result<(), String> {
+    let target: Target = path.parse()?;
+    let subset: Vec<CommandStats> = if picked.is_empty() {
+        all_stats.to_vec()
+    } else {
+        let picked = picked.iter().map(|n| index(all_stats, n).map(|i| all_stats[i].clone()));
+        picked.collect::<Result<_, _>>()?
+    };
+    let export = Export::new(session.clone(), &subset);
+    target.write(&export, &subset)?;
+    output::status(format!(
+        "  {} {} command(s) exported to {}",
+        "✓".green(),
+        subset.len(),
+        target.path
+    ));
+    Ok(())
+}
+
+/// Time and memory units shared by a command's runs.
+struct Units {
+    time: TimeUnit,
+    bytes: ByteUnit,
+}
+
+impl Units {
+    fn new(runs: &[RunResult]) -> Self {
+        Self {
+            time: TimeUnit::common(runs.iter().map(|r| r.duration)),
+            bytes: ByteUnit::common(runs.iter().filter_map(|r| r.peak_memory_bytes)),
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use crate::output::summary_text;
+use crate::types::{CommandStats, Export};
+use crate::units::{ByteUnit, TimeUnit};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+/// Renders a finished session in one file format.
+pub trait Exporter {
+    fn render(&self, export: &Export, all_stats: &[CommandStats]) -> Result<String, String>;
+}
+
+/// The full export: session, per-run values and aggregates.
+pub struct Json;
+
+/// One row per command with its time (in seconds) and memory (in bytes).
+pub struct Csv;
+
+/// The results table and summary, as in the GitHub step summary.
+pub struct Markdown;
+
+/// A standalone page with the results table and summary.
+pub struct Html;
+
+/// One test case per command, failed when any of its runs failed, for CI
+/// dashboards that read JUnit XML.
+pub struct Junit;
+
+impl Exporter for Json {
+    fn render(&self, export: &Export, _: &[CommandStats]) -> Result<String, String> {
+        serde_json::to_string_pretty(export)
+            .map_err(|e| format!("Failed to serialize results: {}", e))
+    }
+}
+
+impl Exporter for Csv {
+    fn render(&self, _: &Export, all_stats: &[CommandStats]) -> Result<String, String> {
+        let mut csv = String::from(
+            "command,label,runs,failed_runs,time_mean,time_center,time_min,time_max,\
+             time_std_dev,peak_memory_bytes\n",
+        );
+        for s in all_stats {
+            let fields = [
+                csv_field(&s.command),
+                csv_field(&s.label),
+                s.runs.to_string(),
+                s.failed_runs.to_string(),
+                s.time_mean.as_secs_f64().to_string(),
+                s.time_center.as_secs_f64().to_string(),
+                s.time_min.as_secs_f64().to_string(),
+                s.time_max.as_secs_f64().to_string(),
+                s.time_std_dev.as_secs_f64().to_string(),
+                s.peak_memory_bytes.map_or(String::new(), |b| b.to_string()),
+            ];
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        Ok(csv)
+    }
+}
+
+impl Exporter for Markdown {
+    fn render(&self, _: &Export, all_stats: &[CommandStats]) -> Result<String, String> {
+        let mut md = String::from("## ⚔️ clash results\n\n");
+        md.push_str(&markdown_table(all_stats));
+        md.push_str(&format!("\n**Summary:** {}\n", summary_text(all_stats)));
+        Ok(md)
+    }
+}
+
+impl Exporter for Html {
+    fn render(&self, export: &Export, all_stats: &[CommandStats]) -> Result<String, String> {
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>clash results</title>\n<style>\n\
+             body { font-family: sans-serif; margin: 2em; }\n\
+             table { border-collapse: collapse; }\n\
+             th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; }\n\
+             td.num { text-align: right; }\n\
+             </style>\n</head>\n<body>\n<h1>⚔️ clash results</h1>\n",
+        );
+        let host = export.session.host.as_deref();
+        html.push_str(&format!(
+            "<p>{}{}</p>\n",
+            export.session.started_at.format("%Y-%m-%d %H:%M UTC"),
+            host.map_or(String::new(), |h| format!(" on {}", escape_html(h)))
+        ));
+        html.push_str(
+            "<table>\n<tr><th>Command</th><th>Mean</th><th>Min</th><th>Max</th>\
+             <th>Std Dev</th><th>Peak RSS</th><th>Failed</th></tr>\n",
+        );
+        for row in rows(all_stats) {
+            html.push_str(&format!(
+                "<tr><td><code>{}</code></td>{}</tr>\n",
+                escape_html(&row[0]),
+                row[1..]
+                    .iter()
+                    .map(|cell| format!("<td class=\"num\">{}</td>", escape_html(cell)))
+                    .collect::<String>()
+            ));
+        }
+        html.push_str(&format!(
+            "</table>\n<p><strong>Summary:</strong> {}</p>\n</body>\n</html>\n",
+            escape_html(&summary_text(all_stats))
+        ));
+        Ok(html)
+    }
+}
+
+impl Exporter for Junit {
+    fn render(&self, export: &Export, all_stats: &[CommandStats]) -> Result<String, String> {
+        let failures = all_stats.iter().filter(|s| s.failed_runs > 0).count();
+        let total: f64 = all_stats.iter().map(|s| s.wall_time.as_secs_f64()).sum();
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuite name=\"clash\" tests=\"{}\" failures=\"{}\" time=\"{:.6}\" \
+             timestamp=\"{}\">\n",
+            all_stats.len(),
+            failures,
+            total,
+            export.session.started_at.format("%Y-%m-%dT%H:%M:%S")
+        ));
+        for s in all_stats {
+            xml.push_str(&format!(
+                "  <testcase classname=\"clash\" name=\"{}\" time=\"{:.6}\">\n",
+                escape_html(&s.label),
+                s.time_center.as_secs_f64()
+            ));
+            if s.failed_runs > 0 {
+                xml.push_str(&format!(
+                    "    <failure message=\"{}/{} runs failed\"/>\n",
+                    s.failed_runs, s.runs
+                ));
+            }
+            if !s.warnings.is_empty() {
+                let warnings: Vec<String> = s.warnings.iter().map(|w| w.to_string()).collect();
+                xml.push_str(&format!(
+                    "    <system-out>{}</system-out>\n",
+                    escape_html(&warnings.join("\n"))
+                ));
+            }
+            xml.push_str("  </testcase>\n");
+        }
+        xml.push_str("</testsuite>\n");
+        Ok(xml)
+    }
+}
+
+/// A file format `--export` can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+    Markdown,
+    Html,
+    Junit,
+}
+
+impl Format {
+    const NAMES: [(&str, Format); 6] = [
+        ("json", Format::Json),
+        ("csv", Format::Csv),
+        ("markdown", Format::Markdown),
+        ("md", Format::Markdown),
+        ("html", Format::Html),
+        ("junit", Format::Junit),
+    ];
+
+    pub fn exporter(self) -> &'static dyn Exporter {
+        match self {
+            Format::Json => &Json,
+            Format::Csv => &Csv,
+            Format::Markdown => &Markdown,
+            Format::Html => &Html,
+            Format::Junit => &Junit,
+        }
+    }
+
+    /// The format a bare path implies by its extension, JSON unless it names
+    /// another one.
+    fn from_extension(path: &str) -> Self {
+        let extension = std::path::Path::new(path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("csv") => Format::Csv,
+            Some("md" | "markdown") => Format::Markdown,
+            Some("html" | "htm") => Format::Html,
+            Some("xml") => Format::Junit,
+            _ => Format::Json,
+        }
+    }
+}
+
+/// One `--export` destination: `FORMAT:PATH`, or a bare `PATH` whose
+/// extension picks the format.
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub format: Format,
+    pub path: String,
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((name, path)) = s.split_once(':')
+            && let Some(&(_, format)) = Format::NAMES.iter().find(|(n, _)| *n == name)
+        {
+            if path.is_empty() {
+                return Err(format!("'{}' needs a path after the colon", s));
+            }
+            return Ok(Target {
+                format,
+                path: path.to_string(),
+            });
+        }
+        Ok(Target {
+            format: Format::from_extension(s),
+            path: s.to_string(),
+        })
+    }
+}
+
+impl Target {
+    /// Render the session in this target's format and write it out.
+    pub fn write(&self, export: &Export, all_stats: &[CommandStats]) -> Result<(), String> {
+        let content = self.format.exporter().render(export, all_stats)?;
+        std::fs::write(&self.path, content)
+            .map_err(|e| format!("Failed to write {}: {}", self.path, e))
+    }
+
+    /// Whether both targets write the same file, however their paths are
+    /// spelled: `out.json`, `./out.json` and `dir/../out.json` all match.
+    pub fn same_file(&self, other: &Target) -> bool {
+        resolved(&self.path) == resolved(&other.path)
+    }
+}
+
+/// `path` made absolute, through symlinks where its directory exists, and
+/// with `.` and `..` resolved.
+fn resolved(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if let (Ok(dir), Some(name)) = (dir.canonicalize(), path.file_name()) {
+        return dir.join(name);
+    }
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut resolved = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+/// The results table in GitHub-flavored markdown.
+pub fn markdown_table(all_stats: &[CommandStats]) -> String {
+    let mut md = String::from("| Command | Mean | Min | Max | Std Dev | Peak RSS | Failed |\n");
+    md.push_str("|---|--:|--:|--:|--:|--:|--:|\n");
+    for row in rows(all_stats) {
+        md.push_str(&format!(
+            "| `{}` | {} |\n",
+            row[0].replace('|', "\\|"),
+            row[1..].join(" | ")
+        ));
+    }
+    md
+}
+
+/// Each command's table cells, in shared units: command, mean, min, max,
+/// standard deviation, peak memory and failed runs.
+fn rows(all_stats: &[CommandStats]) -> Vec<[String; 7]> {
+    let time_unit = TimeUnit::common(all_stats.iter().map(|s| s.time_mean));
+    let byte_unit = ByteUnit::common(all_stats.iter().filter_map(|s| s.peak_memory_bytes));
+    all_stats
+        .iter()
+        .map(|s| {
+            [
+                s.command.clone(),
+                time_unit.format(s.time_mean),
+                time_unit.format(s.time_min),
+                time_unit.format(s.time_max),
+                format!("±{}", time_unit.format(s.time_std_dev)),
+                s.peak_memory_bytes.map_or("—".to_string(), |b| byte_unit.format(b)),
+                format!("{}/{}", s.failed_runs, s.runs),
+            ]
+        })
+        .collect()
+}
+
+/// Quote a CSV field if it holds a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escape text for HTML and XML content and attributes.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
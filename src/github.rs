@@ -0,0 +1,60 @@
+use crate::export::markdown_table;
+use crate::output::summary_text;
+use crate::types::CommandStats;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+/// Severity of a GitHub Actions workflow command.
+#[derive(Debug, Clone, Copy)]
+pub enum Level {
+    Notice,
+    Warning,
+    Error,
+}
+
+/// Print a `::notice`/`::warning`/`::error` workflow command.
+pub fn annotate(level: Level, message: &str) {
+    let level = match level {
+        Level::Notice => "notice",
+        Level::Warning => "warning",
+        Level::Error => "error",
+    };
+    println!("::{} title=clash::{}", level, escape(message));
+}
+
+/// Annotate the finished session and, when running inside GitHub Actions,
+/// append a markdown report to `$GITHUB_STEP_SUMMARY`.
+pub fn report(all_stats: &[CommandStats]) {
+    for s in all_stats {
+        for warning in &s.warnings {
+            annotate(Level::Warning, &format!("{}: {}", s.label, warning));
+        }
+    }
+    annotate(Level::Notice, &summary_text(all_stats));
+
+    if let Ok(path) = std::env::var("GITHUB_STEP_SUMMARY")
+        && let Err(e) = write_step_summary(&path, all_stats)
+    {
+        annotate(
+            Level::Warning,
+            &format!("Failed to write step summary {}: {}", path, e),
+        );
+    }
+}
+
+fn write_step_summary(path: &str, all_stats: &[CommandStats]) -> std::io::Result<()> {
+    let mut md = String::from("## ⚔️ clash results\n\n");
+    md.push_str(&markdown_table(all_stats));
+    md.push_str(&format!("\n**Summary:** {}\n", summary_text(all_stats)));
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(md.as_bytes())
+}
+
+/// Escape data for a workflow command, per the Actions toolkit rules.
+fn escape(message: &str) -> String {
+    message
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
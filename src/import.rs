@@ -0,0 +1,121 @@
+use crate::record::RecordedCommand;
+use crate::types::RunResult;
+use serde_json::Value;
+use std::time::Duration;
+
+/// Every command's runs from a results file: a clash `--export` (or
+/// merge), or a hyperfine `--export-json`.
+pub fn load(path: &str) -> Result<Vec<RecordedCommand>, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+    let json: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("'{}' is not a results file: {}", path, e))?;
+    let Some(results) = json.get("results").and_then(Value::as_array) else {
+        return Err(format!("'{}' is not a results file: no results", path));
+    };
+    results
+        .iter()
+        .map(|result| {
+            if result.get("metrics").is_some() {
+                from_clash(result)
+            } else if result.get("times").is_some() {
+                from_hyperfine(result)
+            } else {
+                Err("expected a clash export or hyperfine JSON".to_string())
+            }
+            .map_err(|e| format!("'{}': {}", path, e))
+        })
+        .collect()
+}
+
+/// A result from a clash export, rebuilt from its per-run metric values.
+fn from_clash(result: &Value) -> Result<RecordedCommand, String> {
+    let command = text(result, "command")?;
+    let values = |metric: &str| -> Vec<Option<f64>> {
+        result
+            .pointer(&format!("/metrics/{}/values", metric))
+            .and_then(Value::as_array)
+            .map(|values| values.iter().map(Value::as_f64).collect())
+            .unwrap_or_default()
+    };
+    let times = values("time");
+    let memory = values("memory");
+    let memory_unrecorded = result.pointer("/metrics/memory").is_none();
+    let details = result.get("run_details").and_then(Value::as_array);
+    let results = times
+        .iter()
+        .enumerate()
+        .map(|(i, time)| {
+            let detail = details.and_then(|d| d.get(i));
+            let field = |name: &str| detail.and_then(|d| d.get(name)).cloned();
+            let status = detail.and_then(|d| d.get("status")).and_then(Value::as_str);
+            RunResult {
+                duration: Duration::from_secs_f64(time.unwrap_or(0.0)),
+                peak_memory_bytes: memory.get(i).copied().flatten().map(|bytes| bytes as u64),
+                exit_code: field("exit_code")
+                    .and_then(|v| v.as_i64())
+                    .map(|code| code as i32),
+                signal: status
+                    .and_then(|s| s.strip_prefix("signal "))
+                    .and_then(|s| s.parse().ok()),
+                timed_out: status == Some("timeout"),
+                expected_exit: status == Some("success")
+                    && field("exit_code").and_then(|v| v.as_i64()).is_some_and(|code| code != 0),
+                started_at: field("started_at").and_then(|v| serde_json::from_value(v).ok()),
+                cpu_mhz: field("cpu_mhz").and_then(|v| v.as_u64()),
+                ..RunResult::default()
+            }
+        })
+        .collect();
+    Ok(RecordedCommand {
+        command,
+        label: result.get("label").and_then(Value::as_str).map(str::to_string),
+        location: result.get("host").and_then(Value::as_str).map(str::to_string),
+        provenance: result
+            .get("provenance")
+            .cloned()
+            .and_then(|v| serde_json::from_value(v).ok()),
+        results,
+        memory_unrecorded,
+    })
+}
+
+/// A result from hyperfine's JSON export: `times` in seconds, with
+/// `exit_codes` and `memory_usage_byte` alongside in newer versions.
+fn from_hyperfine(result: &Value) -> Result<RecordedCommand, String> {
+    let command = text(result, "command")?;
+    let array = |field: &str| result.get(field).and_then(Value::as_array);
+    let times = array("times").ok_or_else(|| format!("'{}' has no times", command))?;
+    let results = times
+        .iter()
+        .enumerate()
+        .map(|(i, time)| {
+            let at = |field: &str| array(field).and_then(|values| values.get(i));
+            RunResult {
+                duration: Duration::from_secs_f64(time.as_f64().unwrap_or(0.0).max(0.0)),
+                peak_memory_bytes: at("memory_usage_byte").and_then(Value::as_u64),
+                exit_code: match at("exit_codes") {
+                    Some(code) => code.as_i64().map(|code| code as i32),
+                    None => Some(0),
+                },
+                ..RunResult::default()
+            }
+        })
+        .collect();
+    Ok(RecordedCommand {
+        command,
+        label: None,
+        location: None,
+        provenance: None,
+        results,
+        memory_unrecorded: array("memory_usage_byte").is_none(),
+    })
+}
+
+fn text(result: &Value, field: &str) -> Result<String, String> {
+    result
+        .get(field)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| format!("a result is missing '{}'", field))
+}
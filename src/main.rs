@@ -1,92 +1,1690 @@
+mod argfile;
+mod attach;
+mod auto;
+mod bundle;
+mod cargo;
+mod cgroup;
+mod cli;
+mod container;
+mod db;
+mod cores;
+mod events;
+mod exec;
+mod explore;
+mod export;
+mod github;
+mod import;
+mod memlimit;
+mod merge;
+mod metric;
+mod notify;
+mod numa;
 mod output;
+mod prefetch;
+mod prepare;
+mod progress;
+mod provenance;
+mod reactor;
+mod record;
+mod remote;
 mod runner;
+mod serve;
 mod stats;
+mod suite;
+mod syscalls;
+mod system;
+mod table;
+mod theme;
+mod thermal;
+mod timecmd;
+#[cfg(test)]
+mod testutil;
 mod types;
+mod units;
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
+use cli::{Cli, QueryKind, ReportArgs, RunArgs, Subcommands};
 use owo_colors::OwoColorize;
+use runner::Runner;
+use std::collections::HashMap;
 use std::process;
+use std::time::Duration;
+use types::CommandStats;
 
-#[derive(Parser, Debug)]
-#[command(
-    name = "clash",
-    version,
-    about = "⚔️  clash — benchmark comparator\n\nRun commands head-to-head and compare their performance.\nMeasures execution time AND peak memory usage.",
-    long_about = None
-)]
-struct Cli {
-    /// Commands to benchmark (at least 2)
-    #[arg(required = true, num_args = 2..)]
-    commands: Vec<String>,
-
-    /// Number of benchmark runs per command
-    #[arg(short, long, default_value_t = 5)]
-    runs: usize,
+/// One command to benchmark and how to run it.
+struct Job {
+    command: String,
+    /// Display label, overriding the one derived from the command
+    label: Option<String>,
+    runner: Box<dyn Runner>,
+    /// Untimed command run before each run
+    prepare: Option<String>,
+    /// Command run once before the benchmark and timed on its own
+    build: Option<String>,
+    /// Position in a `--matrix`, for the heatmap
+    matrix: Option<types::MatrixCell>,
+    /// `--group` the command was listed under, for a sectioned report
+    group: Option<String>,
+    /// `--subtract` baseline whose mean is taken off each run
+    baseline: Option<String>,
+    /// How to launch the command for counting its system calls, with
+    /// `--syscalls`
+    trace: Option<runner::LocalOptions>,
+    plan: runner::RunPlan,
+}
 
-    /// Number of warmup runs before benchmarking
-    #[arg(short, long, default_value_t = 0)]
-    warmup: usize,
+fn main() {
+    let args = argfile::expand(std::env::args()).unwrap_or_else(|e| fail(e));
+    let matches = Cli::command().get_matches_from(args);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-    /// Export results to JSON file
-    #[arg(short, long)]
-    export: Option<String>,
+    match &cli.subcommand {
+        Some(Subcommands::Replay {
+            path,
+            speed,
+            report,
+        }) => replay(path, *speed, report),
+        Some(Subcommands::Report { paths, report }) => report_files(paths, report),
+        Some(Subcommands::Diff {
+            before,
+            after,
+            theme,
+        }) => diff(before, after, theme),
+        Some(Subcommands::CompareRuns {
+            before,
+            after,
+            command,
+            theme,
+        }) => compare_runs(before, after, command.as_deref(), theme),
+        Some(Subcommands::Rerun {
+            path,
+            baseline,
+            only_regressed,
+            threshold,
+            output,
+            run,
+            report,
+        }) => rerun(
+            path,
+            baseline.as_deref(),
+            *only_regressed,
+            *threshold,
+            output,
+            run,
+            report,
+        ),
+        Some(Subcommands::Merge {
+            paths,
+            output,
+            theme,
+            layout,
+        }) => merge(paths, output.as_deref(), theme, *layout),
+        Some(Subcommands::Attach {
+            pids,
+            duration,
+            interval,
+            report,
+        }) => attach(pids, *duration, *interval, report),
+        Some(Subcommands::Cargo {
+            target,
+            example,
+            against,
+            args,
+            run,
+            report,
+        }) => cargo_compare(target, *example, against, args, run, report),
+        Some(Subcommands::EnvCompare {
+            command,
+            sets,
+            run,
+            report,
+        }) => env_compare(command, sets, run, report),
+        Some(Subcommands::Suite {
+            path,
+            filter,
+            skip,
+            run,
+            report,
+        }) => run_suite(path, filter, skip, run, report),
+        Some(Subcommands::Query { db, query }) => run_query(db, query),
+        Some(Subcommands::Verify { bundle, key }) => verify(bundle, key.as_deref()),
+        Some(Subcommands::Trend {
+            label,
+            db,
+            last,
+            threshold,
+            theme,
+        }) => trend(label, db, *last, *threshold, theme),
+        Some(Subcommands::Memlimit {
+            commands,
+            shell,
+            tolerance,
+            theme,
+        }) => memlimit(commands, shell.as_deref(), *tolerance, theme),
+        None => benchmark(&cli, &cli::command_groups(&matches)),
+    }
 }
 
-fn main() {
-    let cli = Cli::parse();
+/// Benchmark the commands given on the command line, each listed under
+/// the `--group` in `groups` at its position, if any.
+fn benchmark(cli: &Cli, groups: &[Option<String>]) {
+    let plan = cli.run.plan().unwrap_or_else(|e| fail(e));
+
+    // An A/A test benchmarks the same command as two contenders
+    let commands = match &cli.aa {
+        Some(cmd) => vec![cmd.clone(), cmd.clone()],
+        None => {
+            let mut commands = cli.commands.clone();
+            for pattern in &cli.each {
+                let expanded = expand_each(pattern, cli.each_args.as_deref());
+                commands.extend(expanded.unwrap_or_else(|e| fail(e)));
+            }
+            if cli.stdin {
+                commands.extend(read_stdin_commands().unwrap_or_else(|e| fail(e)));
+            }
+            commands
+        }
+    };
+    let mut labels: Vec<Option<String>> = match &cli.aa {
+        Some(_) => vec![Some("A".to_string()), Some("A'".to_string())],
+        None => vec![None; commands.len()],
+    };
+    // Expanded commands keep the source file as their label
+    let commands: Vec<String> = if cli.auto {
+        let expanded = commands.iter().map(|cmd| auto::expand(cmd, cli.report.quiet()));
+        let expanded: Vec<String> = expanded.collect::<Result<_, _>>().unwrap_or_else(|e| fail(e));
+        for ((label, cmd), original) in labels.iter_mut().zip(&expanded).zip(&commands) {
+            if cmd != original && label.is_none() {
+                *label = Some(original.clone());
+            }
+        }
+        expanded
+    } else {
+        commands
+    };
+
+    let remotes = per_command(&cli.remote, commands.len(), "--remote");
+    let containers = per_command(&cli.container, commands.len(), "--container");
+    let prepares = per_command(&cli.prepare, commands.len(), "--prepare");
+    let builds = per_command(&cli.build, commands.len(), "--build");
+    let (remotes, containers, prepares, builds) = match (remotes, containers, prepares, builds) {
+        (Ok(r), Ok(c), Ok(p), Ok(b)) => (r, c, p, b),
+        (Err(e), _, _, _) | (_, Err(e), _, _) | (_, _, Err(e), _) | (_, _, _, Err(e)) => fail(e),
+    };
+    let expected_exits: Vec<Vec<i32>> =
+        per_command(&cli.expect_exit, commands.len(), "--expect-exit")
+            .and_then(|codes| codes.iter().map(|c| parse_exit_codes(c.as_deref())).collect())
+            .unwrap_or_else(|e| fail(e));
+
+    // With --matrix, every command once per value; per-command options
+    // still count the commands as given
+    if let Some(scaling) = &cli.scaling {
+        scaling.thread_counts().unwrap_or_else(|e| fail(e));
+    }
+    let parameter_list: Option<types::Matrix> = match &cli.parameter_list[..] {
+        [var, values] => Some(
+            format!("{}={}", var, values)
+                .parse()
+                .unwrap_or_else(|e| fail(format!("-L: {}", e))),
+        ),
+        _ => None,
+    };
+    let matrix = cli.matrix.as_ref().or(cli.scaling.as_ref()).or(parameter_list.as_ref());
+    // A soak watches each command against its own start, so one will do, as
+    // will one that a matrix turns into several
+    let contenders = commands.len() * matrix.map_or(1, |m| m.values.len());
+    if commands.is_empty() || (contenders < 2 && plan.soak.is_none()) {
+        fail("At least 2 commands are required, or one with --soak or 2 or more --matrix values");
+    }
+    let mut variants: Vec<(usize, String, Option<types::MatrixCell>)> = Vec::new();
+    for (i, cmd) in commands.iter().enumerate() {
+        let Some(matrix) = matrix else {
+            variants.push((i, cmd.clone(), None));
+            continue;
+        };
+        let row = labels[i]
+            .clone()
+            .unwrap_or_else(|| stats::make_label(cmd, cli.report.label_width));
+        for value in &matrix.values {
+            let variant = matrix.substitute(cmd, value).unwrap_or_else(|e| fail(e));
+            let cell = types::MatrixCell {
+                row: row.clone(),
+                var: matrix.var.clone(),
+                value: value.clone(),
+                scaling: cli.scaling.is_some(),
+            };
+            variants.push((i, variant, Some(cell)));
+        }
+    }
+
+    let local_options = local_options(&cli.run, commands.len()).unwrap_or_else(|e| fail(e));
+    let mut jobs: Vec<Job> = variants
+        .into_iter()
+        .map(|(i, cmd, cell)| {
+            let runner = runner::select(
+                remotes[i].as_deref(),
+                containers[i].as_deref(),
+                &local_options[i],
+            )
+            .unwrap_or_else(|e| fail(format!("{} ('{}')", e, cmd)));
+            let runner: Box<dyn Runner> = if runner::is_pseudo(&cmd) {
+                Box::new(runner::PseudoRunner::new(runner))
+            } else {
+                runner
+            };
+            let runner: Box<dyn Runner> = if cli.stage.is_empty() {
+                runner
+            } else {
+                Box::new(runner::StagedRunner::new(runner, &cmd, &cli.stage, &expected_exits[i]))
+            };
+            // Staged runs already judge each stage's exit code
+            let runner: Box<dyn Runner> = if expected_exits[i].is_empty() || !cli.stage.is_empty() {
+                runner
+            } else {
+                Box::new(runner::ExpectedExitRunner::new(runner, &cmd, &expected_exits[i]))
+            };
+            // A lone command's variants differ only in the value
+            let label = match &cell {
+                Some(cell) => match &labels[i] {
+                    Some(l) => Some(format!("{} [{}={}]", l, cell.var, cell.value)),
+                    None if commands.len() == 1 => {
+                        Some(format!("{}={}", cell.var, cell.value))
+                    }
+                    None => None,
+                },
+                None => labels[i].clone(),
+            };
+            let trace = cli.syscalls.then(|| {
+                if remotes[i].is_some() || containers[i].is_some() {
+                    fail(format!(
+                        "--syscalls only applies to commands run on this machine ('{}')",
+                        cmd
+                    ));
+                }
+                local_options[i].clone()
+            });
+            Job {
+                baseline: matching_baseline(&cmd, &cli.subtract),
+                trace,
+                command: cmd,
+                label,
+                runner,
+                prepare: prepares[i].clone(),
+                build: builds[i].clone(),
+                matrix: cell,
+                group: groups.get(i).cloned().flatten(),
+                plan,
+            }
+        })
+        .collect();
+    if cli.prepare_once {
+        prepare_once(&mut jobs, &cli.prepare_input, cli.report.quiet());
+    }
+
+    let (all_stats, _) = run_session(&jobs, &cli.run.settings(), &cli.report);
+
+    if cli.aa.is_some() && !cli.report.quiet() {
+        output::print_aa_verdict(&all_stats[0], &all_stats[1]);
+    }
+
+    if let Some(path) = &cli.record {
+        let locations: Vec<Option<String>> = jobs.iter().map(|j| j.runner.location()).collect();
+        match record::save(path, &all_stats, &locations) {
+            Ok(()) => output::status(format!("  {} Session recorded to {}", "✓".green(), path)),
+            Err(e) => eprintln!("  {} {}", "Error:".red().bold(), e),
+        }
+    }
+
+    if cli.verdict_exit
+        && let Some(time) = stats::verdicts(&all_stats).get(metric::TIME.name)
+    {
+        process::exit(time.verdict.exit_code());
+    }
+}
+
+/// Benchmark one command under each `--set` of environment variables,
+/// labeling contenders by the variables that differ between sets.
+fn env_compare(command: &str, sets: &[String], run: &RunArgs, report: &ReportArgs) {
+    let plan = run.plan().unwrap_or_else(|e| fail(e));
+    if sets.len() < 2 {
+        fail("env-compare needs at least 2 --set values");
+    }
+    let envs: Vec<Vec<(String, String)>> = sets
+        .iter()
+        .map(|set| parse_env_set(set).unwrap_or_else(|e| fail(e)))
+        .collect();
+
+    let local_options = local_options(run, envs.len()).unwrap_or_else(|e| fail(e));
+    let jobs: Vec<Job> = envs
+        .iter()
+        .zip(local_options)
+        .map(|(env, options)| Job {
+            command: command.to_string(),
+            label: Some(env_delta(env, &envs)),
+            runner: runner::local(runner::LocalOptions {
+                env: env.clone(),
+                ..options
+            }),
+            prepare: None,
+            build: None,
+            matrix: None,
+            group: None,
+            baseline: None,
+            trace: None,
+            plan,
+        })
+        .collect();
+
+    run_session(&jobs, &run.settings(), report);
+}
+
+/// Benchmark the commands of a `clash.toml` suite that `filter` and `skip`
+/// select.
+fn run_suite(path: &str, filter: &[String], skip: &[String], run: &RunArgs, report: &ReportArgs) {
+    let plan = run.plan().unwrap_or_else(|e| fail(e));
+    let suite = suite::Suite::load(path).unwrap_or_else(|e| fail(e));
+    let benches = suite.select(filter, skip);
+    if benches.len() < 2 {
+        fail(format!(
+            "At least 2 commands are required; {} of the {} in {} selected",
+            benches.len(),
+            suite.bench.len(),
+            path
+        ));
+    }
+
+    let local_options = local_options(run, benches.len()).unwrap_or_else(|e| fail(e));
+    let jobs: Vec<Job> = benches
+        .into_iter()
+        .zip(local_options)
+        .map(|(bench, options)| {
+            let redirect = (bench.stdin.is_some() || bench.stdout.is_some()).then(|| {
+                exec::Redirect {
+                    command: bench.command.clone(),
+                    stdin: bench.stdin.clone(),
+                    stdout: bench.stdout.clone(),
+                }
+            });
+            let mut runner = runner::local(runner::LocalOptions { redirect, ..options });
+            if runner::is_pseudo(&bench.command) {
+                runner = Box::new(runner::PseudoRunner::new(runner));
+            }
+            if !bench.expect_exit.is_empty() {
+                runner = Box::new(runner::ExpectedExitRunner::new(
+                    runner,
+                    &bench.command,
+                    &bench.expect_exit,
+                ));
+            }
+            Job {
+                command: bench.command.clone(),
+                label: bench.name.clone(),
+                runner,
+                prepare: bench.prepare.clone(),
+                build: None,
+                matrix: None,
+                group: None,
+                baseline: None,
+                trace: None,
+                plan,
+            }
+        })
+        .collect();
+
+    run_session(&jobs, &run.settings(), report);
+}
+
+/// Build a Cargo target in the working tree and against another ref or
+/// build, then benchmark the two binaries with the same arguments.
+fn cargo_compare(
+    target: &str,
+    example: bool,
+    against: &str,
+    args: &[String],
+    run: &RunArgs,
+    report: &ReportArgs,
+) {
+    let plan = run.plan().unwrap_or_else(|e| fail(e));
+    let target = cargo::Target {
+        name: target,
+        example,
+    };
+    let (current, reference) = cargo::build_pair(&target, against).unwrap_or_else(|e| fail(e));
+
+    let local_options = local_options(run, 2).unwrap_or_else(|e| fail(e));
+    let jobs: Vec<Job> = [current, reference]
+        .into_iter()
+        .zip(local_options)
+        .map(|(build, options)| {
+            let mut command = remote::shell_quote(&build.path.to_string_lossy());
+            for arg in args {
+                command.push(' ');
+                command.push_str(&remote::shell_quote(arg));
+            }
+            Job {
+                command,
+                label: Some(build.label),
+                runner: runner::local(options),
+                prepare: None,
+                build: None,
+                matrix: None,
+                group: None,
+                baseline: None,
+                trace: None,
+                plan,
+            }
+        })
+        .collect();
+
+    run_session(&jobs, &run.settings(), report);
+}
+
+/// Parse "NAME=VALUE NAME=VALUE" into variable assignments.
+fn parse_env_set(set: &str) -> Result<Vec<(String, String)>, String> {
+    set.split_whitespace()
+        .map(|assignment| match assignment.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+            _ => Err(format!(
+                "Invalid --set assignment '{}' (expected NAME=VALUE)",
+                assignment
+            )),
+        })
+        .collect()
+}
+
+/// The assignments of `env` that aren't shared by every set, so labels show
+/// only what changes between contenders.
+fn env_delta(env: &[(String, String)], all: &[Vec<(String, String)>]) -> String {
+    let delta: Vec<String> = env
+        .iter()
+        .filter(|var| !all.iter().all(|other| other.contains(var)))
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    if delta.is_empty() {
+        "(same env)".to_string()
+    } else {
+        delta.join(" ")
+    }
+}
+
+/// Report on imported results as if they had just been measured. With
+/// several files, labels name the file each command came from.
+fn report_files(paths: &[String], report: &ReportArgs) {
+    let mut jobs = Vec::new();
+    for path in paths {
+        let loaded = import::load(path).unwrap_or_else(|e| fail(e));
+        // A command without runs has nothing to report, as in `diff`
+        for recorded in loaded.into_iter().filter(|r| !r.results.is_empty()) {
+            let label = (paths.len() > 1).then(|| {
+                let stem = std::path::Path::new(path)
+                    .file_stem()
+                    .map_or(path.clone(), |s| s.to_string_lossy().into_owned());
+                format!("{} [{}]", recorded.command, stem)
+            });
+            jobs.push(Job {
+                command: recorded.command.clone(),
+                label,
+                prepare: None,
+                build: None,
+                matrix: None,
+                group: None,
+                baseline: None,
+                trace: None,
+                plan: runner::RunPlan::fixed(recorded.results.len(), 0),
+                runner: Box::new(record::ReplayRunner::new(recorded, f64::INFINITY)),
+            });
+        }
+    }
+    run_session(&jobs, &types::RunSettings::default(), report);
+}
+
+/// Show how each command's results changed between two files.
+fn diff(before: &str, after: &str, theme: &str) {
+    let theme = theme::Theme::from_arg(theme).unwrap_or_else(|e| fail(e));
+    let load = |path: &str| -> Vec<CommandStats> {
+        import::load(path)
+            .unwrap_or_else(|e| fail(e))
+            .iter()
+            .filter(|r| !r.results.is_empty())
+            .map(|r| {
+                let settings = types::RunSettings::default();
+                let mut s = stats::compute_stats(&r.command, &r.results, &settings);
+                s.provenance = r.provenance.clone();
+                s
+            })
+            .collect()
+    };
+    output::print_diff(&load(before), &load(after), &theme);
+}
 
-    if cli.runs == 0 {
-        eprintln!("{} --runs must be at least 1", "Error:".red().bold());
-        process::exit(1);
+/// Benchmark the commands of a results file again, only those that
+/// regressed against `baseline` with `only_regressed`, and write the file's
+/// results with the fresh ones in their place to `output`.
+fn rerun(
+    path: &str,
+    baseline: Option<&str>,
+    only_regressed: bool,
+    threshold: f64,
+    output: &str,
+    run: &RunArgs,
+    report: &ReportArgs,
+) {
+    let recorded = import::load(path).unwrap_or_else(|e| fail(e));
+    let target: export::Target = output.parse().unwrap_or_else(|e| fail(e));
+    let base = baseline.map(|b| import::load(b).unwrap_or_else(|e| fail(e)));
+    let durations = |results: &[types::RunResult]| -> Vec<f64> {
+        results.iter().map(|r| r.duration.as_secs_f64()).collect()
+    };
+    // Whether a command ran slower than in the baseline, when it's there
+    let regressed = |command: &str, results: &[types::RunResult]| {
+        let base = base.as_ref()?.iter().find(|b| b.command == command)?;
+        let (before, after) = (durations(&base.results), durations(results));
+        Some(stats::regressed(&before, &after, threshold / 100.0))
+    };
+    let picked: Vec<usize> = (0..recorded.len())
+        .filter(|&i| {
+            let r = &recorded[i];
+            !only_regressed || regressed(&r.command, &r.results) == Some(true)
+        })
+        .collect();
+    if picked.is_empty() {
+        if !report.quiet() {
+            println!(
+                "  {} No command in {} regressed against {}",
+                "✓".green(),
+                path,
+                baseline.unwrap_or_default()
+            );
+        }
+        return;
     }
 
+    let plan = run.plan().unwrap_or_else(|e| fail(e));
+    let local_options = local_options(run, picked.len()).unwrap_or_else(|e| fail(e));
+    let jobs: Vec<Job> = picked
+        .iter()
+        .zip(local_options)
+        .map(|(&i, options)| {
+            let r = &recorded[i];
+            // Twice the runs the file has, to confirm or dismiss the change
+            let mut plan = plan;
+            if run.runs.is_none() {
+                plan.min_runs = plan.min_runs.max(r.results.len() * 2);
+                plan.max_runs = plan.max_runs.max(plan.min_runs);
+            }
+            Job {
+                command: r.command.clone(),
+                label: r.label.clone(),
+                runner: runner::local(options),
+                prepare: None,
+                build: None,
+                matrix: None,
+                group: None,
+                baseline: None,
+                trace: None,
+                plan,
+            }
+        })
+        .collect();
+    let (fresh, session) = run_session(&jobs, &run.settings(), report);
+
+    if !report.quiet() {
+        for s in &fresh {
+            match regressed(&s.command, &s.all_runs) {
+                Some(true) => {
+                    println!("  {} {} is still slower than the baseline", "⚠".yellow(), s.label)
+                }
+                Some(false) => println!("  {} {} no longer regresses", "✓".green(), s.label),
+                None => {}
+            }
+        }
+    }
+
+    let mut fresh = fresh.into_iter();
+    let merged: Vec<CommandStats> = recorded
+        .iter()
+        .enumerate()
+        .filter_map(|(i, r)| {
+            if picked.contains(&i)
+                && let Some(s) = fresh.next()
+            {
+                return Some(s);
+            }
+            // A command without runs has no stats to carry over
+            if r.results.is_empty() {
+                return None;
+            }
+            let mut s = stats::compute_stats(&r.command, &r.results, &session.settings);
+            s.label = r.label.clone().unwrap_or(s.label);
+            s.provenance = r.provenance.clone();
+            Some(s)
+        })
+        .collect();
+    let export = types::Export::new(session, &merged);
+    target.write(&export, &merged).unwrap_or_else(|e| fail(e));
+    output::status(format!(
+        "  {} {} with {} command(s) re-run written to {}",
+        "✓".green(),
+        path,
+        picked.len(),
+        target.path
+    ));
+}
+
+/// Plot the run-time distributions of one command from two files.
+fn compare_runs(before: &str, after: &str, command: Option<&str>, theme: &str) {
+    let theme = theme::Theme::from_arg(theme).unwrap_or_else(|e| fail(e));
+    let load = |path: &str| -> record::RecordedCommand {
+        let mut recorded = import::load(path).unwrap_or_else(|e| fail(e));
+        let found = match command {
+            Some(wanted) => recorded.iter().position(|r| {
+                r.label.as_deref() == Some(wanted)
+                    || r.command.trim() == wanted.trim()
+                    || stats::make_label(&r.command, stats::LABEL_WIDTH) == wanted
+            }),
+            None if recorded.len() == 1 => Some(0),
+            None => fail(format!(
+                "{} has {} commands; pick one with --command LABEL",
+                path,
+                recorded.len()
+            )),
+        };
+        let Some(index) = found else {
+            fail(format!("{} has no command {}", path, command.unwrap_or_default()))
+        };
+        let recorded = recorded.swap_remove(index);
+        if recorded.results.is_empty() {
+            fail(format!("{} has no runs of {}", path, recorded.command))
+        }
+        recorded
+    };
+    let (old, new) = (load(before), load(after));
+    let label = old
+        .label
+        .clone()
+        .unwrap_or_else(|| stats::make_label(&old.command, stats::LABEL_WIDTH));
+    let durations = |r: &record::RecordedCommand| -> Vec<Duration> {
+        r.results.iter().map(|r| r.duration).collect()
+    };
+    output::print_run_distributions(&label, &durations(&old), &durations(&new), &theme);
+}
+
+/// Combine exports and show each command's results per host.
+fn merge(paths: &[String], output: Option<&str>, theme: &str, layout: output::Layout) {
+    let theme = theme::Theme::from_arg(theme).unwrap_or_else(|e| fail(e));
+    let merged = merge::merge(paths).unwrap_or_else(|e| fail(e));
+    let results = merged.host_results().unwrap_or_else(|e| fail(e));
+    output::print_host_matrix(&results, &theme, layout);
+
+    if let Some(path) = output {
+        let json = serde_json::to_string_pretty(&merged).unwrap_or_default();
+        match std::fs::write(path, json) {
+            Ok(()) => output::status(format!(
+                "  {} Merged results written to {}",
+                "✓".green(),
+                path
+            )),
+            Err(e) => eprintln!("  {} Failed to write merged results: {}", "Error:".red().bold(), e),
+        }
+    }
+}
+
+/// Read stored sessions back from a --db database.
+fn run_query(path: &str, query: &QueryKind) {
+    if !std::path::Path::new(path).is_file() {
+        fail(format!("No database at '{}'; store sessions with --db first", path));
+    }
+    let db = db::Database::open(path).unwrap_or_else(|e| fail(e));
+    match query {
+        QueryKind::Slowest { limit } => {
+            let rows = db.slowest(*limit).unwrap_or_else(|e| fail(e));
+            output::print_stored_commands("Slowest commands", &rows);
+        }
+        QueryKind::Trend { label, limit } => {
+            let rows = db.trend(label, *limit).unwrap_or_else(|e| fail(e));
+            if rows.is_empty() {
+                fail(format!("No stored results for '{}'", label));
+            }
+            output::print_stored_commands(&format!("Trend for {}", label), &rows);
+        }
+        QueryKind::Sessions { host, all_hosts, limit } => {
+            let host = match host {
+                Some(host) => Some(host.clone()),
+                None if *all_hosts => None,
+                None => system::host_name(),
+            };
+            let rows = db.sessions(host.as_deref(), *limit).unwrap_or_else(|e| fail(e));
+            output::print_stored_sessions(&rows);
+        }
+    }
+}
+
+/// Check a signed bundle and show what it vouches for; exits with an error
+/// if it was altered or signed by another key than `trusted_key`.
+fn verify(path: &str, trusted_key: Option<&str>) {
+    let bundle = bundle::Bundle::load(path).unwrap_or_else(|e| fail(e));
+    let trusted = trusted_key.map(|path| {
+        std::fs::read_to_string(path)
+            .unwrap_or_else(|e| fail(format!("Failed to read key '{}': {}", path, e)))
+    });
+    let key = bundle.verify(trusted.as_deref()).unwrap_or_else(|e| fail(e));
+    let signed = bundle.payload().unwrap_or_else(|e| fail(e));
+    output::print_bundle(&signed, &bundle::hex(key.as_bytes()));
+}
+
+/// Chart a label's results across stored sessions, marking each session
+/// that regressed from the one before.
+fn trend(label: &str, path: &str, last: usize, threshold: f64, theme: &str) {
+    let theme = theme::Theme::from_arg(theme).unwrap_or_else(|e| fail(e));
+    if !std::path::Path::new(path).is_file() {
+        fail(format!("No database at '{}'; store sessions with --db first", path));
+    }
+    let db = db::Database::open(path).unwrap_or_else(|e| fail(e));
+    let rows = db.trend(label, last).unwrap_or_else(|e| fail(e));
+    if rows.len() < 2 {
+        fail(format!("'{}' needs at least 2 stored sessions for a trend", label));
+    }
+    let durations: Vec<Vec<f64>> = rows
+        .iter()
+        .map(|r| db.durations(r.id))
+        .collect::<Result<_, _>>()
+        .unwrap_or_else(|e| fail(e));
+
+    let threshold = threshold / 100.0;
+    let time_regressed: Vec<bool> = std::iter::once(false)
+        .chain(durations.windows(2).map(|w| stats::regressed(&w[0], &w[1], threshold)))
+        .collect();
+    // Memory compares each measured session with the last one measured before it
+    let mut previous = None;
+    let memory_regressed: Vec<bool> = rows
+        .iter()
+        .map(|r| {
+            let Some(bytes) = r.peak_memory_bytes else {
+                return false;
+            };
+            let grew = previous.is_some_and(|p| bytes as f64 > p as f64 * (1.0 + threshold));
+            previous = Some(bytes);
+            grew
+        })
+        .collect();
+    output::print_trend(label, &rows, &time_regressed, &memory_regressed, &theme);
+}
+
+/// Bisect the smallest memory cap each command still succeeds under.
+fn memlimit(commands: &[String], shell: Option<&str>, tolerance: f64, theme: &str) {
+    let theme = theme::Theme::from_arg(theme).unwrap_or_else(|e| fail(e));
+    if !(tolerance > 0.0 && tolerance < 100.0) {
+        fail("--tolerance must be between 0 and 100");
+    }
+    let mechanism = memlimit::Mechanism::detect().unwrap_or_else(|e| fail(e));
+    let max = system::total_memory();
+
     println!();
-    println!("  {}  clash — benchmark comparator", "⚔️".bold());
+    println!("  {}  clash — memory limit bisection", "⚔️".bold());
+    println!("  {}", format!("Capping with a {}", mechanism.describe()).dimmed());
     println!();
+    let labels: Vec<String> =
+        commands.iter().map(|cmd| stats::make_label(cmd, stats::LABEL_WIDTH)).collect();
+    let mut limits = Vec::new();
+    for (i, (cmd, label)) in commands.iter().zip(stats::unique_labels(&labels)).enumerate() {
+        let parts = match shell {
+            Some(shell) => vec![shell.to_string(), "-c".to_string(), cmd.clone()],
+            None => runner::shell_split(cmd).unwrap_or_else(|e| fail(e)),
+        };
+        let (program, args) = parts.split_first().unwrap_or_else(|| fail("Empty command"));
+        println!("  [{}] Bisecting: {}", (i + 1).to_string().cyan(), cmd.bold());
+        let works = |limit| memlimit::succeeds(program, args, limit, mechanism);
+        if !works(None).unwrap_or_else(|e| fail(e)) {
+            fail(format!("'{}' fails even without a memory cap", cmd));
+        }
+        let tried = |limit, ok: bool| {
+            let mark = if ok { "✓".green().to_string() } else { "✗".red().to_string() };
+            println!("      {} {}", mark, units::ByteUnit::for_bytes(limit).format(limit));
+        };
+        let limit = memlimit::bisect(|limit| works(Some(limit)), tolerance / 100.0, max, tried)
+            .unwrap_or_else(|e| fail(e));
+        limits.push((label, limit));
+    }
+    output::print_memlimits(&limits, &theme);
+}
 
-    let mut all_stats = Vec::new();
-
-    for (i, cmd) in cli.commands.iter().enumerate() {
+/// Watch running processes side by side and report their samples as runs.
+fn attach(pids: &[u32], duration: Duration, interval: Duration, report: &ReportArgs) {
+    if !report.quiet() {
         println!(
-            "  [{}] Benchmarking: {}",
-            (i + 1).to_string().cyan(),
-            cmd.bold()
+            "  {} Sampling {} process{} for {:?}; each {:?} interval is one run, timed by the \
+             CPU time used in it",
+            "[~]".cyan(),
+            pids.len(),
+            if pids.len() == 1 { "" } else { "es" },
+            duration,
+            interval
         );
+    }
+    let attached = attach::sample(pids, duration, interval).unwrap_or_else(|e| fail(e));
 
-        match runner::run_benchmark(cmd, cli.runs, cli.warmup) {
-            Ok(results) => {
-                let cmd_stats = stats::compute_stats(cmd, &results);
-
-                if cmd_stats.failed_runs > 0 {
-                    eprintln!(
-                        "  {} {}/{} runs exited with non-zero status",
-                        "Warning:".yellow().bold(),
-                        cmd_stats.failed_runs,
-                        cmd_stats.runs
-                    );
+    // The samples are already taken: replay them without pausing
+    let jobs: Vec<Job> = attached
+        .into_iter()
+        .map(|a| Job {
+            command: a.samples.command.clone(),
+            label: None,
+            prepare: None,
+            build: None,
+            matrix: None,
+            group: None,
+            baseline: None,
+            trace: None,
+            plan: runner::RunPlan::fixed(a.samples.results.len(), 0),
+            runner: Box::new(record::ReplayRunner::new(a.samples, f64::INFINITY)),
+        })
+        .collect();
+
+    run_session(&jobs, &types::RunSettings::default(), report);
+}
+
+/// Re-render a recorded session without running any commands.
+fn replay(path: &str, speed: f64, report: &ReportArgs) {
+    if speed.is_nan() || speed <= 0.0 {
+        fail("--speed must be greater than 0");
+    }
+    let session = record::load(path).unwrap_or_else(|e| fail(e));
+
+    let jobs: Vec<Job> = session
+        .commands
+        .into_iter()
+        .map(|recorded| Job {
+            command: recorded.command.clone(),
+            label: None,
+            prepare: None,
+            build: None,
+            matrix: None,
+            group: None,
+            baseline: None,
+            trace: None,
+            plan: runner::RunPlan::fixed(recorded.results.len(), 0),
+            runner: Box::new(record::ReplayRunner::new(recorded, speed)),
+        })
+        .collect();
+
+    run_session(&jobs, &types::RunSettings::default(), report);
+}
+
+/// Run every job in order and report the results, returning them with the
+/// session they were measured in.
+fn run_session(
+    jobs: &[Job],
+    settings: &types::RunSettings,
+    report: &ReportArgs,
+) -> (Vec<CommandStats>, types::SessionInfo) {
+    for (i, target) in report.export.iter().enumerate() {
+        if report.export[..i].iter().any(|t| t.same_file(target)) {
+            fail(format!("--export names {} more than once", target.path));
+        }
+    }
+    units::configure(units::NumberFormat {
+        locale: report.locale,
+        byte_units: report.byte_units,
+        precision: report.precision,
+    });
+    // How runs are analyzed is chosen alongside the report
+    let settings = &types::RunSettings {
+        estimator: report.estimator,
+        status_policy: report.status_policy(),
+        leak_threshold: Some(report.leak_threshold),
+        ..settings.clone()
+    };
+    let theme = match report.style {
+        Some(style) if !style.colored() => {
+            output::disable_colors();
+            theme::Theme::monochrome()
+        }
+        _ => theme::Theme::from_arg(&report.theme).unwrap_or_else(|e| fail(e)),
+    };
+    let opts = output::ReportOptions {
+        theme,
+        weights: report.weight.clone(),
+        layout: report.layout,
+        verbose: report.verbose,
+        tradeoff: report.tradeoff,
+        limits: report.highlight_over.clone().unwrap_or_default(),
+        characterize: report.characterize,
+    };
+    let mut sink = match &report.events {
+        Some(_) => match events::EventSink::open(report.events_output.as_deref()) {
+            Ok(sink) => Some(sink),
+            Err(e) => fail(format!("Failed to open events output: {}", e)),
+        },
+        None => None,
+    };
+    let quiet = report.quiet();
+    if quiet {
+        output::silence();
+    }
+    // Imported results are reported straight away, with no runs to watch
+    let imported = jobs
+        .iter()
+        .all(|job| job.runner.replay_speed() == Some(f64::INFINITY));
+
+    if !quiet && !imported {
+        println!();
+        let title = format!("  {}  clash — benchmark comparator", "⚔️".bold());
+        println!("{}", output::styled(&title));
+        println!();
+    }
+    let dashboard = report.serve.map(|port| {
+        let address = report.serve_address;
+        let dashboard = serve::Dashboard::start(address, port).unwrap_or_else(|e| fail(e));
+        if !quiet {
+            let url = dashboard_url(address, port);
+            let line = format!("  {} Dashboard at {}", "[~]".cyan(), url);
+            println!("{}", output::styled(&line));
+        }
+        dashboard
+    });
+
+    let session_start = chrono::Utc::now();
+    let session_clock = std::time::Instant::now();
+    // Replayed results took their time in another session, not this one
+    let replayed = jobs.iter().all(|job| job.runner.replay_speed().is_some());
+    let budget_end = settings
+        .session_budget
+        .map(|budget| std::time::Instant::now() + budget);
+    let mut builds = run_builds(jobs, quiet);
+    let mut syscalls = count_syscalls(jobs, quiet);
+    // After the builds, which may have replaced the programs
+    let mut provenance: Vec<Option<types::Provenance>> = jobs
+        .iter()
+        .map(|job| {
+            let local = job.runner.host_files() && !runner::is_pseudo(&job.command);
+            local.then(|| provenance::resolve(&job.command)).flatten()
+        })
+        .collect();
+    let baselines = measure_baselines(jobs, settings, quiet, report.progress_format());
+    let frequency = thermal::FrequencyMonitor::start();
+    // Each job's results, one per round
+    let mut per_job: Vec<Vec<CommandStats>> = vec![Vec::new(); jobs.len()];
+    let planned: Vec<usize> = jobs
+        .iter()
+        .map(|j| (j.plan.min_runs + j.plan.warmup) * settings.rounds)
+        .collect();
+    let mut progress = if imported {
+        progress::SessionProgress::hidden(&planned)
+    } else {
+        progress::SessionProgress::new(&planned, report.progress_format())
+    };
+
+    let labels: Vec<String> = jobs
+        .iter()
+        .map(|j| {
+            j.label
+                .clone()
+                .unwrap_or_else(|| stats::make_label(&j.command, report.label_width))
+        })
+        .collect();
+    let labels = stats::unique_labels(&labels);
+
+    let orders = run_orders(jobs.len(), settings.rounds, settings.seed);
+    if let Some(seed) = settings.seed
+        && !quiet
+    {
+        progress.println(format!("  {} Shuffled with --seed {}", "[~]".cyan(), seed));
+    }
+    for (round, order) in orders.iter().enumerate() {
+        if settings.rounds > 1 && !quiet {
+            progress.println(format!(
+                "  {} Round {}/{}",
+                "[~]".cyan(),
+                round + 1,
+                settings.rounds
+            ));
+        }
+        if settings.seed.is_some() && report.verbose && !quiet {
+            let order: Vec<String> = order.iter().map(|i| (i + 1).to_string()).collect();
+            progress.println(format!("  {} Order: {}", "[~]".cyan(), order.join(", ")));
+        }
+        for (position, &i) in order.iter().enumerate() {
+            let job = &jobs[i];
+            let cmd = job.command.as_str();
+            let mut location = job
+                .runner
+                .location()
+                .map(|l| format!(" ({})", l))
+                .unwrap_or_default();
+            if job.label.is_some() {
+                location = format!(" [{}]{}", labels[i], location);
+            }
+            if !quiet && !imported {
+                progress.println(format!(
+                    "  [{}] Benchmarking: {}{}",
+                    (i + 1).to_string().cyan(),
+                    cmd.bold(),
+                    location.dimmed()
+                ));
+            }
+            let start = events::Event::BenchmarkStart {
+                index: i,
+                command: cmd,
+                runs: job.plan.min_runs,
+                warmup: job.plan.warmup,
+            };
+            if let Some(sink) = sink.as_mut() {
+                sink.emit(&start);
+            }
+            if let Some(dashboard) = &dashboard {
+                dashboard.observe(&start);
+            }
+
+            let mut on_run = |run: usize, result: &types::RunResult| {
+                let event = events::Event::RunComplete {
+                    index: i,
+                    run,
+                    result,
+                };
+                if let Some(sink) = sink.as_mut() {
+                    sink.emit(&event);
                 }
+                if let Some(dashboard) = &dashboard {
+                    dashboard.observe(&event);
+                }
+            };
+            let prepared;
+            let mut runner = job.runner.as_ref();
+            if let Some(prepare) = &job.prepare {
+                prepared = runner::PreparedRunner::new(runner, prepare);
+                runner = &prepared;
+            }
+            if !settings.prefetch.is_empty() {
+                let bytes = prefetch::prefetch(&settings.prefetch).unwrap_or_else(|e| fail(e));
+                if !quiet && position == 0 && round == 0 {
+                    progress.println(format!(
+                        "  {} Prefetched {} file(s) ({}), again before each command",
+                        "[~]".cyan(),
+                        settings.prefetch.len(),
+                        units::ByteUnit::common([bytes]).format(bytes)
+                    ));
+                }
+            }
+            let paced = runner::PacedRunner::new(runner, settings.delay, position > 0 || round > 0);
+            let started = std::time::Instant::now();
+            let mut plan = job.plan;
+            if let Some(end) = budget_end {
+                // An equal share of what's left, so time a command doesn't use
+                // carries over to the ones after it
+                let left = (settings.rounds - round) * jobs.len() - position;
+                let share = end.saturating_duration_since(started) / left as u32;
+                plan.deadline = Some(started + share);
+            }
+            let results = runner::run_benchmark(
+                &paced,
+                cmd,
+                &plan,
+                settings,
+                &mut progress,
+                i,
+                &mut on_run,
+            );
+            match results {
+                Ok(mut results) => {
+                    if let Some(monitor) = &frequency
+                        && job.runner.on_host()
+                    {
+                        for r in &mut results {
+                            if let Some(start) = r.started_at {
+                                r.cpu_mhz = monitor.mean_during(start, r.duration);
+                            }
+                        }
+                    }
+                    let baseline = job.baseline.as_ref().map(|command| types::Baseline {
+                        command: command.clone(),
+                        mean: baselines[command],
+                    });
+                    if let Some(baseline) = &baseline {
+                        for r in &mut results {
+                            r.duration = r.duration.saturating_sub(baseline.mean);
+                        }
+                    }
+                    let mut annotations =
+                        stats::status_annotations(&results, &settings.status_policy);
+                    let mut numbers: Vec<usize> = (0..results.len()).collect();
+                    if settings.reject_outliers {
+                        let rejected;
+                        (results, rejected) = stats::reject_outliers(&results);
+                        numbers.retain(|&n| rejected.iter().all(|a| a.run != n));
+                        annotations.extend(rejected);
+                    }
+                    let rejected = annotations
+                        .iter()
+                        .filter(|a| a.kind == types::AnnotationKind::Outlier)
+                        .count();
+                    let mut cmd_stats = stats::compute_stats(cmd, &results, settings);
+                    cmd_stats.outliers_rejected = rejected;
+                    cmd_stats.run_numbers =
+                        cmd_stats.run_numbers.iter().map(|&k| numbers[k]).collect();
+                    annotations.sort_by_key(|a| a.run);
+                    cmd_stats.annotations = annotations;
+                    cmd_stats.baseline = baseline;
+                    cmd_stats.build = builds[i].take();
+                    cmd_stats.syscalls = syscalls[i].take();
+                    cmd_stats.provenance = provenance[i].take();
+                    cmd_stats.matrix = job.matrix.clone();
+                    cmd_stats.group = job.group.clone();
+                    cmd_stats.memory_unrecorded = !job.runner.records_memory();
+                    cmd_stats.soak =
+                        plan.soak.and_then(|_| stats::soak(&cmd_stats.all_runs, settings));
+                    if cmd_stats.runs + rejected < plan.min_runs {
+                        cmd_stats.trimmed_from = Some(plan.min_runs);
+                    }
+                    cmd_stats.precision = plan.target_rse.map(|target| types::Precision {
+                        achieved: stats::relative_std_error(&cmd_stats),
+                        target,
+                    });
+                    cmd_stats.wall_time = started.elapsed();
+                    cmd_stats.label = labels[i].clone();
+                    let done = events::Event::CommandDone {
+                        index: i,
+                        stats: &cmd_stats,
+                    };
+                    if let Some(sink) = sink.as_mut() {
+                        sink.emit(&done);
+                    }
+                    if let Some(dashboard) = &dashboard {
+                        dashboard.observe(&done);
+                    }
 
-                all_stats.push(cmd_stats);
+                    per_job[i].push(cmd_stats);
+                }
+                Err(e) => {
+                    progress.finish();
+                    eprintln!("  {} {}", "Error:".red().bold(), e);
+                    if report.github_annotations {
+                        github::annotate(github::Level::Error, &e);
+                    }
+                    if let Some(target) = &report.notify {
+                        let message = format!("Benchmark failed: {}", e);
+                        target.send::<()>(&message, None).ok();
+                    }
+                    process::exit(1);
+                }
             }
-            Err(e) => {
-                eprintln!("  {} {}", "Error:".red().bold(), e);
-                process::exit(1);
+        }
+        if round + 1 < settings.rounds
+            && let Some(interval) = settings.round_interval
+        {
+            if !quiet {
+                progress.println(format!(
+                    "  {} Waiting {} before the next round",
+                    "[~]".cyan(),
+                    units::TimeUnit::for_duration(interval).format(interval)
+                ));
             }
+            std::thread::sleep(interval);
         }
     }
+    progress.finish();
+    let mut all_stats: Vec<CommandStats> = per_job
+        .into_iter()
+        .map(|mut rounds| match rounds.len() {
+            1 => rounds.remove(0),
+            _ => stats::pool(&rounds, settings),
+        })
+        .collect();
+    let session_end = chrono::Utc::now();
+    let wall_clock = session_clock.elapsed();
+    stats::collect_warnings(&mut all_stats, settings);
+    drop(frequency);
+
+    let finished = events::Event::Report {
+        results: &all_stats,
+    };
+    if let Some(sink) = sink.as_mut() {
+        sink.emit(&finished);
+    }
+    if let Some(dashboard) = &dashboard {
+        dashboard.observe(&finished);
+    }
 
     // Clear the benchmark output and print the report
-    println!();
-    output::print_report(&all_stats);
-
-    // Export to JSON if requested
-    if let Some(path) = &cli.export {
-        match serde_json::to_string_pretty(&all_stats) {
-            Ok(json) => match std::fs::write(path, &json) {
-                Ok(_) => println!("  {} Results exported to {}", "✓".green(), path),
-                Err(e) => eprintln!("  {} Failed to write {}: {}", "Error:".red().bold(), path, e),
+    if !quiet {
+        println!();
+    }
+    if let Some(path) = &report.export_txt {
+        output::start_capture(!quiet);
+        output::print_report(&all_stats, &opts);
+        if !replayed {
+            output::print_session_time(&all_stats, wall_clock);
+        }
+        let mut text = output::take_capture();
+        if report.strip_ansi {
+            text = output::strip_ansi(&text);
+        }
+        match std::fs::write(path, text) {
+            Ok(_) => output::status(format!("  {} Report written to {}", "✓".green(), path)),
+            Err(e) => eprintln!("  {} Failed to write {}: {}", "Error:".red().bold(), path, e),
+        }
+    } else if !quiet {
+        output::print_report(&all_stats, &opts);
+        if !replayed {
+            output::print_session_time(&all_stats, wall_clock);
+        }
+    }
+
+    if report.github_annotations {
+        github::report(&all_stats);
+    }
+
+    let export = types::Export::new(
+        types::SessionInfo {
+            started_at: session_start,
+            finished_at: session_end,
+            wall_clock,
+            setup: wall_clock.saturating_sub(all_stats.iter().map(|s| s.wall_time).sum()),
+            host: system::host_name(),
+            settings: settings.clone(),
+            estimator: report.estimator,
+            numa_topology: numa::topology(),
+            monitor_overhead: stats::monitor_overhead(&all_stats),
+        },
+        &all_stats,
+    );
+
+    for target in &report.export {
+        match target.write(&export, &all_stats) {
+            Ok(()) => output::status(format!(
+                "  {} Results exported to {}",
+                "✓".green(),
+                target.path
+            )),
+            Err(e) => eprintln!("  {} {}", "Error:".red().bold(), e),
+        }
+    }
+
+    if let Some(key) = &report.sign {
+        let written = bundle::Bundle::sign(&export, key).and_then(|bundle| {
+            let json = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+            std::fs::write(&report.bundle, json)
+                .map_err(|e| format!("Failed to write {}: {}", report.bundle, e))
+        });
+        match written {
+            Ok(()) => output::status(format!(
+                "  {} Signed bundle written to {}",
+                "✓".green(),
+                report.bundle
+            )),
+            Err(e) => eprintln!("  {} {}", "Error:".red().bold(), e),
+        }
+    }
+
+    if let Some(path) = &report.db {
+        let stored = db::Database::open(path)
+            .and_then(|mut db| db.record(&export.session, &all_stats));
+        match stored {
+            Ok(()) => output::status(format!("  {} Session stored in {}", "✓".green(), path)),
+            Err(e) => eprintln!("  {} Failed to store session: {}", "Error:".red().bold(), e),
+        }
+    }
+
+    if let Some(target) = &report.notify {
+        let message = format!("Benchmark finished: {}", output::summary_text(&all_stats));
+        if let Err(e) = target.send(&message, Some(&export)) {
+            eprintln!("  {} {}", "Warning:".yellow().bold(), e);
+        }
+    }
+
+    if report.explore {
+        let baselines: Vec<Option<types::Baseline>> =
+            all_stats.iter().map(|s| s.baseline.clone()).collect();
+        let mut rerun = |i: usize, runs: usize| {
+            let baseline = baselines[i].as_ref();
+            remeasure(&jobs[i], i, runs, baseline, settings, report.progress_format())
+        };
+        explore::explore(&mut all_stats, &export.session, &opts, &mut rerun);
+    }
+
+    // Keep the final results up for whoever is watching, until told to stop
+    if let Some(dashboard) = &dashboard {
+        dashboard.finish(&export);
+        if std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+            println!(
+                "  {} Still serving results at {}; press Enter to stop",
+                "[~]".cyan(),
+                dashboard_url(report.serve_address, report.serve.unwrap_or_default())
+            );
+            std::io::stdin().read_line(&mut String::new()).ok();
+        }
+    }
+
+    (all_stats, export.session)
+}
+
+/// Measure `job` again with `runs` measured runs and no warmup, for
+/// `--explore`, taking `baseline` off each run and analyzing the runs as
+/// the session did.
+fn remeasure(
+    job: &Job,
+    index: usize,
+    runs: usize,
+    baseline: Option<&types::Baseline>,
+    settings: &types::RunSettings,
+    format: progress::ProgressFormat,
+) -> Result<CommandStats, String> {
+    let prepared;
+    let mut runner = job.runner.as_ref();
+    if let Some(prepare) = &job.prepare {
+        prepared = runner::PreparedRunner::new(runner, prepare);
+        runner = &prepared;
+    }
+    let plan = runner::RunPlan::fixed(runs, 0);
+    // Progress is kept per job, so this one sits at its usual place
+    let mut planned = vec![0; index + 1];
+    planned[index] = runs;
+    let mut progress = progress::SessionProgress::new(&planned, format);
+    let mut on_run = |_: usize, _: &types::RunResult| {};
+    let results = runner::run_benchmark(
+        runner,
+        &job.command,
+        &plan,
+        settings,
+        &mut progress,
+        index,
+        &mut on_run,
+    );
+    progress.finish();
+    let mut results = results?;
+    if let Some(baseline) = baseline {
+        for r in &mut results {
+            r.duration = r.duration.saturating_sub(baseline.mean);
+        }
+    }
+    let mut stats = stats::compute_stats(&job.command, &results, settings);
+    stats.annotations = stats::status_annotations(&results, &settings.status_policy);
+    Ok(stats)
+}
+
+/// Where teammates can reach the `--serve` dashboard.
+fn dashboard_url(address: std::net::IpAddr, port: u16) -> String {
+    let host = if address.is_loopback() {
+        "localhost".to_string()
+    } else if address.is_unspecified() {
+        system::host_name().unwrap_or_else(|| "localhost".to_string())
+    } else if address.is_ipv6() {
+        format!("[{}]", address)
+    } else {
+        address.to_string()
+    };
+    format!("http://{}:{}/", host, port)
+}
+
+/// One command per file matching `pattern`, in sorted order, with `args`
+/// appended to each.
+fn expand_each(pattern: &str, args: Option<&str>) -> Result<Vec<String>, String> {
+    let paths =
+        glob::glob(pattern).map_err(|e| format!("Invalid --each pattern '{}': {}", pattern, e))?;
+
+    let mut commands = Vec::new();
+    for path in paths.flatten().filter(|p| p.is_file()) {
+        let path = path.to_string_lossy();
+        // Quote paths the shell-style splitter would otherwise break apart
+        let mut cmd = if path.chars().all(|c| c.is_alphanumeric() || "/._-+:".contains(c)) {
+            path.into_owned()
+        } else {
+            format!("'{}'", path.replace('\'', "'\\''"))
+        };
+        if let Some(args) = args {
+            cmd = format!("{} {}", cmd, args);
+        }
+        commands.push(cmd);
+    }
+    if commands.is_empty() {
+        return Err(format!("--each '{}' matched no files", pattern));
+    }
+    Ok(commands)
+}
+
+/// Read commands one per line from stdin, skipping blank lines and `#`
+/// comments.
+fn read_stdin_commands() -> Result<Vec<String>, String> {
+    let mut commands = Vec::new();
+    for line in std::io::stdin().lines() {
+        let line = line.map_err(|e| format!("Failed to read commands from stdin: {}", e))?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            commands.push(line.to_string());
+        }
+    }
+    Ok(commands)
+}
+
+/// Print an error and exit.
+fn fail(msg: impl std::fmt::Display) -> ! {
+    eprintln!("{} {}", "Error:".red().bold(), msg);
+    process::exit(1);
+}
+
+/// How each of `count` commands is launched locally, with its `--pidfile`.
+fn local_options(run: &RunArgs, count: usize) -> Result<Vec<runner::LocalOptions>, String> {
+    let options = run.local_options()?;
+    if options.precise_memory && !exec::PRECISE_MEMORY {
+        eprintln!(
+            "  {} --precise-memory needs Linux or macOS here; memory is sampled every 30ms instead",
+            "Warning:".yellow().bold()
+        );
+    }
+    let pidfiles = per_command(&run.pidfile, count, "--pidfile")?;
+    Ok(pidfiles
+        .into_iter()
+        .map(|pidfile| runner::LocalOptions {
+            follow: match pidfile {
+                Some(path) if !path.is_empty() => exec::Follow::Pidfile(path.into()),
+                _ => options.follow.clone(),
             },
-            Err(e) => eprintln!("  {} Failed to serialize results: {}", "Error:".red().bold(), e),
+            ..options.clone()
+        })
+        .collect())
+}
+
+/// The `--subtract` baseline, if any, that starts with the same program as
+/// `cmd`.
+fn matching_baseline(cmd: &str, baselines: &[String]) -> Option<String> {
+    let program = |c: &str| runner::shell_split(c).ok()?.into_iter().next();
+    let wanted = program(cmd)?;
+    baselines
+        .iter()
+        .find(|b| program(b).as_ref() == Some(&wanted))
+        .cloned()
+}
+
+/// Measure each distinct `--subtract` baseline once, with the runner and run
+/// plan of the first job that uses it, returning its mean time.
+fn measure_baselines(
+    jobs: &[Job],
+    settings: &types::RunSettings,
+    quiet: bool,
+    format: progress::ProgressFormat,
+) -> HashMap<String, Duration> {
+    let mut means = HashMap::new();
+    for job in jobs {
+        let Some(baseline) = &job.baseline else {
+            continue;
+        };
+        if means.contains_key(baseline) {
+            continue;
+        }
+        if !quiet {
+            println!("  [{}] Baseline: {}", "-".cyan(), baseline.bold());
         }
+        let runs = [job.plan.min_runs + job.plan.warmup];
+        // Baseline runs stay out of --progress json, whose indexes are commands
+        let mut progress = match format {
+            progress::ProgressFormat::Json => progress::SessionProgress::hidden(&runs),
+            _ => progress::SessionProgress::new(&runs, format),
+        };
+        let results = runner::run_benchmark(
+            job.runner.as_ref(),
+            baseline,
+            &job.plan,
+            settings,
+            &mut progress,
+            0,
+            &mut |_, _| {},
+        );
+        progress.finish();
+        match results {
+            Ok(results) => {
+                let baseline_stats = stats::compute_stats(baseline, &results, settings);
+                means.insert(baseline.clone(), baseline_stats.time_mean);
+            }
+            Err(e) => fail(format!("Baseline '{}' failed: {}", baseline, e)),
+        }
+    }
+    means
+}
+
+/// Run each distinct `--prepare` command once, up front, rather than before
+/// every run; skip it when it already ran with the same `--prepare-input`
+/// contents in an earlier session.
+fn prepare_once(jobs: &mut [Job], inputs: &[String], quiet: bool) {
+    let mut cache = prepare::Cache::load();
+    let mut done: Vec<(String, Option<String>)> = Vec::new();
+    for (i, job) in jobs.iter_mut().enumerate() {
+        let Some(command) = job.prepare.take() else {
+            continue;
+        };
+        let location = job.runner.location();
+        if done.contains(&(command.clone(), location.clone())) {
+            continue;
+        }
+        let fingerprint = prepare::fingerprint(inputs).unwrap_or_else(|e| fail(e));
+        if cache.is_fresh(&command, location.as_deref(), &fingerprint) {
+            if !quiet {
+                println!("  {} Prepare unchanged, skipping: {}", "[=]".cyan(), command.bold());
+            }
+        } else {
+            if !quiet {
+                println!("  {} Preparing: {}", "[~]".cyan(), command.bold());
+            }
+            let index = runner::RunIndex {
+                command: i,
+                run: 0,
+                total_runs: 1,
+            };
+            let result = job
+                .runner
+                .run_once(&command, &index)
+                .unwrap_or_else(|e| fail(format!("Prepare command '{}' failed: {}", command, e)));
+            match result.status() {
+                types::RunStatus::Success => {}
+                status => fail(format!("Prepare command '{}' failed: {}", command, status)),
+            }
+            // Taken after the run, which may have created or changed inputs
+            let fingerprint = prepare::fingerprint(inputs).unwrap_or_else(|e| fail(e));
+            cache.record(&command, location.as_deref(), fingerprint);
+        }
+        done.push((command, location));
+    }
+    if let Err(e) = cache.save() {
+        eprintln!("  {} {}", "Warning:".yellow().bold(), e);
+    }
+}
+
+/// The order jobs run in each round: as given, or shuffled from `seed`.
+fn run_orders(jobs: usize, rounds: usize, seed: Option<u64>) -> Vec<Vec<usize>> {
+    use rand::SeedableRng;
+    use rand::seq::SliceRandom;
+    let mut rng = seed.map(rand::rngs::StdRng::seed_from_u64);
+    // Default settings, as replays and reports use, leave rounds at 0
+    (0..rounds.max(1))
+        .map(|_| {
+            let mut order: Vec<usize> = (0..jobs).collect();
+            if let Some(rng) = rng.as_mut() {
+                order.shuffle(rng);
+            }
+            order
+        })
+        .collect()
+}
+
+/// Run each job's `--build` command once, timing it on its own.
+fn run_builds(jobs: &[Job], quiet: bool) -> Vec<Option<types::Build>> {
+    jobs.iter()
+        .enumerate()
+        .map(|(i, job)| {
+            let command = job.build.as_ref()?;
+            if !quiet {
+                println!("  [{}] Building: {}", (i + 1).to_string().cyan(), command.bold());
+            }
+            let index = runner::RunIndex {
+                command: i,
+                run: 0,
+                total_runs: 1,
+            };
+            let result = job
+                .runner
+                .run_once(command, &index)
+                .unwrap_or_else(|e| fail(format!("Build '{}' failed: {}", command, e)));
+            match result.status() {
+                types::RunStatus::Success => {}
+                status => fail(format!("Build '{}' failed: {}", command, status)),
+            }
+            Some(types::Build {
+                command: command.clone(),
+                duration: result.duration,
+                peak_memory_bytes: result.peak_memory_bytes,
+            })
+        })
+        .collect()
+}
+
+/// Each `--syscalls` command's system calls, from one untimed run under
+/// strace.
+fn count_syscalls(jobs: &[Job], quiet: bool) -> Vec<Option<types::Syscalls>> {
+    jobs.iter()
+        .enumerate()
+        .map(|(i, job)| {
+            let options = job.trace.as_ref()?;
+            if !quiet {
+                println!(
+                    "  [{}] Counting syscalls: {}",
+                    (i + 1).to_string().cyan(),
+                    job.command.bold()
+                );
+            }
+            let index = runner::RunIndex {
+                command: i,
+                run: 0,
+                total_runs: 1,
+            };
+            let counted = runner::count_syscalls(options, &job.command, &index)
+                .unwrap_or_else(|e| fail(format!("Counting syscalls of '{}': {}", job.command, e)));
+            Some(counted)
+        })
+        .collect()
+}
+
+/// The exit codes in one `--expect-exit` value, none for an empty one.
+fn parse_exit_codes(value: Option<&str>) -> Result<Vec<i32>, String> {
+    value
+        .unwrap_or("")
+        .split(',')
+        .map(str::trim)
+        .filter(|code| !code.is_empty())
+        .map(|code| {
+            code.parse()
+                .map_err(|_| format!("--expect-exit: '{}' is not an exit code", code))
+        })
+        .collect()
+}
+
+/// Resolve an option that may be given once (applying to every command) or
+/// exactly once per command.
+fn per_command(values: &[String], count: usize, flag: &str) -> Result<Vec<Option<String>>, String> {
+    match values.len() {
+        0 => Ok(vec![None; count]),
+        1 => Ok(vec![Some(values[0].clone()); count]),
+        n if n == count => Ok(values.iter().cloned().map(Some).collect()),
+        n => Err(format!(
+            "{} was given {} times; expected once or once per command ({})",
+            flag, n, count
+        )),
     }
 }
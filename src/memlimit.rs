@@ -0,0 +1,150 @@
+use crate::cgroup::MemoryCgroup;
+use std::process::{Command, Stdio};
+
+/// First cap tried; doubled until the command succeeds under it.
+const START: u64 = 16 * 1024 * 1024;
+
+/// Caps are bisected down to whole pages.
+const PAGE: u64 = 4096;
+
+/// How `clash memlimit` caps a command's memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mechanism {
+    /// `memory.max` (or v1's `memory.limit_in_bytes`) of a cgroup of its
+    /// own, with swap off: resident memory, children included
+    Cgroup,
+    /// `setrlimit(RLIMIT_AS)`: the launched process's virtual address
+    /// space, which counts reservations it never touches
+    AddressSpace,
+}
+
+impl Mechanism {
+    /// The most faithful mechanism this machine allows.
+    pub fn detect() -> Result<Self, String> {
+        if MemoryCgroup::create().is_some_and(|c| c.limit(START)) {
+            return Ok(Mechanism::Cgroup);
+        }
+        if cfg!(target_os = "linux") {
+            return Ok(Mechanism::AddressSpace);
+        }
+        Err("clash memlimit needs Linux (memory cgroups or setrlimit)".to_string())
+    }
+
+    /// What the found threshold limits.
+    pub fn describe(self) -> &'static str {
+        match self {
+            Mechanism::Cgroup => "memory cgroup limit (resident memory, children included)",
+            Mechanism::AddressSpace => {
+                "address-space limit via setrlimit (virtual memory, so higher than RSS)"
+            }
+        }
+    }
+}
+
+/// Whether `program` with `args` exits successfully under a cap of `limit`
+/// bytes, or with no cap at all.
+pub fn succeeds(
+    program: &str,
+    args: &[String],
+    limit: Option<u64>,
+    mechanism: Mechanism,
+) -> Result<bool, String> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let Some(limit) = limit else {
+        return run(&mut command, program);
+    };
+    match mechanism {
+        Mechanism::Cgroup => {
+            let cgroup = MemoryCgroup::create()
+                .filter(|c| c.limit(limit))
+                .ok_or("Failed to create a memory cgroup to limit the command in")?;
+            cgroup.enter_on_spawn(&mut command);
+            let mut child = command
+                .spawn()
+                .map_err(|e| format!("Failed to start '{}': {}", program, e))?;
+            if !cgroup.contains(child.id()) {
+                child.kill().ok();
+                child.wait().ok();
+                return Err("Failed to move the command into its memory cgroup".to_string());
+            }
+            child
+                .wait()
+                .map(|status| status.success())
+                .map_err(|e| format!("Failed to wait for '{}': {}", program, e))
+        }
+        Mechanism::AddressSpace => {
+            limit_address_space(&mut command, limit);
+            run(&mut command, program)
+        }
+    }
+}
+
+fn run(command: &mut Command, program: &str) -> Result<bool, String> {
+    command
+        .status()
+        .map(|status| status.success())
+        .map_err(|e| format!("Failed to start '{}': {}", program, e))
+}
+
+#[cfg(target_os = "linux")]
+fn limit_address_space(command: &mut Command, limit: u64) {
+    use std::os::unix::process::CommandExt;
+    let rlimit = libc::rlimit {
+        rlim_cur: limit,
+        rlim_max: limit,
+    };
+    // SAFETY: setrlimit is async-signal-safe and only reads `rlimit`
+    unsafe {
+        command.pre_exec(move || {
+            if libc::setrlimit(libc::RLIMIT_AS, &rlimit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn limit_address_space(_: &mut Command, _: u64) {}
+
+/// The smallest cap, to within `tolerance` (a fraction), under which
+/// `works` still holds, or `None` if it fails even at `max` bytes. Caps
+/// are doubled from 16 MiB until one works, then bisected; `tried` hears
+/// every cap and its outcome.
+pub fn bisect(
+    mut works: impl FnMut(u64) -> Result<bool, String>,
+    tolerance: f64,
+    max: u64,
+    mut tried: impl FnMut(u64, bool),
+) -> Result<Option<u64>, String> {
+    let mut probe = |limit: u64| {
+        let ok = works(limit)?;
+        tried(limit, ok);
+        Ok::<_, String>(ok)
+    };
+    let (mut low, mut high) = (0, START);
+    while !probe(high)? {
+        if high >= max {
+            return Ok(None);
+        }
+        low = high;
+        high = (high * 2).min(max);
+    }
+    while high - low > PAGE && (high - low) as f64 > high as f64 * tolerance {
+        let mid = (low + (high - low) / 2) / PAGE * PAGE;
+        if mid <= low {
+            break;
+        }
+        if probe(mid)? {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    Ok(Some(high))
+}
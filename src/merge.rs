@@ -0,0 +1,116 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use std::time::Duration;
+
+/// One command's numbers from one host, as shown in the merged report.
+#[derive(Debug)]
+pub struct HostResult {
+    pub host: String,
+    pub label: String,
+    pub time_mean: Duration,
+    pub time_std_dev: Duration,
+    pub peak_memory_bytes: Option<u64>,
+}
+
+/// Exports from several machines or sessions combined into one file.
+#[derive(Debug, Serialize)]
+pub struct Merged {
+    /// Where each input came from: its path, host and session details
+    pub sources: Vec<Value>,
+    /// Every input's results, each tagged with its `host`
+    pub results: Vec<Value>,
+}
+
+impl Merged {
+    /// The per-host numbers behind the side-by-side report.
+    pub fn host_results(&self) -> Result<Vec<HostResult>, String> {
+        self.results.iter().map(host_result).collect()
+    }
+}
+
+/// Combine `--export` files (or earlier merges), keeping one result per
+/// command label and host; a later file replaces an earlier one's result.
+pub fn merge(paths: &[String]) -> Result<Merged, String> {
+    let mut merged = Merged {
+        sources: Vec::new(),
+        results: Vec::new(),
+    };
+    for path in paths {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+        let export: Value = serde_json::from_str(&text)
+            .map_err(|e| format!("'{}' is not a clash export: {}", path, e))?;
+        let Some(results) = export.get("results").and_then(Value::as_array) else {
+            return Err(format!("'{}' is not a clash export: no results", path));
+        };
+
+        // Exports name their host in the session; fall back to the file name
+        let default_host = export
+            .pointer("/session/host")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| file_stem(path));
+
+        match export.get("sources").and_then(Value::as_array) {
+            Some(sources) => merged.sources.extend(sources.iter().cloned()),
+            None => merged.sources.push(serde_json::json!({
+                "path": path,
+                "host": default_host,
+                "session": export.get("session"),
+            })),
+        }
+
+        for result in results {
+            let mut result = result.clone();
+            if result.get("host").is_none() {
+                result["host"] = Value::from(default_host.as_str());
+            }
+            let key = |r: &Value| (r.get("label").cloned(), r.get("host").cloned());
+            if let Some(existing) = merged.results.iter_mut().find(|r| key(r) == key(&result)) {
+                *existing = result;
+            } else {
+                merged.results.push(result);
+            }
+        }
+    }
+    Ok(merged)
+}
+
+fn host_result(result: &Value) -> Result<HostResult, String> {
+    let text = |field: &str| {
+        result
+            .get(field)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| format!("Merged result is missing '{}'", field))
+    };
+    // Seconds under `metrics`, or a top-level `Duration` in older exports
+    let duration = |pointer: &str, field: &str| {
+        result
+            .pointer(pointer)
+            .and_then(Value::as_f64)
+            .map(Duration::from_secs_f64)
+            .or_else(|| serde_json::from_value::<Duration>(result.get(field)?.clone()).ok())
+            .ok_or_else(|| format!("Merged result is missing '{}'", field))
+    };
+    Ok(HostResult {
+        host: text("host")?,
+        label: text("label")?,
+        time_mean: duration("/metrics/time/mean", "time_mean")?,
+        time_std_dev: duration("/metrics/time/std_dev", "time_std_dev")?,
+        peak_memory_bytes: result
+            .pointer("/metrics/memory/summary")
+            .and_then(Value::as_f64)
+            .map(|bytes| bytes as u64)
+            .or_else(|| result.get("peak_memory_bytes").and_then(Value::as_u64))
+            .filter(|&bytes| bytes > 0),
+    })
+}
+
+fn file_stem(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
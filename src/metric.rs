@@ -0,0 +1,412 @@
+use crate::types::{CommandStats, Comparison, Estimator, RunResult};
+use crate::units::{self, ByteUnit, TimeUnit};
+use serde::Serialize;
+use std::time::Duration;
+
+/// Which way a metric improves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Better {
+    Lower,
+    /// For throughput-style metrics; no built-in metric uses it yet
+    #[allow(dead_code)]
+    Higher,
+}
+
+/// What a metric's values count, which decides how they're written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    Seconds,
+    Bytes,
+    Count,
+}
+
+/// How a command's per-run values collapse into the one number compared
+/// across commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Mean,
+    Max,
+    /// The `--estimator` the command's stats were computed with: mean,
+    /// median or trimmed mean
+    Center,
+}
+
+impl Aggregate {
+    /// The aggregate of `values`, which must not be empty, with `Center`
+    /// standing for `estimator`.
+    pub fn apply(&self, values: &[f64], estimator: Estimator) -> f64 {
+        match self {
+            Aggregate::Mean => values.iter().sum::<f64>() / values.len() as f64,
+            Aggregate::Max => values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            Aggregate::Center => estimator.apply(values),
+        }
+    }
+
+    /// The aggregate's name in exports, with `Center` standing for
+    /// `estimator`.
+    fn name(&self, estimator: Estimator) -> String {
+        match self {
+            Aggregate::Mean => "mean".to_string(),
+            Aggregate::Max => "max".to_string(),
+            Aggregate::Center => estimator.to_string(),
+        }
+    }
+}
+
+/// A measured quantity that every command is aggregated, compared and
+/// printed on in the same way, whatever it measures.
+#[derive(Debug)]
+pub struct Metric {
+    /// Key used in `--weight` and exports
+    pub name: &'static str,
+    /// Table title
+    pub title: &'static str,
+    /// Name of the aggregate's table row
+    pub row: &'static str,
+    pub unit: Unit,
+    pub better: Better,
+    pub aggregate: Aggregate,
+    /// Verdict for the winner, with `{ratio}` standing for e.g. "1.52x"
+    pub verdict: &'static str,
+    /// Verdict when no command stands out
+    pub tie: &'static str,
+    /// Why a run can lack a value, shown with the count of such runs
+    pub missing: &'static str,
+    /// The metric's value for one run, if it was measured
+    pub per_run: fn(&RunResult) -> Option<f64>,
+}
+
+pub const TIME: Metric = Metric {
+    name: "time",
+    title: "⏱  Time",
+    row: "Mean",
+    unit: Unit::Seconds,
+    better: Better::Lower,
+    aggregate: Aggregate::Center,
+    verdict: "is {ratio} faster",
+    tie: "Roughly the same speed",
+    missing: "not timed",
+    per_run: |r| Some(r.duration.as_secs_f64()),
+};
+
+pub const MEMORY: Metric = Metric {
+    name: "memory",
+    title: "💾  Memory",
+    row: "Peak RSS",
+    unit: Unit::Bytes,
+    better: Better::Lower,
+    aggregate: Aggregate::Max,
+    verdict: "uses {ratio} less memory",
+    tie: "Roughly the same memory usage",
+    missing: "too fast to sample",
+    per_run: |r| r.peak_memory_bytes.map(|bytes| bytes as f64),
+};
+
+pub const STDOUT: Metric = Metric {
+    name: "stdout",
+    title: "📄 Output",
+    row: "stdout / run",
+    unit: Unit::Bytes,
+    better: Better::Lower,
+    aggregate: Aggregate::Mean,
+    verdict: "writes {ratio} less to stdout",
+    tie: "Roughly the same stdout volume",
+    missing: "without --count-output",
+    per_run: |r| r.output.map(|o| o.stdout_bytes as f64),
+};
+
+pub const STDOUT_LINES: Metric = Metric {
+    name: "stdout_lines",
+    title: "📄 Output",
+    row: "stdout lines / run",
+    unit: Unit::Count,
+    better: Better::Lower,
+    aggregate: Aggregate::Mean,
+    verdict: "prints {ratio} fewer stdout lines",
+    tie: "Roughly the same stdout lines",
+    missing: "without --count-output",
+    per_run: |r| r.output.map(|o| o.stdout_lines as f64),
+};
+
+pub const STDERR: Metric = Metric {
+    name: "stderr",
+    title: "📄 Output",
+    row: "stderr / run",
+    unit: Unit::Bytes,
+    better: Better::Lower,
+    aggregate: Aggregate::Mean,
+    verdict: "writes {ratio} less to stderr",
+    tie: "Roughly the same stderr volume",
+    missing: "without --count-output",
+    per_run: |r| r.output.map(|o| o.stderr_bytes as f64),
+};
+
+pub const STDERR_LINES: Metric = Metric {
+    name: "stderr_lines",
+    title: "📄 Output",
+    row: "stderr lines / run",
+    unit: Unit::Count,
+    better: Better::Lower,
+    aggregate: Aggregate::Mean,
+    verdict: "prints {ratio} fewer stderr lines",
+    tie: "Roughly the same stderr lines",
+    missing: "without --count-output",
+    per_run: |r| r.output.map(|o| o.stderr_lines as f64),
+};
+
+pub const CPU_TIME: Metric = Metric {
+    name: "cpu_time",
+    title: "🧮 CPU",
+    row: "CPU time",
+    unit: Unit::Seconds,
+    better: Better::Lower,
+    aggregate: Aggregate::Mean,
+    verdict: "uses {ratio} less CPU time",
+    tie: "Roughly the same CPU time",
+    missing: "not watched",
+    per_run: |r| r.cpu.map(|c| c.time.as_secs_f64()),
+};
+
+pub const MAJOR_FAULTS: Metric = Metric {
+    name: "major_faults",
+    title: "🧾 Resource usage",
+    row: "Major faults / run",
+    unit: Unit::Count,
+    better: Better::Lower,
+    aggregate: Aggregate::Mean,
+    verdict: "takes {ratio} fewer major page faults",
+    tie: "Roughly the same major page faults",
+    missing: "without --backend time",
+    per_run: |r| r.usage.map(|u| u.major_faults as f64),
+};
+
+pub const MINOR_FAULTS: Metric = Metric {
+    name: "minor_faults",
+    title: "🧾 Resource usage",
+    row: "Minor faults / run",
+    unit: Unit::Count,
+    better: Better::Lower,
+    aggregate: Aggregate::Mean,
+    verdict: "takes {ratio} fewer minor page faults",
+    tie: "Roughly the same minor page faults",
+    missing: "without --backend time",
+    per_run: |r| r.usage.map(|u| u.minor_faults as f64),
+};
+
+pub const VOLUNTARY_SWITCHES: Metric = Metric {
+    name: "voluntary_switches",
+    title: "🧾 Resource usage",
+    row: "Voluntary switches / run",
+    unit: Unit::Count,
+    better: Better::Lower,
+    aggregate: Aggregate::Mean,
+    verdict: "blocks {ratio} less often",
+    tie: "Roughly the same voluntary context switches",
+    missing: "without --backend time",
+    per_run: |r| r.usage.map(|u| u.voluntary_switches as f64),
+};
+
+pub const INVOLUNTARY_SWITCHES: Metric = Metric {
+    name: "involuntary_switches",
+    title: "🧾 Resource usage",
+    row: "Involuntary switches / run",
+    unit: Unit::Count,
+    better: Better::Lower,
+    aggregate: Aggregate::Mean,
+    verdict: "is preempted {ratio} less often",
+    tie: "Roughly the same involuntary context switches",
+    missing: "without --backend time",
+    per_run: |r| r.usage.map(|u| u.involuntary_switches as f64),
+};
+
+/// One command's values for a metric, as exported.
+#[derive(Debug, Serialize)]
+pub struct MetricExport {
+    pub unit: Unit,
+    pub better: Better,
+    /// How `summary` was computed from the values
+    pub aggregate: String,
+    /// The value commands are compared on
+    pub summary: f64,
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+    /// Sample standard deviation
+    pub std_dev: f64,
+    /// One entry per run in run order, `null` where a run didn't measure it
+    pub values: Vec<Option<f64>>,
+}
+
+impl MetricExport {
+    /// Aggregate per-run `values`, or `None` if none was measured.
+    pub fn new(
+        unit: Unit,
+        better: Better,
+        aggregate: Aggregate,
+        estimator: Estimator,
+        values: Vec<Option<f64>>,
+    ) -> Option<Self> {
+        let measured: Vec<f64> = values.iter().flatten().copied().collect();
+        if measured.is_empty() {
+            return None;
+        }
+        let n = measured.len() as f64;
+        let mean = measured.iter().sum::<f64>() / n;
+        let min = measured.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = measured.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let std_dev = if measured.len() > 1 {
+            (measured.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        Some(Self {
+            unit,
+            better,
+            aggregate: aggregate.name(estimator),
+            summary: aggregate.apply(&measured, estimator),
+            mean,
+            min,
+            max,
+            std_dev,
+            values,
+        })
+    }
+}
+
+/// A ratio between two commands, e.g. "1.52x"; a zero on the winning side
+/// (say, an idle process's CPU time) makes it "∞x".
+pub fn ratio_text(ratio: f64) -> String {
+    if ratio.is_finite() {
+        format!("{:.2}x", ratio)
+    } else {
+        "∞x".to_string()
+    }
+}
+
+/// Every built-in metric.
+pub const ALL: [&Metric; 11] = [
+    &TIME,
+    &MEMORY,
+    &CPU_TIME,
+    &STDOUT,
+    &STDOUT_LINES,
+    &STDERR,
+    &STDERR_LINES,
+    &MAJOR_FAULTS,
+    &MINOR_FAULTS,
+    &VOLUNTARY_SWITCHES,
+    &INVOLUNTARY_SWITCHES,
+];
+
+impl Metric {
+    /// Look up a metric by its `name`.
+    pub fn find(name: &str) -> Option<&'static Metric> {
+        ALL.into_iter().find(|m| m.name == name)
+    }
+
+    /// Values of every run that measured this metric, in run order.
+    pub fn values(&self, s: &CommandStats) -> Vec<f64> {
+        s.all_runs.iter().filter_map(self.per_run).collect()
+    }
+
+    /// How many of the command's runs didn't measure this metric.
+    pub fn unmeasured(&self, s: &CommandStats) -> usize {
+        s.all_runs.len() - self.values(s).len()
+    }
+
+    /// The command's aggregate value, or `None` if no run measured it.
+    pub fn summary(&self, s: &CommandStats) -> Option<f64> {
+        let values = self.values(s);
+        if values.is_empty() {
+            return None;
+        }
+        Some(self.aggregate.apply(&values, s.estimator))
+    }
+
+    /// Per-run values and aggregates for export, or `None` if no run
+    /// measured this metric.
+    pub fn export(&self, s: &CommandStats) -> Option<MetricExport> {
+        MetricExport::new(
+            self.unit,
+            self.better,
+            self.aggregate,
+            s.estimator,
+            s.all_runs.iter().map(self.per_run).collect(),
+        )
+    }
+
+    /// The best command and how far ahead it is of the worst. `None` with
+    /// fewer than two commands or when any command lacks the metric.
+    pub fn compare(&self, stats: &[CommandStats]) -> Option<Comparison> {
+        if stats.len() < 2 {
+            return None;
+        }
+        let values: Vec<f64> = stats
+            .iter()
+            .map(|s| self.summary(s))
+            .collect::<Option<_>>()?;
+        let order = |a: &&(usize, &f64), b: &&(usize, &f64)| a.1.partial_cmp(b.1).unwrap();
+        let indexed: Vec<(usize, &f64)> = values.iter().enumerate().collect();
+        let lowest = indexed.iter().min_by(order).unwrap();
+        let highest = indexed.iter().max_by(order).unwrap();
+        let winner = match self.better {
+            Better::Lower => lowest.0,
+            Better::Higher => highest.0,
+        };
+        Some(Comparison {
+            winner_index: winner,
+            ratio: highest.1 / lowest.1,
+            inconclusive: false,
+            unstable: false,
+            low_confidence: false,
+        })
+    }
+
+    /// The command closest behind `winner`, if every command has a value.
+    pub fn runner_up(&self, stats: &[CommandStats], winner: usize) -> Option<usize> {
+        let values: Vec<f64> = stats.iter().map(|s| self.summary(s)).collect::<Option<_>>()?;
+        let behind = values.iter().enumerate().filter(|&(i, _)| i != winner);
+        let closest = match self.better {
+            Better::Lower => behind.min_by(|a, b| a.1.total_cmp(b.1)),
+            Better::Higher => behind.max_by(|a, b| a.1.total_cmp(b.1)),
+        };
+        closest.map(|(i, _)| i)
+    }
+
+    /// How close `value` comes to the best, in 0..=1 (1 for the best).
+    pub fn relative(&self, best: f64, value: f64) -> f64 {
+        let ratio = match self.better {
+            Better::Lower => best / value,
+            Better::Higher => value / best,
+        };
+        if ratio.is_finite() { ratio } else { 1.0 }
+    }
+
+    /// The best aggregate among `stats`, if every command has one.
+    pub fn best(&self, stats: &[CommandStats]) -> Option<f64> {
+        let values: Vec<f64> = stats.iter().map(|s| self.summary(s)).collect::<Option<_>>()?;
+        match self.better {
+            Better::Lower => values.into_iter().reduce(f64::min),
+            Better::Higher => values.into_iter().reduce(f64::max),
+        }
+    }
+
+    /// A formatter writing every one of `values` in one shared unit.
+    pub fn formatter(&self, values: &[f64]) -> impl Fn(f64) -> String + use<> {
+        let unit = self.unit;
+        let time = TimeUnit::common(values.iter().map(|&v| Duration::from_secs_f64(v.max(0.0))));
+        let bytes = ByteUnit::common(values.iter().map(|&v| v as u64));
+        move |v| match unit {
+            Unit::Seconds => time.format(Duration::from_secs_f64(v.max(0.0))),
+            Unit::Bytes => bytes.format(v as u64),
+            Unit::Count => units::number(v, 0),
+        }
+    }
+
+    /// The winner's verdict for `ratio`, e.g. "is 1.52x faster".
+    pub fn verdict(&self, ratio: f64) -> String {
+        self.verdict.replace("{ratio}", &ratio_text(ratio))
+    }
+}
@@ -0,0 +1,114 @@
+use serde::Serialize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+/// Where `--notify` sends a message when the session ends.
+#[derive(Debug, Clone)]
+pub enum Notify {
+    /// A desktop notification (notify-send, osascript or PowerShell)
+    Desktop,
+    /// POST a JSON payload to this webhook URL
+    Webhook(String),
+}
+
+impl FromStr for Notify {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "desktop" {
+            Ok(Notify::Desktop)
+        } else if s.starts_with("http://") || s.starts_with("https://") {
+            Ok(Notify::Webhook(s.to_string()))
+        } else {
+            Err(format!("expected 'desktop' or an http(s) webhook URL, got '{}'", s))
+        }
+    }
+}
+
+/// Webhook body: `text` for Slack, `content` for Discord, plus the full
+/// report for anything else.
+#[derive(Serialize)]
+struct Payload<'a, T: Serialize> {
+    text: &'a str,
+    content: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    report: Option<&'a T>,
+}
+
+impl Notify {
+    /// Deliver `message`, attaching `report` to webhook payloads.
+    pub fn send<T: Serialize>(&self, message: &str, report: Option<&T>) -> Result<(), String> {
+        match self {
+            Notify::Desktop => desktop(message),
+            Notify::Webhook(url) => {
+                let payload = Payload {
+                    text: message,
+                    content: message,
+                    report,
+                };
+                let body = serde_json::to_vec(&payload)
+                    .map_err(|e| format!("Failed to serialize notification: {}", e))?;
+                post(url, &body)
+            }
+        }
+    }
+}
+
+fn desktop(message: &str) -> Result<(), String> {
+    let mut command = if cfg!(target_os = "macos") {
+        let script = format!("display notification {:?} with title \"clash\"", message);
+        let mut command = Command::new("osascript");
+        command.args(["-e", &script]);
+        command
+    } else if cfg!(windows) {
+        let script = format!(
+            "New-BurntToastNotification -Text 'clash', '{}'",
+            message.replace('\'', "''")
+        );
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-Command", &script]);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["clash", message]);
+        command
+    };
+    let status = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("Failed to send desktop notification: {}", e))?;
+    if !status.success() {
+        return Err("Desktop notification command failed".to_string());
+    }
+    Ok(())
+}
+
+/// POST `body` with curl, which is available nearly everywhere and keeps
+/// an HTTP stack out of the binary.
+fn post(url: &str, body: &[u8]) -> Result<(), String> {
+    let mut child = Command::new("curl")
+        .args(["-fsS", "-X", "POST", "-H", "Content-Type: application/json"])
+        .args(["--data-binary", "@-", url])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run curl for the webhook: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(body)
+            .map_err(|e| format!("Failed to send webhook payload: {}", e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to wait for curl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Webhook POST failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use serde::Serialize;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const NODES: &str = "/sys/devices/system/node";
+
+/// One NUMA node as the kernel describes it.
+#[derive(Debug, Clone, Serialize)]
+pub struct NumaNode {
+    pub id: u32,
+    /// CPU list in kernel notation, e.g. "0-7,16-23"
+    pub cpus: String,
+    pub memory_bytes: u64,
+}
+
+/// The host's NUMA nodes, or none where the platform doesn't expose them.
+pub fn topology() -> Vec<NumaNode> {
+    let Ok(entries) = std::fs::read_dir(NODES) else {
+        return Vec::new();
+    };
+    let mut nodes: Vec<NumaNode> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let id = name.to_str()?.strip_prefix("node")?.parse().ok()?;
+            let dir = entry.path();
+            let cpus = std::fs::read_to_string(dir.join("cpulist")).ok()?;
+            Some(NumaNode {
+                id,
+                cpus: cpus.trim().to_string(),
+                memory_bytes: memory_total(&dir).unwrap_or(0),
+            })
+        })
+        .collect();
+    nodes.sort_by_key(|n| n.id);
+    nodes
+}
+
+/// Parse "Node 0 MemTotal:  6127352 kB" from a node's meminfo.
+fn memory_total(dir: &Path) -> Option<u64> {
+    let meminfo = std::fs::read_to_string(dir.join("meminfo")).ok()?;
+    let line = meminfo.lines().find(|l| l.contains("MemTotal:"))?;
+    let kb: u64 = line.split_whitespace().rev().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// The `numactl` prefix binding a command's CPUs and memory to `node`,
+/// after checking the node exists and numactl is installed.
+pub fn bind_prefix(node: u32) -> Result<Vec<String>, String> {
+    if !Path::new(NODES).join(format!("node{}", node)).exists() {
+        return Err(format!("NUMA node {} not found", node));
+    }
+    let numactl = Command::new("numactl")
+        .arg("--show")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    if numactl.is_err() {
+        return Err("--numa-node needs numactl to be installed".to_string());
+    }
+    Ok(vec![
+        "numactl".to_string(),
+        format!("--cpunodebind={}", node),
+        format!("--membind={}", node),
+    ])
+}
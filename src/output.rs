@@ -1,194 +1,1845 @@
+use crate::bundle::Environment;
+use crate::db::{CommandRow, SessionRow};
+use crate::merge::HostResult;
+use crate::metric::{self, Metric};
 use crate::stats;
-use crate::types::CommandStats;
-use comfy_table::modifiers::UTF8_ROUND_CORNERS;
-use comfy_table::presets::UTF8_FULL;
-use comfy_table::{Attribute, Cell, Color, ContentArrangement, Table};
+use crate::table::{self, Attribute, Cell, Table};
+use crate::theme::{Paint, Theme};
+use crate::types::{
+    Bound, CommandStats, Estimator, Limits, MatrixCell, Provenance, Soak, SoakBucket, Stability,
+    Syscalls, Weights,
+};
+use crate::units::{self, ByteUnit, TimeUnit};
+use chrono::Local;
 use owo_colors::OwoColorize;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use unicode_width::UnicodeWidthStr;
+
+/// Print the report without colors, for `--style basic` and `nocolor`.
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Print without colors or other terminal escapes from now on.
+pub fn disable_colors() {
+    PLAIN.store(true, Ordering::Relaxed);
+}
+
+/// Leave out notes on where results went, for `--style none` and events on
+/// stdout.
+static SILENT: AtomicBool = AtomicBool::new(false);
+
+/// Print no status lines from now on.
+pub fn silence() {
+    SILENT.store(true, Ordering::Relaxed);
+}
+
+/// Print a status line, such as where results were written, unless
+/// silenced.
+pub fn status(line: impl AsRef<str>) {
+    if !SILENT.load(Ordering::Relaxed) {
+        println!("{}", styled(line.as_ref()));
+    }
+}
+
+thread_local! {
+    /// A copy of everything printed while capturing for `--export-txt`, and
+    /// whether it still goes to the terminal too
+    static CAPTURE: RefCell<Option<(String, bool)>> = const { RefCell::new(None) };
+}
+
+/// Print a line of the report, keeping a copy while capturing.
+macro_rules! outln {
+    () => {
+        emit("")
+    };
+    ($($arg:tt)*) => {
+        emit(&format!($($arg)*))
+    };
+}
+
+/// `line` as it should be printed: without escapes once colors are off.
+pub fn styled(line: &str) -> Cow<'_, str> {
+    if PLAIN.load(Ordering::Relaxed) {
+        Cow::Owned(strip_ansi(line))
+    } else {
+        Cow::Borrowed(line)
+    }
+}
+
+fn emit(line: &str) {
+    let line = &styled(line);
+    let echo = CAPTURE.with_borrow_mut(|capture| match capture {
+        Some((text, echo)) => {
+            text.push_str(line);
+            text.push('\n');
+            *echo
+        }
+        None => true,
+    });
+    if echo {
+        println!("{}", line);
+    }
+}
+
+/// Keep a copy of what is printed from now on, printing it too if `echo`.
+pub fn start_capture(echo: bool) {
+    CAPTURE.set(Some((String::new(), echo)));
+}
+
+/// Stop capturing and return what was printed since `start_capture`.
+pub fn take_capture() -> String {
+    CAPTURE.take().map(|(text, _)| text).unwrap_or_default()
+}
+
+/// `text` without terminal escape sequences (colors, bold and the like).
+pub fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            plain.push(c);
+            continue;
+        }
+        // CSI sequences end at the first byte in @..~
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    plain
+}
+
+/// Table orientation for `--layout`.
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum Layout {
+    /// One row per command, one column per metric
+    Rows,
+    /// One column per command, one row per metric
+    #[default]
+    Columns,
+}
+
+/// hyperfine's `--style`, for scripts written against it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Style {
+    /// Colors and progress bars
+    Full,
+    /// Neither colors nor progress bars
+    Basic,
+    /// Progress bars without colors
+    Nocolor,
+    /// Colors without progress bars
+    Color,
+    /// No output at all
+    None,
+}
+
+impl Style {
+    /// Whether the report is colored.
+    pub fn colored(self) -> bool {
+        matches!(self, Style::Full | Style::Color)
+    }
+
+    /// Whether progress bars are drawn.
+    pub fn interactive(self) -> bool {
+        matches!(self, Style::Full | Style::Nocolor)
+    }
+}
+
+/// Presentation settings for the report.
+#[derive(Debug, Default)]
+pub struct ReportOptions {
+    pub theme: Theme,
+    pub layout: Layout,
+    /// Explicit metric weights for the overall winner
+    pub weights: Option<Weights>,
+    /// Report clash's own measurement overhead
+    pub verbose: bool,
+    /// Plot time against memory
+    pub tradeoff: bool,
+    /// Budgets whose breaches are colored and flagged, with `--highlight-over`
+    pub limits: Limits,
+    /// Say what seems to hold each command back
+    pub characterize: bool,
+}
 
 /// Print the full benchmark comparison report.
-pub fn print_report(all_stats: &[CommandStats]) {
-    println!();
-    println!(
+pub fn print_report(all_stats: &[CommandStats], opts: &ReportOptions) {
+    let theme = &opts.theme;
+    outln!();
+    outln!(
         "  {}  clash — benchmark comparator",
         "⚔️".bold()
     );
-    println!();
+    let estimator = stats::estimator(all_stats);
+    if estimator != Estimator::Mean {
+        outln!("  {}", format!("Times compared by {}", estimator.row().to_lowercase()).dimmed());
+    }
+    outln!();
 
-    // Print run summaries
+    // Print run summaries, with sparklines lined up after the widest
+    let runs = |s: &CommandStats| match s.precision {
+        Some(p) => format!("({} runs, ±{:.1}%)", s.runs, p.achieved * 100.0),
+        None => format!("({} runs)", s.runs),
+    };
+    let summary = |s: &CommandStats| format!("{} {}", s.label, runs(s));
+    let width = all_stats.iter().map(|s| summary(s).width()).max().unwrap_or(0);
     for s in all_stats {
         let status = if s.failed_runs == 0 {
-            "✓".green().to_string()
+            theme.winner.paint("✓")
         } else {
-            format!("⚠ {} failed", s.failed_runs).yellow().to_string()
+            theme.warning.paint(&format!("⚠ {} failed", s.failed_runs))
         };
-        println!(
-            "  {} {} ({} runs)",
+        let durations: Vec<f64> = s.all_runs.iter().map(|r| r.duration.as_secs_f64()).collect();
+        outln!(
+            "  {} {} {}{}  {}",
             status,
             s.label.bold(),
-            s.runs
+            runs(s),
+            " ".repeat(width - summary(s).width()),
+            sparkline(&durations).dimmed()
         );
     }
-    println!();
+    print_baseline_notes(all_stats);
+    print_warnings(all_stats, theme);
+    outln!();
+
+    // One section per --group, each with its own tables and winner
+    let groups = stats::groups(all_stats);
+    if groups.is_empty() {
+        print_comparison(all_stats, opts);
+    } else {
+        for (name, stats) in &groups {
+            outln!("  {} {}", theme.time.paint("▌"), name.bold());
+            outln!();
+            print_comparison(stats, opts);
+            outln!();
+        }
+        print_group_appendix(all_stats, theme, opts.layout);
+    }
+
+    if opts.characterize {
+        print_characterization(all_stats);
+    }
+    if opts.verbose {
+        print_overhead(all_stats);
+    }
+}
+
+/// Every table comparing `all_stats`, then the summary.
+fn print_comparison(all_stats: &[CommandStats], opts: &ReportOptions) {
+    let theme = &opts.theme;
 
     // Time comparison table
-    print_time_table(all_stats);
-    println!();
+    print_time_table(all_stats, theme, opts.layout, opts.limits.of(&metric::TIME));
+    outln!();
+
+    // Each round's times and winner, with --rounds
+    let winners = stats::round_winners(all_stats);
+    if !winners.is_empty() {
+        print_rounds_table(all_stats, &winners, theme, opts.layout);
+        outln!();
+    }
+
+    // Time and memory over the session, with --soak
+    if all_stats.iter().any(|s| s.soak.is_some()) {
+        print_soak_table(all_stats, theme, opts.layout);
+        outln!();
+    }
+
+    // Command × value grid, with --matrix
+    if all_stats.iter().any(|s| s.matrix.is_some()) {
+        print_heatmap(all_stats, theme);
+        outln!();
+    }
+
+    // Speedup and efficiency per thread count, with --scaling
+    if all_stats.iter().any(|s| s.matrix.as_ref().is_some_and(|m| m.scaling)) {
+        print_scaling_table(all_stats, theme);
+        outln!();
+    }
+
+    // One-off build steps, with --build
+    if all_stats.iter().any(|s| s.build.is_some()) {
+        print_build_table(all_stats, theme, opts.layout);
+        outln!();
+    }
+
+    // Where the time went, when commands had --prepare hooks
+    if all_stats.iter().any(|s| s.prepare_mean.is_some()) {
+        print_breakdown_table(all_stats, theme, opts.layout);
+        outln!();
+    }
+
+    // Each copy's share of the work, with --instances
+    if all_stats.iter().any(|s| s.all_runs.iter().any(|r| !r.instances.is_empty())) {
+        print_instances_table(all_stats, theme, opts.layout);
+        outln!();
+    }
+
+    // Per-phase times, with --phases
+    if all_stats.iter().any(|s| s.all_runs.iter().any(|r| !r.phases.is_empty())) {
+        print_phase_table(all_stats, theme, opts.layout);
+        outln!();
+    }
+
+    // CPU time and how many cores it kept busy, where runs were watched
+    if all_stats.iter().any(|s| s.all_runs.iter().any(|r| r.cpu.is_some())) {
+        print_cpu_table(all_stats, theme, opts.layout);
+        outln!();
+    }
+
+    // System call counts, with --syscalls
+    if all_stats.iter().any(|s| s.syscalls.is_some()) {
+        print_syscall_table(all_stats, theme, opts.layout);
+        outln!();
+    }
+
+    // Faults and context switches, with --backend time
+    if all_stats.iter().any(|s| s.all_runs.iter().any(|r| r.usage.is_some())) {
+        print_usage_table(all_stats, theme, opts.layout);
+        outln!();
+    }
 
     // Memory comparison table
-    print_memory_table(all_stats);
-    println!();
+    let limit = opts.limits.of(&metric::MEMORY);
+    print_metric_table(all_stats, &metric::MEMORY, theme.memory, theme, opts.layout, limit);
+    outln!();
+
+    // Time against memory, with --tradeoff
+    if opts.tradeoff {
+        print_tradeoff(all_stats, theme);
+        outln!();
+    }
+
+    // How much each command printed, with --count-output
+    if all_stats.iter().any(|s| s.output_mean.is_some()) {
+        print_output_table(all_stats, theme, opts.layout);
+        outln!();
+    }
 
     // Overall summary
-    print_summary(all_stats);
+    print_summary(all_stats, opts);
 }
 
-fn print_time_table(all_stats: &[CommandStats]) {
+/// Every command side by side after a `--group` report's sections, ranked
+/// by time across groups.
+fn print_group_appendix(all_stats: &[CommandStats], theme: &Theme, layout: Layout) {
+    let unit = TimeUnit::common(all_stats.iter().map(|s| s.time_center));
+    let bytes = ByteUnit::common(all_stats.iter().filter_map(|s| s.peak_memory_bytes));
+    let mut by_time: Vec<usize> = (0..all_stats.len()).collect();
+    by_time.sort_by_key(|&i| all_stats[i].time_center);
+    let fastest = by_time[0];
+
+    let mut header = vec![colored(
+        Cell::new("🗂  Across groups").add_attribute(Attribute::Bold),
+        theme.time,
+    )];
+    for s in all_stats {
+        header.push(Cell::new(&s.label).add_attribute(Attribute::Bold));
+    }
+    let mut grid = vec![header];
+    let mut group_row = vec![Cell::new("Group")];
+    let mut time_row = vec![Cell::new(stats::estimator(all_stats).row())];
+    let mut memory_row = vec![Cell::new("Peak RSS")];
+    let mut rank_row = vec![Cell::new("Rank")];
+    for (i, s) in all_stats.iter().enumerate() {
+        group_row.push(Cell::new(s.group.as_deref().unwrap_or(stats::OTHER_GROUP)));
+        let time = unit.format(s.time_center);
+        time_row.push(if i == fastest {
+            winner_cell(&time, theme)
+        } else {
+            Cell::new(time)
+        });
+        memory_row.push(Cell::new(
+            s.peak_memory_bytes.map_or_else(|| "N/A".to_string(), |b| bytes.format(b)),
+        ));
+        let rank = by_time.iter().position(|&j| j == i).unwrap_or(0) + 1;
+        rank_row.push(Cell::new(format!("#{}", rank)));
+    }
+    grid.extend([group_row, time_row, memory_row, rank_row]);
+    outln!("{}", render_table(grid, layout));
+
+    let s = &all_stats[fastest];
+    outln!(
+        "  {} Fastest overall: {} ({})",
+        theme.time.paint("→"),
+        theme.winner.paint(&s.label).bold(),
+        s.group.as_deref().unwrap_or(stats::OTHER_GROUP)
+    );
+    outln!();
+}
+
+fn print_time_table(all_stats: &[CommandStats], theme: &Theme, layout: Layout, limit: Option<f64>) {
     let time_comp = stats::compare_time(all_stats);
     let winner_idx = time_comp.as_ref().map(|c| c.winner_index);
-
-    let mut table = Table::new();
-    table
-        .load_preset(UTF8_FULL)
-        .apply_modifier(UTF8_ROUND_CORNERS)
-        .set_content_arrangement(ContentArrangement::Dynamic);
+    let unit = TimeUnit::common(all_stats.iter().flat_map(|s| [s.time_mean, s.time_center]));
+    let estimator = stats::estimator(all_stats);
 
     // Header row
-    let mut header = vec![Cell::new("⏱  Time")
-        .add_attribute(Attribute::Bold)
-        .fg(Color::Cyan)];
+    let mut header = vec![colored(
+        Cell::new("⏱  Time").add_attribute(Attribute::Bold),
+        theme.time,
+    )];
     for s in all_stats {
         header.push(Cell::new(&s.label).add_attribute(Attribute::Bold));
     }
-    table.set_header(header);
+    let mut grid = vec![header];
 
-    // Mean row
-    let mut mean_row = vec![Cell::new("Mean")];
+    // Estimate row, on which commands are compared
+    let mut center_row = vec![Cell::new(estimator.row())];
     for (i, s) in all_stats.iter().enumerate() {
-        let cell = Cell::new(format_duration(s.time_mean));
-        mean_row.push(if winner_idx == Some(i) {
-            cell.fg(Color::Green).add_attribute(Attribute::Bold)
+        let value = unit.format(s.time_center);
+        center_row.push(if limit.is_some_and(|l| s.time_center.as_secs_f64() > l) {
+            colored(Cell::new(value), theme.loser)
+        } else if winner_idx == Some(i) {
+            winner_cell(&value, theme)
         } else {
-            cell
+            Cell::new(value)
         });
     }
-    table.add_row(mean_row);
+    grid.push(center_row);
+    grid.push(vs_first_row(all_stats, |s| s.time_center.as_secs_f64()));
 
-    // Min row
-    let mut min_row = vec![Cell::new("Min")];
-    for s in all_stats {
-        min_row.push(Cell::new(format_duration(s.time_min)));
+    // The plain mean too, when something else is compared
+    if estimator != Estimator::Mean {
+        let mut mean_row = vec![Cell::new("Mean")];
+        for s in all_stats {
+            mean_row.push(Cell::new(unit.format(s.time_mean)));
+        }
+        grid.push(mean_row);
     }
-    table.add_row(min_row);
 
-    // Max row
-    let mut max_row = vec![Cell::new("Max")];
-    for s in all_stats {
-        max_row.push(Cell::new(format_duration(s.time_max)));
+    // Min and max rows, unless one is what's compared
+    if estimator != Estimator::Min {
+        let mut min_row = vec![Cell::new("Min")];
+        for s in all_stats {
+            min_row.push(Cell::new(unit.format(s.time_min)));
+        }
+        grid.push(min_row);
+    }
+    if estimator != Estimator::Max {
+        let mut max_row = vec![Cell::new("Max")];
+        for s in all_stats {
+            max_row.push(Cell::new(unit.format(s.time_max)));
+        }
+        grid.push(max_row);
     }
-    table.add_row(max_row);
 
     // Std Dev row
     let mut std_row = vec![Cell::new("Std Dev")];
     for s in all_stats {
-        std_row.push(Cell::new(format!("±{}", format_duration(s.time_std_dev))));
+        std_row.push(Cell::new(format!("±{}", unit.format(s.time_std_dev))));
     }
-    table.add_row(std_row);
+    grid.push(std_row);
 
-    println!("{table}");
+    // Stability rows
+    let mut cv_row = vec![Cell::new("CV")];
+    let mut grade_row = vec![Cell::new("Stability")];
+    for s in all_stats {
+        cv_row.push(Cell::new(format!("{}%", units::number(s.time_cv * 100.0, 1))));
+        let grade = Cell::new(format!("{:?}", s.stability));
+        grade_row.push(if s.stability == Stability::D {
+            colored(grade, theme.warning)
+        } else {
+            grade
+        });
+    }
+    grid.push(cv_row);
+    grid.push(grade_row);
+    outln!("{}", render_table(grid, layout));
 
     // Bar chart
     print_bar_chart(
         all_stats,
-        |s| s.time_mean.as_secs_f64(),
-        |v| format_duration(std::time::Duration::from_secs_f64(v)),
+        |s| s.time_center.as_secs_f64(),
+        |v| unit.format(std::time::Duration::from_secs_f64(v)),
         winner_idx,
+        theme,
     );
 
     // Comparison note
     if let Some(comp) = time_comp {
-        if comp.ratio > 1.01 {
-            println!(
-                "  {} {} is {:.2}x faster",
-                "→".cyan(),
-                all_stats[comp.winner_index].label.green().bold(),
-                comp.ratio
+        if comp.unstable {
+            outln!(
+                "  {} No winner: timings are too unstable (grade D); add runs or quiet the machine",
+                theme.warning.paint("→")
+            );
+        } else if comp.ratio > 1.01 {
+            outln!(
+                "  {} {} {}{}",
+                theme.time.paint("→"),
+                theme.winner.paint(&all_stats[comp.winner_index].label).bold(),
+                metric::TIME.verdict(comp.ratio),
+                if comp.inconclusive {
+                    theme.warning.paint(" (inconclusive: within noise)")
+                } else if comp.low_confidence {
+                    theme.warning.paint(" (low confidence: under 5ms, mostly process startup)")
+                } else {
+                    String::new()
+                }
             );
         } else {
-            println!("  {} Roughly the same speed", "→".cyan());
+            outln!("  {} Roughly the same speed", theme.time.paint("→"));
+        }
+    }
+}
+
+fn print_breakdown_table(all_stats: &[CommandStats], theme: &Theme, layout: Layout) {
+    let unit = TimeUnit::common(
+        all_stats
+            .iter()
+            .flat_map(|s| [s.time_mean, s.prepare_mean.unwrap_or(s.time_mean)]),
+    );
+    let wall_unit = TimeUnit::common(all_stats.iter().map(|s| s.wall_time));
+
+    let mut header = vec![colored(
+        Cell::new("⏳ Breakdown").add_attribute(Attribute::Bold),
+        theme.time,
+    )];
+    for s in all_stats {
+        header.push(Cell::new(&s.label).add_attribute(Attribute::Bold));
+    }
+    let mut grid = vec![header];
+
+    let mut prepare_row = vec![Cell::new("Prepare")];
+    let mut command_row = vec![Cell::new("Command")];
+    let mut share_row = vec![Cell::new("Prepare share")];
+    let mut wall_row = vec![Cell::new("Wall time")];
+    for s in all_stats {
+        let prepare = s.prepare_mean.unwrap_or_default();
+        let share = prepare.as_secs_f64() / (prepare + s.time_mean).as_secs_f64() * 100.0;
+        prepare_row.push(Cell::new(unit.format(prepare)));
+        command_row.push(Cell::new(unit.format(s.time_mean)));
+        share_row.push(if share > 50.0 {
+            colored(Cell::new(format!("{:.0}%", share)), theme.warning)
+        } else {
+            Cell::new(format!("{:.0}%", share))
+        });
+        wall_row.push(Cell::new(wall_unit.format(s.wall_time)));
+    }
+    grid.extend([prepare_row, command_row, share_row, wall_row]);
+    outln!("{}", render_table(grid, layout));
+
+    outln!(
+        "  {} Prepare time is excluded from the timings above",
+        theme.time.paint("→")
+    );
+    for s in all_stats {
+        if let Some(prepare) = s.prepare_mean
+            && prepare > s.time_mean
+        {
+            outln!(
+                "  {} {}: preparation takes {:.1}x as long as the command and dominates the session",
+                theme.warning.paint("⚠"),
+                s.label,
+                prepare.as_secs_f64() / s.time_mean.as_secs_f64()
+            );
+        }
+    }
+}
+
+/// One row per command and one column per `--matrix` value, each cell
+/// shaded from the winner color (the command's fastest value) towards the
+/// loser color (its slowest), so every command's sweet spot stands out.
+fn print_heatmap(all_stats: &[CommandStats], theme: &Theme) {
+    let cells: Vec<(&MatrixCell, &CommandStats)> =
+        all_stats.iter().filter_map(|s| Some((s.matrix.as_ref()?, s))).collect();
+    let mut rows: Vec<&str> = Vec::new();
+    let mut values: Vec<&str> = Vec::new();
+    for (cell, _) in &cells {
+        if !rows.contains(&cell.row.as_str()) {
+            rows.push(&cell.row);
         }
+        if !values.contains(&cell.value.as_str()) {
+            values.push(&cell.value);
+        }
+    }
+    let find = |row: &str, value: &str| {
+        cells
+            .iter()
+            .find(|(c, _)| c.row == row && c.value == value)
+            .map(|(_, s)| s.time_center)
+    };
+    let unit = TimeUnit::common(cells.iter().map(|(_, s)| s.time_center));
+
+    let mut header = vec![colored(
+        Cell::new(format!("🌡  {}", cells[0].0.var)).add_attribute(Attribute::Bold),
+        theme.time,
+    )];
+    header.extend(values.iter().map(|v| Cell::new(v).add_attribute(Attribute::Bold)));
+    let mut grid = vec![header];
+    let mut sweet_spots = Vec::new();
+    for row in &rows {
+        let times: Vec<Option<Duration>> = values.iter().map(|v| find(row, v)).collect();
+        let measured = times.iter().flatten();
+        let (Some(best), Some(worst)) = (measured.clone().min(), measured.clone().max()) else {
+            continue;
+        };
+        let spread = worst.as_secs_f64() / best.as_secs_f64() - 1.0;
+        let mut cells = vec![Cell::new(row)];
+        for time in &times {
+            let Some(time) = time else {
+                cells.push(Cell::new("—"));
+                continue;
+            };
+            let behind = time.as_secs_f64() / best.as_secs_f64() - 1.0;
+            if time == best {
+                cells.push(winner_cell(&unit.format(*time), theme));
+            } else {
+                let text = format!("{} +{}%", unit.format(*time), units::number(behind * 100.0, 0));
+                let heat = if spread > 0.0 { behind / spread } else { 0.0 };
+                cells.push(colored(Cell::new(text), theme.winner.blend(theme.loser, heat)));
+            }
+        }
+        grid.push(cells);
+        if let Some(at) = times.iter().position(|t| *t == Some(*best)) {
+            sweet_spots.push((row, values[at]));
+        }
+    }
+    outln!("{}", render_table(grid, Layout::Columns));
+
+    for (row, value) in sweet_spots {
+        outln!(
+            "  {} {} is fastest at {}={}",
+            theme.time.paint("→"),
+            row,
+            cells[0].0.var,
+            theme.winner.paint(value).bold()
+        );
     }
 }
 
-fn print_memory_table(all_stats: &[CommandStats]) {
-    let mem_comp = stats::compare_memory(all_stats);
-    let winner_idx = mem_comp.as_ref().map(|c| c.winner_index);
+/// One row per command and one column per `--scaling` thread count, each
+/// cell the command's speedup over its fewest threads and the efficiency
+/// of that speedup (100% when doubling threads halves the time), with the
+/// cores it actually kept busy where CPU was measured.
+fn print_scaling_table(all_stats: &[CommandStats], theme: &Theme) {
+    let cells: Vec<(&MatrixCell, &CommandStats)> =
+        all_stats.iter().filter_map(|s| Some((s.matrix.as_ref()?, s))).collect();
+    let var = &cells[0].0.var;
+    let mut rows: Vec<&str> = Vec::new();
+    let mut counts: Vec<u32> = Vec::new();
+    for (cell, _) in &cells {
+        if !rows.contains(&cell.row.as_str()) {
+            rows.push(&cell.row);
+        }
+        // Validated as thread counts when --scaling was parsed
+        let count = cell.value.parse().unwrap_or(1);
+        if !counts.contains(&count) {
+            counts.push(count);
+        }
+    }
+    counts.sort_unstable();
+    let find = |row: &str, count: u32| {
+        cells
+            .iter()
+            .find(|(c, _)| c.row == row && c.value.parse() == Ok(count))
+            .map(|(_, s)| *s)
+    };
+    let cores = |s: &CommandStats| {
+        let used: Vec<f64> = s
+            .all_runs
+            .iter()
+            .filter(|r| !r.duration.is_zero())
+            .filter_map(|r| Some(r.cpu?.time.as_secs_f64() / r.duration.as_secs_f64()))
+            .collect();
+        (!used.is_empty()).then(|| used.iter().sum::<f64>() / used.len() as f64)
+    };
+
+    let mut header = vec![colored(
+        Cell::new(format!("📈 Scaling ({})", var)).add_attribute(Attribute::Bold),
+        theme.time,
+    )];
+    header.extend(counts.iter().map(|n| Cell::new(n).add_attribute(Attribute::Bold)));
+    let mut grid = vec![header];
+    // Each command's speedup and efficiency at the most threads it ran with
+    let mut furthest: Vec<(&str, u32, u32, f64, f64)> = Vec::new();
+    for row in &rows {
+        let Some((base, base_stats)) = counts.iter().find_map(|&n| Some((n, find(row, n)?)))
+        else {
+            continue;
+        };
+        let base_time = base_stats.time_center.as_secs_f64();
+        let mut line = vec![Cell::new(row)];
+        let mut last = None;
+        for &n in &counts {
+            let Some(s) = find(row, n) else {
+                line.push(Cell::new("—"));
+                continue;
+            };
+            let speedup = base_time / s.time_center.as_secs_f64();
+            let efficiency = speedup / (n as f64 / base as f64);
+            let mut text = if n == base {
+                "1.00x".to_string()
+            } else {
+                let percent = units::number(efficiency * 100.0, 0);
+                format!("{} · {}%", metric::ratio_text(speedup), percent)
+            };
+            if let Some(cores) = cores(s) {
+                text.push_str(&format!(" · {} cores", units::number(cores, 1)));
+            }
+            // Shaded from the winner color at perfect scaling to the loser
+            // color where the extra threads bought nothing
+            let heat = if n == base { 0.0 } else { (1.0 - efficiency).clamp(0.0, 1.0) };
+            line.push(colored(Cell::new(text), theme.winner.blend(theme.loser, heat)));
+            if n > base {
+                last = Some((*row, base, n, speedup, efficiency));
+            }
+        }
+        grid.push(line);
+        furthest.extend(last);
+    }
+    outln!("{}", render_table(grid, Layout::Columns));
 
-    // Check if we have any memory data
-    if all_stats.iter().all(|s| s.peak_memory_bytes == 0) {
-        println!(
-            "  {} Memory data unavailable (processes too short-lived to measure)",
-            "💾".dimmed()
+    for (row, base, n, speedup, efficiency) in &furthest {
+        outln!(
+            "  {} {} runs {} faster at {}={} than at {} ({}% efficient)",
+            theme.time.paint("→"),
+            row,
+            metric::ratio_text(*speedup),
+            var,
+            n,
+            base,
+            units::number(efficiency * 100.0, 0)
+        );
+    }
+    // Only commands swept over the same counts compare fairly
+    let comparable: Vec<_> = furthest
+        .iter()
+        .filter(|f| (f.1, f.2) == (furthest[0].1, furthest[0].2))
+        .collect();
+    if furthest.len() > 1
+        && comparable.len() == furthest.len()
+        && let Some(best) = comparable.iter().max_by(|a, b| a.3.total_cmp(&b.3))
+    {
+        outln!(
+            "  {} {} scales best",
+            theme.winner.paint("✓"),
+            theme.winner.paint(best.0).bold()
         );
-        return;
     }
+}
 
-    let mut table = Table::new();
-    table
-        .load_preset(UTF8_FULL)
-        .apply_modifier(UTF8_ROUND_CORNERS)
-        .set_content_arrangement(ContentArrangement::Dynamic);
+fn print_rounds_table(
+    all_stats: &[CommandStats],
+    winners: &[Option<usize>],
+    theme: &Theme,
+    layout: Layout,
+) {
+    let unit = TimeUnit::common(all_stats.iter().flat_map(|s| s.rounds.iter().copied()));
+    let mut header = vec![colored(
+        Cell::new("🔁 Rounds").add_attribute(Attribute::Bold),
+        theme.time,
+    )];
+    for s in all_stats {
+        header.push(Cell::new(&s.label).add_attribute(Attribute::Bold));
+    }
+    let mut grid = vec![header];
+    for (k, winner) in winners.iter().enumerate() {
+        let mut row = vec![Cell::new(format!("Round {}", k + 1))];
+        for (i, s) in all_stats.iter().enumerate() {
+            let value = unit.format(s.rounds[k]);
+            row.push(if *winner == Some(i) {
+                winner_cell(&value, theme)
+            } else {
+                Cell::new(value)
+            });
+        }
+        grid.push(row);
+    }
+    let mut wins_row = vec![Cell::new("Wins")];
+    for i in 0..all_stats.len() {
+        let wins = winners.iter().filter(|w| **w == Some(i)).count();
+        wins_row.push(Cell::new(format!("{}/{}", wins, winners.len())));
+    }
+    grid.push(wins_row);
+    outln!("{}", render_table(grid, layout));
 
-    // Header row
-    let mut header = vec![Cell::new("💾  Memory")
-        .add_attribute(Attribute::Bold)
-        .fg(Color::Magenta)];
+    let note = rounds_summary(all_stats, winners);
+    let stable = winners.iter().all(|w| w.is_some() && *w == winners[0]);
+    outln!(
+        "  {} {}",
+        if stable {
+            theme.winner.paint("✓")
+        } else {
+            theme.warning.paint("⚠")
+        },
+        note
+    );
+}
+
+/// One row per stretch of a `--soak` session with each command's mean
+/// time and RSS in it, then whether either drifted from first to last.
+fn print_soak_table(all_stats: &[CommandStats], theme: &Theme, layout: Layout) {
+    let soaks: Vec<Option<&Soak>> = all_stats.iter().map(|s| s.soak.as_ref()).collect();
+    let buckets = soaks.iter().flatten().map(|s| s.buckets.as_slice());
+    let unit = TimeUnit::common(buckets.clone().flatten().filter_map(|b| b.mean));
+    let bytes =
+        ByteUnit::common(buckets.clone().flatten().filter_map(|b| b.mean_peak_memory_bytes));
+    let rows = buckets.clone().map(<[SoakBucket]>::len).max().unwrap_or(0);
+
+    let mut header = vec![colored(
+        Cell::new("⌛ Soak").add_attribute(Attribute::Bold),
+        theme.time,
+    )];
     for s in all_stats {
         header.push(Cell::new(&s.label).add_attribute(Attribute::Bold));
     }
-    table.set_header(header);
+    let mut grid = vec![header];
+    for k in 0..rows {
+        let from = buckets.clone().find_map(|b| b.get(k)).map_or(0, |b| b.from.as_secs());
+        let mut row = vec![Cell::new(format!("From {}:{:02}", from / 60, from % 60))];
+        for soak in &soaks {
+            let bucket = soak.and_then(|s| s.buckets.get(k));
+            let text = match bucket.and_then(|b| b.mean) {
+                Some(mean) => match bucket.and_then(|b| b.mean_peak_memory_bytes) {
+                    Some(rss) => format!("{} · {}", unit.format(mean), bytes.format(rss)),
+                    None => unit.format(mean),
+                },
+                None => "—".to_string(),
+            };
+            row.push(Cell::new(text));
+        }
+        grid.push(row);
+    }
+    let mut drift_row = vec![Cell::new("Drift")];
+    for soak in &soaks {
+        let text = soak.map_or_else(
+            || "N/A".to_string(),
+            |s| {
+                let signed = |change: f64| {
+                    let sign = if change < 0.0 { "−" } else { "+" };
+                    format!("{}{}%", sign, units::number(change.abs() * 100.0, 1))
+                };
+                match s.memory_drift {
+                    Some(memory) => format!("{} · {}", signed(s.time_drift), signed(memory)),
+                    None => signed(s.time_drift),
+                }
+            },
+        );
+        drift_row.push(Cell::new(text));
+    }
+    grid.push(drift_row);
+    if all_stats.iter().any(|s| s.memory_trend.is_some()) {
+        let mut trend_row = vec![Cell::new("RSS trend")];
+        for s in all_stats {
+            trend_row.push(Cell::new(s.memory_trend.map_or_else(
+                || "N/A".to_string(),
+                |t| {
+                    let sign = if t.kb_per_min < 0.0 { "−" } else { "+" };
+                    format!("{}{} KB/min", sign, units::number(t.kb_per_min.abs(), 1))
+                },
+            )));
+        }
+        grid.push(trend_row);
+    }
+    outln!("{}", render_table(grid, layout));
 
-    // Peak RSS row
-    let mut mem_row = vec![Cell::new("Peak RSS")];
+    for (s, soak) in all_stats.iter().zip(&soaks) {
+        let Some(soak) = soak else {
+            outln!(
+                "  {} {} didn't run long enough to compare the start of the soak with its end",
+                "ℹ".dimmed(),
+                s.label
+            );
+            continue;
+        };
+        let mut drifted = false;
+        if soak.time_drifts {
+            drifted = true;
+            outln!(
+                "  {} {} {} {}% over the soak",
+                theme.warning.paint("⚠"),
+                s.label,
+                if soak.time_drift > 0.0 { "slows down" } else { "speeds up" },
+                units::number(soak.time_drift.abs() * 100.0, 1)
+            );
+        }
+        if let Some(memory) = soak.memory_drift
+            && soak.memory_drifts
+            && memory > 0.0
+        {
+            drifted = true;
+            outln!(
+                "  {} {}'s peak RSS grows {}% over the soak: possibly a leak",
+                theme.warning.paint("⚠"),
+                s.label,
+                units::number(memory * 100.0, 1)
+            );
+        }
+        if !drifted {
+            outln!(
+                "  {} {} holds steady over the soak",
+                theme.winner.paint("✓"),
+                s.label
+            );
+        }
+    }
+}
+
+/// "apple won all 3 rounds, so the conclusion is stable", or how the
+/// wins split when it isn't.
+fn rounds_summary(all_stats: &[CommandStats], winners: &[Option<usize>]) -> String {
+    let rounds = winners.len();
+    let ties = winners.iter().filter(|w| w.is_none()).count();
+    let mut wins: Vec<(usize, usize)> = (0..all_stats.len())
+        .map(|i| (i, winners.iter().filter(|w| **w == Some(i)).count()))
+        .filter(|(_, n)| *n > 0)
+        .collect();
+    wins.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+    match wins.as_slice() {
+        [(i, n)] if *n == rounds => format!(
+            "{} won all {} rounds, so the conclusion is stable",
+            all_stats[*i].label, rounds
+        ),
+        [] => format!("no command won any of the {} rounds by more than 1%", rounds),
+        _ => {
+            let mut split: Vec<String> = wins
+                .iter()
+                .map(|(i, n)| format!("{} won {} of {}", all_stats[*i].label, n, rounds))
+                .collect();
+            if ties > 0 {
+                split.push(format!("{} tied", ties));
+            }
+            format!("the winner changed between rounds: {}", split.join(", "))
+        }
+    }
+}
+
+/// Aggregate and per-instance figures for `--instances` runs.
+fn print_instances_table(all_stats: &[CommandStats], theme: &Theme, layout: Layout) {
+    let mean = |values: &[Duration]| {
+        (!values.is_empty()).then(|| values.iter().sum::<Duration>() / values.len() as u32)
+    };
+    let per_instance: Vec<Option<Duration>> = all_stats
+        .iter()
+        .map(|s| {
+            let times: Vec<Duration> =
+                s.all_runs.iter().flat_map(|r| r.instances.iter().copied()).collect();
+            mean(&times)
+        })
+        .collect();
+    let slowest: Vec<Option<Duration>> = all_stats
+        .iter()
+        .map(|s| {
+            let times: Vec<Duration> =
+                s.all_runs.iter().filter_map(|r| r.instances.iter().max().copied()).collect();
+            mean(&times)
+        })
+        .collect();
+    let copies: Vec<usize> = all_stats
+        .iter()
+        .map(|s| s.all_runs.iter().map(|r| r.instances.len()).max().unwrap_or(0))
+        .collect();
+    let unit = TimeUnit::common(
+        all_stats
+            .iter()
+            .map(|s| s.time_mean)
+            .chain(per_instance.iter().chain(&slowest).flatten().copied()),
+    );
+    let bytes = ByteUnit::common(all_stats.iter().filter_map(|s| s.peak_memory_bytes));
+    let na = || "N/A".to_string();
+
+    let mut header = vec![colored(
+        Cell::new("👥 Instances").add_attribute(Attribute::Bold),
+        theme.time,
+    )];
+    for s in all_stats {
+        header.push(Cell::new(&s.label).add_attribute(Attribute::Bold));
+    }
+    let mut grid = vec![header];
+    let mut count_row = vec![Cell::new("Copies")];
+    let mut total_row = vec![Cell::new("All done")];
+    let mut mean_row = vec![Cell::new("Per instance")];
+    let mut slowest_row = vec![Cell::new("Slowest")];
+    let mut memory_row = vec![Cell::new("RSS/instance")];
     for (i, s) in all_stats.iter().enumerate() {
-        let cell = Cell::new(format_bytes(s.peak_memory_bytes));
-        mem_row.push(if winner_idx == Some(i) {
-            cell.fg(Color::Green).add_attribute(Attribute::Bold)
-        } else {
-            cell
+        count_row.push(Cell::new(copies[i].max(1)));
+        total_row.push(Cell::new(unit.format(s.time_mean)));
+        mean_row.push(Cell::new(per_instance[i].map_or_else(na, |d| unit.format(d))));
+        slowest_row.push(Cell::new(slowest[i].map_or_else(na, |d| unit.format(d))));
+        memory_row.push(Cell::new(
+            s.peak_memory_bytes
+                .map_or_else(na, |b| bytes.format(b / copies[i].max(1) as u64)),
+        ));
+    }
+    grid.extend([count_row, total_row, mean_row, slowest_row, memory_row]);
+    outln!("{}", render_table(grid, layout));
+    outln!(
+        "  {} Times above are until every copy exits; peak RSS is the copies' peaks added up",
+        "ℹ".dimmed()
+    );
+}
+
+fn print_build_table(all_stats: &[CommandStats], theme: &Theme, layout: Layout) {
+    let builds: Vec<Option<Duration>> =
+        all_stats.iter().map(|s| s.build.as_ref().map(|b| b.duration)).collect();
+    let unit = TimeUnit::common(builds.iter().flatten().copied());
+    let bytes = ByteUnit::common(
+        all_stats
+            .iter()
+            .filter_map(|s| s.build.as_ref()?.peak_memory_bytes),
+    );
+
+    // Only a build every command had has a winner
+    let measured: Option<Vec<Duration>> = builds.iter().copied().collect();
+    let winner = measured.filter(|m| m.len() > 1).and_then(|m| {
+        let (best, fastest) = m.iter().enumerate().min_by_key(|(_, d)| **d)?;
+        let slowest = m.iter().max()?;
+        Some((best, slowest.as_secs_f64() / fastest.as_secs_f64()))
+    });
+
+    let mut header = vec![colored(
+        Cell::new("🔨 Build").add_attribute(Attribute::Bold),
+        theme.time,
+    )];
+    for s in all_stats {
+        header.push(Cell::new(&s.label).add_attribute(Attribute::Bold));
+    }
+    let mut grid = vec![header];
+    let mut time_row = vec![Cell::new("Time")];
+    let mut memory_row = vec![Cell::new("Peak RSS")];
+    for (i, s) in all_stats.iter().enumerate() {
+        let value = builds[i].map_or("N/A".to_string(), |d| unit.format(d));
+        time_row.push(match winner {
+            Some((best, ratio)) if best == i && ratio > 1.01 => winner_cell(&value, theme),
+            _ => Cell::new(value),
         });
+        memory_row.push(Cell::new(
+            s.build
+                .as_ref()
+                .and_then(|b| b.peak_memory_bytes)
+                .map_or("N/A".to_string(), |b| bytes.format(b)),
+        ));
     }
-    table.add_row(mem_row);
+    grid.extend([time_row, memory_row]);
+    outln!("{}", render_table(grid, layout));
 
-    println!("{table}");
+    outln!(
+        "  {} Build time is excluded from the timings above",
+        theme.time.paint("→")
+    );
+    let Some((best, ratio)) = winner.filter(|&(_, ratio)| ratio > 1.01) else {
+        return;
+    };
+    outln!(
+        "  {} {} builds {} faster",
+        theme.time.paint("→"),
+        theme.winner.paint(&all_stats[best].label).bold(),
+        metric::ratio_text(ratio)
+    );
+    // When the quickest build isn't the quickest to run, say when the
+    // slower build has paid for itself
+    let fastest = all_stats.iter().enumerate().min_by_key(|(_, s)| s.time_mean);
+    if let Some((fastest, s)) = fastest
+        && fastest != best
+        && let (Some(slow), Some(quick)) = (builds[fastest], builds[best])
+        && s.time_mean < all_stats[best].time_mean
+    {
+        let saved = (all_stats[best].time_mean - s.time_mean).as_secs_f64();
+        let runs = ((slow - quick).as_secs_f64() / saved).ceil();
+        outln!(
+            "  {} {}'s longer build pays for itself after {} run{}",
+            theme.time.paint("→"),
+            s.label,
+            units::number(runs, 0),
+            if runs == 1.0 { "" } else { "s" }
+        );
+    }
+}
 
-    // Bar chart
-    print_bar_chart(
-        all_stats,
-        |s| s.peak_memory_bytes as f64,
-        |v| format_bytes(v as u64),
-        winner_idx,
+fn print_phase_table(all_stats: &[CommandStats], theme: &Theme, layout: Layout) {
+    let mut names: Vec<String> = Vec::new();
+    for name in all_stats.iter().flat_map(stats::phase_names) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    let unit = TimeUnit::common(
+        names
+            .iter()
+            .flat_map(|name| all_stats.iter().filter_map(|s| stats::phase_mean(s, name))),
     );
 
-    // Comparison note
-    if let Some(comp) = mem_comp {
+    let mut header = vec![colored(
+        Cell::new("🧩 Phases").add_attribute(Attribute::Bold),
+        theme.time,
+    )];
+    for s in all_stats {
+        header.push(Cell::new(&s.label).add_attribute(Attribute::Bold));
+    }
+    let mut grid = vec![header];
+    let mut verdicts = Vec::new();
+    for name in &names {
+        let means: Vec<Option<Duration>> =
+            all_stats.iter().map(|s| stats::phase_mean(s, name)).collect();
+        // Only phases every command marked have a winner
+        let measured: Option<Vec<Duration>> = means.iter().copied().collect();
+        let winner = measured.filter(|m| m.len() > 1).and_then(|m| {
+            let (best, fastest) = m.iter().enumerate().min_by_key(|(_, d)| **d)?;
+            let slowest = m.iter().max()?;
+            Some((best, slowest.as_secs_f64() / fastest.as_secs_f64()))
+        });
+        let mut row = vec![Cell::new(name)];
+        for (i, mean) in means.iter().enumerate() {
+            let value = mean.map_or("N/A".to_string(), |d| unit.format(d));
+            row.push(match winner {
+                Some((best, ratio)) if best == i && ratio > 1.01 => winner_cell(&value, theme),
+                _ => Cell::new(value),
+            });
+        }
+        grid.push(row);
+        if let Some((best, ratio)) = winner.filter(|&(_, ratio)| ratio > 1.01) {
+            verdicts.push((name, best, ratio));
+        }
+    }
+    // The whole run, as in the time table, for scale
+    if names.len() > 1 {
+        let mut total_row = vec![Cell::new("Total")];
+        for s in all_stats {
+            total_row.push(Cell::new(unit.format(s.time_mean)));
+        }
+        grid.push(total_row);
+    }
+    outln!("{}", render_table(grid, layout));
+
+    for (name, best, ratio) in verdicts {
+        outln!(
+            "  {} {}: {} {}",
+            theme.time.paint("→"),
+            name,
+            theme.winner.paint(&all_stats[best].label).bold(),
+            metric::TIME.verdict(ratio)
+        );
+    }
+}
+
+fn print_output_table(all_stats: &[CommandStats], theme: &Theme, layout: Layout) {
+    let streams = [
+        (&metric::STDOUT, &metric::STDOUT_LINES),
+        (&metric::STDERR, &metric::STDERR_LINES),
+    ];
+    // Both streams share one byte unit
+    let all_bytes: Vec<f64> = streams
+        .iter()
+        .flat_map(|(bytes, _)| all_stats.iter().filter_map(|s| bytes.summary(s)))
+        .collect();
+    let format_bytes = metric::STDOUT.formatter(&all_bytes);
+    let format_lines = metric::STDOUT_LINES.formatter(&[]);
+
+    let mut header = vec![colored(
+        Cell::new("📄 Output").add_attribute(Attribute::Bold),
+        theme.time,
+    )];
+    for s in all_stats {
+        header.push(Cell::new(&s.label).add_attribute(Attribute::Bold));
+    }
+    let mut grid = vec![header];
+    for (bytes, lines) in streams {
+        let mut row = vec![Cell::new(bytes.row)];
+        for s in all_stats {
+            row.push(Cell::new(match (bytes.summary(s), lines.summary(s)) {
+                (Some(b), Some(l)) if b > 0.0 => {
+                    format!("{} · {} lines", format_bytes(b), format_lines(l))
+                }
+                (Some(_), _) => "—".to_string(),
+                (None, _) => "N/A".to_string(),
+            }));
+        }
+        grid.push(row);
+    }
+    outln!("{}", render_table(grid, layout));
+}
+
+/// Most system calls a `--syscalls` table lists by name.
+const TOP_SYSCALLS: usize = 8;
+
+/// Each command's system call total and its most frequent calls, from one
+/// traced run, then which makes the fewest and where the gap comes from.
+fn print_syscall_table(all_stats: &[CommandStats], theme: &Theme, layout: Layout) {
+    let counts: Vec<Option<&Syscalls>> = all_stats.iter().map(|s| s.syscalls.as_ref()).collect();
+    let calls_of = |c: &Syscalls, name: &str| {
+        c.calls.iter().find(|call| call.name == name).map_or(0, |call| call.calls)
+    };
+    // The calls some command makes most of, busiest first
+    let mut names: Vec<&str> = Vec::new();
+    for c in counts.iter().flatten() {
+        for call in &c.calls {
+            if !names.contains(&call.name.as_str()) {
+                names.push(&call.name);
+            }
+        }
+    }
+    let busiest = |name: &str| counts.iter().flatten().map(|c| calls_of(c, name)).max();
+    names.sort_by_key(|name| std::cmp::Reverse(busiest(name)));
+    names.truncate(TOP_SYSCALLS);
+
+    // Only a count every command has has a winner
+    let totals: Option<Vec<u64>> = counts.iter().map(|c| c.map(|c| c.total)).collect();
+    let fewest = totals.filter(|t| t.len() > 1).and_then(|t| {
+        let (best, least) = t.iter().enumerate().min_by_key(|(_, n)| **n)?;
+        let (worst, most) = t.iter().enumerate().max_by_key(|(_, n)| **n)?;
+        (most > least).then(|| (best, worst, *most as f64 / (*least).max(1) as f64))
+    });
+    let count = |n: u64| units::number(n as f64, 0);
+
+    let mut header = vec![colored(
+        Cell::new("🔎 Syscalls").add_attribute(Attribute::Bold),
+        theme.time,
+    )];
+    for s in all_stats {
+        header.push(Cell::new(&s.label).add_attribute(Attribute::Bold));
+    }
+    let mut grid = vec![header];
+    let mut total_row = vec![Cell::new("Total")];
+    let mut errors_row = vec![Cell::new("Failed")];
+    for (i, c) in counts.iter().enumerate() {
+        let value = c.map_or("N/A".to_string(), |c| count(c.total));
+        total_row.push(match fewest {
+            Some((best, _, _)) if best == i => winner_cell(&value, theme),
+            _ => Cell::new(value),
+        });
+        errors_row.push(Cell::new(c.map_or("N/A".to_string(), |c| count(c.errors))));
+    }
+    grid.extend([total_row, errors_row]);
+    for name in &names {
+        let mut row = vec![Cell::new(*name)];
+        for c in &counts {
+            row.push(Cell::new(c.map_or("N/A".to_string(), |c| count(calls_of(c, name)))));
+        }
+        grid.push(row);
+    }
+    outln!("{}", render_table(grid, layout));
+
+    outln!(
+        "  {} Counted in one extra run under strace, excluded from the timings above",
+        theme.time.paint("→")
+    );
+    let Some((best, worst, ratio)) = fewest else {
+        return;
+    };
+    let (Some(least), Some(most)) = (counts[best], counts[worst]) else {
+        return;
+    };
+    // The call that accounts for most of the gap
+    let widest = names
+        .iter()
+        .max_by_key(|name| calls_of(most, name).saturating_sub(calls_of(least, name)));
+    let mut verdict = format!(
+        "  {} {} makes {} fewer system calls than {}",
+        theme.time.paint("→"),
+        theme.winner.paint(&all_stats[best].label).bold(),
+        metric::ratio_text(ratio),
+        all_stats[worst].label
+    );
+    if let Some(name) = widest {
+        verdict.push_str(&format!(
+            ", mostly {} ({} vs {})",
+            name,
+            count(calls_of(least, name)),
+            count(calls_of(most, name))
+        ));
+    }
+    outln!("{}", verdict);
+}
+
+/// Mean page faults and context switches per run, marking the fewest.
+fn print_usage_table(all_stats: &[CommandStats], theme: &Theme, layout: Layout) {
+    let metrics = [
+        &metric::MAJOR_FAULTS,
+        &metric::MINOR_FAULTS,
+        &metric::VOLUNTARY_SWITCHES,
+        &metric::INVOLUNTARY_SWITCHES,
+    ];
+    let format = metric::MAJOR_FAULTS.formatter(&[]);
+
+    let mut header = vec![colored(
+        Cell::new(metric::MAJOR_FAULTS.title).add_attribute(Attribute::Bold),
+        theme.time,
+    )];
+    for s in all_stats {
+        header.push(Cell::new(&s.label).add_attribute(Attribute::Bold));
+    }
+    let mut grid = vec![header];
+    for metric in metrics {
+        // No mark when every command has the same count
+        let fewest = metric
+            .compare(all_stats)
+            .filter(|c| c.ratio > 1.0)
+            .map(|c| c.winner_index);
+        let mut row = vec![Cell::new(metric.row)];
+        for (i, s) in all_stats.iter().enumerate() {
+            let value = metric.summary(s).map_or_else(|| "N/A".to_string(), &format);
+            row.push(if fewest == Some(i) {
+                winner_cell(&value, theme)
+            } else {
+                Cell::new(value)
+            });
+        }
+        grid.push(row);
+    }
+    outln!("{}", render_table(grid, layout));
+}
+
+fn print_cpu_table(all_stats: &[CommandStats], theme: &Theme, layout: Layout) {
+    let mean = |values: Vec<f64>| {
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    };
+    // CPU time over wall time: how many cores a run kept busy on average
+    let cores: Vec<Option<f64>> = all_stats
+        .iter()
+        .map(|s| {
+            mean(
+                s.all_runs
+                    .iter()
+                    .filter(|r| !r.duration.is_zero())
+                    .filter_map(|r| Some(r.cpu?.time.as_secs_f64() / r.duration.as_secs_f64()))
+                    .collect(),
+            )
+        })
+        .collect();
+    let peaks: Vec<Option<f64>> = all_stats
+        .iter()
+        .map(|s| s.all_runs.iter().filter_map(|r| r.cpu?.peak_cores).reduce(f64::max))
+        .collect();
+    let times: Vec<Option<f64>> = all_stats.iter().map(|s| metric::CPU_TIME.summary(s)).collect();
+    let format = metric::CPU_TIME.formatter(&times.iter().flatten().copied().collect::<Vec<_>>());
+    let least = metric::CPU_TIME.compare(all_stats).map(|c| c.winner_index);
+    let na = || "N/A".to_string();
+
+    let mut header = vec![colored(
+        Cell::new(metric::CPU_TIME.title).add_attribute(Attribute::Bold),
+        theme.time,
+    )];
+    for s in all_stats {
+        header.push(Cell::new(&s.label).add_attribute(Attribute::Bold));
+    }
+    let mut grid = vec![header];
+    let mut time_row = vec![Cell::new(metric::CPU_TIME.row)];
+    let mut cores_row = vec![Cell::new("Cores used")];
+    let mut peak_row = vec![Cell::new("Peak cores")];
+    for i in 0..all_stats.len() {
+        let time = times[i].map_or_else(na, &format);
+        time_row.push(if least == Some(i) {
+            winner_cell(&time, theme)
+        } else {
+            Cell::new(time)
+        });
+        cores_row.push(Cell::new(cores[i].map_or_else(na, |c| units::number(c, 2))));
+        peak_row.push(Cell::new(peaks[i].map_or_else(na, |c| units::number(c, 1))));
+    }
+    grid.extend([time_row, cores_row, peak_row]);
+    outln!("{}", render_table(grid, layout));
+
+    // A win that comes from spreading over more cores, not doing less work
+    let Some(fastest) = metric::TIME.compare(all_stats).map(|c| c.winner_index) else {
+        return;
+    };
+    let Some(other) = metric::TIME.runner_up(all_stats, fastest) else {
+        return;
+    };
+    if let (Some(fast), Some(slow)) = (cores[fastest], cores[other])
+        && fast >= 1.5
+        && fast >= slow * 1.5
+    {
+        outln!(
+            "  {} {} keeps {} cores busy against {} for {}: part of its lead is parallelism",
+            "ℹ".dimmed(),
+            all_stats[fastest].label,
+            units::number(fast, 1),
+            units::number(slow, 1),
+            all_stats[other].label
+        );
+    }
+}
+
+/// A table with one metric's aggregate per command and its change vs the
+/// first command, then a bar chart and a verdict.
+fn print_metric_table(
+    all_stats: &[CommandStats],
+    metric: &Metric,
+    paint: Paint,
+    theme: &Theme,
+    layout: Layout,
+    limit: Option<f64>,
+) {
+    let summaries: Vec<Option<f64>> = all_stats.iter().map(|s| metric.summary(s)).collect();
+    let comparison = metric.compare(all_stats);
+    let winner_idx = comparison.as_ref().map(|c| c.winner_index);
+    let format = metric.formatter(&summaries.iter().flatten().copied().collect::<Vec<_>>());
+    let value_of = |s: &CommandStats| metric.summary(s).unwrap_or(0.0);
+
+    let mut header = vec![colored(
+        Cell::new(metric.title).add_attribute(Attribute::Bold),
+        paint,
+    )];
+    for s in all_stats {
+        header.push(Cell::new(&s.label).add_attribute(Attribute::Bold));
+    }
+    let mut grid = vec![header];
+
+    let mut row = vec![Cell::new(metric.row)];
+    for (i, (s, summary)) in all_stats.iter().zip(&summaries).enumerate() {
+        let value = summary.map_or_else(
+            || {
+                if s.memory_unrecorded && metric.name == metric::MEMORY.name {
+                    return "not recorded by source".to_string();
+                }
+                let runs = metric.unmeasured(s);
+                let plural = if runs == 1 { "" } else { "s" };
+                format!("unmeasured ({} run{} {})", runs, plural, metric.missing)
+            },
+            &format,
+        );
+        row.push(if summary.zip(limit).is_some_and(|(v, l)| v > l) {
+            colored(Cell::new(value), theme.loser)
+        } else if winner_idx == Some(i) {
+            winner_cell(&value, theme)
+        } else {
+            Cell::new(value)
+        });
+    }
+    grid.push(row);
+    if summaries.iter().all(Option::is_none) {
+        outln!("{}", render_table(grid, layout));
+        return;
+    }
+    grid.push(vs_first_row(all_stats, value_of));
+    outln!("{}", render_table(grid, layout));
+
+    print_bar_chart(all_stats, value_of, &format, winner_idx, theme);
+
+    if let Some(comp) = comparison {
         if comp.ratio > 1.01 {
-            println!(
-                "  {} {} uses {:.2}x less memory",
-                "→".magenta(),
-                all_stats[comp.winner_index].label.green().bold(),
-                comp.ratio
+            outln!(
+                "  {} {} {}",
+                paint.paint("→"),
+                theme.winner.paint(&all_stats[comp.winner_index].label).bold(),
+                metric.verdict(comp.ratio)
+            );
+        } else {
+            outln!("  {} {}", paint.paint("→"), metric.tie);
+        }
+    }
+}
+
+/// Columns and rows of the `--tradeoff` plot area.
+const PLOT_WIDTH: usize = 48;
+const PLOT_HEIGHT: usize = 12;
+
+/// A scatter plot of each command's time against its peak memory, numbered
+/// by command, with the Pareto front (commands nothing beats on both) in
+/// the winner's color.
+fn print_tradeoff(all_stats: &[CommandStats], theme: &Theme) {
+    let points: Vec<(usize, f64, f64)> = all_stats
+        .iter()
+        .enumerate()
+        .filter_map(|(i, s)| Some((i, metric::TIME.summary(s)?, metric::MEMORY.summary(s)?)))
+        .collect();
+    if points.is_empty() {
+        outln!("  {} no memory measured to plot against", "⚖  Time vs memory".dimmed());
+        return;
+    }
+    let front = stats::pareto_front(all_stats);
+    let (times, bytes): (Vec<f64>, Vec<f64>) = points.iter().map(|&(_, t, b)| (t, b)).unzip();
+    let (format_time, format_bytes) =
+        (metric::TIME.formatter(&times), metric::MEMORY.formatter(&bytes));
+    let span = |values: &[f64]| {
+        let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        (min, max)
+    };
+    let ((t_min, t_max), (b_min, b_max)) = (span(&times), span(&bytes));
+    let scale = |v: f64, min: f64, max: f64, cells: usize| {
+        if max > min {
+            ((v - min) / (max - min) * (cells - 1) as f64).round() as usize
+        } else {
+            cells / 2
+        }
+    };
+    let marker = |i: usize| {
+        let text = MARKERS.chars().nth(i).map_or("+".to_string(), String::from);
+        let paint = if front.contains(&i) { theme.winner } else { theme.loser };
+        paint.paint(&text).to_string()
+    };
+
+    // Each cell holds its command, or None where several overlap
+    let mut plot = vec![vec![None::<Option<usize>>; PLOT_WIDTH]; PLOT_HEIGHT];
+    for &(i, t, b) in &points {
+        let cell = &mut plot[PLOT_HEIGHT - 1 - scale(b, b_min, b_max, PLOT_HEIGHT)]
+            [scale(t, t_min, t_max, PLOT_WIDTH)];
+        *cell = Some(if cell.is_some() { None } else { Some(i) });
+    }
+
+    outln!(
+        "  {}  {}",
+        theme.memory.paint("⚖  Time vs memory").bold(),
+        "(lower left is better)".dimmed()
+    );
+    let (top, bottom) = (format_bytes(b_max), format_bytes(b_min));
+    let axis_width = top.width().max(bottom.width());
+    for (row, cells) in plot.iter().enumerate() {
+        let (label, tick) = match row {
+            0 => (top.as_str(), '┤'),
+            r if r == PLOT_HEIGHT - 1 => (bottom.as_str(), '┤'),
+            _ => ("", '│'),
+        };
+        let line: String = cells
+            .iter()
+            .map(|cell| match cell {
+                None => " ".to_string(),
+                Some(Some(i)) => marker(*i),
+                Some(None) => "*".to_string(),
+            })
+            .collect();
+        outln!("  {} {}{}", pad_left(label, axis_width), tick, line.trim_end());
+    }
+    outln!("  {} └{}", " ".repeat(axis_width), "─".repeat(PLOT_WIDTH));
+    let (left, right) = (format_time(t_min), format_time(t_max));
+    let gap = (PLOT_WIDTH + 1).saturating_sub(left.width() + right.width()).max(1);
+    outln!("  {}  {}{}{}", " ".repeat(axis_width), left, " ".repeat(gap), right);
+
+    for &(i, _, _) in &points {
+        outln!("  {} {}", marker(i), all_stats[i].label);
+    }
+    if let Some(text) = tradeoff_summary(all_stats) {
+        outln!("  {} {}", theme.memory.paint("→"), text);
+    }
+}
+
+/// Markers for the commands in the `--tradeoff` plot, in order.
+const MARKERS: &str = "123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Most characters a sparkline uses; longer series are averaged into buckets.
+const SPARKLINE_WIDTH: usize = 40;
+
+/// A compact ▁▂▅▃▇ chart of run durations in order, so drift across runs and
+/// periodic spikes show at a glance. Empty for fewer than two runs.
+fn sparkline(values: &[f64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    if values.len() < 2 {
+        return String::new();
+    }
+    let buckets: Vec<f64> = values
+        .chunks(values.len().div_ceil(SPARKLINE_WIDTH))
+        .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+        .collect();
+    let min = buckets.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = buckets.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    buckets
+        .iter()
+        .map(|v| {
+            let level = if max > min {
+                ((v - min) / (max - min) * (BARS.len() - 1) as f64).round() as usize
+            } else {
+                BARS.len() / 2
+            };
+            BARS[level]
+        })
+        .collect()
+}
+
+/// A "vs #1" row giving each command's change relative to the first one,
+/// which is usually the current implementation.
+fn vs_first_row(all_stats: &[CommandStats], metric: impl Fn(&CommandStats) -> f64) -> Vec<Cell> {
+    let first = metric(&all_stats[0]);
+    let mut row = vec![Cell::new("vs #1")];
+    row.push(Cell::new("—"));
+    for s in &all_stats[1..] {
+        row.push(Cell::new(
+            percent_change(first, metric(s)).unwrap_or_else(|| "—".to_string()),
+        ));
+    }
+    row
+}
+
+/// "+12.3%" or "−4.0%" from `before` to `after`, unless either is unknown.
+fn percent_change(before: f64, after: f64) -> Option<String> {
+    if before <= 0.0 || after <= 0.0 {
+        return None;
+    }
+    let change = (after / before - 1.0) * 100.0;
+    let sign = if change < 0.0 { "−" } else { "+" };
+    Some(format!("{}{}%", sign, units::number(change.abs(), 1)))
+}
+
+/// Compare each command's results in `before` and `after` (matched by
+/// command), as from `clash diff`. Time changes a rank-sum test can't tell
+/// from noise are marked as such.
+pub fn print_diff(before: &[CommandStats], after: &[CommandStats], theme: &Theme) {
+    let mut commands: Vec<&str> = before.iter().map(|s| s.command.as_str()).collect();
+    for s in after {
+        if !commands.contains(&s.command.as_str()) {
+            commands.push(&s.command);
+        }
+    }
+    fn find<'a>(side: &'a [CommandStats], cmd: &str) -> Option<&'a CommandStats> {
+        side.iter().find(|s| s.command == cmd)
+    }
+
+    outln!();
+    outln!("  {}  clash — diff of {} commands", "⚔️".bold(), commands.len());
+    outln!();
+
+    let unit = TimeUnit::common(before.iter().chain(after).map(|s| s.time_mean));
+    let mut grid = vec![vec![
+        colored(Cell::new("⏱  Time").add_attribute(Attribute::Bold), theme.time),
+        Cell::new("Before").add_attribute(Attribute::Bold),
+        Cell::new("After").add_attribute(Attribute::Bold),
+        Cell::new("Change").add_attribute(Attribute::Bold),
+    ]];
+    for cmd in &commands {
+        let (old, new) = (find(before, cmd), find(after, cmd));
+        let mean = |s: Option<&CommandStats>| {
+            s.map_or("—".to_string(), |s| {
+                format!("{} ±{}", unit.format(s.time_mean), unit.format(s.time_std_dev))
+            })
+        };
+        let change = match (old, new) {
+            (Some(old), Some(new)) => {
+                let durations = |s: &CommandStats| -> Vec<f64> {
+                    s.all_runs.iter().map(|r| r.duration.as_secs_f64()).collect()
+                };
+                let noise = stats::rank_sum_p_value(&durations(old), &durations(new))
+                    .is_none_or(|p| p >= 0.05);
+                let text = percent_change(old.time_mean.as_secs_f64(), new.time_mean.as_secs_f64())
+                    .unwrap_or_else(|| "—".to_string());
+                if noise {
+                    Cell::new(format!("{} (noise)", text))
+                } else if new.time_mean < old.time_mean {
+                    colored(Cell::new(text), theme.winner)
+                } else {
+                    colored(Cell::new(text), theme.warning)
+                }
+            }
+            _ => Cell::new("—"),
+        };
+        let label = old.or(new).map_or(*cmd, |s| s.label.as_str());
+        grid.push(vec![Cell::new(label), Cell::new(mean(old)), Cell::new(mean(new)), change]);
+    }
+    outln!("{}", render_table(grid, Layout::Columns));
+
+    // A changed binary is often what explains a changed time
+    let mut changed = false;
+    for cmd in &commands {
+        let program = |side| find(side, cmd).and_then(|s| s.provenance.as_ref());
+        if let (Some(old), Some(new)) = (program(before), program(after))
+            && old.sha256 != new.sha256
+        {
+            let label = find(before, cmd).map_or(*cmd, |s| s.label.as_str());
+            let id = |p: &Provenance| {
+                format!("{} ({})", p.path, p.sha256.get(..12).unwrap_or(&p.sha256))
+            };
+            outln!(
+                "  {} {} ran a different program: {} {} {}",
+                theme.warning.paint("⚠"),
+                label,
+                id(old),
+                theme.time.paint("→"),
+                id(new)
             );
+            changed = true;
+        }
+    }
+    if changed {
+        outln!();
+    }
+
+    if before.iter().chain(after).all(|s| s.peak_memory_bytes.is_none()) {
+        return;
+    }
+    let unit = ByteUnit::common(before.iter().chain(after).filter_map(|s| s.peak_memory_bytes));
+    let mut grid = vec![vec![
+        colored(Cell::new("💾  Memory").add_attribute(Attribute::Bold), theme.memory),
+        Cell::new("Before").add_attribute(Attribute::Bold),
+        Cell::new("After").add_attribute(Attribute::Bold),
+        Cell::new("Change").add_attribute(Attribute::Bold),
+    ]];
+    for cmd in &commands {
+        let (old, new) = (find(before, cmd), find(after, cmd));
+        let peak = |s: Option<&CommandStats>| {
+            s.and_then(|s| s.peak_memory_bytes)
+                .map_or("—".to_string(), |bytes| unit.format(bytes))
+        };
+        let change = old
+            .zip(new)
+            .and_then(|(old, new)| {
+                let (old, new) = (old.peak_memory_bytes?, new.peak_memory_bytes?);
+                percent_change(old as f64, new as f64)
+            })
+            .unwrap_or_else(|| "—".to_string());
+        let label = old.or(new).map_or(*cmd, |s| s.label.as_str());
+        grid.push(vec![
+            Cell::new(label),
+            Cell::new(peak(old)),
+            Cell::new(peak(new)),
+            Cell::new(change),
+        ]);
+    }
+    outln!("{}", render_table(grid, Layout::Columns));
+}
+
+/// Density curves of one command's run times from two results files, drawn
+/// on a shared axis so a shift (or the lack of one) is visible at a glance.
+pub fn print_run_distributions(
+    label: &str,
+    before: &[Duration],
+    after: &[Duration],
+    theme: &Theme,
+) {
+    const WIDTH: usize = 60;
+    const HEIGHT: usize = 4;
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let secs = |runs: &[Duration]| -> Vec<f64> { runs.iter().map(Duration::as_secs_f64).collect() };
+    let (old, new) = (secs(before), secs(after));
+    let lo = old.iter().chain(&new).cloned().fold(f64::INFINITY, f64::min);
+    let hi = old.iter().chain(&new).cloned().fold(f64::NEG_INFINITY, f64::max);
+    // Leave room for the curves' tails on both sides
+    let pad = ((hi - lo) * 0.15).max(hi * 0.01);
+    let (lo, hi) = ((lo - pad).max(0.0), hi + pad);
+    let step = (hi - lo) / (WIDTH - 1) as f64;
+    let at: Vec<f64> = (0..WIDTH).map(|i| lo + step * i as f64).collect();
+    let curves = [stats::density(&old, &at), stats::density(&new, &at)];
+    let peak = curves.iter().flatten().cloned().fold(f64::MIN_POSITIVE, f64::max);
+    let overlap: f64 = curves[0].iter().zip(&curves[1]).map(|(a, b)| a.min(*b)).sum::<f64>() * step;
+    let median = |values: &[f64]| {
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let mid = sorted.len() / 2;
+        if sorted.len().is_multiple_of(2) {
+            (sorted[mid - 1] + sorted[mid]) / 2.0
+        } else {
+            sorted[mid]
+        }
+    };
+    let medians = [median(&old), median(&new)];
+    let unit = TimeUnit::common(before.iter().chain(after).copied());
+    let format = |secs: f64| unit.format(Duration::from_secs_f64(secs));
+
+    outln!();
+    outln!("  {}  clash — runs of {}, before vs after", "⚔️".bold(), label.bold());
+    outln!();
+    let sides = [("Before", theme.loser, old.len()), ("After", theme.time, new.len())];
+    let drawn = curves.iter().zip(medians);
+    for ((name, paint, runs), (curve, median)) in sides.into_iter().zip(drawn) {
+        for row in 0..HEIGHT {
+            let floor = (HEIGHT - 1 - row) * 8;
+            let line: String = curve
+                .iter()
+                .map(|d| {
+                    let eighths = (d / peak * (HEIGHT * 8) as f64).round() as usize;
+                    match eighths.saturating_sub(floor).min(8) {
+                        0 => ' ',
+                        e => BARS[e - 1],
+                    }
+                })
+                .collect();
+            let name = if row == HEIGHT - 1 { name } else { "" };
+            outln!("  {}  {}", format!("{:>6}", name).bold(), paint.paint(&line));
+        }
+        let column = (((median - lo) / step).round() as usize).min(WIDTH - 1);
+        let note = format!("median {}, {} runs", format(median), runs);
+        // Put the note on whichever side of the marker has room for it
+        let marker = if column + note.width() + 2 <= WIDTH {
+            format!("{}▲ {}", " ".repeat(column), note)
         } else {
-            println!("  {} Roughly the same memory usage", "→".magenta());
+            pad_left(&format!("{} ▲", note), column + 1)
+        };
+        outln!("  {:>6}  {}", "", marker.dimmed());
+    }
+    let (left, right) = (format(lo), format(hi));
+    outln!("  {:>6}  {}", "", "─".repeat(WIDTH).dimmed());
+    outln!("  {:>6}  {}{}", "", left, pad_left(&right, WIDTH.saturating_sub(left.width())));
+    outln!();
+
+    let change = percent_change(medians[0], medians[1]).unwrap_or_else(|| "—".to_string());
+    outln!(
+        "  {} Median {} {} {} ({}); the curves overlap {:.0}%",
+        theme.time.paint("→"),
+        format(medians[0]),
+        theme.time.paint("→"),
+        format(medians[1]),
+        change,
+        (overlap * 100.0).min(100.0)
+    );
+    match stats::rank_sum_p_value(&old, &new) {
+        Some(p) if p < 0.05 => {
+            let (mark, direction) = if medians[1] < medians[0] {
+                (theme.winner.paint("✓"), "faster")
+            } else {
+                (theme.warning.paint("⚠"), "slower")
+            };
+            let p = if p < 0.001 { "< 0.001".to_string() } else { format!("= {:.3}", p) };
+            outln!("  {} The distribution shifted {} (rank-sum p {})", mark, direction, p)
         }
+        Some(p) => outln!(
+            "  {} No clear shift; the difference is within noise (rank-sum p = {:.2})",
+            "ℹ".dimmed(),
+            p
+        ),
+        None => {}
+    }
+    outln!();
+}
+
+/// Apply a theme color to a table cell, if it has one.
+fn colored(cell: Cell, paint: Paint) -> Cell {
+    match paint.cell() {
+        Some(color) => cell.fg(color),
+        None => cell,
     }
 }
 
+/// A bold, themed and marked cell for a winning value.
+fn winner_cell(value: &str, theme: &Theme) -> Cell {
+    colored(
+        Cell::new(theme.mark(value)).add_attribute(Attribute::Bold),
+        theme.winner,
+    )
+}
+
+/// Build a table from a grid whose first row is the header (title, then one
+/// label per command) and whose other rows are metrics (name, then one value
+/// per command). `Layout::Rows` transposes it so each command is a row.
+fn render_table(grid: Vec<Vec<Cell>>, layout: Layout) -> Table {
+    let mut grid = match layout {
+        Layout::Columns => grid,
+        Layout::Rows => transpose(grid),
+    };
+
+    let rows = grid.split_off(1);
+    table::build(grid.pop().unwrap_or_default(), rows)
+}
+
+/// Right-align `text` to `width` terminal columns, counting wide
+/// characters such as CJK as two.
+fn pad_left(text: &str, width: usize) -> String {
+    format!("{}{}", " ".repeat(width.saturating_sub(text.width())), text)
+}
+
+fn transpose(grid: Vec<Vec<Cell>>) -> Vec<Vec<Cell>> {
+    let width = grid.first().map_or(0, Vec::len);
+    let mut out: Vec<Vec<Cell>> = (0..width).map(|_| Vec::with_capacity(grid.len())).collect();
+    for row in grid {
+        for (column, cell) in out.iter_mut().zip(row) {
+            column.push(cell);
+        }
+    }
+    out
+}
+
 fn print_bar_chart<F, G>(
     all_stats: &[CommandStats],
     value_fn: F,
     format_fn: G,
     winner_idx: Option<usize>,
+    theme: &Theme,
 ) where
     F: Fn(&CommandStats) -> f64,
     G: Fn(f64) -> String,
@@ -201,8 +1852,13 @@ fn print_bar_chart<F, G>(
         return;
     }
 
-    // Find max label length for alignment
-    let max_label_len = all_stats.iter().map(|s| s.label.len()).max().unwrap_or(0);
+    // Find max label and value lengths for alignment
+    let max_label_len = all_stats.iter().map(|s| s.label.width()).max().unwrap_or(0);
+    let value_width = values
+        .iter()
+        .map(|&v| format_fn(v).chars().count())
+        .max()
+        .unwrap_or(0);
 
     for (i, s) in all_stats.iter().enumerate() {
         let val = value_fn(s);
@@ -211,23 +1867,23 @@ fn print_bar_chart<F, G>(
 
         let bar = "━".repeat(bar_len);
         let empty = "─".repeat(empty_len);
-        let formatted_val = format_fn(val);
+        let formatted_val = format!("{:>width$}", format_fn(val), width = value_width);
 
-        let label_padded = format!("{:>width$}", s.label, width = max_label_len);
+        let label_padded = pad_left(&s.label, max_label_len);
 
         if winner_idx == Some(i) {
-            println!(
+            outln!(
                 "  {} {}{}  {}",
-                label_padded.green(),
-                bar.green(),
+                theme.winner.paint(&label_padded),
+                theme.winner.paint(&bar),
                 empty.dimmed(),
-                formatted_val.green()
+                theme.winner.paint(&theme.mark(&formatted_val))
             );
         } else {
-            println!(
+            outln!(
                 "  {} {}{}  {}",
                 label_padded,
-                bar.red(),
+                theme.loser.paint(&bar),
                 empty.dimmed(),
                 formatted_val
             );
@@ -235,70 +1891,762 @@ fn print_bar_chart<F, G>(
     }
 }
 
-fn print_summary(all_stats: &[CommandStats]) {
-    let time_comp = stats::compare_time(all_stats);
-    let mem_comp = stats::compare_memory(all_stats);
+fn print_baseline_notes(all_stats: &[CommandStats]) {
+    for s in all_stats {
+        if let Some(baseline) = &s.baseline {
+            outln!(
+                "  {} {}: {} baseline '{}' subtracted from every run",
+                "Note:".dimmed(),
+                s.label,
+                TimeUnit::for_duration(baseline.mean).format(baseline.mean),
+                baseline.command
+            );
+        }
+    }
+}
 
-    let mut parts = Vec::new();
+/// The "⚠ Notes" section: every command's warnings, in one place.
+fn print_warnings(all_stats: &[CommandStats], theme: &Theme) {
+    if all_stats.iter().all(|s| s.warnings.is_empty()) {
+        return;
+    }
+    outln!();
+    outln!("  {}", theme.warning.paint("⚠ Notes").bold());
+    for s in all_stats {
+        for warning in &s.warnings {
+            outln!("    {}: {}", s.label, warning);
+        }
+    }
+}
+
+/// Show what clash's memory sampling cost, relative to the fastest command,
+/// so observer effects on very short commands can be judged.
+fn print_overhead(all_stats: &[CommandStats]) {
+    let Some(overhead) = stats::monitor_overhead(all_stats) else {
+        return;
+    };
+    let fastest = all_stats.iter().map(|s| s.time_mean).min().unwrap_or_default();
+    let busy = overhead.busy_per_run;
+    let unit = TimeUnit::common([busy, fastest]);
+    outln!(
+        "  {} {:.1} memory samples per run, {} of sampling work per run ({:.1}% of the fastest mean, {})",
+        "Monitor overhead:".dimmed(),
+        overhead.samples_per_run,
+        unit.format(busy),
+        busy.as_secs_f64() / fastest.as_secs_f64() * 100.0,
+        unit.format(fastest)
+    );
+    outln!();
+}
+
+/// One line per command on what seems to hold it back, with a profiler to
+/// look closer.
+fn print_characterization(all_stats: &[CommandStats]) {
+    let width = all_stats.iter().map(|s| s.label.width()).max().unwrap_or(0);
+    for s in all_stats {
+        let percent = |share: f64| format!("{:.0}%", share * 100.0);
+        let (verdict, next) = match stats::characterize(s) {
+            Some(Bound::Cpu { on_cpu }) if on_cpu >= 1.5 => (
+                format!("appears CPU-bound across {:.1} cores on average", on_cpu),
+                "perf record, or a flamegraph",
+            ),
+            Some(Bound::Cpu { on_cpu }) => (
+                format!("appears CPU-bound: {} of wall time on CPU", percent(on_cpu.min(1.0))),
+                "perf record, or a flamegraph",
+            ),
+            Some(Bound::Io { off_cpu }) => (
+                format!("appears I/O-bound: {} of wall time not on CPU", percent(off_cpu)),
+                "strace -c, or off-CPU profiling",
+            ),
+            Some(Bound::Memory { major_faults }) => (
+                format!("appears memory-bound: {:.0} major page faults per run", major_faults),
+                "vmstat, or perf stat -e major-faults",
+            ),
+            Some(Bound::Mixed { on_cpu }) => (
+                format!("no clear bottleneck: {} of wall time on CPU", percent(on_cpu)),
+                "perf record with off-CPU sampling",
+            ),
+            None => ("CPU use wasn't watched".to_string(), ""),
+        };
+        let next = if next.is_empty() { String::new() } else { format!(" (try {})", next) };
+        outln!(
+            "  {}{}  {}{}",
+            s.label.bold(),
+            " ".repeat(width - s.label.width()),
+            verdict,
+            next.dimmed()
+        );
+    }
+    outln!();
+}
+
+/// The footer: how long the whole session took, how much of it each command
+/// needed with its warmup, preparation and pauses, and what went to setup
+/// such as builds and baselines.
+pub fn print_session_time(all_stats: &[CommandStats], wall_clock: Duration) {
+    let setup = wall_clock.saturating_sub(all_stats.iter().map(|s| s.wall_time).sum());
+    let unit = TimeUnit::common(all_stats.iter().map(|s| s.wall_time).chain([wall_clock]));
+    let mut parts: Vec<String> = all_stats
+        .iter()
+        .map(|s| format!("{} {}", s.label, unit.format(s.wall_time)))
+        .collect();
+    parts.push(format!("setup {}", unit.format(setup)));
+    outln!(
+        "  {} {} ({})",
+        "Session time:".dimmed(),
+        unit.format(wall_clock),
+        parts.join(", ")
+    );
+    outln!();
+}
+
+fn print_summary(all_stats: &[CommandStats], opts: &ReportOptions) {
+    outln!("  {} {}", "Summary:".bold(), summary_text(all_stats));
+    print_over_limits(all_stats, &opts.limits, &opts.theme);
 
-    if let Some(tc) = time_comp {
-        if tc.ratio > 1.01 {
-            parts.push(format!(
-                "{} wins on speed ({:.2}x)",
-                all_stats[tc.winner_index].label, tc.ratio
-            ));
+    // A combined score settles split verdicts, or whenever weights are given
+    let time_winner = stats::compare_time(all_stats)
+        .filter(|c| !c.unstable)
+        .map(|c| c.winner_index);
+    let mem_winner = stats::compare_memory(all_stats).map(|c| c.winner_index);
+    let split = matches!((time_winner, mem_winner), (Some(t), Some(m)) if t != m);
+    if opts.weights.is_some() || split {
+        print_overall(
+            all_stats,
+            &opts.weights.clone().unwrap_or_default(),
+            &opts.theme,
+        );
+    }
+    if let Some(window) = measured_window(all_stats) {
+        outln!("  {}", window.dimmed());
+    }
+    outln!();
+}
+
+/// A line for every command whose value for a metric went over its
+/// `--highlight-over` budget.
+fn print_over_limits(all_stats: &[CommandStats], limits: &Limits, theme: &Theme) {
+    for &(metric, limit) in &limits.metrics {
+        let row = if metric.name == metric::TIME.name {
+            stats::estimator(all_stats).row()
+        } else {
+            metric.row.to_string()
+        };
+        for s in all_stats {
+            let Some(value) = metric.summary(s).filter(|&v| v > limit) else {
+                continue;
+            };
+            let format = metric.formatter(&[value, limit]);
+            outln!(
+                "  {} {}: {} {} is over the {} budget",
+                theme.loser.paint("⚠"),
+                s.label,
+                row,
+                format(value),
+                format(limit)
+            );
         }
     }
+}
+
+/// "Measured over 14:32–14:38" for sessions long enough that results may
+/// need correlating with other events on the machine.
+fn measured_window(all_stats: &[CommandStats]) -> Option<String> {
+    let runs = all_stats.iter().flat_map(|s| &s.all_runs);
+    let start = runs.clone().filter_map(|r| r.started_at).min()?;
+    let end = runs.filter_map(|r| Some(r.started_at? + r.duration)).max()?;
+    if end - start < chrono::TimeDelta::minutes(1) {
+        return None;
+    }
+    let (start, end) = (start.with_timezone(&Local), end.with_timezone(&Local));
+    let end_format = if start.date_naive() == end.date_naive() {
+        "%H:%M"
+    } else {
+        "%Y-%m-%d %H:%M"
+    };
+    Some(format!(
+        "Measured over {}–{}",
+        start.format("%Y-%m-%d %H:%M"),
+        end.format(end_format)
+    ))
+}
+
+fn print_overall(all_stats: &[CommandStats], weights: &Weights, theme: &Theme) {
+    let scores = stats::overall_scores(all_stats, weights);
+    let Some((winner, _)) = scores
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total.total_cmp(&b.1.total))
+    else {
+        return;
+    };
 
-    if let Some(mc) = mem_comp {
-        if mc.ratio > 1.01 {
-            parts.push(format!(
-                "{} wins on memory ({:.2}x)",
-                all_stats[mc.winner_index].label, mc.ratio
-            ));
+    let listed: Vec<String> = weights
+        .metrics
+        .iter()
+        .map(|(m, w)| format!("{} {:.2}", m.name, w))
+        .collect();
+    outln!(
+        "  {} {} (weights: {})",
+        "Overall:".bold(),
+        theme.winner.paint(&all_stats[winner].label).bold(),
+        listed.join(", ")
+    );
+    let max_label_len = all_stats.iter().map(|s| s.label.width()).max().unwrap_or(0);
+    for (s, score) in all_stats.iter().zip(&scores) {
+        let mut terms = Vec::new();
+        let mut unmeasured = Vec::new();
+        for (&(m, w), part) in weights.metrics.iter().zip(&score.parts) {
+            match part {
+                Some(p) => terms.push(format!("{:.2}×{:.2} {}", w, p, m.name)),
+                None => unmeasured.push(format!(" ({} unmeasured)", m.name)),
+            }
         }
+        outln!(
+            "    {}  {:.2} = {}{}",
+            pad_left(&s.label, max_label_len),
+            score.total,
+            terms.join(" + "),
+            unmeasured.concat()
+        );
     }
+}
 
+/// The verdict in a sentence: how much faster the winner is than its closest
+/// challenger and how sure that is, then whether memory really differs.
+pub fn summary_text(all_stats: &[CommandStats]) -> String {
+    // A --group of one
+    if let [only] = all_stats {
+        return format!("{} has no other command to compare against.", only.label);
+    }
+    let mut parts = Vec::new();
+    if let Some(part) = speed_summary(all_stats) {
+        parts.push(part);
+    }
+    if let Some(part) = memory_summary(all_stats) {
+        parts.push(part);
+    }
+    let winners = stats::round_winners(all_stats);
+    if !winners.is_empty() {
+        parts.push(rounds_summary(all_stats, &winners));
+    }
+    // Two commands' tradeoff is already plain from the parts above
+    if all_stats.len() > 2
+        && let Some(part) = tradeoff_summary(all_stats)
+    {
+        parts.push(part);
+    }
     if parts.is_empty() {
-        println!("  {} Both commands perform similarly.", "Summary:".bold());
+        return "Both commands perform similarly.".to_string();
+    }
+    format!("{}.", parts.join("; "))
+}
+
+/// "apple is 2.31x ±0.12 faster than orange with 95% confidence over 20 runs"
+fn speed_summary(all_stats: &[CommandStats]) -> Option<String> {
+    let tc = stats::compare_time(all_stats)?;
+    if tc.unstable {
+        return Some("no speed winner, as timings are too unstable (grade D)".to_string());
+    }
+    if tc.ratio <= 1.01 {
+        return Some("speed is roughly the same".to_string());
+    }
+    let winner = &all_stats[tc.winner_index];
+    let other = &all_stats[metric::TIME.runner_up(all_stats, tc.winner_index)?];
+    let (ratio, margin) = stats::speedup(winner, other);
+    // Confident when the whole interval lies above "equally fast"
+    let confident = margin.is_some_and(|m| !tc.inconclusive && ratio - m > 1.0);
+    let ratio = match margin {
+        Some(margin) => format!("{} ±{}", metric::ratio_text(ratio), units::number(margin, 2)),
+        None => metric::ratio_text(ratio),
+    };
+    let low_confidence = if tc.low_confidence {
+        " (low confidence: runs under 5ms are mostly process startup)"
     } else {
-        println!("  {} {}", "Summary:".bold(), parts.join(", "));
-    }
-    println!();
-}
-
-/// Format a Duration into a human-readable string.
-fn format_duration(d: std::time::Duration) -> String {
-    let secs = d.as_secs_f64();
-    if secs >= 60.0 {
-        let mins = (secs / 60.0).floor() as u64;
-        let remaining = secs - (mins as f64 * 60.0);
-        format!("{}m {:.3}s", mins, remaining)
-    } else if secs >= 1.0 {
-        format!("{:.3}s", secs)
-    } else if secs >= 0.001 {
-        format!("{:.1}ms", secs * 1000.0)
+        ""
+    };
+    let runs = if winner.runs == other.runs {
+        format!("{} run{}", winner.runs, if winner.runs == 1 { "" } else { "s" })
     } else {
-        format!("{:.0}µs", secs * 1_000_000.0)
+        format!("{} and {} runs", winner.runs, other.runs)
+    };
+    Some(format!(
+        "{} {} than {} {}{}",
+        winner.label,
+        metric::TIME.verdict.replace("{ratio}", &ratio),
+        other.label,
+        if confident {
+            format!("with 95% confidence over {}", runs)
+        } else if margin.is_some() {
+            format!("over {}, but that is within noise", runs)
+        } else {
+            format!("over {}, too few to judge noise", runs)
+        },
+        low_confidence
+    ))
+}
+
+/// "memory usage is statistically indistinguishable", unless the leanest
+/// command's peaks are reliably lower than its closest challenger's.
+fn memory_summary(all_stats: &[CommandStats]) -> Option<String> {
+    let mc = stats::compare_memory(all_stats)?;
+    let winner = &all_stats[mc.winner_index];
+    let other = &all_stats[metric::MEMORY.runner_up(all_stats, mc.winner_index)?];
+    let (lean, heavy) = (metric::MEMORY.summary(winner)?, metric::MEMORY.summary(other)?);
+    if heavy / lean <= 1.01 || !stats::differs(&metric::MEMORY, winner, other) {
+        return Some("memory usage is statistically indistinguishable".to_string());
+    }
+    Some(format!(
+        "{} {} than {}",
+        winner.label,
+        metric::MEMORY.verdict(heavy / lean),
+        other.label
+    ))
+}
+
+/// "apple dominates pear; orange is a tradeoff": which commands are beaten
+/// on both time and memory, and by whom.
+fn tradeoff_summary(all_stats: &[CommandStats]) -> Option<String> {
+    metric::MEMORY.best(all_stats)?;
+    let front = stats::pareto_front(all_stats);
+    if front.len() == all_stats.len() {
+        return Some("no command beats another on both time and memory".to_string());
     }
+    let parts: Vec<String> = front
+        .iter()
+        .map(|&i| {
+            let beaten: Vec<&str> = (0..all_stats.len())
+                .filter(|&j| !front.contains(&j) && stats::dominates(&all_stats[i], &all_stats[j]))
+                .map(|j| all_stats[j].label.as_str())
+                .collect();
+            if beaten.is_empty() {
+                format!("{} is a tradeoff", all_stats[i].label)
+            } else {
+                format!("{} dominates {}", all_stats[i].label, join_and(&beaten))
+            }
+        })
+        .collect();
+    Some(parts.join("; "))
 }
 
-/// Format bytes into a human-readable string.
-fn format_bytes(bytes: u64) -> String {
-    if bytes == 0 {
-        return "N/A".to_string();
+/// "a", "a and b", "a, b and c".
+fn join_and(items: &[&str]) -> String {
+    match items {
+        [] => String::new(),
+        [one] => one.to_string(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
     }
-    let kb = bytes as f64 / 1024.0;
-    let mb = kb / 1024.0;
-    let gb = mb / 1024.0;
+}
+
+/// Report whether an A/A test (the same command benchmarked twice) found a
+/// difference that isn't there, i.e. how noisy the environment is.
+pub fn print_aa_verdict(a: &CommandStats, b: &CommandStats) {
+    let durations =
+        |s: &CommandStats| -> Vec<f64> { s.all_runs.iter().map(|r| r.duration.as_secs_f64()).collect() };
+    let Some(p) = stats::rank_sum_p_value(&durations(a), &durations(b)) else {
+        return;
+    };
+    let (ma, mb) = (a.time_mean.as_secs_f64(), b.time_mean.as_secs_f64());
+    let gap = (ma - mb).abs() / ma.min(mb) * 100.0;
 
-    if gb >= 1.0 {
-        format!("{:.1} GB", gb)
-    } else if mb >= 1.0 {
-        format!("{:.1} MB", mb)
-    } else if kb >= 1.0 {
-        format!("{:.1} KB", kb)
+    outln!("  {}", "A/A test".bold());
+    if p >= 0.05 {
+        outln!(
+            "  {} No significant difference between identical runs (p = {:.3}, gap {:.1}%).",
+            "✓".green(),
+            p,
+            gap
+        );
+        outln!("    Your environment looks quiet enough for A/B comparisons.");
     } else {
-        format!("{} B", bytes)
+        outln!(
+            "  {} Identical runs differ significantly (p = {:.3}, gap {:.1}%).",
+            "✗".red(),
+            p,
+            gap
+        );
+        outln!(
+            "    Treat A/B differences below ~{:.0}% as noise, or quiet the machine first.",
+            gap.ceil()
+        );
+    }
+    if a.runs < 10 {
+        outln!(
+            "    {} With only {} runs per side the test has little power; try --runs 20.",
+            "Note:".dimmed(),
+            a.runs
+        );
+    }
+    outln!();
+}
+
+/// Side-by-side report for `clash merge`: one row per command label, one
+/// column per host, marking each command's fastest (or leanest) host and
+/// each host's fastest command.
+pub fn print_host_matrix(results: &[HostResult], theme: &Theme, layout: Layout) {
+    let mut hosts: Vec<&str> = Vec::new();
+    let mut labels: Vec<&str> = Vec::new();
+    for r in results {
+        if !hosts.contains(&r.host.as_str()) {
+            hosts.push(&r.host);
+        }
+        if !labels.contains(&r.label.as_str()) {
+            labels.push(&r.label);
+        }
+    }
+    let find = |label: &str, host: &str| {
+        results.iter().find(|r| r.label == label && r.host == host)
+    };
+
+    outln!();
+    outln!(
+        "  {}  clash — {} commands across {} hosts",
+        "⚔️".bold(),
+        labels.len(),
+        hosts.len()
+    );
+    outln!();
+
+    let unit = TimeUnit::common(results.iter().map(|r| r.time_mean));
+    let mut header = vec![colored(
+        Cell::new("⏱  Time").add_attribute(Attribute::Bold),
+        theme.time,
+    )];
+    header.extend(hosts.iter().map(|h| Cell::new(h).add_attribute(Attribute::Bold)));
+    let mut grid = vec![header];
+    for label in &labels {
+        let fastest = results
+            .iter()
+            .filter(|r| r.label == *label)
+            .min_by_key(|r| r.time_mean)
+            .map(|r| r.host.as_str());
+        let mut row = vec![Cell::new(label)];
+        for host in &hosts {
+            row.push(match find(label, host) {
+                Some(r) => {
+                    let value = format!(
+                        "{} ±{}",
+                        unit.format(r.time_mean),
+                        unit.format(r.time_std_dev)
+                    );
+                    if hosts.len() > 1 && fastest == Some(*host) {
+                        winner_cell(&value, theme)
+                    } else {
+                        Cell::new(value)
+                    }
+                }
+                None => Cell::new("—"),
+            });
+        }
+        grid.push(row);
+    }
+    let mut fastest_row = vec![Cell::new("Fastest")];
+    for host in &hosts {
+        let best = results
+            .iter()
+            .filter(|r| r.host == *host)
+            .min_by_key(|r| r.time_mean)
+            .map(|r| r.label.as_str())
+            .unwrap_or("—");
+        fastest_row.push(Cell::new(best).add_attribute(Attribute::Bold));
+    }
+    grid.push(fastest_row);
+    outln!("{}", render_table(grid, layout));
+
+    if results.iter().all(|r| r.peak_memory_bytes.is_none()) {
+        return;
+    }
+    let unit = ByteUnit::common(results.iter().filter_map(|r| r.peak_memory_bytes));
+    let mut header = vec![colored(
+        Cell::new("💾  Memory").add_attribute(Attribute::Bold),
+        theme.memory,
+    )];
+    header.extend(hosts.iter().map(|h| Cell::new(h).add_attribute(Attribute::Bold)));
+    let mut grid = vec![header];
+    for label in &labels {
+        let leanest = results
+            .iter()
+            .filter(|r| r.label == *label && r.peak_memory_bytes.is_some())
+            .min_by_key(|r| r.peak_memory_bytes)
+            .map(|r| r.host.as_str());
+        let mut row = vec![Cell::new(label)];
+        for host in &hosts {
+            row.push(match find(label, host).and_then(|r| r.peak_memory_bytes) {
+                Some(bytes) => {
+                    let value = unit.format(bytes);
+                    if hosts.len() > 1 && leanest == Some(*host) {
+                        winner_cell(&value, theme)
+                    } else {
+                        Cell::new(value)
+                    }
+                }
+                None => Cell::new("—"),
+            });
+        }
+        grid.push(row);
+    }
+    outln!("{}", render_table(grid, layout));
+}
+
+/// Print commands read back from a `--db` database, one row per stored
+/// result.
+pub fn print_stored_commands(title: &str, rows: &[CommandRow]) {
+    outln!();
+    outln!("  {}  clash — {}", "⚔️".bold(), title);
+    outln!();
+
+    let time = TimeUnit::common(rows.iter().map(|r| r.time_mean));
+    let bytes = ByteUnit::common(rows.iter().filter_map(|r| r.peak_memory_bytes));
+    let header = ["Command", "Session", "Host", "Runs", "Mean", "Peak RSS"];
+    let mut grid = vec![header.map(|h| Cell::new(h).add_attribute(Attribute::Bold)).to_vec()];
+    for r in rows {
+        grid.push(vec![
+            Cell::new(&r.label),
+            Cell::new(r.started_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")),
+            Cell::new(r.host.as_deref().unwrap_or("—")),
+            Cell::new(r.runs),
+            Cell::new(format!("{} ±{}", time.format(r.time_mean), time.format(r.time_std_dev))),
+            Cell::new(r.peak_memory_bytes.map_or("—".to_string(), |b| bytes.format(b))),
+        ]);
+    }
+    outln!("{}", render_table(grid, Layout::Columns));
+}
+
+/// Print sessions read back from a `--db` database, newest first.
+pub fn print_stored_sessions(rows: &[SessionRow]) {
+    outln!();
+    outln!("  {}  clash — {} stored sessions", "⚔️".bold(), rows.len());
+    outln!();
+
+    let header = ["Session", "Started", "Host", "Commands"];
+    let mut grid = vec![header.map(|h| Cell::new(h).add_attribute(Attribute::Bold)).to_vec()];
+    for r in rows {
+        grid.push(vec![
+            Cell::new(r.id),
+            Cell::new(r.started_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")),
+            Cell::new(r.host.as_deref().unwrap_or("—")),
+            Cell::new(r.labels.join(", ")),
+        ]);
+    }
+    outln!("{}", render_table(grid, Layout::Columns));
+}
+
+/// Chart `rows`, one label's results in session order, with `▲` under
+/// each session that regressed from the one before, then list those
+/// sessions.
+pub fn print_trend(
+    label: &str,
+    rows: &[CommandRow],
+    time_regressed: &[bool],
+    memory_regressed: &[bool],
+    theme: &Theme,
+) {
+    let (first, last) = (&rows[0], &rows[rows.len() - 1]);
+    let date = |r: &CommandRow| r.started_at.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+    outln!();
+    outln!("  {}  clash — trend for {} over {} sessions", "⚔️".bold(), label.bold(), rows.len());
+    outln!("      {} → {}", date(first), date(last));
+    outln!();
+
+    let time = TimeUnit::common(rows.iter().map(|r| r.time_mean));
+    let times: Vec<(f64, bool)> = rows
+        .iter()
+        .zip(time_regressed)
+        .map(|(r, &regressed)| (r.time_mean.as_secs_f64(), regressed))
+        .collect();
+    print_trend_line(theme.time.paint("⏱  Time"), &times, theme, |v| {
+        time.format(Duration::from_secs_f64(v))
+    });
+    let bytes = ByteUnit::common(rows.iter().filter_map(|r| r.peak_memory_bytes));
+    let memory: Vec<(f64, bool)> = rows
+        .iter()
+        .zip(memory_regressed)
+        .filter_map(|(r, &regressed)| Some((r.peak_memory_bytes? as f64, regressed)))
+        .collect();
+    if memory.len() >= 2 {
+        print_trend_line(theme.memory.paint("💾  Memory"), &memory, theme, |v| {
+            bytes.format(v as u64)
+        });
+    }
+    outln!();
+
+    let mut regressions = Vec::new();
+    for (i, r) in rows.iter().enumerate().skip(1) {
+        if time_regressed[i] {
+            let before = rows[..i].last().map_or(0.0, |p| p.time_mean.as_secs_f64());
+            regressions.push((r, "time", before, r.time_mean.as_secs_f64(), true));
+        }
+        if memory_regressed[i] {
+            let before = rows[..i].iter().rev().find_map(|p| p.peak_memory_bytes);
+            let before = before.unwrap_or_default() as f64;
+            regressions.push((r, "memory", before, r.peak_memory_bytes.unwrap_or(0) as f64, false));
+        }
+    }
+    if regressions.is_empty() {
+        outln!("  {} No regressions between consecutive sessions", theme.winner.paint("✓"));
+        return;
+    }
+    outln!("  {}", "Regressions over the session before:".bold());
+    for (r, what, before, after, is_time) in regressions {
+        let format = |v: f64| {
+            if is_time {
+                time.format(Duration::from_secs_f64(v))
+            } else {
+                bytes.format(v as u64)
+            }
+        };
+        outln!(
+            "    {} {}  {:<6} {} → {} ({})",
+            theme.warning.paint("▲"),
+            date(r),
+            what,
+            format(before),
+            format(after),
+            percent_change(before, after).unwrap_or_else(|| "—".to_string())
+        );
+    }
+}
+
+/// One `clash trend` chart row: a sparkline of `values` with the first and last
+/// value, and a row of `▲` under the regressed entries.
+fn print_trend_line(
+    title: String,
+    values: &[(f64, bool)],
+    theme: &Theme,
+    format: impl Fn(f64) -> String,
+) {
+    const TITLE_WIDTH: usize = 12;
+    let series: Vec<f64> = values.iter().map(|&(v, _)| v).collect();
+    let line = sparkline(&series);
+    let (first, last) = (series[0], series[series.len() - 1]);
+    let pad = " ".repeat(TITLE_WIDTH.saturating_sub(strip_ansi(&title).width()));
+    outln!(
+        "  {}{}{}  {} → {} ({})",
+        title,
+        pad,
+        line,
+        format(first),
+        format(last),
+        percent_change(first, last).unwrap_or_else(|| "—".to_string())
+    );
+
+    // Markers go under the sparkline bucket holding each regressed entry
+    let chunk = values.len().div_ceil(SPARKLINE_WIDTH);
+    let mut markers = vec![false; line.chars().count()];
+    for (i, _) in values.iter().enumerate().filter(|(_, (_, regressed))| *regressed) {
+        markers[i / chunk] = true;
+    }
+    if markers.contains(&true) {
+        let row: String = markers.iter().map(|&m| if m { '▲' } else { ' ' }).collect();
+        outln!("  {}{}", " ".repeat(TITLE_WIDTH), theme.warning.paint(row.trim_end()));
+    }
+}
+
+/// Print what a verified bundle vouches for: its signer, the machine the
+/// results came from and each command's time.
+pub fn print_bundle(signed: &serde_json::Value, key: &str) {
+    let session = &signed["export"]["session"];
+    let text = |value: &serde_json::Value| value.as_str().unwrap_or("—").to_string();
+    let started = session["started_at"]
+        .as_str()
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+        .map_or("—".to_string(), |t| {
+            t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+        });
+
+    outln!();
+    outln!("  {} Signature valid", "✓".green());
+    outln!("    {:<12} {}", "Signed by", key);
+    outln!("    {:<12} {} on {}", "Session", started, text(&session["host"]));
+    if let Ok(env) = serde_json::from_value::<Environment>(signed["environment"].clone()) {
+        let system = [env.os, env.kernel.map(|k| format!("kernel {}", k))];
+        let system: Vec<String> = system.into_iter().flatten().collect();
+        outln!("    {:<12} {} ({})", "System", system.join(", "), env.arch);
+        outln!(
+            "    {:<12} {} ({} CPUs, {} memory)",
+            "Hardware",
+            env.cpu.as_deref().unwrap_or("unknown CPU"),
+            env.cpus,
+            ByteUnit::common([env.memory_bytes]).format(env.memory_bytes)
+        );
+        outln!("    {:<12} {}", "clash", env.clash_version);
+    }
+    outln!();
+
+    let results = signed["export"]["results"].as_array().cloned().unwrap_or_default();
+    let time = |r: &serde_json::Value| r["metrics"]["time"]["summary"].as_f64();
+    let unit = TimeUnit::common(results.iter().filter_map(time).map(Duration::from_secs_f64));
+    let header = ["Command", "Runs", "Time"];
+    let mut grid = vec![header.map(|h| Cell::new(h).add_attribute(Attribute::Bold)).to_vec()];
+    for r in &results {
+        grid.push(vec![
+            Cell::new(text(&r["label"])),
+            Cell::new(r["runs"].as_u64().map_or("—".to_string(), |n| n.to_string())),
+            Cell::new(time(r).map_or("—".to_string(), |t| unit.format(Duration::from_secs_f64(t)))),
+        ]);
+    }
+    outln!("{}", render_table(grid, Layout::Columns));
+}
+
+/// Each command's smallest working memory cap from `clash memlimit`, with
+/// the lowest marked; `None` for a command that failed under every cap.
+pub fn print_memlimits(limits: &[(String, Option<u64>)], theme: &Theme) {
+    let bytes = ByteUnit::common(limits.iter().filter_map(|(_, limit)| *limit));
+    let measured: Vec<u64> = limits.iter().filter_map(|(_, limit)| *limit).collect();
+    let best = measured.iter().min().copied();
+    let ratio = best.map(|b| *measured.iter().max().unwrap_or(&b) as f64 / b as f64);
+
+    let mut header = vec![colored(
+        Cell::new("🧱 Memory cap").add_attribute(Attribute::Bold),
+        theme.memory,
+    )];
+    let mut needs_row = vec![Cell::new("Needs at least")];
+    let mut vs_row = vec![Cell::new("vs #1")];
+    for (i, (label, limit)) in limits.iter().enumerate() {
+        header.push(Cell::new(label).add_attribute(Attribute::Bold));
+        let value = limit.map_or("fails".to_string(), |b| bytes.format(b));
+        needs_row.push(match (best, ratio) {
+            (Some(best), Some(ratio)) if *limit == Some(best) && ratio > 1.01 => {
+                winner_cell(&value, theme)
+            }
+            _ => Cell::new(value),
+        });
+        let first = limits[0].1;
+        vs_row.push(Cell::new(match (i, first, limit) {
+            (0, _, _) => "—".to_string(),
+            (_, Some(first), Some(limit)) => {
+                percent_change(first as f64, *limit as f64).unwrap_or_else(|| "—".to_string())
+            }
+            _ => "—".to_string(),
+        }));
+    }
+    let mut grid = vec![header, needs_row];
+    if limits.len() > 1 {
+        grid.push(vs_row);
+    }
+    outln!();
+    outln!("{}", render_table(grid, Layout::Columns));
+
+    match (best, ratio) {
+        (Some(best), Some(ratio)) if limits.len() > 1 && ratio > 1.01 => {
+            let (label, _) = limits.iter().find(|(_, limit)| *limit == Some(best)).unwrap();
+            outln!(
+                "  {} {} {}",
+                theme.memory.paint("→"),
+                theme.winner.paint(label).bold(),
+                metric::MEMORY.verdict(ratio)
+            );
+        }
+        _ if limits.len() > 1 => {
+            outln!("  {} Roughly the same memory needed", theme.memory.paint("→"));
+        }
+        _ => {}
+    }
+    for (label, _) in limits.iter().filter(|(_, limit)| limit.is_none()) {
+        outln!(
+            "  {} {} failed even with all of this machine's memory",
+            theme.warning.paint("⚠"),
+            label
+        );
     }
 }
@@ -0,0 +1,26 @@
+use std::fs::File;
+use std::io::Read;
+
+/// Read every file in `paths` to the end, so the page cache holds them
+/// before the next command runs. Returns the bytes read.
+pub fn prefetch(paths: &[String]) -> Result<u64, String> {
+    let mut buffer = vec![0; 1 << 20];
+    let mut total = 0;
+    for path in paths {
+        let mut file =
+            File::open(path).map_err(|e| format!("--prefetch: failed to open '{}': {}", path, e))?;
+        if file.metadata().is_ok_and(|m| m.is_dir()) {
+            return Err(format!("--prefetch: '{}' is a directory, not a file", path));
+        }
+        loop {
+            let read = file
+                .read(&mut buffer)
+                .map_err(|e| format!("--prefetch: failed to read '{}': {}", path, e))?;
+            if read == 0 {
+                break;
+            }
+            total += read as u64;
+        }
+    }
+    Ok(total)
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::PathBuf;
+
+/// Fingerprints of the `--prepare-once` commands that last ran successfully,
+/// kept between sessions so an unchanged prepare step is skipped.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    /// Prepare command (and where it ran) to the fingerprint of its inputs
+    /// right after it ran
+    entries: BTreeMap<String, String>,
+}
+
+impl Cache {
+    /// The cache from earlier sessions; empty if there is none or it can't
+    /// be read.
+    pub fn load() -> Self {
+        std::fs::read_to_string(path())
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create '{}': {}", dir.display(), e))?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, json)
+            .map_err(|e| format!("Failed to write '{}': {}", path.display(), e))
+    }
+
+    /// Whether `command` last ran with its inputs as they are now.
+    pub fn is_fresh(&self, command: &str, location: Option<&str>, fingerprint: &str) -> bool {
+        self.entries.get(&key(command, location)).is_some_and(|f| f == fingerprint)
+    }
+
+    pub fn record(&mut self, command: &str, location: Option<&str>, fingerprint: String) {
+        self.entries.insert(key(command, location), fingerprint);
+    }
+}
+
+fn key(command: &str, location: Option<&str>) -> String {
+    match location {
+        Some(location) => format!("{} @ {}", command, location),
+        None => command.to_string(),
+    }
+}
+
+/// Where the cache lives: the user's cache directory, else the temporary
+/// directory.
+fn path() -> PathBuf {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let dir = var("XDG_CACHE_HOME")
+        .or_else(|| var("HOME").map(|home| home.join(".cache")))
+        .or_else(|| var("LOCALAPPDATA"))
+        .unwrap_or_else(std::env::temp_dir);
+    dir.join("clash").join("prepare.json")
+}
+
+/// A hash of every input's path and contents. A missing input hashes as
+/// missing, so declaring a file the prepare command creates makes it run
+/// again once that file is deleted.
+pub fn fingerprint(inputs: &[String]) -> Result<String, String> {
+    let mut hash = Fnv::default();
+    for input in inputs {
+        hash.write(input.as_bytes());
+        hash.write(&[0]);
+        let mut file = match std::fs::File::open(input) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                hash.write(b"missing");
+                continue;
+            }
+            Err(e) => return Err(format!("Failed to read --prepare-input '{}': {}", input, e)),
+        };
+        let mut buf = vec![0u8; 1 << 16];
+        loop {
+            let n = file
+                .read(&mut buf)
+                .map_err(|e| format!("Failed to read --prepare-input '{}': {}", input, e))?;
+            if n == 0 {
+                break;
+            }
+            hash.write(&buf[..n]);
+        }
+    }
+    Ok(format!("{:016x}", hash.0))
+}
+
+/// 64-bit FNV-1a, which unlike std's hasher gives the same value in every
+/// build, so fingerprints stay comparable across clash versions.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
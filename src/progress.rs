@@ -0,0 +1,262 @@
+use serde::Serialize;
+use std::time::Duration;
+
+// Bars are drawn by indicatif with the `progress` feature, and not at all
+// without it
+#[cfg(feature = "progress")]
+pub use indicatif::ProgressBar as Bar;
+#[cfg(feature = "progress")]
+use indicatif::{MultiProgress, ProgressDrawTarget, ProgressStyle};
+#[cfg(not(feature = "progress"))]
+pub use undrawn::Bar;
+#[cfg(not(feature = "progress"))]
+use undrawn::{MultiProgress, ProgressDrawTarget};
+
+/// A bar of `len` steps laid out by an indicatif `template` such as
+/// "    Running {bar:20.cyan/dim} {pos}/{len} runs".
+#[cfg(feature = "progress")]
+pub fn bar(len: u64, template: &str) -> Bar {
+    let bar = Bar::new(len);
+    bar.set_style(ProgressStyle::with_template(template).unwrap().progress_chars("━━─"));
+    bar
+}
+
+#[cfg(not(feature = "progress"))]
+pub fn bar(len: u64, _template: &str) -> Bar {
+    Bar::new(len)
+}
+
+/// How session progress is shown, for `--progress`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProgressFormat {
+    /// Progress bars on the terminal
+    #[default]
+    Bars,
+    /// One JSON object per run on stderr, for wrappers that draw their own
+    Json,
+    /// No progress at all
+    None,
+}
+
+/// A `--progress json` line, written after every run (warmup included).
+#[derive(Debug, Serialize)]
+struct ProgressLine {
+    /// 0-based index of the command that ran
+    command: usize,
+    /// Runs of that command done so far
+    run: u32,
+    /// Runs planned for that command, warmup included
+    runs: usize,
+    /// Share of the whole session done, by estimated time
+    percent: f64,
+    /// Estimated time left in the session
+    eta_secs: f64,
+}
+
+/// Session-wide progress weighted by estimated run time rather than run
+/// count, so a session with one slow command doesn't look nearly done after
+/// the fast ones finish.
+///
+/// Each command's per-run cost is estimated from its own runs so far
+/// (warmup runs count); commands that haven't started yet are assumed to
+/// cost the average of those that have.
+pub struct SessionProgress {
+    multi: MultiProgress,
+    overall: Bar,
+    /// Runs still to go per job (warmup + measured)
+    remaining: Vec<usize>,
+    /// Total measured time and run count per job
+    observed: Vec<(Duration, u32)>,
+    spent: Duration,
+    /// Runs planned per job, for `--progress json`; `None` to draw bars
+    json: Option<Vec<usize>>,
+}
+
+impl SessionProgress {
+    /// Start tracking a session whose jobs each need `runs[i]` runs in total.
+    pub fn new(runs: &[usize], format: ProgressFormat) -> Self {
+        let mut progress = Self::hidden(runs);
+        match format {
+            ProgressFormat::Bars => progress.multi.set_draw_target(ProgressDrawTarget::stderr()),
+            ProgressFormat::Json => progress.json = Some(runs.to_vec()),
+            ProgressFormat::None => {}
+        }
+        progress
+    }
+
+    /// Track progress without showing it.
+    pub fn hidden(runs: &[usize]) -> Self {
+        let multi = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+        let overall = multi.add(bar(1, "  Session {bar:20.green/dim} {percent:>3}%  {msg}"));
+        overall.set_message("estimating…");
+        Self {
+            multi,
+            overall,
+            remaining: runs.to_vec(),
+            observed: vec![(Duration::ZERO, 0); runs.len()],
+            spent: Duration::ZERO,
+            json: None,
+        }
+    }
+
+    /// Attach a per-command bar so it renders alongside the session bar.
+    pub fn add(&self, bar: Bar) -> Bar {
+        self.multi.insert_before(&self.overall, bar)
+    }
+
+    /// Remove a finished per-command bar.
+    pub fn remove(&self, bar: &Bar) {
+        bar.finish_and_clear();
+        self.multi.remove(bar);
+    }
+
+    /// Print a line without tearing the progress bars.
+    pub fn println(&self, line: impl AsRef<str>) {
+        self.suspend(|| println!("{}", crate::output::styled(line.as_ref())));
+    }
+
+    /// Hide the bars while `f` writes to the terminal.
+    pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.multi.suspend(f)
+    }
+
+    /// Record that one run of job `job` completed after `took`.
+    pub fn record(&mut self, job: usize, took: Duration) {
+        self.remaining[job] = self.remaining[job].saturating_sub(1);
+        let (total, count) = &mut self.observed[job];
+        *total += took;
+        *count += 1;
+        self.spent += took;
+
+        let estimate = self.estimate_remaining();
+        self.overall
+            .set_length((self.spent + estimate).as_millis() as u64);
+        self.overall.set_position(self.spent.as_millis() as u64);
+        self.overall
+            .set_message(format!("~{} left", format_eta(estimate)));
+
+        if let Some(planned) = &self.json {
+            let total = self.spent + estimate;
+            let line = ProgressLine {
+                command: job,
+                run: self.observed[job].1,
+                runs: planned[job],
+                percent: if total.is_zero() {
+                    100.0
+                } else {
+                    (self.spent.as_secs_f64() / total.as_secs_f64() * 1000.0).round() / 10.0
+                },
+                eta_secs: (estimate.as_secs_f64() * 1000.0).round() / 1000.0,
+            };
+            if let Ok(json) = serde_json::to_string(&line) {
+                eprintln!("{}", json);
+            }
+        }
+    }
+
+    /// Drop job `job`'s remaining runs from the estimate, e.g. when they
+    /// were cut short.
+    pub fn skip_rest(&mut self, job: usize) {
+        self.remaining[job] = 0;
+    }
+
+    /// Clear the session bar once everything has run.
+    pub fn finish(&self) {
+        self.overall.finish_and_clear();
+    }
+
+    fn estimate_remaining(&self) -> Duration {
+        let known: Vec<Duration> = self
+            .observed
+            .iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(total, count)| *total / *count)
+            .collect();
+        if known.is_empty() {
+            return Duration::ZERO;
+        }
+        let average = known.iter().sum::<Duration>() / known.len() as u32;
+
+        self.remaining
+            .iter()
+            .zip(&self.observed)
+            .map(|(&left, &(total, count))| {
+                let per_run = if count > 0 { total / count } else { average };
+                per_run * left as u32
+            })
+            .sum()
+    }
+}
+
+/// Coarse human-readable time-left string.
+fn format_eta(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// The slice of indicatif's API clash uses, drawing nothing, for builds
+/// without the `progress` feature.
+#[cfg(not(feature = "progress"))]
+mod undrawn {
+    use std::borrow::Cow;
+
+    pub struct Bar;
+
+    impl Bar {
+        pub fn new(_len: u64) -> Self {
+            Bar
+        }
+
+        pub fn set_length(&self, _len: u64) {}
+
+        pub fn set_position(&self, _pos: u64) {}
+
+        pub fn set_message(&self, _msg: impl Into<Cow<'static, str>>) {}
+
+        pub fn inc(&self, _delta: u64) {}
+
+        pub fn finish_and_clear(&self) {}
+    }
+
+    pub struct ProgressDrawTarget;
+
+    impl ProgressDrawTarget {
+        pub fn stderr() -> Self {
+            ProgressDrawTarget
+        }
+
+        pub fn hidden() -> Self {
+            ProgressDrawTarget
+        }
+    }
+
+    pub struct MultiProgress;
+
+    impl MultiProgress {
+        pub fn with_draw_target(_target: ProgressDrawTarget) -> Self {
+            MultiProgress
+        }
+
+        pub fn set_draw_target(&self, _target: ProgressDrawTarget) {}
+
+        pub fn add(&self, bar: Bar) -> Bar {
+            bar
+        }
+
+        pub fn insert_before(&self, _before: &Bar, bar: Bar) -> Bar {
+            bar
+        }
+
+        pub fn remove(&self, _bar: &Bar) {}
+
+        pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+            f()
+        }
+    }
+}
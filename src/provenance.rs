@@ -0,0 +1,48 @@
+use crate::types::Provenance;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Identify the program `cmd` starts: its first word after any leading
+/// `VAR=value` assignments, found the way the shell would (through PATH
+/// unless it names a path). `None` for shell builtins and keywords, and
+/// for programs that can't be read.
+pub fn resolve(cmd: &str) -> Option<Provenance> {
+    let program = cmd
+        .split_whitespace()
+        .find(|word| !is_assignment(word))?
+        .trim_matches(['\'', '"']);
+    let path = locate(program)?.canonicalize().ok()?;
+    let metadata = path.metadata().ok()?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(&path).ok()?, &mut hasher).ok()?;
+    let sha256 = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    Some(Provenance {
+        path: path.display().to_string(),
+        sha256,
+        size_bytes: metadata.len(),
+        modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+    })
+}
+
+/// Whether `word` sets a variable for the command, as in `RUST_LOG=info`.
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty() && name.chars().all(|c| c == '_' || c.is_ascii_alphanumeric())
+    })
+}
+
+fn locate(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        return Some(PathBuf::from(program)).filter(|p| p.is_file());
+    }
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find_map(|dir| {
+        let candidates = [
+            dir.join(program),
+            dir.join(format!("{}{}", program, std::env::consts::EXE_SUFFIX)),
+        ];
+        candidates.into_iter().find(|p| Path::is_file(p))
+    })
+}
@@ -0,0 +1,110 @@
+//! The event loop clash watches its runs from. Child exits, time limits,
+//! memory sampling and output capture are all driven by its timers and
+//! signals, and it turns Ctrl-C into a clean stop.
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::sync::{Notify, watch};
+
+/// How long runs in flight get after Ctrl-C to stop their processes and
+/// remove their cgroups before clash exits regardless.
+const STOP_GRACE: Duration = Duration::from_secs(2);
+
+/// Exit status after Ctrl-C, as shells report death by SIGINT.
+const INTERRUPTED_EXIT: i32 = 130;
+
+struct Reactor {
+    runtime: Runtime,
+    /// Flips to true on Ctrl-C
+    interrupt: watch::Sender<bool>,
+    /// Runs in flight
+    active: AtomicUsize,
+    /// Signalled as each run in flight stops
+    stopped: Notify,
+}
+
+fn reactor() -> &'static Reactor {
+    static REACTOR: OnceLock<Reactor> = OnceLock::new();
+    REACTOR.get_or_init(|| {
+        // Two threads drive timers, signals, pipes and sampling for every
+        // run, however many are in flight, so one is free to notice an exit
+        // while the other samples; the threads that started runs wait on
+        // them without busy work
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("clash-events")
+            .enable_all()
+            .build()
+            .expect("failed to start the event loop");
+        runtime.spawn(stop_on_ctrl_c());
+        Reactor {
+            runtime,
+            interrupt: watch::channel(false).0,
+            active: AtomicUsize::new(0),
+            stopped: Notify::new(),
+        }
+    })
+}
+
+/// The runtime runs are watched on, started on first use.
+pub fn runtime() -> &'static Runtime {
+    &reactor().runtime
+}
+
+/// Changes once Ctrl-C has been pressed.
+pub fn interrupt() -> watch::Receiver<bool> {
+    reactor().interrupt.subscribe()
+}
+
+/// On Ctrl-C, have every run in flight stop its process, give them a
+/// moment to clean up, then exit.
+async fn stop_on_ctrl_c() {
+    if tokio::signal::ctrl_c().await.is_err() {
+        return;
+    }
+    let reactor = reactor();
+    reactor.interrupt.send_replace(true);
+    let all_stopped = async {
+        while reactor.active.load(Ordering::SeqCst) > 0 {
+            reactor.stopped.notified().await;
+        }
+    };
+    let _ = tokio::time::timeout(STOP_GRACE, all_stopped).await;
+    std::process::exit(INTERRUPTED_EXIT);
+}
+
+/// A run in flight, which Ctrl-C waits on until it's dropped.
+pub struct InFlight(());
+
+impl InFlight {
+    /// Count a run as in flight. After Ctrl-C, when clash is on its way
+    /// out, this never returns.
+    pub fn start() -> Self {
+        let reactor = reactor();
+        reactor.active.fetch_add(1, Ordering::SeqCst);
+        let run = InFlight(());
+        if *reactor.interrupt.borrow() {
+            drop(run);
+            halt();
+        }
+        run
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let reactor = reactor();
+        reactor.active.fetch_sub(1, Ordering::SeqCst);
+        reactor.stopped.notify_one();
+    }
+}
+
+/// Block the calling thread for good, once Ctrl-C has stopped its run;
+/// the event loop exits the process.
+pub fn halt() -> ! {
+    loop {
+        std::thread::park();
+    }
+}
@@ -0,0 +1,124 @@
+use crate::runner::{RunIndex, Runner};
+use crate::types::{CommandStats, Provenance, RunResult};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+const SESSION_VERSION: u32 = 1;
+
+/// Raw measurements of a whole benchmark session, as written by `--record`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    pub commands: Vec<RecordedCommand>,
+}
+
+/// Every measured run of one command.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordedCommand {
+    pub command: String,
+    /// Display name, when the source had one (clash exports do)
+    #[serde(default)]
+    pub label: Option<String>,
+    pub location: Option<String>,
+    /// The program the command started, when the source recorded it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    pub results: Vec<RunResult>,
+    /// The source didn't record memory (hyperfine without
+    /// `memory_usage_byte`), as opposed to runs too fast to sample
+    #[serde(default, skip_serializing_if = "crate::types::is_false")]
+    pub memory_unrecorded: bool,
+}
+
+/// Write a session file from the finished stats.
+pub fn save(
+    path: &str,
+    all_stats: &[CommandStats],
+    locations: &[Option<String>],
+) -> Result<(), String> {
+    let session = Session {
+        version: SESSION_VERSION,
+        commands: all_stats
+            .iter()
+            .zip(locations)
+            .map(|(s, location)| RecordedCommand {
+                command: s.command.clone(),
+                label: Some(s.label.clone()),
+                location: location.clone(),
+                provenance: s.provenance.clone(),
+                results: s.all_runs.clone(),
+                memory_unrecorded: s.memory_unrecorded,
+            })
+            .collect(),
+    };
+    let json = serde_json::to_string_pretty(&session)
+        .map_err(|e| format!("Failed to serialize session: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// Read a session file written by `save`.
+pub fn load(path: &str) -> Result<Session, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let session: Session =
+        serde_json::from_str(&text).map_err(|e| format!("Invalid session file {}: {}", path, e))?;
+    if session.version != SESSION_VERSION {
+        return Err(format!(
+            "{} has session version {}, expected {}",
+            path, session.version, SESSION_VERSION
+        ));
+    }
+    Ok(session)
+}
+
+/// Plays back recorded runs in order, sleeping for each run's recorded
+/// duration (divided by `speed`) so progress looks like the real session.
+pub struct ReplayRunner {
+    location: Option<String>,
+    results: RefCell<VecDeque<RunResult>>,
+    speed: f64,
+    memory_unrecorded: bool,
+}
+
+impl ReplayRunner {
+    pub fn new(recorded: RecordedCommand, speed: f64) -> Self {
+        Self {
+            location: recorded.location,
+            results: RefCell::new(recorded.results.into()),
+            speed,
+            memory_unrecorded: recorded.memory_unrecorded,
+        }
+    }
+}
+
+impl Runner for ReplayRunner {
+    fn run_once(&self, cmd: &str, _index: &RunIndex) -> Result<RunResult, String> {
+        let result = self
+            .results
+            .borrow_mut()
+            .pop_front()
+            .ok_or_else(|| format!("No recorded runs left for '{}'", cmd))?;
+        std::thread::sleep(Duration::from_secs_f64(
+            result.duration.as_secs_f64() / self.speed,
+        ));
+        Ok(result)
+    }
+
+    fn location(&self) -> Option<String> {
+        self.location.clone()
+    }
+
+    fn on_host(&self) -> bool {
+        false
+    }
+
+    fn replay_speed(&self) -> Option<f64> {
+        Some(self.speed)
+    }
+
+    fn records_memory(&self) -> bool {
+        !self.memory_unrecorded
+    }
+}
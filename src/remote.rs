@@ -0,0 +1,129 @@
+use crate::runner::{RunIndex, Runner};
+use crate::types::RunResult;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Runs commands on a remote host over SSH.
+///
+/// The command is timed on the remote side with nanosecond timestamps, and
+/// wrapped in GNU `/usr/bin/time -v` for its peak RSS, so both reflect the
+/// target machine rather than the SSH round trip.
+pub struct SshRunner {
+    pub host: String,
+}
+
+impl Runner for SshRunner {
+    fn run_once(&self, cmd: &str, index: &RunIndex) -> Result<RunResult, String> {
+        run_once(cmd, &self.host, index)
+    }
+
+    fn location(&self) -> Option<String> {
+        Some(format!("on {}", self.host))
+    }
+
+    fn on_host(&self) -> bool {
+        false
+    }
+}
+
+fn run_once(cmd: &str, host: &str, index: &RunIndex) -> Result<RunResult, String> {
+    let vars: Vec<String> = index
+        .vars()
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    let remote_cmd = format!(
+        "env {} /usr/bin/time -v sh -c {} </dev/null",
+        vars.join(" "),
+        shell_quote(&format!(
+            "c0=$(date +%s%N); c1=$(date +%s%N); s=$(date +%s%N)\n\
+             (\n{}\n) >/dev/null 2>&1\n\
+             rc=$?\n\
+             e=$(date +%s%N)\n\
+             case $c0$c1$s$e in *[!0-9]*) ;; *) echo \"{}: $((e - s - (c1 - c0)))\" >&2 ;; esac\n\
+             exit $rc",
+            cmd, ELAPSED_NS
+        ))
+    );
+
+    let start = Instant::now();
+    let output = Command::new("ssh")
+        .args(["-o", "BatchMode=yes", host, &remote_cmd])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| format!("Failed to start ssh for '{}': {}", cmd, e))?;
+    let local_duration = start.elapsed();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let report = parse_time_report(&stderr);
+
+    let Some(peak_kb) = report.max_rss_kb else {
+        let reason = stderr.lines().last().unwrap_or("no output").trim();
+        return Err(if output.status.code() == Some(255) {
+            format!("ssh to '{}' failed: {}", host, reason)
+        } else {
+            format!(
+                "'/usr/bin/time -v' produced no report on '{}' (is GNU time installed?): {}",
+                host, reason
+            )
+        });
+    };
+
+    Ok(RunResult {
+        // GNU time's own elapsed time only has 10ms resolution
+        duration: report.elapsed_ns.or(report.elapsed).unwrap_or(local_duration),
+        peak_memory_bytes: Some(peak_kb * 1024).filter(|&b| b > 0),
+        exit_code: report.exit_status.or(output.status.code()),
+        ..RunResult::default()
+    })
+}
+
+/// Key of the line giving the command's time in nanoseconds, less what the
+/// `date` calls taking the timestamps add, which is measured beforehand.
+const ELAPSED_NS: &str = "Clash elapsed (ns)";
+
+/// Fields of interest from a GNU `time -v` report, and the line with the
+/// command's own timing before it.
+#[derive(Debug, Default)]
+struct TimeReport {
+    elapsed_ns: Option<Duration>,
+    elapsed: Option<Duration>,
+    max_rss_kb: Option<u64>,
+    exit_status: Option<i32>,
+}
+
+fn parse_time_report(text: &str) -> TimeReport {
+    let mut report = TimeReport::default();
+    for line in text.lines() {
+        let Some((key, value)) = line.trim().rsplit_once(": ") else {
+            continue;
+        };
+        if key == ELAPSED_NS {
+            let nanos: Option<i64> = value.trim().parse().ok();
+            report.elapsed_ns = nanos.map(|n| Duration::from_nanos(n.max(0) as u64));
+        } else if key.starts_with("Elapsed (wall clock) time") {
+            report.elapsed = parse_clock(value.trim());
+        } else if key == "Maximum resident set size (kbytes)" {
+            report.max_rss_kb = value.trim().parse().ok();
+        } else if key == "Exit status" {
+            report.exit_status = value.trim().parse().ok();
+        }
+    }
+    report
+}
+
+/// Parse GNU time's `h:mm:ss` / `m:ss.ss` elapsed format.
+fn parse_clock(value: &str) -> Option<Duration> {
+    let mut secs = 0.0;
+    for part in value.split(':') {
+        secs = secs * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(Duration::from_secs_f64(secs))
+}
+
+/// Quote a string for a POSIX shell using single quotes.
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
@@ -1,122 +1,940 @@
-use crate::types::RunResult;
-use indicatif::{ProgressBar, ProgressStyle};
+use crate::container::ContainerRunner;
+use crate::exec::{self, Executor, Follow, Redirect, SystemSpawner};
+use crate::progress::{self, SessionProgress};
+use crate::remote::SshRunner;
+use crate::stats;
+use crate::syscalls;
+use crate::timecmd::Flavor;
+use crate::types::{
+    CpuUsage, Delay, OutputCounts, Phase, ResourceUsage, RunResult, RunSettings, RunStatus,
+    Syscalls,
+};
+use crate::units;
+use chrono::Utc;
+use serde::Serialize;
+use std::cell::Cell;
+use std::path::PathBuf;
 use std::process::Command;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
-use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
-
-/// Run a single iteration of a command, measuring time and peak memory.
-fn run_once(cmd: &str) -> Result<RunResult, String> {
-    // Parse command into program + args (shell-style)
-    let parts = shell_split(cmd)?;
-    let (program, args) = parts
-        .split_first()
-        .ok_or_else(|| "Empty command".to_string())?;
-
-    let mut child = Command::new(program)
-        .args(args)
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null())
-        .spawn()
-        .map_err(|e| format!("Failed to start '{}': {}", cmd, e))?;
-
-    let pid = child.id();
-    let peak_memory = Arc::new(AtomicU64::new(0));
-    let process_alive = Arc::new(std::sync::atomic::AtomicBool::new(true));
-
-    // Spawn memory monitoring thread
-    let monitor_handle = {
-        let peak = peak_memory.clone();
-        let alive = process_alive.clone();
-        std::thread::spawn(move || {
-            let mut sys = System::new();
-            let refresh_kind = ProcessRefreshKind::nothing()
-                .with_memory();
-
-            while alive.load(Ordering::Relaxed) {
-                sys.refresh_processes_specifics(
-                    ProcessesToUpdate::Some(&[Pid::from_u32(pid)]),
-                    true,
-                    refresh_kind,
-                );
-
-                if let Some(proc) = sys.process(Pid::from_u32(pid)) {
-                    peak.fetch_max(proc.memory(), Ordering::Relaxed);
-                }
 
-                std::thread::sleep(Duration::from_millis(30));
+/// An execution environment that can run one iteration of a command.
+///
+/// Backends only have to produce a `RunResult`; everything downstream
+/// (statistics, comparison, output) is shared.
+pub trait Runner {
+    /// Run `cmd` once, measuring wall-clock time and peak memory, with
+    /// `index` exposed to it through the environment.
+    fn run_once(&self, cmd: &str, index: &RunIndex) -> Result<RunResult, String>;
+
+    /// Short description of where commands run, shown next to each command.
+    fn location(&self) -> Option<String> {
+        None
+    }
+
+    /// Whether runs execute on this machine, so host-side sampling such as
+    /// CPU frequency applies to them.
+    fn on_host(&self) -> bool {
+        true
+    }
+
+    /// Whether commands see this machine's files, so the programs they
+    /// start can be looked up here.
+    fn host_files(&self) -> bool {
+        self.on_host()
+    }
+
+    /// How fast recorded results are played back, when they come from a
+    /// file rather than being measured (infinite for imported results);
+    /// the session's own wall-clock time says nothing about them.
+    fn replay_speed(&self) -> Option<f64> {
+        None
+    }
+
+    /// Whether runs can report peak memory at all. Results imported from
+    /// a tool that didn't record it can't.
+    fn records_memory(&self) -> bool {
+        true
+    }
+}
+
+/// Where a run falls in the session, passed to commands and `--prepare` as
+/// `CLASH_RUN`, `CLASH_TOTAL_RUNS` and `CLASH_COMMAND_INDEX` so scripts can
+/// pick per-run inputs or output paths.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunIndex {
+    /// 0-based position of the command in the session
+    pub command: usize,
+    /// 0-based run of this command, counting warmup runs first
+    pub run: usize,
+    /// Runs planned for this command, warmup included (an upper bound when
+    /// runs are adaptive)
+    pub total_runs: usize,
+}
+
+impl RunIndex {
+    /// The environment variables describing this run.
+    pub fn vars(&self) -> [(&'static str, String); 3] {
+        [
+            ("CLASH_RUN", self.run.to_string()),
+            ("CLASH_TOTAL_RUNS", self.total_runs.to_string()),
+            ("CLASH_COMMAND_INDEX", self.command.to_string()),
+        ]
+    }
+}
+
+/// Runs the command directly, splitting it into program and arguments.
+#[derive(Default)]
+pub struct LocalRunner {
+    pub exec: Executor,
+    /// Extra environment variables for the command
+    pub env: Vec<(String, String)>,
+    /// Program and arguments the command is launched through, e.g. numactl
+    pub prefix: Vec<String>,
+}
+
+impl Runner for LocalRunner {
+    fn run_once(&self, cmd: &str, index: &RunIndex) -> Result<RunResult, String> {
+        // Parse command into program + args (shell-style)
+        let mut parts = self.prefix.clone();
+        parts.extend(shell_split(cmd)?);
+        let (program, args) = parts
+            .split_first()
+            .ok_or_else(|| "Empty command".to_string())?;
+
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .envs(index.vars());
+        self.exec.measure(command, cmd)
+    }
+}
+
+/// Runs the command through a shell, e.g. `sh -c CMD`.
+pub struct ShellRunner {
+    pub shell: String,
+    pub exec: Executor,
+    /// Extra environment variables for the command
+    pub env: Vec<(String, String)>,
+    /// Program and arguments the shell is launched through, e.g. numactl
+    pub prefix: Vec<String>,
+}
+
+impl Runner for ShellRunner {
+    fn run_once(&self, cmd: &str, index: &RunIndex) -> Result<RunResult, String> {
+        let mut command = match self.prefix.split_first() {
+            Some((program, args)) => {
+                let mut command = Command::new(program);
+                command.args(args).arg(&self.shell);
+                command
             }
+            None => Command::new(&self.shell),
+        };
+        command
+            .args(["-c", cmd])
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .envs(index.vars());
+        self.exec.measure(command, cmd)
+    }
+
+    fn location(&self) -> Option<String> {
+        Some(format!("via {}", self.shell))
+    }
+}
+
+/// Wraps another runner to pause before each run, outside the measured
+/// time, so the system can settle between runs.
+pub struct PacedRunner<'a> {
+    inner: &'a dyn Runner,
+    delay: Delay,
+    pause_next: Cell<bool>,
+}
+
+impl<'a> PacedRunner<'a> {
+    /// `pause_first` also delays the first run, e.g. after another command.
+    pub fn new(inner: &'a dyn Runner, delay: Delay, pause_first: bool) -> Self {
+        Self {
+            inner,
+            delay,
+            pause_next: Cell::new(pause_first),
+        }
+    }
+}
+
+impl Runner for PacedRunner<'_> {
+    fn run_once(&self, cmd: &str, index: &RunIndex) -> Result<RunResult, String> {
+        if self.pause_next.replace(true) && !self.delay.is_zero() {
+            self.delay.pause();
+        }
+        self.inner.run_once(cmd, index)
+    }
+
+    fn location(&self) -> Option<String> {
+        self.inner.location()
+    }
+
+    fn on_host(&self) -> bool {
+        self.inner.on_host()
+    }
+
+    fn host_files(&self) -> bool {
+        self.inner.host_files()
+    }
+
+    fn replay_speed(&self) -> Option<f64> {
+        self.inner.replay_speed()
+    }
+
+    fn records_memory(&self) -> bool {
+        self.inner.records_memory()
+    }
+}
+
+/// Wraps another runner to run an untimed `--prepare` command before each
+/// run, in the same environment, recording how long preparation took.
+pub struct PreparedRunner<'a> {
+    inner: &'a dyn Runner,
+    prepare: &'a str,
+}
+
+impl<'a> PreparedRunner<'a> {
+    pub fn new(inner: &'a dyn Runner, prepare: &'a str) -> Self {
+        Self { inner, prepare }
+    }
+}
+
+impl Runner for PreparedRunner<'_> {
+    fn run_once(&self, cmd: &str, index: &RunIndex) -> Result<RunResult, String> {
+        let prepared = self.inner.run_once(self.prepare, index)?;
+        match prepared.status() {
+            RunStatus::Success => {}
+            status => return Err(format!("Prepare command '{}' failed: {}", self.prepare, status)),
+        }
+        let mut result = self.inner.run_once(cmd, index)?;
+        result.prepare = Some(prepared.duration);
+        Ok(result)
+    }
+
+    fn location(&self) -> Option<String> {
+        self.inner.location()
+    }
 
-            // One final check
-            sys.refresh_processes_specifics(
-                ProcessesToUpdate::Some(&[Pid::from_u32(pid)]),
-                true,
-                refresh_kind,
-            );
-            if let Some(proc) = sys.process(Pid::from_u32(pid)) {
-                peak.fetch_max(proc.memory(), Ordering::Relaxed);
+    fn on_host(&self) -> bool {
+        self.inner.on_host()
+    }
+
+    fn host_files(&self) -> bool {
+        self.inner.host_files()
+    }
+
+    fn replay_speed(&self) -> Option<f64> {
+        self.inner.replay_speed()
+    }
+
+    fn records_memory(&self) -> bool {
+        self.inner.records_memory()
+    }
+}
+
+/// Prefix of pseudo-commands that sleep inside clash instead of starting a
+/// process, e.g. `sleep:250ms`.
+const SLEEP_PREFIX: &str = "sleep:";
+
+/// Whether `cmd` is a `sleep:DURATION` pseudo-command.
+pub fn is_pseudo(cmd: &str) -> bool {
+    cmd.starts_with(SLEEP_PREFIX)
+}
+
+/// Wraps another runner to simulate `sleep:DURATION` pseudo-commands: a run
+/// of known length with no process behind it, for checking clash's own
+/// accuracy and exercising the whole pipeline. Other commands (such as a
+/// `--prepare`) go to the wrapped runner.
+pub struct PseudoRunner {
+    inner: Box<dyn Runner>,
+}
+
+impl PseudoRunner {
+    pub fn new(inner: Box<dyn Runner>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Runner for PseudoRunner {
+    fn run_once(&self, cmd: &str, index: &RunIndex) -> Result<RunResult, String> {
+        let Some(literal) = cmd.strip_prefix(SLEEP_PREFIX) else {
+            return self.inner.run_once(cmd, index);
+        };
+        let target = units::parse_duration(literal)?;
+        let start = Instant::now();
+        std::thread::sleep(target);
+        Ok(RunResult {
+            duration: start.elapsed(),
+            exit_code: Some(0),
+            ..RunResult::default()
+        })
+    }
+
+    fn location(&self) -> Option<String> {
+        Some("simulated".to_string())
+    }
+}
+
+/// Wraps another runner to run a command as a pipeline, with `--stage`:
+/// each stage is the command with the stage's arguments appended, run in
+/// order and timed as a phase of its own. A stage that fails, other than by
+/// exiting with one of `expected` codes, ends the run. Other commands (such
+/// as a `--prepare`) go to the wrapped runner.
+pub struct StagedRunner {
+    inner: Box<dyn Runner>,
+    command: String,
+    stages: Vec<String>,
+    expected: Vec<i32>,
+}
+
+impl StagedRunner {
+    pub fn new(inner: Box<dyn Runner>, command: &str, stages: &[String], expected: &[i32]) -> Self {
+        Self {
+            inner,
+            command: command.to_string(),
+            stages: stages.to_vec(),
+            expected: expected.to_vec(),
+        }
+    }
+}
+
+impl Runner for StagedRunner {
+    fn run_once(&self, cmd: &str, index: &RunIndex) -> Result<RunResult, String> {
+        if cmd != self.command {
+            return self.inner.run_once(cmd, index);
+        }
+        let mut run: Option<RunResult> = None;
+        for stage in &self.stages {
+            let mut result = self.inner.run_once(&format!("{} {}", cmd, stage), index)?;
+            if !result.timed_out {
+                result.expected_exit =
+                    result.exit_code.is_some_and(|code| self.expected.contains(&code));
             }
+            let phase = Phase {
+                name: stage.clone(),
+                duration: result.duration,
+            };
+            let failed = result.status() != RunStatus::Success;
+            run = Some(match run {
+                None => RunResult {
+                    phases: vec![phase],
+                    ..result
+                },
+                Some(mut run) => {
+                    run.duration += result.duration;
+                    run.peak_memory_bytes = run.peak_memory_bytes.max(result.peak_memory_bytes);
+                    run.cpu = match (run.cpu, result.cpu) {
+                        (Some(a), Some(b)) => Some(CpuUsage {
+                            time: a.time + b.time,
+                            peak_cores: [a.peak_cores, b.peak_cores]
+                                .into_iter()
+                                .flatten()
+                                .reduce(f64::max),
+                        }),
+                        (a, b) => a.or(b),
+                    };
+                    run.usage = match (run.usage, result.usage) {
+                        (Some(a), Some(b)) => Some(add_usage(&[a, b])),
+                        (a, b) => a.or(b),
+                    };
+                    (run.exit_code, run.signal, run.timed_out, run.expected_exit) = (
+                        result.exit_code,
+                        result.signal,
+                        result.timed_out,
+                        result.expected_exit,
+                    );
+                    run.phases.push(phase);
+                    run
+                }
+            });
+            if failed {
+                break;
+            }
+        }
+        run.ok_or_else(|| "No --stage to run".to_string())
+    }
+
+    fn location(&self) -> Option<String> {
+        let stages = format!("{} stages", self.stages.len());
+        Some(match self.inner.location() {
+            Some(location) => format!("{}, {}", stages, location),
+            None => stages,
         })
+    }
+
+    fn on_host(&self) -> bool {
+        self.inner.on_host()
+    }
+
+    fn host_files(&self) -> bool {
+        self.inner.host_files()
+    }
+
+    fn replay_speed(&self) -> Option<f64> {
+        self.inner.replay_speed()
+    }
+
+    fn records_memory(&self) -> bool {
+        self.inner.records_memory()
+    }
+}
+
+/// Wraps another runner so that runs of a command ending in one of the
+/// exit codes it's expected to exit with count as successful. Other
+/// commands (such as a `--prepare`) go to the wrapped runner.
+pub struct ExpectedExitRunner {
+    inner: Box<dyn Runner>,
+    command: String,
+    codes: Vec<i32>,
+}
+
+impl ExpectedExitRunner {
+    pub fn new(inner: Box<dyn Runner>, command: &str, codes: &[i32]) -> Self {
+        Self {
+            inner,
+            command: command.to_string(),
+            codes: codes.to_vec(),
+        }
+    }
+}
+
+impl Runner for ExpectedExitRunner {
+    fn run_once(&self, cmd: &str, index: &RunIndex) -> Result<RunResult, String> {
+        let mut result = self.inner.run_once(cmd, index)?;
+        if cmd == self.command && !result.timed_out {
+            result.expected_exit = result.exit_code.is_some_and(|code| self.codes.contains(&code));
+        }
+        Ok(result)
+    }
+
+    fn location(&self) -> Option<String> {
+        self.inner.location()
+    }
+
+    fn on_host(&self) -> bool {
+        self.inner.on_host()
+    }
+
+    fn host_files(&self) -> bool {
+        self.inner.host_files()
+    }
+
+    fn replay_speed(&self) -> Option<f64> {
+        self.inner.replay_speed()
+    }
+
+    fn records_memory(&self) -> bool {
+        self.inner.records_memory()
+    }
+}
+
+/// How commands on this machine are launched.
+#[derive(Debug, Clone, Default)]
+pub struct LocalOptions {
+    /// Run through this shell instead of directly
+    pub shell: Option<String>,
+    /// Extra environment variables
+    pub env: Vec<(String, String)>,
+    /// Program and arguments commands are launched through, e.g. numactl
+    pub prefix: Vec<String>,
+    /// Pipe stdout/stderr to count bytes and lines instead of discarding them
+    pub count_output: bool,
+    /// Which processes each run lasts for
+    pub follow: Follow,
+    /// Pipe stderr to time the phases the command marks on it
+    pub phases: bool,
+    /// Kill a run that takes longer than this
+    pub timeout: Option<Duration>,
+    /// Read each run's peak RSS from the kernel as well as sampling it
+    pub precise_memory: bool,
+    /// Copies of the command each run launches at once; 0 and 1 both mean one
+    pub instances: usize,
+    /// Launch commands through this `/usr/bin/time` and take memory, faults
+    /// and context switches from its report (`--backend time`)
+    pub time: Option<Flavor>,
+    /// Files the benchmarked command reads stdin from and writes stdout to
+    pub redirect: Option<Redirect>,
+}
+
+/// Pick the runner for one command from the CLI flags that apply to it.
+pub fn select(
+    remote: Option<&str>,
+    container: Option<&str>,
+    local_options: &LocalOptions,
+) -> Result<Box<dyn Runner>, String> {
+    let prefix = &local_options.prefix;
+    // Waiting for every adopted descendant reaps whatever exits, so one
+    // instance would take its siblings' children from them
+    if matches!(local_options.follow, Follow::Forks) && local_options.instances > 1 {
+        return Err("--follow-forks cannot be combined with --instances".to_string());
+    }
+    match (remote, container) {
+        (Some(_), Some(_)) => {
+            Err("--remote and --container cannot both apply to a command".to_string())
+        }
+        (Some(_), None) | (None, Some(_)) if !prefix.is_empty() => {
+            Err("--numa-node and --core-type only apply to commands run on this machine".to_string())
+        }
+        (Some(_), None) | (None, Some(_))
+            if !matches!(local_options.follow, Follow::Launcher) =>
+        {
+            Err("--follow-forks and --pidfile only apply to commands run on this machine".to_string())
+        }
+        (Some(_), None) | (None, Some(_)) if local_options.phases => {
+            Err("--phases only applies to commands run on this machine".to_string())
+        }
+        (Some(_), None) | (None, Some(_)) if local_options.timeout.is_some() => {
+            Err("--timeout only applies to commands run on this machine".to_string())
+        }
+        (Some(_), None) | (None, Some(_)) if local_options.precise_memory => {
+            Err("--precise-memory only applies to commands run on this machine".to_string())
+        }
+        (Some(_), None) | (None, Some(_)) if local_options.instances > 1 => {
+            Err("--instances only applies to commands run on this machine".to_string())
+        }
+        (Some(_), None) | (None, Some(_)) if local_options.time.is_some() => {
+            Err("--backend time only applies to commands run on this machine".to_string())
+        }
+        (Some(host), None) => Ok(Box::new(SshRunner {
+            host: host.to_string(),
+        })),
+        (None, Some(image)) => Ok(Box::new(ContainerRunner {
+            image: image.to_string(),
+        })),
+        (None, None) => Ok(local(local_options.clone())),
+    }
+}
+
+/// A runner for this machine.
+pub fn local(options: LocalOptions) -> Box<dyn Runner> {
+    if options.instances > 1 {
+        return Box::new(ConcurrentRunner {
+            instances: options.instances,
+            options: LocalOptions {
+                instances: 1,
+                ..options
+            },
+        });
+    }
+    let exec = Executor {
+        count_output: options.count_output,
+        phases: options.phases,
+        redirect: options.redirect,
+        spawner: Box::new(SystemSpawner {
+            follow: options.follow,
+            timeout: options.timeout,
+            precise_memory: options.precise_memory,
+        }),
+        ..Executor::default()
+    };
+    let time = options.time.map(|flavor| (flavor, report_path("time")));
+    let mut prefix = options.prefix;
+    if let Some((flavor, report)) = &time {
+        prefix.splice(0..0, flavor.prefix(report));
+    }
+    let runner: Box<dyn Runner> = match options.shell {
+        Some(shell) => Box::new(ShellRunner {
+            shell,
+            exec,
+            env: options.env,
+            prefix,
+        }),
+        None => Box::new(LocalRunner {
+            exec,
+            env: options.env,
+            prefix,
+        }),
+    };
+    match time {
+        Some((flavor, report)) => Box::new(TimeRunner {
+            inner: runner,
+            flavor,
+            report,
+        }),
+        None => runner,
+    }
+}
+
+/// A file of its own for a wrapping `tool`'s report, so concurrent
+/// instances don't overwrite each other's.
+fn report_path(tool: &str) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    std::env::temp_dir().join(format!(
+        "clash-{}-{}-{}.txt",
+        tool,
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Run `cmd` once under `strace -c`, untimed, and count its system calls,
+/// for `--syscalls`.
+pub fn count_syscalls(
+    options: &LocalOptions,
+    cmd: &str,
+    index: &RunIndex,
+) -> Result<Syscalls, String> {
+    let report = report_path("strace");
+    let mut options = LocalOptions {
+        instances: 1,
+        time: None,
+        ..options.clone()
     };
+    options.prefix.splice(0..0, syscalls::prefix(&report)?);
+    let result = local(options).run_once(cmd, index);
+    let text = std::fs::read_to_string(&report);
+    std::fs::remove_file(&report).ok();
+    result?;
+    let text = text.map_err(|e| format!("Failed to read strace's summary for '{}': {}", cmd, e))?;
+    syscalls::parse(&text)
+}
+
+/// Wraps a runner whose commands are launched through `/usr/bin/time`,
+/// taking each run's memory, faults and context switches from the report
+/// it writes to `report`, where sampling can miss short-lived peaks.
+pub struct TimeRunner {
+    inner: Box<dyn Runner>,
+    flavor: Flavor,
+    report: PathBuf,
+}
+
+impl Runner for TimeRunner {
+    fn run_once(&self, cmd: &str, index: &RunIndex) -> Result<RunResult, String> {
+        // A report left from the previous run would pass for this one's
+        std::fs::remove_file(&self.report).ok();
+        let mut result = self.inner.run_once(cmd, index)?;
+        // Killed along with the command, time writes no report
+        if result.timed_out {
+            return Ok(result);
+        }
+        let text = std::fs::read_to_string(&self.report)
+            .map_err(|e| format!("Failed to read /usr/bin/time's report for '{}': {}", cmd, e))?;
+        let report = self.flavor.parse(&text)?;
+        result.peak_memory_bytes = Some(report.max_rss_bytes);
+        result.usage = Some(report.usage);
+        if let Some(signal) = report.signal {
+            (result.exit_code, result.signal) = (None, Some(signal));
+        }
+        Ok(result)
+    }
+
+    fn location(&self) -> Option<String> {
+        self.inner.location()
+    }
+}
+
+impl Drop for TimeRunner {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.report).ok();
+    }
+}
+
+/// Several runs' faults and context switches added up.
+fn add_usage(usages: &[ResourceUsage]) -> ResourceUsage {
+    ResourceUsage {
+        major_faults: usages.iter().map(|u| u.major_faults).sum(),
+        minor_faults: usages.iter().map(|u| u.minor_faults).sum(),
+        voluntary_switches: usages.iter().map(|u| u.voluntary_switches).sum(),
+        involuntary_switches: usages.iter().map(|u| u.involuntary_switches).sum(),
+    }
+}
+
+/// Launches several copies of a command at once for each run, with
+/// `--instances`, and measures them together: the run lasts until the last
+/// copy exits and its memory is the sum of their peaks.
+///
+/// Each copy runs on its own thread with its own local runner, and sees
+/// its 0-based number as `CLASH_INSTANCE`.
+pub struct ConcurrentRunner {
+    options: LocalOptions,
+    instances: usize,
+}
 
-    let start = Instant::now();
-    let status = child.wait().map_err(|e| format!("Failed to wait for '{}': {}", cmd, e))?;
-    let duration = start.elapsed();
+impl Runner for ConcurrentRunner {
+    fn run_once(&self, cmd: &str, index: &RunIndex) -> Result<RunResult, String> {
+        let started_at = Utc::now();
+        let start = Instant::now();
+        let cpu_before = exec::children_cpu_time();
+        // Each copy's result, with how long after `start` its thread began
+        let results: Vec<Result<(Duration, RunResult), String>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..self.instances)
+                .map(|instance| {
+                    let mut options = self.options.clone();
+                    options
+                        .env
+                        .push(("CLASH_INSTANCE".to_string(), instance.to_string()));
+                    scope.spawn(move || {
+                        let offset = start.elapsed();
+                        local(options).run_once(cmd, index).map(|r| (offset, r))
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap_or_else(|_| Err("An instance panicked".to_string())))
+                .collect()
+        });
+        let results = results.into_iter().collect::<Result<Vec<_>, _>>()?;
+        // Every copy's children were reaped by this process, so each copy's
+        // own count of reaped CPU time overlaps the others'
+        let cpu_time = exec::children_cpu_time()
+            .zip(cpu_before)
+            .map(|(after, before)| after - before);
+        // Until the last copy exited, not until its thread wound down
+        let duration = results
+            .iter()
+            .map(|(offset, r)| *offset + r.duration)
+            .max()
+            .unwrap_or_default();
+        let results: Vec<RunResult> = results.into_iter().map(|(_, r)| r).collect();
 
-    process_alive.store(false, Ordering::Relaxed);
-    monitor_handle.join().ok();
+        // The first instance that didn't succeed speaks for the run
+        let first = results
+            .iter()
+            .find(|r| r.status() != RunStatus::Success)
+            .unwrap_or(&results[0]);
+        let peaks: Vec<u64> = results.iter().filter_map(|r| r.peak_memory_bytes).collect();
+        let outputs: Vec<OutputCounts> = results.iter().filter_map(|r| r.output).collect();
+        let cpus: Vec<CpuUsage> = results.iter().filter_map(|r| r.cpu).collect();
+        let usages: Vec<ResourceUsage> = results.iter().filter_map(|r| r.usage).collect();
+        let cpu_time = cpu_time.or_else(|| {
+            (!cpus.is_empty()).then(|| cpus.iter().map(|c| c.time).sum())
+        });
+        Ok(RunResult {
+            duration,
+            peak_memory_bytes: (!peaks.is_empty()).then(|| peaks.iter().sum()),
+            exit_code: first.exit_code,
+            signal: first.signal,
+            timed_out: first.timed_out,
+            expected_exit: first.expected_exit,
+            started_at: Some(started_at),
+            output: (!outputs.is_empty()).then(|| OutputCounts {
+                stdout_bytes: outputs.iter().map(|o| o.stdout_bytes).sum(),
+                stdout_lines: outputs.iter().map(|o| o.stdout_lines).sum(),
+                stderr_bytes: outputs.iter().map(|o| o.stderr_bytes).sum(),
+                stderr_lines: outputs.iter().map(|o| o.stderr_lines).sum(),
+            }),
+            phases: results[0].phases.clone(),
+            instances: results.iter().map(|r| r.duration).collect(),
+            cpu: cpu_time.map(|time| CpuUsage {
+                time,
+                // Copies' busiest stretches needn't overlap, so this is an upper bound
+                peak_cores: cpus
+                    .iter()
+                    .filter_map(|c| c.peak_cores)
+                    .reduce(|a, b| a + b)
+                    .map(exec::machine_cores_at_most),
+            }),
+            usage: (!usages.is_empty()).then(|| add_usage(&usages)),
+            ..RunResult::default()
+        })
+    }
 
-    Ok(RunResult {
-        duration,
-        peak_memory_bytes: peak_memory.load(Ordering::Relaxed),
-        exit_code: status.code(),
-    })
+    fn location(&self) -> Option<String> {
+        Some(format!("×{} at once", self.instances))
+    }
+}
+
+/// Canned combinations of run counts and rigor for `--preset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    /// 3 runs, no warmup: a fast sanity check
+    Quick,
+    /// Warmup, then runs until the mean is within 2%, with outliers rejected
+    Thorough,
+    /// Thorough to 1%, with pauses between runs and a significance test
+    /// behind every time verdict. Commands still run one after another on
+    /// any core: add --rounds with --shuffle to interleave them, and
+    /// --core-type or --numa-node to pin them
+    Paranoid,
+}
+
+impl Preset {
+    /// Runs made for this preset when `--runs`/`--warmup` aren't given.
+    pub fn plan(self) -> RunPlan {
+        match self {
+            Preset::Quick => RunPlan::fixed(3, 0),
+            Preset::Thorough => RunPlan {
+                warmup: 2,
+                min_runs: 10,
+                target_rse: Some(0.02),
+                max_runs: 100,
+                deadline: None,
+                soak: None,
+            },
+            Preset::Paranoid => RunPlan {
+                warmup: 3,
+                min_runs: 20,
+                target_rse: Some(0.01),
+                max_runs: 200,
+                deadline: None,
+                soak: None,
+            },
+        }
+    }
+
+    /// Pause before each run, in milliseconds, when `--delay` isn't given.
+    pub fn delay_ms(self) -> u64 {
+        match self {
+            Preset::Paranoid => 100,
+            _ => 0,
+        }
+    }
+
+    /// Whether runs far from the median are dropped before computing stats.
+    pub fn rejects_outliers(self) -> bool {
+        self != Preset::Quick
+    }
+}
+
+/// How many times to run one command.
+#[derive(Debug, Clone, Copy)]
+pub struct RunPlan {
+    pub warmup: usize,
+    /// Measured runs always made
+    pub min_runs: usize,
+    /// Keep running past `min_runs` until the mean's relative standard
+    /// error is at most this, up to `max_runs`
+    pub target_rse: Option<f64>,
+    pub max_runs: usize,
+    /// Stop early, after at least one measured run, rather than run past
+    /// this point (`--session-budget`)
+    pub deadline: Option<Instant>,
+    /// Keep running for this long instead, however many runs it takes
+    /// (`--soak`)
+    pub soak: Option<Duration>,
+}
+
+impl RunPlan {
+    /// Exactly `runs` measured runs after `warmup`.
+    pub fn fixed(runs: usize, warmup: usize) -> Self {
+        Self {
+            warmup,
+            min_runs: runs,
+            target_rse: None,
+            max_runs: runs,
+            deadline: None,
+            soak: None,
+        }
+    }
+
+    /// Whether a run expected to take `per_run` would end past the deadline.
+    fn out_of_time(&self, per_run: Duration) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() + per_run > deadline)
+    }
+
+    /// Whether another measured run is needed after `results`.
+    fn wants_more(&self, cmd: &str, results: &[RunResult], settings: &RunSettings) -> bool {
+        if results.len() < self.min_runs {
+            return true;
+        }
+        match self.target_rse {
+            Some(target) if results.len() < self.max_runs => {
+                stats::relative_std_error(&stats::compute_stats(cmd, results, settings)) > target
+            }
+            _ => false,
+        }
+    }
 }
 
 /// Run a command multiple times with optional warmup, showing progress.
+///
+/// Every run (warmup included) is reported to `progress` as job `job`;
+/// `on_run` is called with the run index and result after each measured run.
+/// Adaptive plans judge precision on the runs `settings` count.
 pub fn run_benchmark(
+    runner: &dyn Runner,
     cmd: &str,
-    runs: usize,
-    warmup: usize,
+    plan: &RunPlan,
+    settings: &RunSettings,
+    progress: &mut SessionProgress,
+    job: usize,
+    on_run: &mut dyn FnMut(usize, &RunResult),
 ) -> Result<Vec<RunResult>, String> {
+    let warmup = plan.warmup;
+    let mut index = RunIndex {
+        command: job,
+        run: 0,
+        total_runs: warmup + plan.max_runs,
+    };
     // Warmup runs (not measured)
     if warmup > 0 {
-        let warmup_pb = ProgressBar::new(warmup as u64);
-        warmup_pb.set_style(
-            ProgressStyle::with_template("    Warmup  {bar:20.dim} {pos}/{len}")
-                .unwrap()
-                .progress_chars("━━─"),
-        );
-        for _ in 0..warmup {
-            run_once(cmd)?;
+        let warmup_pb = progress.add(progress::bar(
+            warmup as u64,
+            "    Warmup  {bar:20.dim} {pos}/{len}",
+        ));
+        for run in 0..warmup {
+            if plan.out_of_time(Duration::ZERO) {
+                break;
+            }
+            index.run = run;
+            let result = runner.run_once(cmd, &index)?;
+            progress.record(job, result.duration);
             warmup_pb.inc(1);
         }
-        warmup_pb.finish_and_clear();
+        progress.remove(&warmup_pb);
     }
 
     // Benchmark runs
-    let pb = ProgressBar::new(runs as u64);
-    pb.set_style(
-        ProgressStyle::with_template("    Running {bar:20.cyan/dim} {pos}/{len} runs")
-            .unwrap()
-            .progress_chars("━━─"),
-    );
-
-    let mut results = Vec::with_capacity(runs);
-    for _ in 0..runs {
-        let result = run_once(cmd)?;
+    let pb = match plan.soak {
+        // A soak's bar fills with time rather than runs
+        Some(soak) => progress.add(progress::bar(
+            soak.as_millis() as u64,
+            "    Soaking {bar:20.cyan/dim} {msg}",
+        )),
+        None => progress.add(progress::bar(
+            plan.min_runs as u64,
+            "    Running {bar:20.cyan/dim} {pos}/{len} runs",
+        )),
+    };
+
+    let mut results = Vec::with_capacity(plan.min_runs);
+    let measuring = Instant::now();
+    let keep_going = |results: &[RunResult]| match plan.soak {
+        Some(soak) => results.is_empty() || measuring.elapsed() < soak,
+        None => plan.wants_more(cmd, results, settings),
+    };
+    while keep_going(&results) {
+        let run = results.len();
+        if run > 0 && plan.out_of_time(measuring.elapsed() / run as u32) {
+            break;
+        }
+        if run >= plan.min_runs && plan.soak.is_none() {
+            // Adaptive runs beyond the minimum extend the bar one at a time
+            pb.set_length(run as u64 + 1);
+        }
+        index.run = warmup + run;
+        let started_at = Utc::now();
+        let mut result = runner.run_once(cmd, &index)?;
+        // Replayed runs keep the time they were originally measured
+        result.started_at.get_or_insert(started_at);
+        progress.record(job, result.duration);
+        on_run(run, &result);
         results.push(result);
-        pb.inc(1);
+        match plan.soak {
+            Some(_) => {
+                pb.set_position(measuring.elapsed().as_millis() as u64);
+                pb.set_message(format!("{} runs", results.len()));
+            }
+            None => pb.inc(1),
+        }
     }
-    pb.finish_and_clear();
+    progress.remove(&pb);
+    progress.skip_rest(job);
 
     Ok(results)
 }
 
 /// Simple shell-like argument splitting.
 /// Handles double quotes and single quotes.
-fn shell_split(cmd: &str) -> Result<Vec<String>, String> {
+pub fn shell_split(cmd: &str) -> Result<Vec<String>, String> {
     let mut parts = Vec::new();
     let mut current = String::new();
     let mut in_single_quote = false;
@@ -137,7 +955,7 @@ fn shell_split(cmd: &str) -> Result<Vec<String>, String> {
                     current.clear();
                 }
             }
-            '\\' if in_double_quote || (!in_single_quote && !in_double_quote) => {
+            '\\' if in_double_quote || !in_single_quote => {
                 if let Some(next) = chars.next() {
                     current.push(next);
                 }
@@ -162,3 +980,140 @@ fn shell_split(cmd: &str) -> Result<Vec<String>, String> {
 
     Ok(parts)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{FakeRun, fake_runner, run_result};
+
+    fn first_run() -> RunIndex {
+        RunIndex {
+            command: 0,
+            run: 0,
+            total_runs: 1,
+        }
+    }
+
+    #[test]
+    fn local_runner_splits_the_command_shell_style() {
+        let (mut runner, spawned) = fake_runner([FakeRun::ok(30, 2048)]);
+        runner.prefix = vec!["numactl".to_string(), "-N0".to_string()];
+
+        let result = runner.run_once("grep -r 'two words' src", &first_run()).unwrap();
+        assert_eq!(result.duration, Duration::from_millis(30));
+        assert_eq!(result.peak_memory_bytes, Some(2048));
+        assert_eq!(
+            *spawned.borrow(),
+            [["numactl", "-N0", "grep", "-r", "two words", "src"]]
+        );
+    }
+
+    #[test]
+    fn local_runner_rejects_an_empty_command() {
+        let (runner, spawned) = fake_runner([FakeRun::ok(1, 0)]);
+        assert_eq!(runner.run_once("  ", &first_run()).unwrap_err(), "Empty command");
+        assert!(spawned.borrow().is_empty());
+    }
+
+    #[test]
+    fn benchmark_discards_warmup_runs() {
+        let script = [10, 20, 30, 40, 50].map(|ms| FakeRun::ok(ms, 1024));
+        let (runner, spawned) = fake_runner(script);
+        let mut progress = SessionProgress::hidden(&[3]);
+        let mut seen = Vec::new();
+
+        let results = run_benchmark(
+            &runner,
+            "app",
+            &RunPlan::fixed(3, 2),
+            &RunSettings::default(),
+            &mut progress,
+            0,
+            &mut |run, result| seen.push((run, result.duration)),
+        )
+        .unwrap();
+
+        let durations: Vec<_> = results.iter().map(|r| r.duration.as_millis()).collect();
+        assert_eq!(durations, [30, 40, 50]);
+        assert_eq!(seen.len(), 3);
+        assert_eq!(seen[0], (0, Duration::from_millis(30)));
+        assert_eq!(spawned.borrow().len(), 5);
+    }
+
+    #[test]
+    fn benchmark_stops_at_a_command_that_fails_to_start() {
+        let script = [FakeRun::ok(10, 0), FakeRun::SpawnError(std::io::ErrorKind::NotFound)];
+        let (runner, _) = fake_runner(script);
+        let mut progress = SessionProgress::hidden(&[3]);
+
+        let err = run_benchmark(
+            &runner,
+            "app",
+            &RunPlan::fixed(3, 0),
+            &RunSettings::default(),
+            &mut progress,
+            0,
+            &mut |_, _| {},
+        )
+        .unwrap_err();
+        assert!(err.starts_with("Failed to start 'app'"), "{}", err);
+    }
+
+    fn runs(millis: &[u64]) -> Vec<RunResult> {
+        millis.iter().map(|&ms| run_result(ms, 0)).collect()
+    }
+
+    fn wants_more(plan: &RunPlan, millis: &[u64]) -> bool {
+        plan.wants_more("app", &runs(millis), &RunSettings::default())
+    }
+
+    #[test]
+    fn adaptive_plans_stop_once_the_mean_is_precise_enough() {
+        let plan = Preset::Paranoid.plan();
+        assert!(wants_more(&plan, &[100; 19]));
+        assert!(!wants_more(&plan, &[100; 20]));
+    }
+
+    #[test]
+    fn adaptive_plans_keep_noisy_commands_running_up_to_their_maximum() {
+        let plan = Preset::Thorough.plan();
+        let noisy: Vec<u64> = (0..100).map(|i| if i % 2 == 0 { 50 } else { 150 }).collect();
+        assert!(wants_more(&plan, &noisy[..10]));
+        assert!(wants_more(&plan, &noisy[..99]));
+        assert!(!wants_more(&plan, &noisy));
+    }
+
+    #[test]
+    fn fixed_plans_ignore_precision() {
+        let plan = RunPlan::fixed(3, 0);
+        assert!(wants_more(&plan, &[50, 150]));
+        assert!(!wants_more(&plan, &[50, 150, 50]));
+    }
+
+    #[test]
+    fn precision_is_judged_on_the_runs_the_policy_keeps() {
+        let plan = Preset::Thorough.plan();
+        let mut results = runs(&[100; 10]);
+        results[3] = RunResult {
+            exit_code: Some(1),
+            ..run_result(5000, 0)
+        };
+        let mut settings = RunSettings::default();
+        assert!(plan.wants_more("app", &results, &settings));
+        settings.status_policy.discarded.nonzero = true;
+        assert!(!plan.wants_more("app", &results, &settings));
+    }
+
+    #[test]
+    fn benchmark_runs_a_steady_command_the_plan_minimum() {
+        let (runner, spawned) = fake_runner(vec![FakeRun::ok(100, 0); 30]);
+        let mut progress = SessionProgress::hidden(&[30]);
+        let plan = Preset::Thorough.plan();
+
+        let settings = RunSettings::default();
+        let results =
+            run_benchmark(&runner, "app", &plan, &settings, &mut progress, 0, &mut |_, _| {});
+        assert_eq!(results.unwrap().len(), plan.min_runs);
+        assert_eq!(spawned.borrow().len(), plan.warmup + plan.min_runs);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::events::Event;
+use crate::types::Export;
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long a client may take to send its request or read the response.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The page `/` serves: polls `/state` and redraws a table of commands.
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>clash — live</title>
+<style>
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; }
+td.num { text-align: right; }
+</style>
+</head>
+<body>
+<h1>⚔️ clash</h1>
+<p id="status">Connecting…</p>
+<table>
+<thead><tr><th>Command</th><th>Runs</th><th>Last run</th><th>Mean</th><th>Peak RSS</th></tr></thead>
+<tbody id="commands"></tbody>
+</table>
+<p><a href="/results">Final results (JSON)</a></p>
+<script>
+const ms = s => s == null ? "—" : (s * 1000).toFixed(1) + " ms";
+const mib = b => b == null ? "—" : (b / 1048576).toFixed(1) + " MiB";
+const cell = (text, num) => {
+  const td = document.createElement("td");
+  if (num) td.className = "num";
+  td.textContent = text;
+  return td;
+};
+async function refresh() {
+  try {
+    const state = await (await fetch("/state")).json();
+    document.getElementById("status").textContent = state.finished
+      ? "Finished." : "Running…";
+    const rows = state.commands.map(c => {
+      const tr = document.createElement("tr");
+      tr.append(cell(c.command), cell(c.completed + "/" + c.planned, true),
+        cell(ms(c.last_duration), true), cell(ms(c.mean), true),
+        cell(mib(c.peak_memory_bytes), true));
+      return tr;
+    });
+    document.getElementById("commands").replaceChildren(...rows);
+    if (state.finished) return;
+  } catch (e) {
+    document.getElementById("status").textContent = "Disconnected.";
+  }
+  setTimeout(refresh, 1000);
+}
+refresh();
+</script>
+</body>
+</html>
+"#;
+
+/// What `/state` reports while a session runs.
+#[derive(Debug, Default, Serialize)]
+struct State {
+    finished: bool,
+    commands: Vec<CommandState>,
+}
+
+/// One command's progress, in seconds and bytes.
+#[derive(Debug, Default, Serialize)]
+struct CommandState {
+    command: String,
+    /// Measured runs planned for the current benchmark, as `completed`
+    /// counts them; adaptive runs past the minimum raise it as they go
+    planned: usize,
+    completed: usize,
+    last_duration: Option<f64>,
+    /// Mean run time, once the command is done
+    mean: Option<f64>,
+    peak_memory_bytes: Option<u64>,
+}
+
+/// A small HTTP server showing a session's progress and results, for
+/// `--serve`.
+#[derive(Clone)]
+pub struct Dashboard {
+    state: Arc<Mutex<State>>,
+    results: Arc<Mutex<Option<String>>>,
+}
+
+impl Dashboard {
+    /// Listen on `address` and `port`, serving each client from a thread of
+    /// its own so one that stalls holds up nobody else.
+    pub fn start(address: IpAddr, port: u16) -> Result<Self, String> {
+        let listener = TcpListener::bind((address, port))
+            .map_err(|e| format!("Failed to listen on {}:{}: {}", address, port, e))?;
+        let dashboard = Self {
+            state: Arc::default(),
+            results: Arc::default(),
+        };
+        let server = dashboard.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let server = server.clone();
+                std::thread::spawn(move || {
+                    // A client hanging up or stalling only loses its own response
+                    stream.set_read_timeout(Some(CLIENT_TIMEOUT)).ok();
+                    stream.set_write_timeout(Some(CLIENT_TIMEOUT)).ok();
+                    let _ = server.respond(stream);
+                });
+            }
+        });
+        Ok(dashboard)
+    }
+
+    /// Update the live state from a session event.
+    pub fn observe(&self, event: &Event) {
+        let mut state = self.state.lock().unwrap();
+        match event {
+            Event::BenchmarkStart {
+                index,
+                command,
+                runs,
+                ..
+            } => {
+                if state.commands.len() <= *index {
+                    state.commands.resize_with(index + 1, CommandState::default);
+                }
+                let c = &mut state.commands[*index];
+                c.command = command.to_string();
+                c.planned = *runs;
+                c.completed = 0;
+            }
+            Event::RunComplete { index, run, result } => {
+                if let Some(c) = state.commands.get_mut(*index) {
+                    c.completed = run + 1;
+                    c.planned = c.planned.max(c.completed);
+                    c.last_duration = Some(result.duration.as_secs_f64());
+                    c.peak_memory_bytes = c.peak_memory_bytes.max(result.peak_memory_bytes);
+                }
+            }
+            Event::CommandDone { index, stats } => {
+                if let Some(c) = state.commands.get_mut(*index) {
+                    c.mean = Some(stats.time_mean.as_secs_f64());
+                    c.peak_memory_bytes = stats.peak_memory_bytes;
+                }
+            }
+            Event::Report { .. } => state.finished = true,
+        }
+    }
+
+    /// Serve the session's export at `/results` from now on.
+    pub fn finish(&self, export: &Export) {
+        *self.results.lock().unwrap() = serde_json::to_string_pretty(export).ok();
+    }
+
+    fn respond(&self, stream: TcpStream) -> std::io::Result<()> {
+        let mut reader = BufReader::new(&stream);
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+        // Drain the headers; nothing in them matters here
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+
+        let path = match request.split_whitespace().collect::<Vec<_>>()[..] {
+            ["GET", path, ..] => path,
+            _ => return reply(&stream, "405 Method Not Allowed", "text/plain", "GET only\n"),
+        };
+        match path {
+            "/" => reply(&stream, "200 OK", "text/html; charset=utf-8", PAGE),
+            "/state" => {
+                let json = serde_json::to_string(&*self.state.lock().unwrap())?;
+                reply(&stream, "200 OK", "application/json", &json)
+            }
+            "/results" => match &*self.results.lock().unwrap() {
+                Some(json) => reply(&stream, "200 OK", "application/json", json),
+                None => reply(&stream, "404 Not Found", "text/plain", "Still running\n"),
+            },
+            _ => reply(&stream, "404 Not Found", "text/plain", "Not found\n"),
+        }
+    }
+}
+
+fn reply(
+    mut stream: &TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
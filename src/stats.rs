@@ -1,8 +1,64 @@
-use crate::types::{CommandStats, Comparison, RunResult};
+use crate::types::{
+    Annotation, AnnotationKind, Bound, CommandStats, Comparison, Estimator, MemoryTrend,
+    OutputCounts, OverheadSummary, Precision, RunResult, RunSettings, RunStatus, Score, Soak,
+    SoakBucket, Stability, StatusPolicy, Throttling, Verdict, VerdictKind, Warning, Weights,
+};
+use crate::metric::{self, Better, Metric};
+use crate::runner;
+use crate::units::TimeUnit;
+use std::collections::BTreeMap;
 use std::time::Duration;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
-/// Compute aggregated statistics from a set of run results.
-pub fn compute_stats(command: &str, results: &[RunResult]) -> CommandStats {
+/// Default display width of command labels, in terminal columns.
+pub const LABEL_WIDTH: usize = 30;
+
+/// Narrowest label that still shows something of the command: the "..."
+/// plus one grapheme, which may be two columns wide.
+pub const MIN_LABEL_WIDTH: usize = 5;
+
+/// Widest label accepted, well past any terminal.
+pub const MAX_LABEL_WIDTH: usize = 1000;
+
+/// Relative standard error above which a command's mean isn't trusted.
+pub const MAX_RELATIVE_STD_ERROR: f64 = 0.05;
+
+/// Significance level for the rank-sum test behind time verdicts.
+const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+/// The estimator run times in `stats` are compared on: the session's.
+pub fn estimator(stats: &[CommandStats]) -> Estimator {
+    stats.first().map_or_else(Estimator::default, |s| s.estimator)
+}
+
+/// RSS growth, in KB per minute, past which a command is warned about a
+/// possible leak unless configured.
+pub const DEFAULT_LEAK_THRESHOLD: f64 = 1024.0;
+
+/// Compute aggregated statistics from a set of run results, analyzed as
+/// `settings` ask.
+pub fn compute_stats(
+    command: &str,
+    results: &[RunResult],
+    settings: &RunSettings,
+) -> CommandStats {
+    let policy = settings.status_policy;
+    let failed_runs = results
+        .iter()
+        .filter(|r| policy.failed.contains(r.status()))
+        .count();
+    // Discarded runs count toward failures but not measurements, unless
+    // that would leave nothing to measure
+    let mut run_numbers: Vec<usize> = (0..results.len())
+        .filter(|&k| !policy.discarded.contains(results[k].status()))
+        .collect();
+    if run_numbers.is_empty() {
+        run_numbers = (0..results.len()).collect();
+    }
+    let discarded_runs = results.len() - run_numbers.len();
+    let kept: Vec<RunResult> = run_numbers.iter().map(|&k| results[k].clone()).collect();
+    let results = &kept[..];
     let durations: Vec<f64> = results.iter().map(|r| r.duration.as_secs_f64()).collect();
     let n = durations.len() as f64;
 
@@ -21,82 +77,1060 @@ pub fn compute_stats(command: &str, results: &[RunResult]) -> CommandStats {
         0.0
     };
 
-    let peak_memory = results.iter().map(|r| r.peak_memory_bytes).max().unwrap_or(0);
-    let failed_runs = results
-        .iter()
-        .filter(|r| r.exit_code != Some(0))
-        .count();
+    let cv = if time_mean_f > 0.0 {
+        time_std_dev_f / time_mean_f
+    } else {
+        0.0
+    };
+
+    let peak_memory = results.iter().filter_map(|r| r.peak_memory_bytes).max();
+
+    let prepares: Vec<Duration> = results.iter().filter_map(|r| r.prepare).collect();
+    let prepare_mean = (!prepares.is_empty())
+        .then(|| prepares.iter().sum::<Duration>() / prepares.len() as u32);
+
+    let outputs: Vec<OutputCounts> = results.iter().filter_map(|r| r.output).collect();
+    let output_mean = (!outputs.is_empty()).then(|| {
+        let n = outputs.len() as u64;
+        let mean = |f: fn(&OutputCounts) -> u64| outputs.iter().map(f).sum::<u64>() / n;
+        OutputCounts {
+            stdout_bytes: mean(|o| o.stdout_bytes),
+            stdout_lines: mean(|o| o.stdout_lines),
+            stderr_bytes: mean(|o| o.stderr_bytes),
+            stderr_lines: mean(|o| o.stderr_lines),
+        }
+    });
 
     // Create a short label from the command
-    let label = make_label(command);
+    let label = make_label(command, LABEL_WIDTH);
 
     CommandStats {
         command: command.to_string(),
         label,
         runs: results.len(),
         time_mean: Duration::from_secs_f64(time_mean_f),
+        time_center: Duration::from_secs_f64(if durations.is_empty() {
+            0.0
+        } else {
+            settings.estimator.apply(&durations)
+        }),
         time_min: Duration::from_secs_f64(time_min_f),
         time_max: Duration::from_secs_f64(time_max_f),
         time_std_dev: Duration::from_secs_f64(time_std_dev_f),
+        time_cv: cv,
+        stability: stability(cv),
         peak_memory_bytes: peak_memory,
         all_runs: results.to_vec(),
         failed_runs,
+        discarded_runs,
+        prepare_mean,
+        output_mean,
+        wall_time: Duration::ZERO,
+        outliers_rejected: 0,
+        trimmed_from: None,
+        baseline: None,
+        precision: None,
+        build: None,
+        matrix: None,
+        group: None,
+        warnings: Vec::new(),
+        rounds: Vec::new(),
+        annotations: Vec::new(),
+        soak: None,
+        memory_trend: memory_trend(results),
+        syscalls: None,
+        provenance: None,
+        run_numbers,
+        memory_unrecorded: false,
+        estimator: settings.estimator,
+        significance: settings.significance,
     }
 }
 
-/// Compare two stats on time — returns which is faster and by how much.
-pub fn compare_time(stats: &[CommandStats]) -> Option<Comparison> {
-    if stats.len() < 2 {
+/// One command's `--rounds` results pooled into a single set of stats,
+/// keeping each round's time so the report can compare them.
+pub fn pool(rounds: &[CommandStats], settings: &RunSettings) -> CommandStats {
+    let first = &rounds[0];
+    let runs: Vec<RunResult> = rounds.iter().flat_map(|r| r.all_runs.clone()).collect();
+    let mut pooled = compute_stats(&first.command, &runs, settings);
+    pooled.label = first.label.clone();
+    pooled.failed_runs = rounds.iter().map(|r| r.failed_runs).sum();
+    pooled.discarded_runs = rounds.iter().map(|r| r.discarded_runs).sum();
+    pooled.outliers_rejected = rounds.iter().map(|r| r.outliers_rejected).sum();
+    pooled.wall_time = rounds.iter().map(|r| r.wall_time).sum();
+    pooled.trimmed_from = rounds.iter().any(|r| r.trimmed_from.is_some()).then(|| {
+        rounds
+            .iter()
+            .map(|r| r.trimmed_from.unwrap_or(r.runs + r.outliers_rejected))
+            .sum()
+    });
+    pooled.baseline = first.baseline.clone();
+    pooled.build = first.build.clone();
+    pooled.syscalls = first.syscalls.clone();
+    pooled.provenance = first.provenance.clone();
+    pooled.matrix = first.matrix.clone();
+    pooled.group = first.group.clone();
+    pooled.memory_unrecorded = first.memory_unrecorded;
+    pooled.precision = first.precision.map(|p| Precision {
+        achieved: relative_std_error(&pooled),
+        target: p.target,
+    });
+    pooled.rounds = rounds.iter().map(|r| r.time_center).collect();
+    // Run numbers continue from one round to the next
+    let mut offset = 0;
+    let mut numbers = Vec::new();
+    for r in rounds {
+        numbers.extend(r.run_numbers.iter().map(|n| n + offset));
+        pooled.annotations.extend(r.annotations.iter().map(|a| Annotation {
+            run: a.run + offset,
+            ..a.clone()
+        }));
+        offset += r.runs + r.discarded_runs + r.outliers_rejected;
+    }
+    pooled.run_numbers = pooled.run_numbers.iter().map(|&k| numbers[k]).collect();
+    pooled
+}
+
+/// Shortest stretch of time runs' RSS is fitted over: growth across a few
+/// seconds of runs says little about leaks.
+const TREND_MIN_SPAN: Duration = Duration::from_secs(10);
+
+/// Least-squares line through each run's peak RSS against its start, when
+/// at least three runs with both span `TREND_MIN_SPAN`.
+fn memory_trend(results: &[RunResult]) -> Option<MemoryTrend> {
+    let sampled: Vec<_> = results
+        .iter()
+        .filter_map(|r| Some((r.started_at?, r.peak_memory_bytes?)))
+        .collect();
+    let first = sampled.iter().map(|(at, _)| *at).min()?;
+    let last = sampled.iter().map(|(at, _)| *at).max()?;
+    if sampled.len() < 3 || (last - first).to_std().ok()? < TREND_MIN_SPAN {
         return None;
     }
-    let times: Vec<f64> = stats.iter().map(|s| s.time_mean.as_secs_f64()).collect();
-    let (min_idx, min_val) = times
+    // Minutes since the first run, KB
+    let points: Vec<(f64, f64)> = sampled
         .iter()
-        .enumerate()
-        .min_by(|a, b| a.1.partial_cmp(b.1).unwrap())
-        .unwrap();
-    let max_val = times
+        .map(|(at, bytes)| {
+            let minutes = (*at - first).to_std().unwrap_or_default().as_secs_f64() / 60.0;
+            (minutes, *bytes as f64 / 1024.0)
+        })
+        .collect();
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let slope = sxy / sxx;
+    let residuals: f64 = points
         .iter()
-        .cloned()
-        .fold(f64::NEG_INFINITY, f64::max);
+        .map(|(x, y)| (y - mean_y - slope * (x - mean_x)).powi(2))
+        .sum();
+    Some(MemoryTrend {
+        kb_per_min: slope,
+        std_error: (residuals / (n - 2.0) / sxx).sqrt(),
+    })
+}
+
+/// Stretches of time a `--soak` session's runs are split into.
+const SOAK_BUCKETS: usize = 6;
 
-    Some(Comparison {
-        winner_index: min_idx,
-        ratio: max_val / min_val,
+/// Smallest change from the first stretch to the last counted as drift,
+/// however certain: long soaks make tiny changes significant.
+const SOAK_DRIFT: f64 = 0.05;
+
+/// How a soaked command's runs drifted from the first stretch of the
+/// session to the last, or `None` without two stretches to compare.
+pub fn soak(runs: &[RunResult], settings: &RunSettings) -> Option<Soak> {
+    let starts: Vec<_> = runs.iter().map(|r| r.started_at).collect::<Option<_>>()?;
+    let first = *starts.iter().min()?;
+    let span = (*starts.iter().max()? - first).to_std().ok()?;
+    let mut grouped: Vec<Vec<RunResult>> = vec![Vec::new(); SOAK_BUCKETS];
+    for (run, start) in runs.iter().zip(&starts) {
+        let offset = (*start - first).to_std().unwrap_or_default();
+        let bucket = (offset.as_secs_f64() / span.as_secs_f64() * SOAK_BUCKETS as f64) as usize;
+        grouped[bucket.min(SOAK_BUCKETS - 1)].push(run.clone());
+    }
+    let stats: Vec<Option<CommandStats>> = grouped
+        .iter()
+        .map(|runs| (!runs.is_empty()).then(|| compute_stats("", runs, settings)))
+        .collect();
+    let [head, .., tail] = &stats.iter().flatten().collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let time_drift = tail.time_mean.as_secs_f64() / head.time_mean.as_secs_f64() - 1.0;
+    let mean_memory = |s: &CommandStats| metric::MEMORY.export(s).map(|e| e.mean);
+    let memory_drift = mean_memory(head)
+        .zip(mean_memory(tail))
+        .map(|(head, tail)| tail / head - 1.0);
+    Some(Soak {
+        buckets: stats
+            .iter()
+            .enumerate()
+            .map(|(i, s)| SoakBucket {
+                from: span.mul_f64(i as f64 / SOAK_BUCKETS as f64),
+                runs: s.as_ref().map_or(0, |s| s.runs),
+                mean: s.as_ref().map(|s| s.time_mean),
+                mean_peak_memory_bytes: s.as_ref().and_then(mean_memory).map(|m| m as u64),
+            })
+            .collect(),
+        time_drift,
+        time_drifts: time_drift.abs() >= SOAK_DRIFT && differs(&metric::TIME, head, tail),
+        memory_drift,
+        memory_drifts: memory_drift.is_some_and(|d| d.abs() >= SOAK_DRIFT)
+            && differs(&metric::MEMORY, head, tail),
     })
 }
 
-/// Compare two stats on memory — returns which uses less and by how much.
-pub fn compare_memory(stats: &[CommandStats]) -> Option<Comparison> {
-    if stats.len() < 2 {
+/// Annotations for runs that timed out or failed under `policy`, by
+/// position in `results`.
+pub fn status_annotations(results: &[RunResult], policy: &StatusPolicy) -> Vec<Annotation> {
+    results
+        .iter()
+        .enumerate()
+        .filter_map(|(run, r)| {
+            let kind = match r.status() {
+                RunStatus::Timeout => AnnotationKind::Timeout,
+                status if policy.failed.contains(status) => AnnotationKind::Failed,
+                _ => return None,
+            };
+            let took = TimeUnit::for_duration(r.duration).format(r.duration);
+            Some(Annotation {
+                run,
+                kind,
+                details: format!("{} after {}", r.status(), took),
+            })
+        })
+        .collect()
+}
+
+/// Modified z-score (from the median absolute deviation) beyond which a run
+/// counts as an outlier.
+const OUTLIER_Z: f64 = 3.5;
+
+/// Split runs into those kept and an annotation for each one rejected as an
+/// outlier by duration. Nothing is rejected when most runs share the same
+/// duration.
+pub fn reject_outliers(results: &[RunResult]) -> (Vec<RunResult>, Vec<Annotation>) {
+    let secs: Vec<f64> = results.iter().map(|r| r.duration.as_secs_f64()).collect();
+    let median_of = |mut v: Vec<f64>| -> f64 {
+        v.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mid = v.len() / 2;
+        if v.len().is_multiple_of(2) {
+            (v[mid - 1] + v[mid]) / 2.0
+        } else {
+            v[mid]
+        }
+    };
+    if secs.len() < 3 {
+        return (results.to_vec(), Vec::new());
+    }
+    let median = median_of(secs.clone());
+    let mad = median_of(secs.iter().map(|s| (s - median).abs()).collect());
+    if mad <= 0.0 {
+        return (results.to_vec(), Vec::new());
+    }
+    let unit = TimeUnit::common(results.iter().map(|r| r.duration));
+    let mut kept = Vec::new();
+    let mut rejected = Vec::new();
+    for (run, (r, s)) in results.iter().zip(&secs).enumerate() {
+        let z = 0.6745 * (s - median).abs() / mad;
+        if z <= OUTLIER_Z {
+            kept.push(r.clone());
+            continue;
+        }
+        rejected.push(Annotation {
+            run,
+            kind: AnnotationKind::Outlier,
+            details: format!(
+                "took {} against a median of {} (modified z-score {:.1})",
+                unit.format(r.duration),
+                unit.format(Duration::from_secs_f64(median)),
+                z
+            ),
+        });
+    }
+    (kept, rejected)
+}
+
+/// Grade a coefficient of variation.
+pub fn stability(cv: f64) -> Stability {
+    match cv {
+        cv if cv < 0.02 => Stability::A,
+        cv if cv < 0.05 => Stability::B,
+        cv if cv < 0.10 => Stability::C,
+        _ => Stability::D,
+    }
+}
+
+/// Mean run time below which process startup, not the work a command does,
+/// dominates what is measured.
+const STARTUP_DOMINATED: Duration = Duration::from_millis(5);
+
+/// Whether a command's runs are too short to compare reliably: process
+/// startup dominates them and no `--subtract` baseline takes it out.
+pub fn startup_dominated(s: &CommandStats) -> bool {
+    s.time_mean < STARTUP_DOMINATED && s.baseline.is_none() && !runner::is_pseudo(&s.command)
+}
+
+/// Fewest runs a run-order trend is looked for in.
+const RUN_ORDER_MIN_RUNS: usize = 10;
+
+/// Weakest run-order correlation worth warning about, however certain:
+/// with many runs even a negligible trend is significant.
+const RUN_ORDER_RHO: f64 = 0.3;
+
+/// Spearman correlation between run index and duration when runs get
+/// clearly faster (negative) or slower (positive) as the session goes on.
+pub fn run_order_trend(s: &CommandStats) -> Option<f64> {
+    let n = s.all_runs.len();
+    if n < RUN_ORDER_MIN_RUNS {
         return None;
     }
-    let mems: Vec<u64> = stats.iter().map(|s| s.peak_memory_bytes).collect();
+    let durations: Vec<f64> = s.all_runs.iter().map(|r| r.duration.as_secs_f64()).collect();
+    let ranks = ranks(&durations);
+    // Pearson correlation of the ranks against run order, which has no ties
+    let mean = (n as f64 + 1.0) / 2.0;
+    let cov: f64 = ranks
+        .iter()
+        .enumerate()
+        .map(|(i, r)| (i as f64 + 1.0 - mean) * (r - mean))
+        .sum();
+    let var_order: f64 = (1..=n).map(|i| (i as f64 - mean).powi(2)).sum();
+    let var_ranks: f64 = ranks.iter().map(|r| (r - mean).powi(2)).sum();
+    if var_ranks == 0.0 {
+        return None;
+    }
+    let rho = cov / (var_order * var_ranks).sqrt();
+    // Under no trend, rho is roughly normal with variance 1 / (n - 1)
+    (rho.abs() >= RUN_ORDER_RHO && rho.abs() * ((n - 1) as f64).sqrt() > Z_95).then_some(rho)
+}
+
+/// 1-based ranks of `values`, averaged across ties.
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    let mut ranks = vec![0.0; values.len()];
+    let mut i = 0;
+    while i < order.len() {
+        let mut j = i;
+        while j + 1 < order.len() && values[order[j + 1]] == values[order[i]] {
+            j += 1;
+        }
+        for &k in &order[i..=j] {
+            ranks[k] = (i + j) as f64 / 2.0 + 1.0;
+        }
+        i = j + 1;
+    }
+    ranks
+}
+
+/// Compare commands on the estimated time, flagging verdicts that noise could
+/// explain or that rest on unstable or startup-dominated timings.
+pub fn compare_time(stats: &[CommandStats]) -> Option<Comparison> {
+    let mut comparison = metric::TIME.compare(stats)?;
+    let winner = comparison.winner_index;
+    let runner_up = metric::TIME.runner_up(stats, winner)?;
+
+    comparison.inconclusive = time_inconclusive(&stats[winner], &stats[runner_up]);
+    comparison.unstable =
+        stats[winner].stability == Stability::D && stats[runner_up].stability == Stability::D;
+    comparison.low_confidence =
+        startup_dominated(&stats[winner]) || startup_dominated(&stats[runner_up]);
+    Some(comparison)
+}
+
+/// z-value of a two-sided 95% confidence interval.
+const Z_95: f64 = 1.96;
+
+/// How many times faster `fast` is than `slow`, with the half-width of its
+/// 95% confidence interval when both have enough runs to estimate one.
+pub fn speedup(fast: &CommandStats, slow: &CommandStats) -> (f64, Option<f64>) {
+    let ratio = slow.time_center.as_secs_f64() / fast.time_center.as_secs_f64();
+    if fast.runs < 2 || slow.runs < 2 {
+        return (ratio, None);
+    }
+    // Relative errors of a quotient add in quadrature
+    let relative = relative_std_error(fast).hypot(relative_std_error(slow));
+    (ratio, Some(Z_95 * ratio * relative))
+}
+
+/// Whether the mean per-run values of `metric` differ between two commands
+/// by more than their 95% confidence intervals allow.
+pub fn differs(metric: &Metric, a: &CommandStats, b: &CommandStats) -> bool {
+    let mean_and_error = |s: &CommandStats| {
+        let export = metric.export(s)?;
+        let n = export.values.iter().flatten().count() as f64;
+        Some((export.mean, export.std_dev / n.sqrt()))
+    };
+    let (Some((mean_a, se_a)), Some((mean_b, se_b))) = (mean_and_error(a), mean_and_error(b)) else {
+        return false;
+    };
+    (mean_a - mean_b).abs() > Z_95 * se_a.hypot(se_b)
+}
+
+/// Verdict on each metric measured for every command: a winner only when
+/// its lead over the runner-up is beyond noise, as in the report's summary.
+pub fn verdicts(stats: &[CommandStats]) -> BTreeMap<&'static str, Verdict> {
+    let mut verdicts = BTreeMap::new();
+    for metric in metric::ALL {
+        let comparison = if metric.name == metric::TIME.name {
+            compare_time(stats)
+        } else {
+            metric.compare(stats)
+        };
+        let Some(c) = comparison else { continue };
+        let Some(runner_up) = metric.runner_up(stats, c.winner_index) else { continue };
+        let (winner, other) = (&stats[c.winner_index], &stats[runner_up]);
+        let (ratio, reason) = if metric.name == metric::TIME.name {
+            let (ratio, margin) = speedup(winner, other);
+            // Confident when the whole interval lies above "equally fast"
+            let reason = if c.unstable {
+                Some("timings are too unstable (grade D)")
+            } else if margin.is_none() {
+                Some("too few runs to judge noise")
+            } else if c.inconclusive || margin.is_some_and(|m| ratio - m <= 1.0) {
+                Some("within noise")
+            } else {
+                None
+            };
+            (ratio, reason)
+        } else {
+            let (best, next) = (metric.summary(winner), metric.summary(other));
+            let ratio = best.zip(next).map_or(1.0, |(b, n)| 1.0 / metric.relative(b, n));
+            (ratio, (!differs(metric, winner, other)).then_some("within noise"))
+        };
+        let verdict = if !c.unstable && ratio <= 1.01 {
+            VerdictKind::Tie
+        } else if reason.is_some() {
+            VerdictKind::Inconclusive
+        } else {
+            VerdictKind::Winner
+        };
+        verdicts.insert(
+            metric.name,
+            Verdict {
+                verdict,
+                winner: winner.label.clone(),
+                runner_up: other.label.clone(),
+                ratio,
+                reason: reason.filter(|_| verdict == VerdictKind::Inconclusive),
+            },
+        );
+    }
+    verdicts
+}
+
+/// Whether `a` beats `b` on time or memory by more than noise, without
+/// being worse on the other.
+pub fn dominates(a: &CommandStats, b: &CommandStats) -> bool {
+    let mut better = false;
+    for metric in [&metric::TIME, &metric::MEMORY] {
+        let (Some(va), Some(vb)) = (metric.summary(a), metric.summary(b)) else {
+            return false;
+        };
+        if !differs(metric, a, b) {
+            continue;
+        }
+        let ahead = match metric.better {
+            Better::Lower => va < vb,
+            Better::Higher => va > vb,
+        };
+        if !ahead {
+            return false;
+        }
+        better = true;
+    }
+    better
+}
+
+/// Commands no other command dominates, in order.
+pub fn pareto_front(stats: &[CommandStats]) -> Vec<usize> {
+    (0..stats.len())
+        .filter(|&i| !stats.iter().any(|other| dominates(other, &stats[i])))
+        .collect()
+}
+
+/// The fastest command in each `--rounds` round, or `None` for a round
+/// where the two fastest were within 1%. Empty without several rounds.
+pub fn round_winners(all_stats: &[CommandStats]) -> Vec<Option<usize>> {
+    let rounds = all_stats.first().map_or(0, |s| s.rounds.len());
+    if all_stats.len() < 2 || rounds < 2 || all_stats.iter().any(|s| s.rounds.len() != rounds) {
+        return Vec::new();
+    }
+    (0..rounds)
+        .map(|k| {
+            let mut order: Vec<usize> = (0..all_stats.len()).collect();
+            order.sort_by_key(|&i| all_stats[i].rounds[k]);
+            let best = all_stats[order[0]].rounds[k].as_secs_f64();
+            let next = all_stats[order[1]].rounds[k].as_secs_f64();
+            (best > 0.0 && next / best > 1.01).then_some(order[0])
+        })
+        .collect()
+}
+
+/// Standard error of the mean run time, relative to the mean.
+pub fn relative_std_error(s: &CommandStats) -> f64 {
+    let mean = s.time_mean.as_secs_f64();
+    if s.runs < 2 || mean <= 0.0 {
+        return 0.0;
+    }
+    s.time_std_dev.as_secs_f64() / (s.runs as f64).sqrt() / mean
+}
 
-    // Skip comparison if any memory is 0 (unmeasurable)
-    if mems.iter().any(|&m| m == 0) {
+/// Number of runs that would bring the relative standard error under
+/// `MAX_RELATIVE_STD_ERROR`, if the current runs don't.
+pub fn suggested_runs(s: &CommandStats) -> Option<usize> {
+    if relative_std_error(s) <= MAX_RELATIVE_STD_ERROR {
         return None;
     }
+    let cv = s.time_std_dev.as_secs_f64() / s.time_mean.as_secs_f64();
+    let needed = (cv / MAX_RELATIVE_STD_ERROR).powi(2).ceil() as usize;
+    Some(needed.clamp(s.runs + 1, 1000))
+}
+
+/// A time comparison can't be trusted when either side is noisy and the
+/// estimates are within two combined standard errors of each other.
+fn time_inconclusive(a: &CommandStats, b: &CommandStats) -> bool {
+    if a.significance || b.significance {
+        let durations =
+            |s: &CommandStats| -> Vec<f64> { s.all_runs.iter().map(|r| r.duration.as_secs_f64()).collect() };
+        let p = rank_sum_p_value(&durations(a), &durations(b));
+        if p.is_none_or(|p| p >= SIGNIFICANCE_LEVEL) {
+            return true;
+        }
+    }
+    if relative_std_error(a) <= MAX_RELATIVE_STD_ERROR
+        && relative_std_error(b) <= MAX_RELATIVE_STD_ERROR
+    {
+        return false;
+    }
+    let se = |s: &CommandStats| s.time_std_dev.as_secs_f64() / (s.runs as f64).sqrt();
+    let gap = (a.time_center.as_secs_f64() - b.time_center.as_secs_f64()).abs();
+    gap < 2.0 * (se(a).powi(2) + se(b).powi(2)).sqrt()
+}
 
-    let (min_idx, &min_val) = mems
+/// Runs whose CPU frequency fell below this fraction of the session's peak
+/// are reported as possibly throttled.
+pub const THROTTLE_RATIO: f64 = 0.85;
+
+/// Find stretches of runs that ran well below the highest CPU frequency seen
+/// in the session, a sign of thermal throttling skewing the comparison.
+pub fn throttled_runs(stats: &[CommandStats]) -> Vec<Throttling> {
+    let peak_mhz = stats
         .iter()
-        .enumerate()
-        .min_by_key(|&(_, &v)| v)
-        .unwrap();
-    let &max_val = mems.iter().max().unwrap();
+        .flat_map(|s| &s.all_runs)
+        .filter_map(|r| r.cpu_mhz)
+        .max()
+        .unwrap_or(0);
+    let threshold = peak_mhz as f64 * THROTTLE_RATIO;
+
+    let mut found = Vec::new();
+    for (command_index, s) in stats.iter().enumerate() {
+        let mut current: Option<Throttling> = None;
+        for (run, r) in s.all_runs.iter().enumerate() {
+            match r.cpu_mhz {
+                Some(mhz) if (mhz as f64) < threshold => {
+                    let t = current.get_or_insert(Throttling {
+                        command_index,
+                        first_run: run,
+                        last_run: run,
+                        lowest_mhz: mhz,
+                        peak_mhz,
+                    });
+                    t.last_run = run;
+                    t.lowest_mhz = t.lowest_mhz.min(mhz);
+                }
+                _ => found.extend(current.take()),
+            }
+        }
+        found.extend(current);
+    }
+    found
+}
+
+/// Attach every warning that applies to each command's results, analyzed
+/// as `settings` ask. Run once the session is over, since throttling is
+/// judged against the whole session's peak frequency.
+pub fn collect_warnings(stats: &mut [CommandStats], settings: &RunSettings) {
+    let throttling = throttled_runs(stats);
+    for (i, s) in stats.iter_mut().enumerate() {
+        let mut warnings = Vec::new();
+        if s.failed_runs > 0 {
+            warnings.push(Warning::FailedRuns {
+                failed: s.failed_runs,
+                runs: s.runs + s.discarded_runs,
+            });
+        }
+        let signaled = s
+            .all_runs
+            .iter()
+            .filter(|r| matches!(r.status(), RunStatus::Signaled(_)))
+            .count();
+        if signaled > 0 && !settings.status_policy.failed.signaled {
+            warnings.push(Warning::Signaled { runs: signaled });
+        }
+        if s.discarded_runs > 0 {
+            warnings.push(Warning::Discarded {
+                runs: s.discarded_runs,
+            });
+        }
+        if s.outliers_rejected > 0 {
+            warnings.push(Warning::OutliersRejected {
+                count: s.outliers_rejected,
+            });
+        }
+        if let Some(p) = s.precision {
+            if p.achieved > p.target {
+                warnings.push(Warning::TargetMissed {
+                    achieved: p.achieved,
+                    target: p.target,
+                });
+            }
+        } else if let Some(suggested_runs) = suggested_runs(s) {
+            warnings.push(Warning::HighVariance {
+                cv: s.time_cv,
+                suggested_runs,
+            });
+        }
+        // Pseudo-commands have no process to measure
+        let unmeasured = s.all_runs.iter().filter(|r| r.peak_memory_bytes.is_none()).count();
+        if s.memory_unrecorded {
+            warnings.push(Warning::MemoryNotRecorded);
+        } else if unmeasured > 0 && !runner::is_pseudo(&s.command) {
+            warnings.push(Warning::MemoryUnmeasured { runs: unmeasured });
+        }
+        for t in throttling.iter().filter(|t| t.command_index == i) {
+            for run in t.first_run..=t.last_run {
+                let mhz = s.all_runs[run].cpu_mhz.unwrap_or(0);
+                s.annotations.push(Annotation {
+                    run: s.run_numbers.get(run).copied().unwrap_or(run),
+                    kind: AnnotationKind::Throttled,
+                    details: format!(
+                        "CPU at {} MHz, {:.0}% of the session's peak {} MHz",
+                        mhz,
+                        mhz as f64 / t.peak_mhz as f64 * 100.0,
+                        t.peak_mhz
+                    ),
+                });
+            }
+            warnings.push(Warning::Throttling {
+                first_run: t.first_run,
+                last_run: t.last_run,
+                lowest_mhz: t.lowest_mhz,
+                peak_mhz: t.peak_mhz,
+            });
+        }
+        if startup_dominated(s) {
+            warnings.push(Warning::StartupDominated { mean: s.time_mean });
+        }
+        if let Some(rho) = run_order_trend(s) {
+            warnings.push(Warning::RunOrder { rho });
+        }
+        if let Some(trend) = s.memory_trend
+            && trend.kb_per_min > settings.leak_threshold.unwrap_or(DEFAULT_LEAK_THRESHOLD)
+            && trend.kb_per_min > Z_95 * trend.std_error
+        {
+            warnings.push(Warning::MemoryGrowth {
+                kb_per_min: trend.kb_per_min,
+            });
+        }
+        if let Some(planned) = s.trimmed_from {
+            warnings.push(Warning::Trimmed {
+                runs: s.runs,
+                planned,
+            });
+        }
+        s.warnings = warnings;
+        s.annotations.sort_by_key(|a| a.run);
+    }
+}
+
+/// Names of the phases `s`'s runs marked, in the order they first appear.
+pub fn phase_names(s: &CommandStats) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for phase in s.all_runs.iter().flat_map(|r| &r.phases) {
+        if !names.contains(&phase.name) {
+            names.push(phase.name.clone());
+        }
+    }
+    names
+}
+
+/// Time a run spent in phase `name`, summed if marked more than once, or
+/// `None` if the run never marked it.
+pub fn phase_time(r: &RunResult, name: &str) -> Option<Duration> {
+    let mut times = r.phases.iter().filter(|p| p.name == name).map(|p| p.duration).peekable();
+    times.peek()?;
+    Some(times.sum())
+}
+
+/// Mean time in phase `name` over the runs that marked it.
+pub fn phase_mean(s: &CommandStats, name: &str) -> Option<Duration> {
+    let times: Vec<Duration> = s.all_runs.iter().filter_map(|r| phase_time(r, name)).collect();
+    (!times.is_empty()).then(|| times.iter().sum::<Duration>() / times.len() as u32)
+}
+
+/// Major faults per run from which a command counts as paging.
+const PAGING_FAULTS: f64 = 10.0;
 
-    Some(Comparison {
-        winner_index: min_idx,
-        ratio: max_val as f64 / min_val as f64,
+/// What seems to hold `s` back, from its mean CPU time against its wall
+/// time and its major faults. `None` when its CPU use wasn't watched.
+pub fn characterize(s: &CommandStats) -> Option<Bound> {
+    let mean = |metric: &Metric| metric.export(s).map(|e| e.mean);
+    let wall = s.time_mean.as_secs_f64();
+    let cpu = mean(&metric::CPU_TIME)?;
+    if wall <= 0.0 {
+        return None;
+    }
+    if let Some(major_faults) = mean(&metric::MAJOR_FAULTS)
+        && major_faults >= PAGING_FAULTS
+    {
+        return Some(Bound::Memory { major_faults });
+    }
+    let on_cpu = cpu / wall;
+    Some(if on_cpu >= 0.8 {
+        Bound::Cpu { on_cpu }
+    } else if on_cpu <= 0.5 {
+        Bound::Io { off_cpu: 1.0 - on_cpu }
+    } else {
+        Bound::Mixed { on_cpu }
+    })
+}
+
+/// Average the memory monitor's cost over every run that reported it.
+pub fn monitor_overhead(stats: &[CommandStats]) -> Option<OverheadSummary> {
+    let overheads: Vec<_> = stats
+        .iter()
+        .flat_map(|s| &s.all_runs)
+        .filter_map(|r| r.overhead)
+        .collect();
+    if overheads.is_empty() {
+        return None;
+    }
+    let runs = overheads.len();
+    let samples: u32 = overheads.iter().map(|o| o.samples).sum();
+    let busy: Duration = overheads.iter().map(|o| o.busy).sum();
+    Some(OverheadSummary {
+        runs,
+        samples_per_run: samples as f64 / runs as f64,
+        busy_per_run: busy / runs as u32,
     })
 }
 
-/// Create a short display label from a command string.
-fn make_label(cmd: &str) -> String {
+/// Compare commands on peak memory; `None` unless every command's memory
+/// was measured.
+pub fn compare_memory(stats: &[CommandStats]) -> Option<Comparison> {
+    metric::MEMORY.compare(stats)
+}
+
+/// Score every command on a 0..1 scale per metric (best value / own value)
+/// and combine them with `weights`. A metric is left out, and the weights
+/// renormalized, when it wasn't measured for every command.
+pub fn overall_scores(stats: &[CommandStats], weights: &Weights) -> Vec<Score> {
+    let bests: Vec<Option<f64>> = weights.metrics.iter().map(|(m, _)| m.best(stats)).collect();
+    let total_weight: f64 = weights
+        .metrics
+        .iter()
+        .zip(&bests)
+        .filter(|(_, best)| best.is_some())
+        .map(|(&(_, w), _)| w)
+        .sum();
+
+    stats
+        .iter()
+        .map(|s| {
+            let parts: Vec<Option<f64>> = weights
+                .metrics
+                .iter()
+                .zip(&bests)
+                .map(|(&(m, _), best)| Some(m.relative((*best)?, m.summary(s)?)))
+                .collect();
+            let weighted: f64 = weights
+                .metrics
+                .iter()
+                .zip(&parts)
+                .map(|(&(_, w), part)| w * part.unwrap_or(0.0))
+                .sum();
+            Score {
+                total: if total_weight > 0.0 { weighted / total_weight } else { 0.0 },
+                parts,
+            }
+        })
+        .collect()
+}
+
+/// Whether runs `after` are slower than runs `before` by more than
+/// `threshold` (a fraction) in the mean, by a margin a rank-sum test can
+/// tell from noise.
+pub fn regressed(before: &[f64], after: &[f64], threshold: f64) -> bool {
+    if before.is_empty() || after.is_empty() {
+        return false;
+    }
+    let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len() as f64;
+    mean(after) > mean(before) * (1.0 + threshold)
+        && rank_sum_p_value(before, after).is_some_and(|p| p < 0.05)
+}
+
+/// Gaussian kernel density estimate of `values` at each point of `at`,
+/// with Silverman's rule-of-thumb bandwidth.
+pub fn density(values: &[f64], at: &[f64]) -> Vec<f64> {
+    if values.is_empty() {
+        return vec![0.0; at.len()];
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let quartile = |q: f64| sorted[((n - 1.0) * q).round() as usize];
+    let iqr = quartile(0.75) - quartile(0.25);
+    let spread = if iqr > 0.0 { variance.sqrt().min(iqr / 1.34) } else { variance.sqrt() };
+    // Identical values would give a zero bandwidth; draw them as a narrow peak
+    let bandwidth = (0.9 * spread * n.powf(-0.2)).max(mean.abs() * 1e-3).max(1e-12);
+    let scale = n * bandwidth * (2.0 * std::f64::consts::PI).sqrt();
+    at.iter()
+        .map(|x| {
+            let z = |v: &f64| (x - v) / bandwidth;
+            values.iter().map(|v| (-0.5 * z(v).powi(2)).exp()).sum::<f64>() / scale
+        })
+        .collect()
+}
+
+/// Two-sided p-value of the Wilcoxon rank-sum (Mann–Whitney U) test that
+/// `a` and `b` come from the same distribution, using the normal
+/// approximation with tie and continuity corrections.
+pub fn rank_sum_p_value(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+
+    let mut all: Vec<(f64, bool)> = a
+        .iter()
+        .map(|&v| (v, true))
+        .chain(b.iter().map(|&v| (v, false)))
+        .collect();
+    all.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    // Average ranks across ties, accumulating the tie correction term
+    let mut rank_sum_a = 0.0;
+    let mut tie_term = 0.0;
+    let mut i = 0;
+    while i < all.len() {
+        let mut j = i;
+        while j + 1 < all.len() && all[j + 1].0 == all[i].0 {
+            j += 1;
+        }
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        rank_sum_a += rank * all[i..=j].iter().filter(|(_, in_a)| *in_a).count() as f64;
+        let t = (j - i + 1) as f64;
+        tie_term += t.powi(3) - t;
+        i = j + 1;
+    }
+
+    let u = rank_sum_a - n1 * (n1 + 1.0) / 2.0;
+    let n = n1 + n2;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        return Some(1.0);
+    }
+    let z = ((u - n1 * n2 / 2.0).abs() - 0.5).max(0.0) / variance.sqrt();
+    Some(erfc(z / std::f64::consts::SQRT_2).min(1.0))
+}
+
+/// Complementary error function (Numerical Recipes' Chebyshev fit,
+/// accurate to about 1e-7).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t * (-z * z - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398
+                                + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+        .exp();
+    if x >= 0.0 { r } else { 2.0 - r }
+}
+
+/// Section for commands listed before the first `--group`.
+pub const OTHER_GROUP: &str = "Other";
+
+/// Commands split by `--group` in order of first appearance, or nothing
+/// when no command has a group.
+pub fn groups(all_stats: &[CommandStats]) -> Vec<(String, Vec<CommandStats>)> {
+    if all_stats.iter().all(|s| s.group.is_none()) {
+        return Vec::new();
+    }
+    let mut groups: Vec<(String, Vec<CommandStats>)> = Vec::new();
+    for s in all_stats {
+        let name = s.group.as_deref().unwrap_or(OTHER_GROUP);
+        match groups.iter_mut().find(|(n, _)| n == name) {
+            Some((_, stats)) => stats.push(s.clone()),
+            None => groups.push((name.to_string(), vec![s.clone()])),
+        }
+    }
+    groups
+}
+
+/// Number colliding labels ("name #1", "name #2") so every label in a
+/// session is a unique key, even when long commands truncate alike.
+pub fn unique_labels(labels: &[String]) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::with_capacity(labels.len());
+    for (i, label) in labels.iter().enumerate() {
+        if labels.iter().filter(|l| *l == label).count() == 1 {
+            unique.push(label.clone());
+            continue;
+        }
+        let mut n = labels[..i].iter().filter(|l| *l == label).count() + 1;
+        let mut candidate = format!("{} #{}", label, n);
+        while labels.contains(&candidate) || unique.contains(&candidate) {
+            n += 1;
+            candidate = format!("{} #{}", label, n);
+        }
+        unique.push(candidate);
+    }
+    unique
+}
+
+/// Create a display label at most `width` terminal columns wide from a
+/// command string, cutting only between whole graphemes so emoji and CJK
+/// file names stay intact.
+pub fn make_label(cmd: &str, width: usize) -> String {
     let trimmed = cmd.trim();
-    if trimmed.len() <= 30 {
-        trimmed.to_string()
-    } else {
-        format!("{}...", &trimmed[..27])
+    if trimmed.width() <= width {
+        return trimmed.to_string();
+    }
+    let budget = width.saturating_sub(3);
+    let mut label = String::new();
+    let mut used = 0;
+    for grapheme in trimmed.graphemes(true) {
+        used += grapheme.width();
+        if used > budget {
+            break;
+        }
+        label.push_str(grapheme);
+    }
+    label.push_str("...");
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{command_stats, run_result};
+
+    /// Whether `label` is `cmd` cut at a grapheme boundary with "..." after.
+    fn cut_cleanly(cmd: &str, label: &str) -> bool {
+        let Some(kept) = label.strip_suffix("...") else {
+            return false;
+        };
+        let mut boundaries = cmd.grapheme_indices(true).map(|(i, _)| i).chain([cmd.len()]);
+        cmd.starts_with(kept) && boundaries.any(|i| i == kept.len())
+    }
+
+    #[test]
+    fn short_labels_are_kept_whole() {
+        assert_eq!(make_label("  ls -la  ", 10), "ls -la");
+        assert_eq!(make_label("grep 検索", 9), "grep 検索");
+    }
+
+    #[test]
+    fn cjk_is_cut_between_characters_within_width() {
+        let cmd = "cat 日本語のファイル名.txt";
+        for width in 4..cmd.width() {
+            let label = make_label(cmd, width);
+            assert!(label.width() <= width, "{:?} is wider than {}", label, width);
+            assert!(cut_cleanly(cmd, &label), "{:?} splits a character", label);
+        }
+        // A double-width character that would overflow is left out whole
+        assert_eq!(make_label(cmd, 9), "cat 日...");
+    }
+
+    #[test]
+    fn emoji_sequences_are_never_split() {
+        // Family (joined with zero-width joiners) and a flag
+        let cmd = "echo 👨‍👩‍👧‍👦🇯🇵 done";
+        for width in 4..cmd.width() {
+            let label = make_label(cmd, width);
+            assert!(label.width() <= width, "{:?} is wider than {}", label, width);
+            assert!(cut_cleanly(cmd, &label), "{:?} splits an emoji", label);
+        }
+        assert_eq!(make_label(cmd, 8), "echo ...");
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_base() {
+        // "é" and "ñ" as base letters followed by combining marks
+        let cmd = "touch cafe\u{301} man\u{303}ana.txt";
+        for width in 4..cmd.width() {
+            let label = make_label(cmd, width);
+            assert!(label.width() <= width, "{:?} is wider than {}", label, width);
+            assert!(cut_cleanly(cmd, &label), "{:?} drops a combining mark", label);
+        }
+        assert_eq!(make_label(cmd, 13), "touch cafe\u{301}...");
+    }
+
+    #[test]
+    fn duplicate_labels_are_numbered() {
+        let labels: Vec<String> =
+            ["日本語", "🚀", "日本語", "🚀 #2", "🚀"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(unique_labels(&labels), ["日本語 #1", "🚀 #1", "日本語 #2", "🚀 #2", "🚀 #3"]);
+    }
+
+    #[test]
+    fn the_narrowest_label_keeps_a_wide_grapheme() {
+        assert_eq!(make_label("日本語のファイル", MIN_LABEL_WIDTH), "日...");
+        assert_eq!(make_label("🚀 launch", MIN_LABEL_WIDTH), "🚀...");
+    }
+
+    #[test]
+    fn the_faster_command_wins_by_the_ratio_of_means() {
+        let stats = [
+            command_stats("slow", &[40, 41, 39, 40, 40], 1 << 20),
+            command_stats("fast", &[20, 21, 19, 20, 20], 1 << 20),
+        ];
+        let comparison = compare_time(&stats).unwrap();
+        assert_eq!(comparison.winner_index, 1);
+        assert!((comparison.ratio - 2.0).abs() < 0.01, "{}", comparison.ratio);
+        assert!(!comparison.inconclusive && !comparison.unstable);
+    }
+
+    #[test]
+    fn times_are_centered_on_the_settings_estimator() {
+        let results: Vec<RunResult> = [10, 11, 12, 13, 100].map(|ms| run_result(ms, 0)).into();
+        let settings = RunSettings {
+            estimator: Estimator::Median,
+            ..RunSettings::default()
+        };
+        let s = compute_stats("app", &results, &settings);
+        assert_eq!(s.time_center, Duration::from_millis(12));
+        assert_eq!(metric::TIME.summary(&s), Some(0.012));
+        assert_eq!(metric::TIME.export(&s).unwrap().aggregate, "median");
+    }
+
+    #[test]
+    fn memory_growth_is_warned_about_past_the_settings_threshold() {
+        // RSS grows by 512 KB every 10s: 3072 KB a minute
+        let start = chrono::Utc::now();
+        let results: Vec<RunResult> = (0..4)
+            .map(|i| RunResult {
+                started_at: Some(start + chrono::Duration::seconds(10 * i)),
+                ..run_result(10, (10_240 + 512 * i as u64) * 1024)
+            })
+            .collect();
+        let grew = |leak_threshold| {
+            let settings = RunSettings {
+                leak_threshold,
+                ..RunSettings::default()
+            };
+            let mut stats = [compute_stats("app", &results, &settings)];
+            collect_warnings(&mut stats, &settings);
+            stats[0].warnings.iter().any(|w| matches!(w, Warning::MemoryGrowth { .. }))
+        };
+        assert!(grew(None));
+        assert!(!grew(Some(4096.0)));
     }
 }
@@ -0,0 +1,60 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// A `clash.toml` suite: the benchmarks `clash suite` compares, each a
+/// `[[bench]]` table.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suite {
+    #[serde(default)]
+    pub bench: Vec<Bench>,
+}
+
+/// One command in a suite.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bench {
+    /// Label in the report; the command itself if unset
+    pub name: Option<String>,
+    pub command: String,
+    /// Groups for `--filter` and `--skip`, e.g. "io", "cpu", "quick"
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Untimed command run before every run of this one
+    pub prepare: Option<String>,
+    /// File fed to the command's stdin on every run
+    pub stdin: Option<PathBuf>,
+    /// File the command's stdout is written to, rather than discarded
+    pub stdout: Option<PathBuf>,
+    /// Nonzero exit codes that count as success, e.g. 1 for a `grep` that
+    /// finds nothing
+    #[serde(default)]
+    pub expect_exit: Vec<i32>,
+}
+
+impl Suite {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read suite {}: {}", path, e))?;
+        toml::from_str(&text).map_err(|e| format!("Invalid suite {}: {}", path, e))
+    }
+
+    /// The benchmarks matching any of `filter` (all of them if it's empty)
+    /// and none of `skip`. Like `cargo bench`, a pattern matches a bench
+    /// with that tag or whose name or command contains it.
+    pub fn select(&self, filter: &[String], skip: &[String]) -> Vec<&Bench> {
+        self.bench
+            .iter()
+            .filter(|b| filter.is_empty() || filter.iter().any(|p| b.matches(p)))
+            .filter(|b| !skip.iter().any(|p| b.matches(p)))
+            .collect()
+    }
+}
+
+impl Bench {
+    fn matches(&self, pattern: &str) -> bool {
+        self.tags.iter().any(|t| t == pattern)
+            || self.name.as_ref().is_some_and(|n| n.contains(pattern))
+            || self.command.contains(pattern)
+    }
+}
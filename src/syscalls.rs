@@ -0,0 +1,60 @@
+use crate::types::{SyscallCount, Syscalls};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// The prefix that runs a command under `strace -c`, following forks and
+/// writing the summary table to `report` instead of stderr.
+pub fn prefix(report: &Path) -> Result<Vec<String>, String> {
+    if !cfg!(target_os = "linux") {
+        return Err("--syscalls needs Linux (strace)".to_string());
+    }
+    let strace = Command::new("strace")
+        .arg("-V")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    if strace.is_err() {
+        return Err("--syscalls needs strace to be installed".to_string());
+    }
+    Ok(vec![
+        "strace".to_string(),
+        "-f".to_string(),
+        "-c".to_string(),
+        "-o".to_string(),
+        report.display().to_string(),
+    ])
+}
+
+/// Read the table `strace -c` writes: a header, then a row per system call
+/// such as "41.18  0.000070  10  7  mmap" (% time, seconds, usecs/call,
+/// calls, then errors when some failed, and the name), then a total row.
+pub fn parse(text: &str) -> Result<Syscalls, String> {
+    let mut calls = Vec::new();
+    for line in text.lines() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        // Rows have a percentage, seconds, usecs/call, calls, maybe errors
+        // and the name; the total row lacks usecs/call, so it's skipped
+        let (count, errors) = match words[..] {
+            [_, _, _, count, name] if name != "total" => (count, None),
+            [_, _, _, count, errors, name] if name != "total" => (count, Some(errors)),
+            _ => continue,
+        };
+        let (Ok(count), Ok(errors)) = (count.parse(), errors.map_or(Ok(0), str::parse)) else {
+            continue;
+        };
+        calls.push(SyscallCount {
+            name: words[words.len() - 1].to_string(),
+            calls: count,
+            errors,
+        });
+    }
+    if calls.is_empty() {
+        return Err("strace's summary lists no system calls".to_string());
+    }
+    calls.sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.name.cmp(&b.name)));
+    Ok(Syscalls {
+        total: calls.iter().map(|c| c.calls).sum(),
+        errors: calls.iter().map(|c| c.errors).sum(),
+        calls,
+    })
+}
@@ -0,0 +1,328 @@
+use std::time::Duration;
+
+/// One process as last sampled.
+#[derive(Debug, Clone)]
+pub struct ProcessSample {
+    pub name: String,
+    /// CPU time used since the process started
+    pub cpu_time: Duration,
+    pub memory_bytes: u64,
+}
+
+/// The system's process table, as the memory and CPU monitors read it.
+pub trait ProcessTable: Send {
+    /// `pid`'s name, CPU time and RSS, or `None` once it's gone.
+    fn sample(&mut self, pid: u32) -> Option<ProcessSample>;
+
+    /// RSS of `pids` added up, skipping those that are gone.
+    fn memory(&mut self, pids: &[u32]) -> u64;
+
+    /// RSS of every live process descended from `root` added up.
+    fn tree_memory(&mut self, root: u32) -> u64;
+
+    /// CPU time used by `roots` and every process descended from them, or
+    /// `None` when this table can't see it.
+    fn tree_cpu_time(&mut self, roots: &[u32]) -> Option<Duration>;
+
+    /// Whether `pid` is still running; a zombie isn't.
+    fn running(&mut self, pid: u32) -> bool;
+
+    /// Kill `pid`, returning whether it was there to kill.
+    fn kill(&mut self, pid: u32) -> bool;
+}
+
+/// Whether processes' memory can be sampled without a cgroup, which takes
+/// the `memory` feature.
+pub const SAMPLES_PROCESSES: bool = cfg!(feature = "memory");
+
+// The process table, CPU frequencies and host details come from sysinfo
+// with the `memory` feature, and from /proc or nowhere without it
+#[cfg(feature = "memory")]
+pub use sampled::{
+    CpuFrequency, cpu_brand, host_name, kernel_version, os_version, process_table, total_memory,
+};
+
+#[cfg(not(feature = "memory"))]
+pub use bare::{
+    CpuFrequency, cpu_brand, host_name, kernel_version, os_version, process_table, total_memory,
+};
+
+#[cfg(feature = "memory")]
+mod sampled {
+    use super::{ProcessSample, ProcessTable};
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use sysinfo::{
+        CpuRefreshKind, Pid, Process, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate,
+        RefreshKind, System,
+    };
+
+    #[derive(Default)]
+    pub struct SysinfoTable {
+        sys: System,
+    }
+
+    pub fn process_table() -> Box<dyn ProcessTable> {
+        Box::new(SysinfoTable::default())
+    }
+
+    impl SysinfoTable {
+        fn refresh(&mut self, pids: &[u32], kind: ProcessRefreshKind) {
+            if pids.is_empty() {
+                self.sys.refresh_processes_specifics(ProcessesToUpdate::All, true, kind);
+            } else {
+                let pids: Vec<Pid> = pids.iter().map(|&p| Pid::from_u32(p)).collect();
+                self.sys.refresh_processes_specifics(ProcessesToUpdate::Some(&pids), true, kind);
+            }
+        }
+
+        /// Every process descended from `root`, excluding `root`.
+        fn descendants(&self, root: Pid) -> Vec<&Process> {
+            let parents: HashMap<Pid, Pid> = self
+                .sys
+                .processes()
+                .iter()
+                .filter_map(|(pid, p)| Some((*pid, p.parent()?)))
+                .collect();
+            let descends = |mut pid: Pid| {
+                while let Some(&parent) = parents.get(&pid) {
+                    if parent == root {
+                        return true;
+                    }
+                    pid = parent;
+                }
+                false
+            };
+            self.sys
+                .processes()
+                .iter()
+                .filter(|(pid, p)| {
+                    // Threads are listed as processes too, sharing their owner's RSS
+                    p.thread_kind().is_none()
+                        && p.status() != ProcessStatus::Zombie
+                        && descends(**pid)
+                })
+                .map(|(_, p)| p)
+                .collect()
+        }
+    }
+
+    impl ProcessTable for SysinfoTable {
+        fn sample(&mut self, pid: u32) -> Option<ProcessSample> {
+            self.refresh(&[pid], ProcessRefreshKind::nothing().with_cpu().with_memory());
+            let process = self.sys.process(Pid::from_u32(pid))?;
+            Some(ProcessSample {
+                name: process.name().to_string_lossy().into_owned(),
+                cpu_time: Duration::from_millis(process.accumulated_cpu_time()),
+                memory_bytes: process.memory(),
+            })
+        }
+
+        fn memory(&mut self, pids: &[u32]) -> u64 {
+            self.refresh(pids, ProcessRefreshKind::nothing().with_memory());
+            let found = pids.iter().filter_map(|&p| self.sys.process(Pid::from_u32(p)));
+            found.map(|p| p.memory()).sum()
+        }
+
+        fn tree_memory(&mut self, root: u32) -> u64 {
+            self.refresh(&[], ProcessRefreshKind::nothing().with_memory());
+            self.descendants(Pid::from_u32(root)).iter().map(|p| p.memory()).sum()
+        }
+
+        fn tree_cpu_time(&mut self, roots: &[u32]) -> Option<Duration> {
+            self.refresh(&[], ProcessRefreshKind::nothing().with_cpu());
+            let ms = roots
+                .iter()
+                .map(|&root| Pid::from_u32(root))
+                .flat_map(|root| {
+                    let tree = self.descendants(root).into_iter();
+                    self.sys.process(root).into_iter().chain(tree)
+                })
+                .map(|p| p.accumulated_cpu_time())
+                .sum();
+            Some(Duration::from_millis(ms))
+        }
+
+        fn running(&mut self, pid: u32) -> bool {
+            self.refresh(&[pid], ProcessRefreshKind::nothing());
+            let process = self.sys.process(Pid::from_u32(pid));
+            process.is_some_and(|p| p.status() != ProcessStatus::Zombie)
+        }
+
+        fn kill(&mut self, pid: u32) -> bool {
+            self.refresh(&[pid], ProcessRefreshKind::nothing());
+            self.sys.process(Pid::from_u32(pid)).is_some_and(|p| p.kill())
+        }
+    }
+
+    /// Samples the average frequency of the host's CPUs.
+    pub struct CpuFrequency {
+        sys: System,
+    }
+
+    impl CpuFrequency {
+        pub fn new() -> Self {
+            let cpu = CpuRefreshKind::nothing().with_frequency();
+            Self {
+                sys: System::new_with_specifics(RefreshKind::nothing().with_cpu(cpu)),
+            }
+        }
+
+        /// Average current frequency in MHz, or `None` where the platform
+        /// doesn't report one.
+        pub fn average_mhz(&mut self) -> Option<u64> {
+            self.sys.refresh_cpu_frequency();
+            let cpus = self.sys.cpus();
+            let total: u64 = cpus.iter().map(|c| c.frequency()).sum();
+            if cpus.is_empty() || total == 0 {
+                return None;
+            }
+            Some(total / cpus.len() as u64)
+        }
+    }
+
+    pub fn host_name() -> Option<String> {
+        System::host_name()
+    }
+
+    pub fn os_version() -> Option<String> {
+        System::long_os_version()
+    }
+
+    pub fn kernel_version() -> Option<String> {
+        System::kernel_version()
+    }
+
+    pub fn cpu_brand() -> Option<String> {
+        let mut sys = System::new();
+        sys.refresh_cpu_all();
+        sys.cpus().first().map(|c| c.brand().trim().to_string())
+    }
+
+    /// Physical memory in bytes, or 0 when unknown.
+    pub fn total_memory() -> u64 {
+        let mut sys = System::new();
+        sys.refresh_memory();
+        sys.total_memory()
+    }
+}
+
+/// Stand-ins for builds without sysinfo, reading what `/proc` or a
+/// standard command offers and otherwise going without.
+#[cfg(not(feature = "memory"))]
+mod bare {
+    use super::{ProcessSample, ProcessTable};
+    use std::process::{Command, Stdio};
+    use std::time::Duration;
+
+    /// Sees no process's memory or CPU time, leaving memory to cgroups.
+    pub struct BareTable;
+
+    pub fn process_table() -> Box<dyn ProcessTable> {
+        Box::new(BareTable)
+    }
+
+    impl ProcessTable for BareTable {
+        fn sample(&mut self, _pid: u32) -> Option<ProcessSample> {
+            None
+        }
+
+        fn memory(&mut self, _pids: &[u32]) -> u64 {
+            0
+        }
+
+        fn tree_memory(&mut self, _root: u32) -> u64 {
+            0
+        }
+
+        fn tree_cpu_time(&mut self, _roots: &[u32]) -> Option<Duration> {
+            None
+        }
+
+        fn running(&mut self, pid: u32) -> bool {
+            if cfg!(target_os = "linux") {
+                // The state follows the parenthesized name: "Z" for a zombie
+                let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid));
+                return stat.is_ok_and(|s| {
+                    s.rsplit_once(')').is_some_and(|(_, rest)| !rest.trim_start().starts_with('Z'))
+                });
+            }
+            signal(pid, "-0")
+        }
+
+        fn kill(&mut self, pid: u32) -> bool {
+            if cfg!(windows) {
+                let mut taskkill = Command::new("taskkill");
+                taskkill.args(["/F", "/PID", &pid.to_string()]);
+                return quietly(&mut taskkill);
+            }
+            signal(pid, "-KILL")
+        }
+    }
+
+    fn signal(pid: u32, signal: &str) -> bool {
+        quietly(Command::new("kill").args([signal, &pid.to_string()]))
+    }
+
+    fn quietly(command: &mut Command) -> bool {
+        let status = command.stdout(Stdio::null()).stderr(Stdio::null()).status();
+        status.is_ok_and(|s| s.success())
+    }
+
+    /// The value of every `key: value` line with this key in a file such as
+    /// `/proc/cpuinfo`.
+    fn proc_values(file: &str, key: &str) -> Vec<String> {
+        let text = std::fs::read_to_string(file).unwrap_or_default();
+        text.lines()
+            .filter_map(|line| line.split_once(':'))
+            .filter(|(k, _)| k.trim() == key)
+            .map(|(_, v)| v.trim().to_string())
+            .collect()
+    }
+
+    /// Averages the "cpu MHz" lines of `/proc/cpuinfo`, on Linux.
+    pub struct CpuFrequency;
+
+    impl CpuFrequency {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn average_mhz(&mut self) -> Option<u64> {
+            let mhz: Vec<f64> = proc_values("/proc/cpuinfo", "cpu MHz")
+                .iter()
+                .filter_map(|v| v.parse().ok())
+                .collect();
+            if mhz.is_empty() {
+                return None;
+            }
+            Some((mhz.iter().sum::<f64>() / mhz.len() as f64) as u64)
+        }
+    }
+
+    pub fn host_name() -> Option<String> {
+        let output = Command::new("hostname").stderr(Stdio::null()).output().ok()?;
+        let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !name.is_empty()).then_some(name)
+    }
+
+    pub fn os_version() -> Option<String> {
+        None
+    }
+
+    pub fn kernel_version() -> Option<String> {
+        let release = std::fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+        Some(release.trim().to_string())
+    }
+
+    pub fn cpu_brand() -> Option<String> {
+        proc_values("/proc/cpuinfo", "model name").into_iter().next()
+    }
+
+    /// Physical memory in bytes from `/proc/meminfo`, or 0 when unknown.
+    pub fn total_memory() -> u64 {
+        let total = proc_values("/proc/meminfo", "MemTotal").into_iter().next();
+        let kb = total.and_then(|v| v.trim_end_matches("kB").trim().parse::<u64>().ok());
+        kb.map_or(0, |kb| kb * 1024)
+    }
+}
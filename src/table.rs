@@ -0,0 +1,158 @@
+// Report tables are drawn by comfy-table with the `tables` feature, and as
+// plain aligned columns without it
+#[cfg(feature = "tables")]
+pub use comfy_table::{Attribute, Cell, Color, Table};
+#[cfg(not(feature = "tables"))]
+pub use plain::{Attribute, Cell, Color, Table};
+
+/// A table with `header` on top and every value column right-aligned so
+/// magnitudes line up.
+#[cfg(feature = "tables")]
+pub fn build(header: Vec<Cell>, rows: Vec<Vec<Cell>>) -> Table {
+    use comfy_table::modifiers::UTF8_ROUND_CORNERS;
+    use comfy_table::presets::UTF8_FULL;
+    use comfy_table::{CellAlignment, ContentArrangement};
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(header);
+    for row in rows {
+        table.add_row(row);
+    }
+    for column in table.column_iter_mut().skip(1) {
+        column.set_cell_alignment(CellAlignment::Right);
+    }
+    table
+}
+
+#[cfg(not(feature = "tables"))]
+pub fn build(header: Vec<Cell>, rows: Vec<Vec<Cell>>) -> Table {
+    let mut all = vec![header];
+    all.extend(rows);
+    Table { rows: all }
+}
+
+/// The slice of comfy-table's API the report uses, laid out without
+/// borders, for builds without the `tables` feature.
+#[cfg(not(feature = "tables"))]
+mod plain {
+    use owo_colors::{DynColors, OwoColorize};
+    use std::fmt;
+    use unicode_width::UnicodeWidthStr;
+
+    #[derive(Clone, Copy)]
+    pub enum Attribute {
+        Bold,
+    }
+
+    #[derive(Clone, Copy)]
+    pub enum Color {
+        Black,
+        Red,
+        Green,
+        Yellow,
+        Blue,
+        Magenta,
+        Cyan,
+        DarkGrey,
+        White,
+        Rgb { r: u8, g: u8, b: u8 },
+    }
+
+    impl Color {
+        fn dyn_color(self) -> DynColors {
+            use owo_colors::AnsiColors as Ansi;
+            match self {
+                Color::Black => DynColors::Ansi(Ansi::Black),
+                Color::Red => DynColors::Ansi(Ansi::Red),
+                Color::Green => DynColors::Ansi(Ansi::Green),
+                Color::Yellow => DynColors::Ansi(Ansi::Yellow),
+                Color::Blue => DynColors::Ansi(Ansi::Blue),
+                Color::Magenta => DynColors::Ansi(Ansi::Magenta),
+                Color::Cyan => DynColors::Ansi(Ansi::Cyan),
+                Color::DarkGrey => DynColors::Ansi(Ansi::BrightBlack),
+                Color::White => DynColors::Ansi(Ansi::White),
+                Color::Rgb { r, g, b } => DynColors::Rgb(r, g, b),
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct Cell {
+        text: String,
+        bold: bool,
+        color: Option<Color>,
+    }
+
+    impl Cell {
+        pub fn new(text: impl ToString) -> Self {
+            Self {
+                text: text.to_string(),
+                bold: false,
+                color: None,
+            }
+        }
+
+        pub fn add_attribute(mut self, _attribute: Attribute) -> Self {
+            self.bold = true;
+            self
+        }
+
+        pub fn fg(mut self, color: Color) -> Self {
+            self.color = Some(color);
+            self
+        }
+
+        /// Line `line` of the cell padded to `width`, styled.
+        fn line(&self, line: usize, width: usize, right: bool) -> String {
+            let text = self.text.lines().nth(line).unwrap_or("");
+            let pad = " ".repeat(width.saturating_sub(text.width()));
+            let mut styled = text.to_string();
+            if let Some(color) = self.color {
+                styled = styled.color(color.dyn_color()).to_string();
+            }
+            if self.bold {
+                styled = styled.bold().to_string();
+            }
+            if right { pad + &styled } else { styled + &pad }
+        }
+    }
+
+    pub struct Table {
+        pub(super) rows: Vec<Vec<Cell>>,
+    }
+
+    impl fmt::Display for Table {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let columns = self.rows.iter().map(Vec::len).max().unwrap_or(0);
+            let widths: Vec<usize> = (0..columns)
+                .map(|c| {
+                    let cells = self.rows.iter().filter_map(|row| row.get(c));
+                    cells.flat_map(|cell| cell.text.lines().map(|l| l.width())).max().unwrap_or(0)
+                })
+                .collect();
+            let mut lines = Vec::new();
+            for (r, row) in self.rows.iter().enumerate() {
+                let height = row.iter().map(|c| c.text.lines().count()).max().unwrap_or(1);
+                for line in 0..height.max(1) {
+                    let cells: Vec<String> = row
+                        .iter()
+                        .zip(&widths)
+                        .enumerate()
+                        .map(|(c, (cell, &width))| cell.line(line, width, c > 0))
+                        .collect();
+                    lines.push(cells.join("  ").trim_end().to_string());
+                }
+                // A rule under the header
+                if r == 0 {
+                    let rules: Vec<String> = widths.iter().map(|&w| "─".repeat(w)).collect();
+                    lines.push(rules.join("  ").dimmed().to_string());
+                }
+            }
+            write!(f, "{}", lines.join("\n"))
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Deterministic fakes for testing the measurement, stats and output layers
+//! without spawning real processes.
+
+use crate::exec::{Clock, Executor, Observed, ProcessSpawner, RunningProcess};
+use crate::runner::LocalRunner;
+use crate::stats;
+use crate::types::{CommandStats, RunResult, RunSettings, RunStatus};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::io;
+use std::process::Command;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// A clock that only moves when told to. Clones share the same time.
+#[derive(Clone)]
+pub struct FakeClock {
+    base: Instant,
+    offset: Rc<Cell<Duration>>,
+}
+
+impl FakeClock {
+    pub fn new() -> Self {
+        Self {
+            base: Instant::now(),
+            offset: Rc::new(Cell::new(Duration::ZERO)),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.offset.set(self.offset.get() + by);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.base + self.offset.get()
+    }
+}
+
+/// The scripted outcome of one fake process.
+#[derive(Debug, Clone)]
+pub enum FakeRun {
+    /// The process runs for `duration`, peaks at `memory` bytes and exits
+    /// with `exit_code` (`None` simulates death by signal).
+    Exit {
+        duration: Duration,
+        memory: u64,
+        exit_code: Option<i32>,
+    },
+    /// Spawning fails, as for a missing program.
+    SpawnError(io::ErrorKind),
+}
+
+impl FakeRun {
+    pub fn ok(millis: u64, memory: u64) -> Self {
+        FakeRun::Exit {
+            duration: Duration::from_millis(millis),
+            memory,
+            exit_code: Some(0),
+        }
+    }
+
+    pub fn failed(millis: u64, memory: u64, code: i32) -> Self {
+        FakeRun::Exit {
+            duration: Duration::from_millis(millis),
+            memory,
+            exit_code: Some(code),
+        }
+    }
+}
+
+/// Program and arguments of every command a `FakeSpawner` was asked to
+/// start, shared so a test can read them once the spawner is boxed.
+pub type Spawned = Rc<RefCell<Vec<Vec<String>>>>;
+
+/// Plays back scripted runs in order, advancing the shared `FakeClock` by
+/// each run's duration while "waiting" for it.
+pub struct FakeSpawner {
+    clock: FakeClock,
+    script: RefCell<VecDeque<FakeRun>>,
+    spawned: Spawned,
+}
+
+impl FakeSpawner {
+    pub fn new(clock: FakeClock, script: impl IntoIterator<Item = FakeRun>) -> Self {
+        Self {
+            clock,
+            script: RefCell::new(script.into_iter().collect()),
+            spawned: Spawned::default(),
+        }
+    }
+}
+
+impl ProcessSpawner for FakeSpawner {
+    fn spawn(&self, command: &mut Command) -> io::Result<Box<dyn RunningProcess>> {
+        let argv = std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        self.spawned.borrow_mut().push(argv);
+        match self.script.borrow_mut().pop_front() {
+            Some(FakeRun::Exit {
+                duration,
+                memory,
+                exit_code,
+            }) => Ok(Box::new(FakeProcess {
+                clock: self.clock.clone(),
+                duration,
+                memory,
+                exit_code,
+            })),
+            Some(FakeRun::SpawnError(kind)) => Err(io::Error::from(kind)),
+            None => Err(io::Error::other("FakeSpawner script exhausted")),
+        }
+    }
+}
+
+struct FakeProcess {
+    clock: FakeClock,
+    duration: Duration,
+    memory: u64,
+    exit_code: Option<i32>,
+}
+
+impl RunningProcess for FakeProcess {
+    fn wait(&mut self) -> io::Result<RunStatus> {
+        self.clock.advance(self.duration);
+        Ok(match self.exit_code {
+            Some(0) => RunStatus::Success,
+            Some(code) => RunStatus::Nonzero(code),
+            None => RunStatus::Signaled(None),
+        })
+    }
+
+    fn finish(self: Box<Self>) -> Observed {
+        Observed {
+            // Like a real monitor, zero stands for never sampled
+            peak_memory_bytes: Some(self.memory).filter(|&m| m > 0),
+            overhead: None,
+            output: None,
+            phases: Vec::new(),
+            cpu: None,
+        }
+    }
+}
+
+/// An `Executor` that plays back `script` instead of spawning processes,
+/// and what it was asked to spawn.
+pub fn fake_executor(script: impl IntoIterator<Item = FakeRun>) -> (Executor, Spawned) {
+    let clock = FakeClock::new();
+    let spawner = FakeSpawner::new(clock.clone(), script);
+    let spawned = spawner.spawned.clone();
+    let executor = Executor {
+        spawner: Box::new(spawner),
+        clock: Box::new(clock),
+        count_output: false,
+        phases: false,
+        redirect: None,
+    };
+    (executor, spawned)
+}
+
+/// A `LocalRunner` that plays back `script`, and what it was asked to spawn.
+pub fn fake_runner(script: impl IntoIterator<Item = FakeRun>) -> (LocalRunner, Spawned) {
+    let (exec, spawned) = fake_executor(script);
+    let runner = LocalRunner {
+        exec,
+        env: Vec::new(),
+        prefix: Vec::new(),
+    };
+    (runner, spawned)
+}
+
+/// A successful run result.
+pub fn run_result(millis: u64, memory: u64) -> RunResult {
+    RunResult {
+        duration: Duration::from_millis(millis),
+        peak_memory_bytes: Some(memory),
+        exit_code: Some(0),
+        ..RunResult::default()
+    }
+}
+
+/// Stats for `cmd` computed from successful runs of the given durations
+/// (in milliseconds) and peak memory.
+pub fn command_stats(cmd: &str, millis: &[u64], memory: u64) -> CommandStats {
+    let results: Vec<RunResult> = millis.iter().map(|&ms| run_result(ms, memory)).collect();
+    stats::compute_stats(cmd, &results, &RunSettings::default())
+}
@@ -0,0 +1,224 @@
+use owo_colors::{AnsiColors, DynColors, OwoColorize};
+use serde::Deserialize;
+
+/// A color used by the report, or no color at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Paint {
+    Plain,
+    Ansi(AnsiColors),
+    Rgb(u8, u8, u8),
+}
+
+impl Paint {
+    /// Parse a color name ("green", "bright-blue", "none") or "#rrggbb".
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim().to_ascii_lowercase();
+        if let Some(hex) = value.strip_prefix('#') {
+            let channel = |i: usize| {
+                hex.get(i..i + 2)
+                    .and_then(|c| u8::from_str_radix(c, 16).ok())
+                    .ok_or_else(|| format!("Invalid hex color '#{}'", hex))
+            };
+            if hex.len() != 6 {
+                return Err(format!("Invalid hex color '#{}'", hex));
+            }
+            return Ok(Paint::Rgb(channel(0)?, channel(2)?, channel(4)?));
+        }
+        let ansi = match value.as_str() {
+            "none" | "plain" => return Ok(Paint::Plain),
+            "black" => AnsiColors::Black,
+            "red" => AnsiColors::Red,
+            "green" => AnsiColors::Green,
+            "yellow" => AnsiColors::Yellow,
+            "blue" => AnsiColors::Blue,
+            "magenta" => AnsiColors::Magenta,
+            "cyan" => AnsiColors::Cyan,
+            "white" => AnsiColors::White,
+            "bright-black" => AnsiColors::BrightBlack,
+            "bright-red" => AnsiColors::BrightRed,
+            "bright-green" => AnsiColors::BrightGreen,
+            "bright-yellow" => AnsiColors::BrightYellow,
+            "bright-blue" => AnsiColors::BrightBlue,
+            "bright-magenta" => AnsiColors::BrightMagenta,
+            "bright-cyan" => AnsiColors::BrightCyan,
+            "bright-white" => AnsiColors::BrightWhite,
+            other => return Err(format!("Unknown color '{}'", other)),
+        };
+        Ok(Paint::Ansi(ansi))
+    }
+
+    /// Colorize terminal text.
+    pub fn paint(self, text: &str) -> String {
+        match self {
+            Paint::Plain => text.to_string(),
+            Paint::Ansi(c) => text.color(DynColors::Ansi(c)).to_string(),
+            Paint::Rgb(r, g, b) => text.color(DynColors::Rgb(r, g, b)).to_string(),
+        }
+    }
+
+    /// A color `t` (0..=1) of the way from this one to `other`, for
+    /// gradients. Plain when either is plain.
+    pub fn blend(self, other: Paint, t: f64) -> Paint {
+        let (Some((r1, g1, b1)), Some((r2, g2, b2))) = (self.rgb(), other.rgb()) else {
+            return Paint::Plain;
+        };
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        Paint::Rgb(mix(r1, r2), mix(g1, g2), mix(b1, b2))
+    }
+
+    /// Approximate RGB value, using the common xterm palette for ANSI colors.
+    fn rgb(self) -> Option<(u8, u8, u8)> {
+        match self {
+            Paint::Plain => None,
+            Paint::Rgb(r, g, b) => Some((r, g, b)),
+            Paint::Ansi(c) => Some(match c {
+                AnsiColors::Black => (0, 0, 0),
+                AnsiColors::Red => (205, 0, 0),
+                AnsiColors::Green => (0, 205, 0),
+                AnsiColors::Yellow => (205, 205, 0),
+                AnsiColors::Blue => (0, 0, 238),
+                AnsiColors::Magenta => (205, 0, 205),
+                AnsiColors::Cyan => (0, 205, 205),
+                AnsiColors::White | AnsiColors::Default => (229, 229, 229),
+                AnsiColors::BrightBlack => (127, 127, 127),
+                AnsiColors::BrightRed => (255, 0, 0),
+                AnsiColors::BrightGreen => (0, 255, 0),
+                AnsiColors::BrightYellow => (255, 255, 0),
+                AnsiColors::BrightBlue => (92, 92, 255),
+                AnsiColors::BrightMagenta => (255, 0, 255),
+                AnsiColors::BrightCyan => (0, 255, 255),
+                AnsiColors::BrightWhite => (255, 255, 255),
+            }),
+        }
+    }
+
+    /// The equivalent table cell color.
+    pub fn cell(self) -> Option<crate::table::Color> {
+        use crate::table::Color;
+        match self {
+            Paint::Plain => None,
+            Paint::Rgb(r, g, b) => Some(Color::Rgb { r, g, b }),
+            Paint::Ansi(c) => Some(match c {
+                AnsiColors::Black => Color::Black,
+                AnsiColors::Red | AnsiColors::BrightRed => Color::Red,
+                AnsiColors::Green | AnsiColors::BrightGreen => Color::Green,
+                AnsiColors::Yellow | AnsiColors::BrightYellow => Color::Yellow,
+                AnsiColors::Blue | AnsiColors::BrightBlue => Color::Blue,
+                AnsiColors::Magenta | AnsiColors::BrightMagenta => Color::Magenta,
+                AnsiColors::Cyan | AnsiColors::BrightCyan => Color::Cyan,
+                AnsiColors::BrightBlack => Color::DarkGrey,
+                AnsiColors::White | AnsiColors::BrightWhite | AnsiColors::Default => Color::White,
+            }),
+        }
+    }
+}
+
+/// Colors and markers used to signal winners and losers in the report.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub winner: Paint,
+    pub loser: Paint,
+    pub warning: Paint,
+    pub time: Paint,
+    pub memory: Paint,
+    /// Shown next to winning values so the winner is visible without color
+    pub marker: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            winner: Paint::Ansi(AnsiColors::Green),
+            loser: Paint::Ansi(AnsiColors::Red),
+            warning: Paint::Ansi(AnsiColors::Yellow),
+            time: Paint::Ansi(AnsiColors::Cyan),
+            memory: Paint::Ansi(AnsiColors::Magenta),
+            marker: String::new(),
+        }
+    }
+}
+
+/// Palette file format for `--theme custom.toml`; unset keys keep the
+/// default theme's value.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    winner: Option<String>,
+    loser: Option<String>,
+    warning: Option<String>,
+    time: Option<String>,
+    memory: Option<String>,
+    marker: Option<String>,
+}
+
+impl Theme {
+    /// Blue/orange palette (Okabe–Ito) that stays distinguishable with
+    /// red-green color blindness.
+    pub fn colorblind() -> Self {
+        Self {
+            winner: Paint::Rgb(0x00, 0x72, 0xB2),
+            loser: Paint::Rgb(0xE6, 0x9F, 0x00),
+            warning: Paint::Rgb(0xCC, 0x79, 0xA7),
+            time: Paint::Rgb(0x56, 0xB4, 0xE9),
+            memory: Paint::Rgb(0xF0, 0xE4, 0x42),
+            marker: "★".to_string(),
+        }
+    }
+
+    /// No colors at all; winners are marked with a star.
+    pub fn monochrome() -> Self {
+        Self {
+            winner: Paint::Plain,
+            loser: Paint::Plain,
+            warning: Paint::Plain,
+            time: Paint::Plain,
+            memory: Paint::Plain,
+            marker: "★".to_string(),
+        }
+    }
+
+    /// Resolve `--theme`: a built-in name or the path to a TOML palette.
+    pub fn from_arg(arg: &str) -> Result<Self, String> {
+        match arg {
+            "default" => Ok(Self::default()),
+            "colorblind" => Ok(Self::colorblind()),
+            "monochrome" => Ok(Self::monochrome()),
+            path => Self::load(path),
+        }
+    }
+
+    fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read theme {}: {}", path, e))?;
+        let file: ThemeFile =
+            toml::from_str(&text).map_err(|e| format!("Invalid theme {}: {}", path, e))?;
+
+        let mut theme = Self::default();
+        let fields = [
+            (&file.winner, &mut theme.winner),
+            (&file.loser, &mut theme.loser),
+            (&file.warning, &mut theme.warning),
+            (&file.time, &mut theme.time),
+            (&file.memory, &mut theme.memory),
+        ];
+        for (value, slot) in fields {
+            if let Some(value) = value {
+                *slot = Paint::parse(value).map_err(|e| format!("{} in {}", e, path))?;
+            }
+        }
+        if let Some(marker) = file.marker {
+            theme.marker = marker;
+        }
+        Ok(theme)
+    }
+
+    /// Append the winner marker, if the theme has one.
+    pub fn mark(&self, text: &str) -> String {
+        if self.marker.is_empty() {
+            text.to_string()
+        } else {
+            format!("{} {}", text, self.marker)
+        }
+    }
+}
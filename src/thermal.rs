@@ -0,0 +1,73 @@
+use crate::system::CpuFrequency;
+use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How often the average CPU frequency is sampled.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Timestamped average CPU frequencies, in MHz.
+type Samples = Arc<Mutex<Vec<(DateTime<Utc>, u64)>>>;
+
+/// Samples the host's average CPU frequency in the background for the whole
+/// session, so each run can be tagged with the frequency it ran at.
+pub struct FrequencyMonitor {
+    samples: Samples,
+    running: Arc<AtomicBool>,
+    sampler: Option<JoinHandle<()>>,
+}
+
+impl FrequencyMonitor {
+    /// Start sampling, or `None` if the platform doesn't report frequencies.
+    pub fn start() -> Option<Self> {
+        let mut cpus = CpuFrequency::new();
+        cpus.average_mhz()?;
+
+        let samples: Samples = Arc::default();
+        let running = Arc::new(AtomicBool::new(true));
+        let sampler = {
+            let samples = samples.clone();
+            let running = running.clone();
+            std::thread::spawn(move || {
+                while running.load(Ordering::Relaxed) {
+                    if let Some(mhz) = cpus.average_mhz() {
+                        samples.lock().unwrap().push((Utc::now(), mhz));
+                    }
+                    std::thread::sleep(SAMPLE_INTERVAL);
+                }
+            })
+        };
+        Some(Self {
+            samples,
+            running,
+            sampler: Some(sampler),
+        })
+    }
+
+    /// Mean frequency over a run, falling back to the latest sample before
+    /// it ended for runs shorter than the sampling interval.
+    pub fn mean_during(&self, start: DateTime<Utc>, took: Duration) -> Option<u64> {
+        let end = start + took;
+        let samples = self.samples.lock().unwrap();
+        let during: Vec<u64> = samples
+            .iter()
+            .filter(|(at, _)| *at >= start && *at <= end)
+            .map(|&(_, mhz)| mhz)
+            .collect();
+        if during.is_empty() {
+            return samples.iter().rev().find(|(at, _)| *at <= end).map(|&(_, mhz)| mhz);
+        }
+        Some(during.iter().sum::<u64>() / during.len() as u64)
+    }
+}
+
+impl Drop for FrequencyMonitor {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(sampler) = self.sampler.take() {
+            sampler.join().ok();
+        }
+    }
+}
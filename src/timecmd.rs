@@ -0,0 +1,141 @@
+use crate::types::ResourceUsage;
+use std::path::Path;
+
+/// The standalone `time`, as opposed to the shell keyword.
+const TIME: &str = "/usr/bin/time";
+
+/// How local runs are measured, for `--backend`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Backend {
+    /// Sample the command's memory while it runs
+    #[default]
+    Sample,
+    /// Launch the command through /usr/bin/time and read its report
+    Time,
+}
+
+/// Which `/usr/bin/time` this system has; the two write different reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+    /// GNU time (and BusyBox's), with `-v`
+    Gnu,
+    /// The BSDs' and macOS's, with `-l`
+    Bsd,
+}
+
+/// What one run's `time` report says, normalized across flavors.
+#[derive(Debug, Clone, Copy)]
+pub struct Report {
+    pub max_rss_bytes: u64,
+    pub usage: ResourceUsage,
+    /// Signal that ended the command, which GNU time otherwise reports as
+    /// an exit code of 128 plus the signal
+    pub signal: Option<i32>,
+}
+
+impl Flavor {
+    /// The flavor of this system's `/usr/bin/time`, which must exist.
+    pub fn detect() -> Result<Self, String> {
+        if !Path::new(TIME).exists() {
+            return Err(format!(
+                "--backend time needs {} (the \"time\" package on most Linux distributions)",
+                TIME
+            ));
+        }
+        if cfg!(any(
+            target_os = "macos",
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "netbsd",
+            target_os = "dragonfly"
+        )) {
+            Ok(Flavor::Bsd)
+        } else {
+            Ok(Flavor::Gnu)
+        }
+    }
+
+    /// The prefix that runs a command under `time`, writing the report to
+    /// `report` rather than mixing it into the command's stderr.
+    pub fn prefix(self, report: &Path) -> Vec<String> {
+        let verbose = match self {
+            Flavor::Gnu => "-v",
+            Flavor::Bsd => "-l",
+        };
+        vec![
+            TIME.to_string(),
+            verbose.to_string(),
+            "-o".to_string(),
+            report.display().to_string(),
+        ]
+    }
+
+    /// Read a report `time` wrote.
+    pub fn parse(self, text: &str) -> Result<Report, String> {
+        match self {
+            Flavor::Gnu => parse_gnu(text),
+            Flavor::Bsd => parse_bsd(text),
+        }
+        .ok_or_else(|| format!("{}'s report has no maximum resident set size", TIME))
+    }
+}
+
+/// `-v` output: "\tMaximum resident set size (kbytes): 1920" and so on,
+/// after "Command terminated by signal 9" when the command was killed.
+fn parse_gnu(text: &str) -> Option<Report> {
+    let mut max_rss = None;
+    let mut usage = ResourceUsage::default();
+    let mut signal = None;
+    for line in text.lines().map(str::trim) {
+        if let Some(n) = line.strip_prefix("Command terminated by signal ") {
+            signal = n.trim().parse().ok();
+            continue;
+        }
+        let Some((key, value)) = line.rsplit_once(": ") else {
+            continue;
+        };
+        let Ok(value) = value.trim().parse::<u64>() else {
+            continue;
+        };
+        match key {
+            "Maximum resident set size (kbytes)" => max_rss = Some(value * 1024),
+            "Major (requiring I/O) page faults" => usage.major_faults = value,
+            "Minor (reclaiming a frame) page faults" => usage.minor_faults = value,
+            "Voluntary context switches" => usage.voluntary_switches = value,
+            "Involuntary context switches" => usage.involuntary_switches = value,
+            _ => {}
+        }
+    }
+    Some(Report {
+        max_rss_bytes: max_rss?,
+        usage,
+        signal,
+    })
+}
+
+/// `-l` output: "   1114112  maximum resident set size" and so on, in
+/// bytes on macOS but kilobytes on the other BSDs.
+fn parse_bsd(text: &str) -> Option<Report> {
+    let rss_unit = if cfg!(target_os = "macos") { 1 } else { 1024 };
+    let mut max_rss = None;
+    let mut usage = ResourceUsage::default();
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        let Some(Ok(value)) = words.next().map(str::parse::<u64>) else {
+            continue;
+        };
+        match words.collect::<Vec<_>>().join(" ").as_str() {
+            "maximum resident set size" => max_rss = Some(value * rss_unit),
+            "page faults" => usage.major_faults = value,
+            "page reclaims" => usage.minor_faults = value,
+            "voluntary context switches" => usage.voluntary_switches = value,
+            "involuntary context switches" => usage.involuntary_switches = value,
+            _ => {}
+        }
+    }
+    Some(Report {
+        max_rss_bytes: max_rss?,
+        usage,
+        signal: None,
+    })
+}
@@ -1,12 +1,244 @@
-use serde::Serialize;
+use crate::cores::CoreType;
+use crate::numa::NumaNode;
+use crate::metric::{self, Metric, MetricExport};
+use crate::runner::Preset;
+use crate::stats;
+use crate::units;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::time::Duration;
 
 /// Result of a single benchmark run
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RunResult {
     pub duration: Duration,
-    pub peak_memory_bytes: u64,
+    /// `None` when the process ended before memory could be sampled
+    #[serde(deserialize_with = "unsampled_as_none")]
+    pub peak_memory_bytes: Option<u64>,
     pub exit_code: Option<i32>,
+    /// Signal that killed the process, where the platform reports one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<i32>,
+    /// Killed by clash for running past `--timeout`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub timed_out: bool,
+    /// Exited with a nonzero code the command was declared to exit with
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub expected_exit: bool,
+    /// Wall-clock time the run started (absent in older recordings)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    /// Mean CPU frequency during the run, in MHz, where the host reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_mhz: Option<u64>,
+    /// Time spent in the `--prepare` command before this run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prepare: Option<Duration>,
+    /// Cost of clash's own memory sampling during this run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overhead: Option<MonitorOverhead>,
+    /// How much the run printed, with `--count-output`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputCounts>,
+    /// Phases the command marked, with `--phases`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phases: Vec<Phase>,
+    /// Each instance's own time, with `--instances`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub instances: Vec<Duration>,
+    /// CPU the run used, where its processes could be watched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<CpuUsage>,
+    /// Faults and context switches, with `--backend time`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ResourceUsage>,
+}
+
+impl RunResult {
+    /// How the run ended.
+    pub fn status(&self) -> RunStatus {
+        if self.timed_out {
+            return RunStatus::Timeout;
+        }
+        match self.exit_code {
+            Some(0) => RunStatus::Success,
+            Some(_) if self.expected_exit => RunStatus::Success,
+            Some(code) => RunStatus::Nonzero(code),
+            None => RunStatus::Signaled(self.signal),
+        }
+    }
+}
+
+/// How a run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    Success,
+    /// Exited with this non-zero code
+    Nonzero(i32),
+    /// Killed by a signal, and which one where the platform says
+    Signaled(Option<i32>),
+    /// Killed by clash for running past `--timeout`
+    Timeout,
+}
+
+impl fmt::Display for RunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunStatus::Success => write!(f, "success"),
+            RunStatus::Nonzero(code) => write!(f, "exit code {}", code),
+            RunStatus::Signaled(Some(signal)) => write!(f, "signal {}", signal),
+            RunStatus::Signaled(None) => write!(f, "signal"),
+            RunStatus::Timeout => write!(f, "timeout"),
+        }
+    }
+}
+
+impl Serialize for RunStatus {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A set of unsuccessful run statuses, written as a comma-separated list
+/// of `nonzero`, `signaled` and `timeout`, or `none`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Statuses {
+    pub nonzero: bool,
+    pub signaled: bool,
+    pub timeout: bool,
+}
+
+impl Statuses {
+    pub fn contains(&self, status: RunStatus) -> bool {
+        match status {
+            RunStatus::Success => false,
+            RunStatus::Nonzero(_) => self.nonzero,
+            RunStatus::Signaled(_) => self.signaled,
+            RunStatus::Timeout => self.timeout,
+        }
+    }
+}
+
+impl std::str::FromStr for Statuses {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut statuses = Statuses::default();
+        if s.trim() == "none" {
+            return Ok(statuses);
+        }
+        for name in s.split(',').map(str::trim) {
+            match name {
+                "nonzero" => statuses.nonzero = true,
+                "signaled" => statuses.signaled = true,
+                "timeout" => statuses.timeout = true,
+                _ => {
+                    return Err(format!(
+                        "unknown run status '{}' (expected nonzero, signaled, timeout or none)",
+                        name
+                    ));
+                }
+            }
+        }
+        Ok(statuses)
+    }
+}
+
+/// Which run statuses count toward a command's failed runs, and which
+/// make a run's measurements unusable.
+#[derive(Debug, Clone, Copy)]
+pub struct StatusPolicy {
+    pub failed: Statuses,
+    /// Runs left out of every statistic, though still counted as failed
+    pub discarded: Statuses,
+}
+
+impl Default for StatusPolicy {
+    fn default() -> Self {
+        Self {
+            failed: Statuses {
+                nonzero: true,
+                signaled: false,
+                timeout: true,
+            },
+            // A run killed at the limit took as long as the limit, not
+            // as long as the command
+            discarded: Statuses {
+                nonzero: false,
+                signaled: false,
+                timeout: true,
+            },
+        }
+    }
+}
+
+/// Part of a run that the command ended with a `CLASH_MARK:<name>` line,
+/// timed from the previous mark or the start of the run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Phase {
+    pub name: String,
+    pub duration: Duration,
+}
+
+/// Bytes and lines a run wrote to stdout and stderr.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct OutputCounts {
+    pub stdout_bytes: u64,
+    pub stdout_lines: u64,
+    pub stderr_bytes: u64,
+    pub stderr_lines: u64,
+}
+
+/// CPU one run used across all its processes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CpuUsage {
+    /// User plus system time
+    pub time: Duration,
+    /// Most cores busy at once over any 100ms stretch, when the run lasted
+    /// long enough to tell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_cores: Option<f64>,
+}
+
+/// Page faults and context switches over one run, from `--backend time`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    pub major_faults: u64,
+    pub minor_faults: u64,
+    pub voluntary_switches: u64,
+    pub involuntary_switches: u64,
+}
+
+/// What the memory monitor thread cost during one run.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MonitorOverhead {
+    /// Number of memory samples taken
+    pub samples: u32,
+    /// Time the monitor thread spent sampling, excluding its sleeps
+    pub busy: Duration,
+}
+
+/// What seems to hold a command back, with `--characterize`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+    /// On CPU for this share of wall time, above 1 across several cores
+    Cpu { on_cpu: f64 },
+    /// Off CPU for this share of wall time: blocked on I/O, or waiting
+    Io { off_cpu: f64 },
+    /// Paging: this many major faults per run
+    Memory { major_faults: f64 },
+    /// Neither on nor off CPU most of the time
+    Mixed { on_cpu: f64 },
+}
+
+/// Monitor overhead averaged over every sampled run of a session.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct OverheadSummary {
+    pub runs: usize,
+    pub samples_per_run: f64,
+    pub busy_per_run: Duration,
 }
 
 /// Aggregated statistics for all runs of a single command
@@ -16,12 +248,742 @@ pub struct CommandStats {
     pub label: String,
     pub runs: usize,
     pub time_mean: Duration,
+    /// The session's `--estimator` over run times, which comparisons use
+    pub time_center: Duration,
     pub time_min: Duration,
     pub time_max: Duration,
     pub time_std_dev: Duration,
-    pub peak_memory_bytes: u64,
+    /// Coefficient of variation: standard deviation over mean
+    pub time_cv: f64,
+    pub stability: Stability,
+    /// Highest peak of any run whose memory was sampled
+    pub peak_memory_bytes: Option<u64>,
     pub all_runs: Vec<RunResult>,
+    /// Runs that ended in a status counted as failure, discarded ones included
+    pub failed_runs: usize,
+    /// Runs left out of `all_runs` and every statistic for their status
+    #[serde(skip_serializing_if = "is_zero")]
+    pub discarded_runs: usize,
+    /// Mean time spent in `--prepare` before each run, if there was one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prepare_mean: Option<Duration>,
+    /// Mean output per run, with `--count-output`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_mean: Option<OutputCounts>,
+    /// Wall-clock time for everything this command needed: warmup,
+    /// preparation, pauses and the measured runs
+    pub wall_time: Duration,
+    /// Runs dropped as outliers, not counted in `runs`
+    #[serde(skip_serializing_if = "is_zero")]
+    pub outliers_rejected: usize,
+    /// Measured runs planned, when `--session-budget` cut them short
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trimmed_from: Option<usize>,
+    /// `--subtract` baseline whose mean was taken off every run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<Baseline>,
+    /// Precision reached by adaptive runs, against the one aimed for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub precision: Option<Precision>,
+    /// The `--build` step run before the benchmark
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build: Option<Build>,
+    /// The `--matrix` value this command was run with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<MatrixCell>,
+    /// The `--group` this command was listed under
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Problems with these results, collected once the session ends
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+    /// The `--estimator` time of each `--rounds` round, when there were several
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rounds: Vec<Duration>,
+    /// Runs worth a second look: outliers, timeouts, failures, throttling
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    /// Drift over a `--soak` session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soak: Option<Soak>,
+    /// How RSS changed over the runs, when they spanned long enough
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_trend: Option<MemoryTrend>,
+    /// System calls of one traced run, with `--syscalls`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syscalls: Option<Syscalls>,
+    /// The program the command started, when it ran on this machine
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    /// Each of `all_runs`' position among every measured run, rejected
+    /// and discarded ones included, for annotations
+    #[serde(skip)]
+    pub run_numbers: Vec<usize>,
+    /// The results came from a tool that didn't record memory
+    #[serde(skip_serializing_if = "is_false")]
+    pub memory_unrecorded: bool,
+    /// The estimator `time_center` was computed with
+    #[serde(skip)]
+    pub estimator: Estimator,
+    /// Only call a time winner when a rank-sum test finds the gap significant
+    #[serde(skip)]
+    pub significance: bool,
+}
+
+/// One run flagged in the export, so it can be filtered or investigated
+/// without re-running.
+#[derive(Debug, Clone, Serialize)]
+pub struct Annotation {
+    /// 0-based position among the command's measured runs in the order
+    /// they ran, across rounds, counting runs left out of the stats
+    pub run: usize,
+    pub kind: AnnotationKind,
+    pub details: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationKind {
+    /// Rejected by outlier rejection
+    Outlier,
+    /// Killed for running past `--timeout`
+    Timeout,
+    /// Ended in a status `--fail-on` counts as failed
+    Failed,
+    /// Ran well below the session's peak CPU frequency
+    Throttled,
+}
+
+/// Something about a command's results that deserves a second look.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Warning {
+    /// Runs ended in a status counted as failure
+    FailedRuns { failed: usize, runs: usize },
+    /// Runs were killed by a signal, without counting as failed
+    Signaled { runs: usize },
+    /// Runs were left out of the stats for their status
+    Discarded { runs: usize },
+    /// Runs dropped as outliers before computing stats
+    OutliersRejected { count: usize },
+    /// The mean is too noisy to trust; `suggested_runs` would fix that
+    HighVariance { cv: f64, suggested_runs: usize },
+    /// Runs that ended before their memory could be sampled
+    MemoryUnmeasured { runs: usize },
+    /// Imported from a tool that didn't record memory
+    MemoryNotRecorded,
+    /// Consecutive runs (0-based) ran well below the session's peak CPU
+    /// frequency
+    Throttling {
+        first_run: usize,
+        last_run: usize,
+        lowest_mhz: u64,
+        peak_mhz: u64,
+    },
+    /// `--session-budget` cut the planned runs short
+    Trimmed { runs: usize, planned: usize },
+    /// Adaptive runs hit `--max-runs` before the mean was precise enough
+    TargetMissed { achieved: f64, target: f64 },
+    /// The mean is so short that process startup dominates it
+    StartupDominated { mean: Duration },
+    /// RSS rose over the session faster than `--leak-threshold`
+    MemoryGrowth { kb_per_min: f64 },
+    /// Run times correlate with run order (Spearman's rho): negative when
+    /// runs speed up, positive when they slow down
+    RunOrder { rho: f64 },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::FailedRuns { failed, runs } => {
+                write!(f, "{}/{} runs failed", failed, runs)
+            }
+            Warning::Signaled { runs } => write!(
+                f,
+                "{} run{} killed by a signal; --treat-signals-as-failures counts them as failed",
+                runs,
+                if *runs == 1 { "" } else { "s" }
+            ),
+            Warning::Discarded { runs } => write!(
+                f,
+                "discarded {} run{} for their exit status",
+                runs,
+                if *runs == 1 { "" } else { "s" }
+            ),
+            Warning::OutliersRejected { count } => write!(
+                f,
+                "rejected {} outlier run{}",
+                count,
+                if *count == 1 { "" } else { "s" }
+            ),
+            Warning::HighVariance { cv, suggested_runs } => write!(
+                f,
+                "std dev is {:.0}% of mean; consider --runs {}",
+                cv * 100.0,
+                suggested_runs
+            ),
+            Warning::MemoryUnmeasured { runs } => write!(
+                f,
+                "memory unmeasured ({} run{} too fast to sample)",
+                runs,
+                if *runs == 1 { "" } else { "s" }
+            ),
+            Warning::MemoryNotRecorded => write!(f, "memory not recorded by source"),
+            Warning::Throttling {
+                first_run,
+                last_run,
+                lowest_mhz,
+                peak_mhz,
+            } => {
+                if first_run == last_run {
+                    write!(f, "possible thermal throttling during run {}", first_run + 1)?;
+                } else {
+                    write!(
+                        f,
+                        "possible thermal throttling during runs {}–{}",
+                        first_run + 1,
+                        last_run + 1
+                    )?;
+                }
+                write!(
+                    f,
+                    " (CPU at {:.1} GHz vs {:.1} GHz peak)",
+                    *lowest_mhz as f64 / 1000.0,
+                    *peak_mhz as f64 / 1000.0
+                )
+            }
+            Warning::Trimmed { runs, planned } => {
+                write!(f, "trimmed to {} of {} runs to fit --session-budget", runs, planned)
+            }
+            Warning::TargetMissed { achieved, target } => write!(
+                f,
+                "mean only within ±{:.1}% (target ±{:.1}%); raise --max-runs",
+                achieved * 100.0,
+                target * 100.0
+            ),
+            Warning::StartupDominated { mean } => write!(
+                f,
+                "mean of {:.2}ms is mostly process startup, so comparisons are low confidence; \
+                 --subtract a no-op baseline or do more work per run",
+                mean.as_secs_f64() * 1000.0
+            ),
+            Warning::MemoryGrowth { kb_per_min } => write!(
+                f,
+                "RSS grows {:.0} KB/min over the session, past --leak-threshold; possibly a leak",
+                kb_per_min
+            ),
+            Warning::RunOrder { rho } if *rho < 0.0 => write!(
+                f,
+                "performance improves across runs (Spearman ρ = {:.2}); consider more --warmup",
+                rho
+            ),
+            Warning::RunOrder { rho } => write!(
+                f,
+                "performance degrades across runs (Spearman ρ = {:.2}); \
+                 possible thermal or leak effects",
+                rho
+            ),
+        }
+    }
+}
+
+/// Relative standard error of the mean that adaptive runs reached.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Precision {
+    pub achieved: f64,
+    pub target: f64,
+}
+
+/// A line fitted to each run's peak RSS against when it started, which
+/// shows a leak in a `--soak` session or `clash attach` samples.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MemoryTrend {
+    /// Growth in KB per minute; negative when RSS shrinks
+    pub kb_per_min: f64,
+    /// Standard error of that slope
+    pub std_error: f64,
+}
+
+/// How a command held up over a `--soak` session: its runs split into
+/// equal stretches of time, the first with runs against the last.
+#[derive(Debug, Clone, Serialize)]
+pub struct Soak {
+    pub buckets: Vec<SoakBucket>,
+    /// The last bucket's mean time over the first's, minus one
+    pub time_drift: f64,
+    /// Whether that drift is more than noise
+    pub time_drifts: bool,
+    /// The same for memory, when runs' peak RSS was sampled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_drift: Option<f64>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub memory_drifts: bool,
+}
+
+/// The runs that started within one stretch of a `--soak` session.
+#[derive(Debug, Clone, Serialize)]
+pub struct SoakBucket {
+    /// When the stretch starts, from the first measured run
+    pub from: Duration,
+    pub runs: usize,
+    /// Mean run time, unless no run started in this stretch
+    pub mean: Option<Duration>,
+    /// Mean of the runs' peak RSS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean_peak_memory_bytes: Option<u64>,
+}
+
+/// A no-op command measured to exclude startup cost from another's runs.
+#[derive(Debug, Clone, Serialize)]
+pub struct Baseline {
+    pub command: String,
+    pub mean: Duration,
+}
+
+/// `--matrix VAR=v1,v2,...`: every command is benchmarked once per value,
+/// with `{VAR}` replaced by it.
+#[derive(Debug, Clone)]
+pub struct Matrix {
+    pub var: String,
+    pub values: Vec<String>,
+}
+
+impl Matrix {
+    /// Check the values are thread counts, for `--scaling`.
+    pub fn thread_counts(&self) -> Result<(), String> {
+        if self.values.len() < 2 {
+            return Err("--scaling needs at least two thread counts".to_string());
+        }
+        match self.values.iter().find(|v| !v.parse::<u32>().is_ok_and(|n| n > 0)) {
+            Some(v) => Err(format!("--scaling: '{}' is not a thread count", v)),
+            None => Ok(()),
+        }
+    }
+
+    /// `cmd` for one of the values, or an error if it never uses `{VAR}`.
+    pub fn substitute(&self, cmd: &str, value: &str) -> Result<String, String> {
+        let placeholder = format!("{{{}}}", self.var);
+        if !cmd.contains(&placeholder) {
+            return Err(format!("'{}' doesn't use {}", cmd, placeholder));
+        }
+        Ok(cmd.replace(&placeholder, value))
+    }
+}
+
+impl std::str::FromStr for Matrix {
+    type Err = String;
+
+    /// Parse `threads=1,2,4,8`.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (var, values) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected VAR=VALUE,VALUE,..., got '{}'", s))?;
+        let var = var.trim();
+        if var.is_empty() || !var.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(format!("invalid variable name '{}'", var));
+        }
+        let values: Vec<String> = values.split(',').map(|v| v.trim().to_string()).collect();
+        if values.iter().any(String::is_empty) {
+            return Err("--matrix values must not be empty".to_string());
+        }
+        Ok(Matrix {
+            var: var.to_string(),
+            values,
+        })
+    }
+}
+
+/// Where a command sits in a `--matrix`.
+#[derive(Debug, Clone, Serialize)]
+pub struct MatrixCell {
+    /// Label of the command before substitution
+    pub row: String,
+    pub var: String,
+    pub value: String,
+    /// The value is a thread count, with `--scaling`
+    #[serde(skip_serializing_if = "is_false")]
+    pub scaling: bool,
+}
+
+/// The program a command started, so results can be tied to one exact
+/// binary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Absolute path with symlinks resolved
+    pub path: String,
+    pub sha256: String,
+    pub size_bytes: u64,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// The system calls one traced run of a command made, with `--syscalls`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Syscalls {
+    pub total: u64,
+    /// Calls that returned an error
+    pub errors: u64,
+    /// Each system call made, most frequent first
+    pub calls: Vec<SyscallCount>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyscallCount {
+    pub name: String,
+    pub calls: u64,
+    pub errors: u64,
+}
+
+/// A `--build` command's one timed execution.
+#[derive(Debug, Clone, Serialize)]
+pub struct Build {
+    pub command: String,
+    pub duration: Duration,
+    pub peak_memory_bytes: Option<u64>,
+}
+
+/// Older recordings wrote 0 for a run whose memory wasn't sampled.
+fn unsampled_as_none<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    Ok(Option::<u64>::deserialize(deserializer)?.filter(|&bytes| bytes > 0))
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+fn is_one(n: &usize) -> bool {
+    *n == 1
+}
+
+pub fn is_false(b: &bool) -> bool {
+    !b
+}
+
+/// A stretch of consecutive runs of one command that ran at a markedly lower
+/// CPU frequency than the session's peak.
+#[derive(Debug)]
+pub struct Throttling {
+    pub command_index: usize,
+    /// 0-based indexes of the first and last affected runs
+    pub first_run: usize,
+    pub last_run: usize,
+    pub lowest_mhz: u64,
+    pub peak_mhz: u64,
+}
+
+/// When a benchmark session ran, by the wall clock.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// The whole session, builds and baselines included
+    pub wall_clock: Duration,
+    /// Time spent outside every command's `wall_time`: builds, baselines,
+    /// `--syscalls` traces and waits between rounds
+    pub setup: Duration,
+    /// Name of the machine that ran the session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(flatten)]
+    pub settings: RunSettings,
+    /// What run times were compared on
+    pub estimator: Estimator,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub numa_topology: Vec<NumaNode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monitor_overhead: Option<OverheadSummary>,
+}
+
+/// Pause inserted before each run so the system can settle, plus up to
+/// `jitter` of random extra time.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Delay {
+    pub base: Duration,
+    pub jitter: Duration,
+}
+
+impl Delay {
+    pub fn is_zero(&self) -> bool {
+        self.base.is_zero() && self.jitter.is_zero()
+    }
+
+    /// Sleep for the base delay plus a random share of the jitter.
+    pub fn pause(&self) {
+        let extra = rand::random_range(0..=self.jitter.as_nanos() as u64);
+        std::thread::sleep(self.base + Duration::from_nanos(extra));
+    }
+}
+
+/// Session-wide settings for how runs are executed.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunSettings {
+    #[serde(skip_serializing_if = "Delay::is_zero")]
+    pub delay: Delay,
+    /// NUMA node every local command is bound to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub numa_node: Option<u32>,
+    /// Kind of core every local command is confined to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub core_type: Option<CoreType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_budget: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<Preset>,
+    /// Drop runs far from the median before computing stats
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub reject_outliers: bool,
+    /// Only call a time winner when a rank-sum test finds the gap significant
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub significance: bool,
+    /// Files read into the page cache before each command's runs
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub prefetch: Vec<String>,
+    /// Peak memory also came from the kernel's accounting, not just sampling
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub precise_memory: bool,
+    /// Times the whole comparison was repeated
+    #[serde(skip_serializing_if = "is_one")]
+    pub rounds: usize,
+    /// Pause between rounds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub round_interval: Option<Duration>,
+    /// Seed the `--shuffle` order was drawn from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// How long each command ran for with `--soak`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soak: Option<Duration>,
+    /// Central value run times are compared on, exported as the
+    /// session's `estimator`
+    #[serde(skip)]
+    pub estimator: Estimator,
+    /// Which run statuses count as failed and which are discarded
+    #[serde(skip)]
+    pub status_policy: StatusPolicy,
+    /// RSS growth, in KB per minute, past which a command is warned about a
+    /// possible leak; `stats::DEFAULT_LEAK_THRESHOLD` when unset
+    #[serde(skip)]
+    pub leak_threshold: Option<f64>,
+}
+
+/// Top-level layout of the `--export` JSON file.
+#[derive(Debug, Serialize)]
+pub struct Export {
+    pub session: SessionInfo,
+    pub results: Vec<CommandExport>,
+    /// How each metric measured for every command compares, by name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub verdicts: BTreeMap<&'static str, Verdict>,
+}
+
+impl Export {
+    pub fn new(session: SessionInfo, stats: &[CommandStats]) -> Self {
+        Self {
+            session,
+            results: stats.iter().map(CommandExport::new).collect(),
+            verdicts: stats::verdicts(stats),
+        }
+    }
+}
+
+/// How one metric's comparison came out, so that a gap within noise isn't
+/// read as a win.
+#[derive(Debug, Clone, Serialize)]
+pub struct Verdict {
+    pub verdict: VerdictKind,
+    /// Label of the best command, also when its lead is inconclusive
+    pub winner: String,
+    /// Label of the closest contender
+    pub runner_up: String,
+    /// How many times better the winner is than the runner-up
+    pub ratio: f64,
+    /// Why the comparison is inconclusive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerdictKind {
+    /// The winner's lead is beyond noise
+    Winner,
+    /// Within 1% of each other
+    Tie,
+    /// A lead that noise or unstable timings could explain
+    Inconclusive,
+}
+
+impl VerdictKind {
+    /// Process exit code for `--verdict-exit`.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            VerdictKind::Winner => 0,
+            VerdictKind::Tie => 2,
+            VerdictKind::Inconclusive => 3,
+        }
+    }
+}
+
+/// A command's results as exported: per-run values and aggregates for each
+/// measured metric, plus what else is known about the command and its runs.
+#[derive(Debug, Serialize)]
+pub struct CommandExport {
+    pub command: String,
+    pub label: String,
+    pub runs: usize,
     pub failed_runs: usize,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub discarded_runs: usize,
+    /// Coefficient of variation of the run times
+    pub time_cv: f64,
+    pub stability: Stability,
+    /// Runs too short to compare reliably, with startup dominating them
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub low_confidence: bool,
+    /// Wall-clock time for everything this command needed
+    pub wall_time: Duration,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub outliers_rejected: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trimmed_from: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<Baseline>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub precision: Option<Precision>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build: Option<Build>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<MatrixCell>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rounds: Vec<Duration>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soak: Option<Soak>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_trend: Option<MemoryTrend>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syscalls: Option<Syscalls>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    /// Every metric some run measured, by name; marked phases appear as
+    /// `phase:<name>`, in seconds
+    pub metrics: BTreeMap<String, MetricExport>,
+    /// Each run's details besides its metrics, in the order of the
+    /// metrics' `values`
+    pub run_details: Vec<RunDetails>,
+}
+
+impl CommandExport {
+    pub fn new(s: &CommandStats) -> Self {
+        Self {
+            command: s.command.clone(),
+            label: s.label.clone(),
+            runs: s.runs,
+            failed_runs: s.failed_runs,
+            discarded_runs: s.discarded_runs,
+            time_cv: s.time_cv,
+            stability: s.stability,
+            low_confidence: crate::stats::startup_dominated(s),
+            wall_time: s.wall_time,
+            outliers_rejected: s.outliers_rejected,
+            trimmed_from: s.trimmed_from,
+            baseline: s.baseline.clone(),
+            precision: s.precision,
+            build: s.build.clone(),
+            matrix: s.matrix.clone(),
+            group: s.group.clone(),
+            warnings: s.warnings.clone(),
+            rounds: s.rounds.clone(),
+            annotations: s.annotations.clone(),
+            soak: s.soak.clone(),
+            memory_trend: s.memory_trend,
+            syscalls: s.syscalls.clone(),
+            provenance: s.provenance.clone(),
+            metrics: metric::ALL
+                .iter()
+                .filter_map(|m| Some((m.name.to_string(), m.export(s)?)))
+                .chain(stats::phase_names(s).into_iter().filter_map(|name| {
+                    let values = s
+                        .all_runs
+                        .iter()
+                        .map(|r| stats::phase_time(r, &name).map(|d| d.as_secs_f64()))
+                        .collect();
+                    let export = MetricExport::new(
+                        metric::Unit::Seconds,
+                        metric::Better::Lower,
+                        metric::Aggregate::Mean,
+                        s.estimator,
+                        values,
+                    )?;
+                    Some((format!("phase:{}", name), export))
+                }))
+                .collect(),
+            run_details: s
+                .all_runs
+                .iter()
+                .enumerate()
+                .map(|(k, r)| RunDetails::new(s.run_numbers.get(k).copied().unwrap_or(k), r))
+                .collect(),
+        }
+    }
+}
+
+/// The parts of a `RunResult` that aren't metrics.
+#[derive(Debug, Serialize)]
+pub struct RunDetails {
+    /// Position among the command's measured runs, as in `annotations`
+    pub run: usize,
+    pub status: RunStatus,
+    pub exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_mhz: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prepare: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overhead: Option<MonitorOverhead>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub instances: Vec<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_cores: Option<f64>,
+}
+
+impl RunDetails {
+    fn new(run: usize, r: &RunResult) -> Self {
+        Self {
+            run,
+            status: r.status(),
+            exit_code: r.exit_code,
+            started_at: r.started_at,
+            cpu_mhz: r.cpu_mhz,
+            prepare: r.prepare,
+            overhead: r.overhead,
+            instances: r.instances.clone(),
+            peak_cores: r.cpu.and_then(|c| c.peak_cores),
+        }
+    }
+}
+
+/// How repeatable a command's run times are, graded from their coefficient
+/// of variation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum Stability {
+    /// Under 2%
+    A,
+    /// Under 5%
+    B,
+    /// Under 10%
+    C,
+    /// 10% or more: too noisy to compare
+    D,
 }
 
 /// Comparison between two commands for a specific metric
@@ -29,4 +991,240 @@ pub struct CommandStats {
 pub struct Comparison {
     pub winner_index: usize,
     pub ratio: f64,
+    /// Too noisy to call: a contender is unstable and the gap is within noise
+    pub inconclusive: bool,
+    /// No winner at all: the two best contenders are both grade D
+    pub unstable: bool,
+    /// A contender's time is mostly process startup
+    pub low_confidence: bool,
+}
+
+/// Relative importance of each metric when picking an overall winner.
+#[derive(Debug, Clone)]
+pub struct Weights {
+    /// Weighted metrics in the order given; unlisted metrics weigh nothing
+    pub metrics: Vec<(&'static Metric, f64)>,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            metrics: vec![(&metric::TIME, 0.5), (&metric::MEMORY, 0.5)],
+        }
+    }
+}
+
+impl std::str::FromStr for Weights {
+    type Err = String;
+
+    /// Parse `time=0.7,memory=0.3`; omitted metrics get weight 0.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut weights = Weights {
+            metrics: Vec::new(),
+        };
+        for part in s.split(',') {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected METRIC=WEIGHT, got '{}'", part))?;
+            let value: f64 = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid weight '{}'", value))?;
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("weight for {} must be non-negative", name));
+            }
+            let Some(metric) = Metric::find(name.trim()) else {
+                let known: Vec<&str> = metric::ALL.iter().map(|m| m.name).collect();
+                return Err(format!(
+                    "unknown metric '{}' (expected one of {})",
+                    name.trim(),
+                    known.join(", ")
+                ));
+            };
+            weights.metrics.retain(|(m, _)| m.name != metric.name);
+            weights.metrics.push((metric, value));
+        }
+        if weights.metrics.iter().all(|&(_, w)| w == 0.0) {
+            return Err("at least one weight must be positive".to_string());
+        }
+        Ok(weights)
+    }
+}
+
+/// Absolute budgets per metric, from `--highlight-over`: a command whose
+/// value goes over one is flagged, whether or not it wins.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    pub metrics: Vec<(&'static Metric, f64)>,
+}
+
+impl Limits {
+    /// The budget for `metric`, if one was set.
+    pub fn of(&self, metric: &Metric) -> Option<f64> {
+        self.metrics.iter().find(|(m, _)| m.name == metric.name).map(|&(_, limit)| limit)
+    }
+}
+
+impl std::str::FromStr for Limits {
+    type Err = String;
+
+    /// Parse `time=1s,memory=500MB`, each value written in its metric's unit.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut limits = Limits::default();
+        for part in s.split(',') {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected METRIC=LIMIT, got '{}'", part))?;
+            let Some(metric) = Metric::find(name.trim()) else {
+                let known: Vec<&str> = metric::ALL.iter().map(|m| m.name).collect();
+                return Err(format!(
+                    "unknown metric '{}' (expected one of {})",
+                    name.trim(),
+                    known.join(", ")
+                ));
+            };
+            let limit = match metric.unit {
+                metric::Unit::Seconds => units::parse_duration(value)?.as_secs_f64(),
+                metric::Unit::Bytes => units::parse_bytes(value)? as f64,
+                metric::Unit::Count => value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid limit '{}' for {}", value, metric.name))?,
+            };
+            limits.metrics.retain(|(m, _)| m.name != metric.name);
+            limits.metrics.push((metric, limit));
+        }
+        Ok(limits)
+    }
+}
+
+/// The central value run times are compared on.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Estimator {
+    #[default]
+    Mean,
+    Median,
+    /// Mean of the runs left after dropping this fraction from each end
+    Trimmed(f64),
+    /// The run this fraction of runs are at most as slow as, e.g. 0.95 for p95
+    Percentile(f64),
+    Min,
+    Max,
+}
+
+impl Estimator {
+    /// Fraction `trimmed` drops from each end when no amount is given.
+    const DEFAULT_TRIM: f64 = 0.1;
+
+    /// A percentile's fraction as the percent it was given as, without the
+    /// float noise of dividing by 100 and back (57, not 56.99999999999999).
+    fn percent(q: f64) -> f64 {
+        (q * 100.0 * 1e6).round() / 1e6
+    }
+
+    /// The estimate for `values`, which must not be empty.
+    pub fn apply(&self, values: &[f64]) -> f64 {
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n = sorted.len();
+        let kept = match *self {
+            Estimator::Mean => &sorted[..],
+            // Both middle runs when there's an even number of them
+            Estimator::Median => &sorted[(n - 1) / 2..n / 2 + 1],
+            Estimator::Trimmed(fraction) => {
+                let cut = ((n as f64 * fraction).floor() as usize).min((n - 1) / 2);
+                &sorted[cut..n - cut]
+            }
+            // Nearest rank
+            Estimator::Percentile(q) => {
+                let rank = ((n as f64 * q).ceil() as usize).clamp(1, n);
+                &sorted[rank - 1..rank]
+            }
+            Estimator::Min => &sorted[..1],
+            Estimator::Max => &sorted[n - 1..],
+        };
+        kept.iter().sum::<f64>() / kept.len() as f64
+    }
+
+    /// Name of the estimate's table row.
+    pub fn row(&self) -> String {
+        match self {
+            Estimator::Mean => "Mean".to_string(),
+            Estimator::Median => "Median".to_string(),
+            Estimator::Trimmed(fraction) => format!("Trimmed mean ({:.0}%)", fraction * 100.0),
+            Estimator::Percentile(q) => format!("p{}", Self::percent(*q)),
+            Estimator::Min => "Min".to_string(),
+            Estimator::Max => "Max".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Estimator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Estimator::Mean => write!(f, "mean"),
+            Estimator::Median => write!(f, "median"),
+            Estimator::Trimmed(fraction) => write!(f, "trimmed({:.0}%)", fraction * 100.0),
+            Estimator::Percentile(q) => write!(f, "p{}", Self::percent(*q)),
+            Estimator::Min => write!(f, "min"),
+            Estimator::Max => write!(f, "max"),
+        }
+    }
+}
+
+impl Serialize for Estimator {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl std::str::FromStr for Estimator {
+    type Err = String;
+
+    /// Parse `mean`, `median`, `trimmed`, `trimmed(N%)`, `pN`, `min` or `max`.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "unknown estimator '{}' (expected mean, median, trimmed(N%), pN, min or max)",
+                s
+            )
+        };
+        match s.trim() {
+            "mean" => Ok(Estimator::Mean),
+            "median" => Ok(Estimator::Median),
+            "trimmed" => Ok(Estimator::Trimmed(Self::DEFAULT_TRIM)),
+            "min" => Ok(Estimator::Min),
+            "max" => Ok(Estimator::Max),
+            other if other.starts_with('p') => {
+                let percent: f64 = other[1..].parse().map_err(|_| invalid())?;
+                if !(percent > 0.0 && percent <= 100.0) {
+                    return Err("pN needs N above 0 and up to 100".to_string());
+                }
+                Ok(Estimator::Percentile(percent / 100.0))
+            }
+            other => {
+                let percent = other
+                    .strip_prefix("trimmed(")
+                    .and_then(|rest| rest.strip_suffix(')'))
+                    .map(|p| p.trim().trim_end_matches('%'))
+                    .ok_or_else(invalid)?;
+                let percent: f64 = percent.parse().map_err(|_| invalid())?;
+                if !(0.0..50.0).contains(&percent) {
+                    return Err("trimmed(N%) needs N from 0 up to (not including) 50".to_string());
+                }
+                Ok(Estimator::Trimmed(percent / 100.0))
+            }
+        }
+    }
+}
+
+/// A command's combined score under some `Weights`.
+#[derive(Debug)]
+pub struct Score {
+    /// Weighted score in 0..=1; 1 means best on every weighted metric
+    pub total: f64,
+    /// best / this for each weighted metric, in `Weights` order (1.0 for
+    /// the best command); `None` when the metric wasn't measured for every
+    /// command
+    pub parts: Vec<Option<f64>>,
 }
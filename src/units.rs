@@ -0,0 +1,261 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Separator conventions for `--locale`.
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+pub enum Locale {
+    /// 1234.5
+    #[default]
+    Plain,
+    /// 1,234.5
+    En,
+    /// 1.234,5
+    De,
+    /// 1 234,5
+    Fr,
+}
+
+/// Byte unit convention for `--byte-units`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ByteUnits {
+    /// Powers of 1024: KiB, MiB, GiB
+    #[default]
+    Iec,
+    /// Powers of 1000: KB, MB, GB
+    Si,
+}
+
+impl ByteUnits {
+    fn base(self) -> f64 {
+        match self {
+            ByteUnits::Iec => 1024.0,
+            ByteUnits::Si => 1000.0,
+        }
+    }
+}
+
+/// How every displayed number is written.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NumberFormat {
+    pub locale: Locale,
+    pub byte_units: ByteUnits,
+    /// Fixed decimal places, overriding each unit's default
+    pub precision: Option<usize>,
+}
+
+static FORMAT: OnceLock<NumberFormat> = OnceLock::new();
+
+/// Set the number format for the rest of the process; the first call wins.
+pub fn configure(format: NumberFormat) {
+    FORMAT.set(format).ok();
+}
+
+/// Write `value` with `decimals` places (unless `--precision` overrides it)
+/// and the configured separators.
+pub fn number(value: f64, decimals: usize) -> String {
+    let decimals = FORMAT.get().and_then(|f| f.precision).unwrap_or(decimals);
+    localize(&format!("{:.*}", decimals, value))
+}
+
+/// Apply the configured separators to a plainly formatted number.
+fn localize(plain: &str) -> String {
+    let (group, point) = match FORMAT.get().map(|f| f.locale).unwrap_or_default() {
+        Locale::Plain => (None, '.'),
+        Locale::En => (Some(','), '.'),
+        Locale::De => (Some('.'), ','),
+        Locale::Fr => (Some('\u{202f}'), ','),
+    };
+    let (sign, digits) = match plain.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", plain),
+    };
+    let (int, frac) = match digits.split_once('.') {
+        Some((int, frac)) => (int, Some(frac)),
+        None => (digits, None),
+    };
+
+    let mut out = sign.to_string();
+    for (i, c) in int.chars().enumerate() {
+        if let Some(group) = group
+            && i > 0
+            && (int.len() - i) % 3 == 0
+        {
+            out.push(group);
+        }
+        out.push(c);
+    }
+    if let Some(frac) = frac {
+        out.push(point);
+        out.push_str(frac);
+    }
+    out
+}
+
+/// A fixed time unit, so every value in one table is directly comparable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TimeUnit {
+    Micros,
+    Millis,
+    Secs,
+}
+
+impl TimeUnit {
+    /// The most readable unit for `d` on its own.
+    pub fn for_duration(d: Duration) -> Self {
+        let secs = d.as_secs_f64();
+        if secs >= 1.0 {
+            TimeUnit::Secs
+        } else if secs >= 0.001 {
+            TimeUnit::Millis
+        } else {
+            TimeUnit::Micros
+        }
+    }
+
+    /// One unit for a set of values: the unit of the largest, but at most
+    /// one step coarser than the smallest, so small values never read as 0.
+    pub fn common(values: impl IntoIterator<Item = Duration>) -> Self {
+        let units: Vec<Self> = values
+            .into_iter()
+            .filter(|d| !d.is_zero())
+            .map(Self::for_duration)
+            .collect();
+        match (units.iter().min(), units.iter().max()) {
+            (Some(&smallest), Some(&largest)) => largest.min(smallest.coarser()),
+            _ => TimeUnit::Millis,
+        }
+    }
+
+    fn coarser(self) -> Self {
+        match self {
+            TimeUnit::Micros => TimeUnit::Millis,
+            TimeUnit::Millis | TimeUnit::Secs => TimeUnit::Secs,
+        }
+    }
+
+    /// Format `d` in this unit with fixed decimals.
+    pub fn format(self, d: Duration) -> String {
+        let secs = d.as_secs_f64();
+        match self {
+            TimeUnit::Secs => format!("{}s", number(secs, 3)),
+            TimeUnit::Millis => format!("{}ms", number(secs * 1000.0, 1)),
+            TimeUnit::Micros => format!("{}µs", number(secs * 1_000_000.0, 0)),
+        }
+    }
+}
+
+/// A fixed byte unit, so every value in one table is directly comparable.
+/// Whether `KB` means 1000 or 1024 bytes follows `--byte-units`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ByteUnit {
+    B,
+    KB,
+    MB,
+    GB,
+}
+
+impl ByteUnit {
+    /// The most readable unit for `bytes` on its own.
+    pub fn for_bytes(bytes: u64) -> Self {
+        let base = byte_units().base();
+        let kb = bytes as f64 / base;
+        if kb >= base * base {
+            ByteUnit::GB
+        } else if kb >= base {
+            ByteUnit::MB
+        } else if kb >= 1.0 {
+            ByteUnit::KB
+        } else {
+            ByteUnit::B
+        }
+    }
+
+    /// One unit for a set of values: the unit of the largest, but at most
+    /// one step coarser than the smallest measured one.
+    pub fn common(values: impl IntoIterator<Item = u64>) -> Self {
+        let units: Vec<Self> = values
+            .into_iter()
+            .filter(|&b| b > 0)
+            .map(Self::for_bytes)
+            .collect();
+        match (units.iter().min(), units.iter().max()) {
+            (Some(&smallest), Some(&largest)) => largest.min(smallest.coarser()),
+            _ => ByteUnit::MB,
+        }
+    }
+
+    fn coarser(self) -> Self {
+        match self {
+            ByteUnit::B => ByteUnit::KB,
+            ByteUnit::KB => ByteUnit::MB,
+            ByteUnit::MB | ByteUnit::GB => ByteUnit::GB,
+        }
+    }
+
+    /// Format `bytes` in this unit with fixed decimals ("N/A" for 0).
+    pub fn format(self, bytes: u64) -> String {
+        if bytes == 0 {
+            return "N/A".to_string();
+        }
+        let units = byte_units();
+        let (power, label) = match (self, units) {
+            (ByteUnit::B, _) => return format!("{} B", localize(&bytes.to_string())),
+            (ByteUnit::KB, ByteUnits::Iec) => (1, "KiB"),
+            (ByteUnit::MB, ByteUnits::Iec) => (2, "MiB"),
+            (ByteUnit::GB, ByteUnits::Iec) => (3, "GiB"),
+            (ByteUnit::KB, ByteUnits::Si) => (1, "KB"),
+            (ByteUnit::MB, ByteUnits::Si) => (2, "MB"),
+            (ByteUnit::GB, ByteUnits::Si) => (3, "GB"),
+        };
+        format!("{} {}", number(bytes as f64 / units.base().powi(power), 1), label)
+    }
+}
+
+fn byte_units() -> ByteUnits {
+    FORMAT.get().map(|f| f.byte_units).unwrap_or_default()
+}
+
+/// Parse a size such as "500MB", "1.5GiB" or "4096" (bytes): KB, MB and GB
+/// are powers of 1000, KiB, MiB and GiB powers of 1024, in any case.
+pub fn parse_bytes(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (value, unit) = text.split_at(split);
+    let invalid = || format!("Invalid size '{}' (expected e.g. 512KiB, 500MB, 2GiB)", text);
+    let value: f64 = value.parse().map_err(|_| invalid())?;
+    let scale = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1.0,
+        "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "KIB" => 1024.0,
+        "MIB" => 1024.0 * 1024.0,
+        "GIB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return Err(invalid()),
+    };
+    Ok((value * scale).round() as u64)
+}
+
+/// Parse a duration literal such as "250ms", "1.5s" or "2m" (units: ns, us,
+/// µs, ms, s, m, h).
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (value, unit) = text.split_at(split);
+    let invalid = || format!("Invalid duration '{}' (expected e.g. 250ms, 1.5s, 2m)", text);
+    let value: f64 = value.parse().map_err(|_| invalid())?;
+    let secs = match unit.trim() {
+        "ns" => value / 1e9,
+        "us" | "µs" => value / 1e6,
+        "ms" => value / 1e3,
+        "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        _ => return Err(invalid()),
+    };
+    Duration::try_from_secs_f64(secs).map_err(|_| invalid())
+}